@@ -89,6 +89,8 @@ fn show_all_config(config: &Config) {
     println!("format = \"{}\"", config.defaults.format);
     println!("type = \"{}\"", config.defaults.anomaly_type);
     println!("mode = \"{}\"", config.defaults.mode);
+    println!("method = \"{}\"", config.defaults.method);
+    println!("entropy_check = \"{}\"", config.defaults.entropy_check);
     println!();
 
     println!("[server]");
@@ -117,4 +119,56 @@ fn show_all_config(config: &Config) {
     } else {
         println!("anu = \"***\" # configured");
     }
+    println!();
+
+    println!("[pool]");
+    println!("reserve_bytes = {}", config.pool.reserve_bytes);
+    println!();
+
+    println!("[usage]");
+    println!("daily_budget_bytes = {}", config.usage.daily_budget_bytes);
+    println!();
+
+    println!("[health]");
+    println!("min_entropy = {}", config.health.min_entropy);
+    println!();
+
+    println!("[monitor]");
+    println!("interval_secs = {}", config.monitor.interval_secs);
+    println!("sample_bytes = {}", config.monitor.sample_bytes);
+    println!("max_samples = {}", config.monitor.max_samples);
+    if !config.monitor.backends.is_empty() {
+        println!("backends = {:?}", config.monitor.backends);
+    }
+
+    if !config.exclusions.files.is_empty() {
+        println!();
+        println!("[exclusions]");
+        println!("files = {:?}", config.exclusions.files);
+    }
+
+    let mut names: Vec<&String> = config.backends.keys().collect();
+    names.sort();
+    for name in names {
+        let backend = &config.backends[name];
+        println!();
+        println!("[backends.{}]", name);
+        if let Some(kind) = &backend.kind {
+            println!("type = \"{}\"", kind);
+        }
+        if let Some(description) = &backend.description {
+            println!("description = \"{}\"", description);
+        }
+        if backend.api_key.is_some() {
+            println!("api_key = \"***\" # configured");
+        }
+        if let Some(endpoint) = &backend.endpoint {
+            println!("endpoint = \"{}\"", endpoint);
+        }
+        let mut options: Vec<(&String, &String)> = backend.options.iter().collect();
+        options.sort();
+        for (key, value) in options {
+            println!("options.{} = \"{}\"", key, value);
+        }
+    }
 }
@@ -0,0 +1,115 @@
+//! Entropy command handler
+//!
+//! Fills and inspects the offline entropy bank used by the `bank` backend.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::qrng::bank::Bank;
+use crate::qrng::{get_async_backend, registry};
+use crate::usage;
+use clap::{Args, Subcommand};
+
+/// Bytes drawn and deposited at a time, so an interrupted fetch keeps what
+/// it already got
+const FETCH_CHUNK_BYTES: usize = 65_536;
+
+/// Entropy command arguments
+#[derive(Args)]
+pub struct EntropyArgs {
+    #[command(subcommand)]
+    pub command: EntropyCommand,
+}
+
+/// Entropy subcommands
+#[derive(Subcommand)]
+pub enum EntropyCommand {
+    /// Download entropy into the offline bank
+    Fetch {
+        /// Number of bytes to fetch
+        #[arg(long, short = 'n')]
+        bytes: usize,
+
+        /// Backend to fetch from (default from config)
+        #[arg(long, short = 'b')]
+        backend: Option<String>,
+    },
+    /// Show what the offline bank holds
+    Status,
+}
+
+/// Run the entropy command
+pub async fn run(args: EntropyArgs) -> Result<()> {
+    let config = Config::load()?;
+    let bank = Bank::from_settings(config.backends.get("bank"))?;
+
+    match args.command {
+        EntropyCommand::Fetch { bytes, backend } => {
+            let backend = backend.unwrap_or(config.defaults.backend.clone());
+            fetch(&config, &bank, &backend, bytes).await
+        }
+        EntropyCommand::Status => status(&bank),
+    }
+}
+
+/// Draw `bytes` from `spec` and deposit them in `bank`
+async fn fetch(config: &Config, bank: &Bank, spec: &str, bytes: usize) -> Result<()> {
+    // Also catches a bank wrapped in e.g. `pool:bank` or `mix:bank,anu`
+    if registry().kinds(spec, config)?.contains(&"bank") {
+        return Err(Error::Config(
+            "Can't fill the entropy bank from itself; pick an online backend with --backend".to_string(),
+        ));
+    }
+
+    usage::persist()?;
+    let backend = get_async_backend(spec, config)?;
+    usage::check_budget(config, spec, bytes as u64)?;
+
+    let mut fetched = 0;
+    let result = async {
+        while fetched < bytes {
+            let chunk = backend.bytes_async(FETCH_CHUNK_BYTES.min(bytes - fetched)).await?;
+            bank.deposit(spec, backend.sources(), &chunk)?;
+            fetched += chunk.len();
+        }
+        Ok::<_, Error>(())
+    }
+    .await;
+    if let Err(e) = usage::meter().flush() {
+        eprintln!("Warning: Failed to save entropy usage: {}", e);
+    }
+    if let Err(e) = result {
+        if fetched > 0 {
+            eprintln!("Deposited {} of {} bytes before the error", fetched, bytes);
+        }
+        return Err(e);
+    }
+
+    println!("Deposited {} bytes from {}", fetched, spec);
+    println!("Bank holds {} bytes ({})", bank.index()?.remaining(), bank.dir().display());
+    Ok(())
+}
+
+/// Print the bank's balance and deposits
+fn status(bank: &Bank) -> Result<()> {
+    let index = bank.index()?;
+    println!("Entropy bank: {}", bank.dir().display());
+    println!("  Unspent: {} bytes", index.remaining());
+    println!("  Spent:   {} bytes", index.spent);
+
+    let deposits: Vec<_> = index.deposits.iter().filter(|d| d.remaining > 0).collect();
+    if !deposits.is_empty() {
+        println!();
+        println!("Deposits:");
+        for deposit in deposits {
+            println!(
+                "  {}  {:<12} {} of {} bytes left ({})",
+                deposit.fetched_at,
+                deposit.backend,
+                deposit.remaining,
+                deposit.bytes,
+                deposit.sources.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
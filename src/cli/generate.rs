@@ -3,13 +3,19 @@
 //! Generates random coordinates based on user input.
 
 use crate::config::Config;
-use crate::coord::flower::generate;
-use crate::coord::{AnomalyType, Coordinates, GenerationMode};
+use crate::coord::area::Area;
+use crate::coord::detector::detectors;
+use crate::coord::flower::{
+    entropy_bytes_needed_excluding, generate_async, generate_in_area_async, GenerationOptions,
+};
+use crate::coord::{AnomalyType, Coordinates, DetectionMethod, GenerationMode};
+use crate::entropy::EntropyCheck;
 use crate::error::Result;
 use crate::format::{get_formatter, available_formats};
 use crate::geo::{get_geocoder, get_ip_locator, GeoBackend};
 use crate::history::History;
-use crate::qrng::get_backend_with_key;
+use crate::qrng::get_async_backend;
+use crate::usage;
 use clap::Args;
 use std::str::FromStr;
 
@@ -32,6 +38,15 @@ pub struct GenerateArgs {
     #[arg(long, conflicts_with_all = ["lat", "lng", "location"])]
     pub here: bool,
 
+    /// Search a GeoJSON polygon or multipolygon instead of a circle
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["lat", "lng", "location", "here", "radius"])]
+    pub area: Option<std::path::PathBuf>,
+
+    /// Keep points out of the polygons in a GeoJSON file (repeatable; adds
+    /// to the `exclusions.files` in config)
+    #[arg(long, value_name = "FILE")]
+    pub exclude: Vec<std::path::PathBuf>,
+
     /// Search radius in meters
     #[arg(long, short = 'r')]
     pub radius: Option<f64>,
@@ -56,10 +71,18 @@ pub struct GenerateArgs {
     #[arg(long, short = 'm')]
     pub mode: Option<String>,
 
+    /// Anomaly detection method: grid or kde
+    #[arg(long)]
+    pub method: Option<String>,
+
     /// Include all generated points in response
     #[arg(long)]
     pub include_points: bool,
 
+    /// Test the entropy consumed: off, warn or reject
+    #[arg(long)]
+    pub entropy_check: Option<String>,
+
     /// Don't save to history
     #[arg(long)]
     pub no_history: bool,
@@ -93,8 +116,17 @@ pub async fn run(args: GenerateArgs) -> Result<()> {
     // Load config
     let config = Config::load()?;
 
+    let area = args.area.as_deref().map(Area::load).transpose()?;
+
+    let mut zone_files: Vec<std::path::PathBuf> =
+        config.exclusions.files.iter().map(Into::into).collect();
+    zone_files.extend(args.exclude);
+    let exclude = Area::load_all(&zone_files)?;
+
     // Determine location
-    let center = if args.here {
+    let center = if let Some(area) = &area {
+        area.center()
+    } else if args.here {
         let ip_locator = get_ip_locator();
         let location = ip_locator.locate().await?;
         eprintln!("Using IP location: {}", location.display_name);
@@ -133,37 +165,76 @@ pub async fn run(args: GenerateArgs) -> Result<()> {
     let radius = args.radius.unwrap_or(config.defaults.radius);
     let points = args.points.unwrap_or(config.defaults.points);
     let backend_name = args.backend.unwrap_or(config.defaults.backend.clone());
-    let mode_str = args.mode.unwrap_or(config.defaults.mode.clone());
+    // Flower power lays out circles, so areas default to standard
+    let mode_str = args.mode.unwrap_or_else(|| match &area {
+        Some(_) => "standard".to_string(),
+        None => config.defaults.mode.clone(),
+    });
     let format = args.format.unwrap_or(config.defaults.format.clone());
     let anomaly_type_str = args.r#type.unwrap_or(config.defaults.anomaly_type.clone());
+    let method_str = args.method.unwrap_or(config.defaults.method.clone());
+    let entropy_check_str = args.entropy_check.unwrap_or(config.defaults.entropy_check.clone());
 
     // Parse mode
     let mode = GenerationMode::from_str(&mode_str)
-        .map_err(|e| crate::error::Error::Config(e))?;
+        .map_err(crate::error::Error::Config)?;
+    if area.is_some() && mode != GenerationMode::Standard {
+        return Err(crate::error::Error::InvalidArea(format!("{} mode can't search an area", mode_str)));
+    }
+
+    let method = DetectionMethod::from_str(&method_str)
+        .map_err(crate::error::Error::Config)?;
+
+    let entropy_check = EntropyCheck::from_str(&entropy_check_str)
+        .map_err(crate::error::Error::Config)?;
 
     // Parse anomaly type for display
     let display_type = AnomalyType::from_str(&anomaly_type_str)
-        .map_err(|e| crate::error::Error::Config(e))?;
-
-    // Get backend with API key if configured
-    let api_key = if backend_name == "anu" && !config.api_keys.anu.is_empty() {
-        Some(config.api_keys.anu.as_str())
-    } else {
-        None
+        .map_err(crate::error::Error::Config)?;
+
+    // Get backend (API keys and endpoints come from config)
+    usage::persist()?;
+    let backend = get_async_backend(&backend_name, &config)?;
+
+    // Refuse before drawing anything if the daily budget can't cover it
+    let needed = match &area {
+        Some(area) => area.entropy_bytes_needed(points, exclude.as_ref(), backend.float_precision())?,
+        None => entropy_bytes_needed_excluding(
+            center,
+            radius,
+            points,
+            mode,
+            exclude.as_ref(),
+            backend.float_precision(),
+        )?,
     };
-    let backend = get_backend_with_key(&backend_name, api_key);
+    usage::check_budget(&config, &backend_name, needed)?;
 
     // Generate
-    let response = generate(
-        center,
-        radius,
-        points,
-        50, // grid_resolution
-        args.include_points,
-        mode,
-        backend.name(),
-        backend.as_ref(),
-    )?;
+    let options = GenerationOptions::new(backend.name())
+        .with_points(points)
+        .with_grid_resolution(50)
+        .with_method(method)
+        .with_include_points(args.include_points)
+        .with_exclude(exclude.as_ref())
+        .with_entropy_check(entropy_check);
+    let response = match &area {
+        Some(area) => generate_in_area_async(area, &options, backend.as_ref()).await,
+        None => generate_async(center, radius, mode, &options, backend.as_ref()).await,
+    };
+    if let Err(e) = usage::meter().flush() {
+        eprintln!("Warning: Failed to save entropy usage: {}", e);
+    }
+    let response = response?;
+
+    if let Some(failures) = response
+        .metadata
+        .entropy_quality
+        .as_ref()
+        .and_then(|quality| quality.failure_summary())
+    {
+        eprintln!("Warning: Entropy failed quality tests: {}", failures);
+    }
 
     // Save to history (unless disabled)
     if !args.no_history {
@@ -193,10 +264,9 @@ pub async fn run(args: GenerateArgs) -> Result<()> {
 /// Print available anomaly types
 fn list_types() {
     println!("Available anomaly types:");
-    println!("  blind_spot  - Random point with no analysis");
-    println!("  attractor   - Densest cluster of points");
-    println!("  void        - Emptiest region");
-    println!("  power       - Most statistically anomalous");
+    for detector in detectors() {
+        println!("  {:11} - {}", detector.name(), detector.description());
+    }
 }
 
 /// Print available output formats
@@ -107,6 +107,7 @@ fn show_entry(id: &str) -> Result<()> {
     println!("  Center: ({}, {})", entry.response.request.lat, entry.response.request.lng);
     println!("  Radius: {}m", entry.response.request.radius);
     println!("  Mode: {:?}", entry.response.request.mode);
+    println!("  Method: {}", entry.response.request.method);
     println!("  Backend: {}", entry.response.request.backend);
 
     println!("\nResults:");
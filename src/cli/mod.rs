@@ -3,10 +3,12 @@
 //! Each subcommand has its own module with handler functions.
 
 pub mod config;
+pub mod entropy;
 pub mod generate;
 pub mod history;
 pub mod serve;
 pub mod status;
+pub mod verify;
 
 use clap::{Parser, Subcommand};
 
@@ -19,6 +21,8 @@ pub struct Cli {
     pub command: Commands,
 }
 
+// Parsed once per run, so the size of the generate args doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Generate random coordinates
@@ -35,6 +39,12 @@ pub enum Commands {
 
     /// View and manage history
     History(history::HistoryArgs),
+
+    /// Verify a saved result against a replay capture
+    Verify(verify::VerifyArgs),
+
+    /// Manage the offline entropy bank
+    Entropy(entropy::EntropyArgs),
 }
 
 /// Run the CLI
@@ -47,5 +57,7 @@ pub async fn run() -> crate::error::Result<()> {
         Commands::Config(args) => config::run(args),
         Commands::Status(args) => status::run(args).await,
         Commands::History(args) => history::run(args).await,
+        Commands::Verify(args) => verify::run(args),
+        Commands::Entropy(args) => entropy::run(args).await,
     }
 }
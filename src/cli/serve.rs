@@ -47,6 +47,9 @@ pub async fn run(args: ServeArgs) -> Result<()> {
         config.server_addr()
     );
 
+    // Share the daily entropy count with other q-explore processes
+    crate::usage::persist()?;
+
     // Run the server
     server::run(config).await
 }
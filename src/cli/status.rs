@@ -1,11 +1,14 @@
 //! Status command handler
 //!
-//! Shows server status and entropy quality.
+//! Shows server status, entropy quality and entropy usage. `--history`
+//! shows the server's scheduled entropy samples instead of testing afresh.
 
 use crate::config::Config;
-use crate::entropy::run_all_tests;
-use crate::error::Result;
-use crate::qrng::get_backend;
+use crate::entropy::{run_tests, EntropySeries, NistTest};
+use crate::error::{Error, Result};
+use crate::qrng::get_async_backend;
+use crate::usage::{self, Usage};
+use std::collections::HashMap;
 use clap::Args;
 
 /// Status command arguments
@@ -19,23 +22,46 @@ pub struct StatusArgs {
     #[arg(long, default_value = "10000")]
     pub entropy_bytes: usize,
 
+    /// Also run extended SP 800-22 tests (comma-separated names, or "all")
+    #[arg(long = "test", short = 't', value_delimiter = ',')]
+    pub tests: Vec<String>,
+
     /// Check if server is running (tries to connect)
     #[arg(long)]
     pub server: bool,
+
+    /// Show recorded entropy samples and trends instead of drawing new bytes
+    #[arg(long)]
+    pub history: bool,
+
+    /// Recent samples to list per backend with --history
+    #[arg(long, default_value = "10")]
+    pub samples: usize,
 }
 
 /// Run the status command
 pub async fn run(args: StatusArgs) -> Result<()> {
     let config = Config::load()?;
+    let extended = parse_tests(&args.tests)?;
 
     // Check server status if requested
     if args.server {
         check_server_status(&config).await;
     }
 
+    if args.history {
+        let series =
+            EntropySeries::load(EntropySeries::series_path()?, config.monitor.max_samples)?;
+        println!("q-explore v{}", env!("CARGO_PKG_VERSION"));
+        println!();
+        print_history(&series, args.backend.as_deref(), args.samples);
+        return Ok(());
+    }
+
     // Get backend
+    usage::persist()?;
     let backend_name = args.backend.unwrap_or(config.defaults.backend.clone());
-    let backend = get_backend(&backend_name);
+    let backend = get_async_backend(&backend_name, &config)?;
 
     println!("q-explore v{}", env!("CARGO_PKG_VERSION"));
     println!();
@@ -45,9 +71,9 @@ pub async fn run(args: StatusArgs) -> Result<()> {
 
     // Run entropy tests
     println!("Entropy Quality Test ({} bytes):", args.entropy_bytes);
-    match backend.bytes(args.entropy_bytes) {
+    match backend.bytes_async(args.entropy_bytes).await {
         Ok(bytes) => {
-            let results = run_all_tests(&bytes);
+            let results = run_tests(&bytes, &extended);
 
             let status = |score: f64| {
                 if score >= 0.1 {
@@ -74,6 +100,21 @@ pub async fn run(args: StatusArgs) -> Result<()> {
                 results.scattered,
                 status(results.scattered)
             );
+            if !results.extended.is_empty() {
+                println!();
+                println!("  SP 800-22:");
+                for (name, &p) in &results.extended {
+                    println!("    {:<26} {:.4} [{}]", name, p, status(p));
+                }
+            }
+            println!();
+            let min_entropy = &results.min_entropy;
+            println!("  Min-entropy (SP 800-90B, bits/byte):");
+            println!("    Most common value:    {:.3}", min_entropy.most_common_value);
+            println!("    Collision:            {:.3}", min_entropy.collision);
+            println!("    Markov:               {:.3}", min_entropy.markov);
+            println!("    Compression:          {:.3}", min_entropy.compression);
+            println!("    Assessed:             {:.3}", min_entropy.min);
             println!();
             println!(
                 "  Overall: {:.4} [{}]",
@@ -86,6 +127,89 @@ pub async fn run(args: StatusArgs) -> Result<()> {
         }
     }
 
+    if let Err(e) = usage::meter().flush() {
+        eprintln!("Warning: Failed to save entropy usage: {}", e);
+    }
+    println!();
+    print_usage(&config, &usage::meter().today())?;
+
+    Ok(())
+}
+
+/// Parse `--test` names; "all" selects every extended test
+fn parse_tests(names: &[String]) -> Result<Vec<NistTest>> {
+    if names.iter().any(|name| name == "all") {
+        return Ok(NistTest::ALL.to_vec());
+    }
+    let mut tests = names
+        .iter()
+        .map(|name| name.parse().map_err(Error::Config))
+        .collect::<Result<Vec<NistTest>>>()?;
+    tests.sort();
+    tests.dedup();
+    Ok(tests)
+}
+
+/// Print recorded entropy trends and the most recent samples per backend
+fn print_history(series: &EntropySeries, only: Option<&str>, recent: usize) {
+    let backends: Vec<&str> = series
+        .backends()
+        .into_iter()
+        .filter(|name| only.is_none_or(|only| only == *name))
+        .collect();
+    println!("Entropy History:");
+    if backends.is_empty() {
+        println!("  (no samples; the server records them every monitor.interval_secs)");
+        return;
+    }
+
+    for name in backends {
+        let trend = series.trend(name);
+        println!();
+        println!("  {} ({} samples, {} failed draws)", name, trend.samples, trend.errors);
+        println!("    Passed:       {:.0}%", trend.pass_rate * 100.0);
+        println!("    Mean overall: {:.4}", trend.mean_overall);
+        println!("    Min-entropy:  {:.3} bits/byte", trend.mean_min_entropy);
+        println!("    Trend:        {:+.4} overall/day", trend.overall_per_day);
+
+        let samples = series.samples(name);
+        for sample in &samples[samples.len().saturating_sub(recent)..] {
+            let detail = match (&sample.results, &sample.error) {
+                (Some(results), _) => format!(
+                    "{:.4} {:.3} bits/byte [{}]",
+                    results.overall,
+                    results.min_entropy.min,
+                    if results.all_passed() { "PASS" } else { "FAIL" }
+                ),
+                (None, Some(error)) => format!("error: {}", error),
+                (None, None) => "no result".to_string(),
+            };
+            println!("    {}  {}", sample.timestamp, detail);
+        }
+    }
+}
+
+/// Print today's entropy usage per backend, with budgets
+fn print_usage(config: &Config, today: &HashMap<String, Usage>) -> Result<()> {
+    println!("Entropy Usage ({} UTC):", usage::today());
+    if today.is_empty() {
+        println!("  (none)");
+        return Ok(());
+    }
+
+    let mut names: Vec<&String> = today.keys().collect();
+    names.sort();
+    for name in names {
+        let used = today[name];
+        let budget = match usage::daily_budget(config, name)? {
+            0 => String::new(),
+            budget => format!(" of {} budget", budget),
+        };
+        println!(
+            "  {:<12} {} bytes{}, {} requests",
+            name, used.bytes, budget, used.requests
+        );
+    }
     Ok(())
 }
 
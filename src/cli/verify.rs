@@ -0,0 +1,68 @@
+//! Verify command handler
+//!
+//! Checks a saved generation against the capture of the entropy it consumed
+//! (from a `record:<backend>:<path>` backend).
+
+use crate::coord::flower::GenerationResponse;
+use crate::coord::provenance::verify;
+use crate::error::{Error, Result};
+use crate::history::{History, HistoryEntry};
+use clap::Args;
+use std::path::{Path, PathBuf};
+
+/// Verify command arguments
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// History entry ID (or prefix), or a saved JSON result file
+    pub result: String,
+
+    /// Capture file holding the entropy bytes the generation consumed
+    #[arg(short, long)]
+    pub capture: PathBuf,
+}
+
+/// Run the verify command
+pub fn run(args: VerifyArgs) -> Result<()> {
+    let response = load_result(&args.result)?;
+    let provenance = response.metadata.provenance.as_ref().ok_or_else(|| {
+        Error::Config(format!("Generation {} has no provenance record", response.id))
+    })?;
+
+    println!("Generation: {}", response.id);
+    println!("Backend: {}", provenance.backend);
+    println!("Entropy: {} bytes, {}-bit floats", provenance.bytes, provenance.float_bits);
+    println!("Recorded SHA-256: {}", provenance.sha256);
+
+    let result = verify(&response, &args.capture)?;
+    println!("Capture SHA-256:  {}", result.capture_sha256);
+    println!("Digest: {}", if result.digest_matches { "match" } else { "MISMATCH" });
+    println!("Winners: {}", if result.winners_match { "match" } else { "MISMATCH" });
+
+    if !result.passed() {
+        return Err(Error::Verification(format!(
+            "{} was not derived unaltered from {}",
+            response.id,
+            args.capture.display()
+        )));
+    }
+    println!("\nVerified.");
+    Ok(())
+}
+
+/// Load a result from a JSON file, or from history by ID prefix
+fn load_result(result: &str) -> Result<GenerationResponse> {
+    let path = Path::new(result);
+    if path.is_file() {
+        let content = std::fs::read_to_string(path)?;
+        // Accept both a bare response and a history entry
+        return Ok(serde_json::from_str::<HistoryEntry>(&content)?.response);
+    }
+
+    let history = History::load()?;
+    history
+        .entries()
+        .iter()
+        .find(|e| e.response.id.starts_with(result))
+        .map(|e| e.response.clone())
+        .ok_or_else(|| Error::Config(format!("Entry not found: {}", result)))
+}
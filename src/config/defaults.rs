@@ -20,6 +20,12 @@ pub const DEFAULT_TYPE: &str = "attractor";
 /// Default generation mode
 pub const DEFAULT_MODE: &str = "standard";
 
+/// Default anomaly detection method (grid or kde)
+pub const DEFAULT_METHOD: &str = "grid";
+
+/// Default entropy quality check for generations (off, warn or reject)
+pub const DEFAULT_ENTROPY_CHECK: &str = "off";
+
 /// Default server host
 pub const DEFAULT_HOST: &str = "127.0.0.1";
 
@@ -29,6 +35,21 @@ pub const DEFAULT_PORT: u16 = 7878;
 /// Default shutdown timeout in seconds (after last client disconnects)
 pub const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
 
+/// Default number of bytes kept in reserve by `pool:` backends
+pub const DEFAULT_POOL_RESERVE_BYTES: usize = 65_536;
+
+/// Default min-entropy (bits per byte) assumed by the continuous health tests
+pub const DEFAULT_HEALTH_MIN_ENTROPY: f64 = 2.0;
+
+/// Default seconds between scheduled entropy samples (0 = off)
+pub const DEFAULT_MONITOR_INTERVAL_SECS: u64 = 3600;
+
+/// Default bytes drawn per scheduled entropy sample
+pub const DEFAULT_MONITOR_SAMPLE_BYTES: usize = 10_000;
+
+/// Default entropy samples kept per backend (a week at the default interval)
+pub const DEFAULT_MONITOR_MAX_SAMPLES: usize = 168;
+
 /// Default URL provider
 pub const DEFAULT_URL_PROVIDER: &str = "google";
 
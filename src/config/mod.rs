@@ -13,7 +13,7 @@ use std::fs;
 use std::path::PathBuf;
 
 /// Main configuration structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     /// Default values for generation
     #[serde(default)]
@@ -34,6 +34,33 @@ pub struct Config {
     /// API keys for various services
     #[serde(default)]
     pub api_keys: ApiKeysConfig,
+
+    /// Prefetching entropy pool settings
+    #[serde(default)]
+    pub pool: PoolConfig,
+
+    /// Entropy consumption limits
+    #[serde(default)]
+    pub usage: UsageConfig,
+
+    /// Continuous health tests on backend output
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    /// Scheduled entropy quality sampling by the server
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+
+    /// Exclusion zones every generation keeps points out of
+    #[serde(default)]
+    pub exclusions: ExclusionsConfig,
+
+    /// Per-backend settings, one `[backends.<name>]` table each
+    ///
+    /// A table named after a registered backend configures it; any other name
+    /// defines a new backend instance whose `type` picks the implementation.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub backends: HashMap<String, BackendConfig>,
 }
 
 /// Default values for generation
@@ -62,6 +89,15 @@ pub struct DefaultsConfig {
     /// Default generation mode
     #[serde(default = "default_mode")]
     pub mode: String,
+
+    /// Default anomaly detection method (grid or kde)
+    #[serde(default = "default_method")]
+    pub method: String,
+
+    /// What to do about generations whose entropy fails the quality tests
+    /// (off, warn or reject)
+    #[serde(default = "default_entropy_check")]
+    pub entropy_check: String,
 }
 
 /// Server settings
@@ -81,7 +117,7 @@ pub struct ServerConfig {
 }
 
 /// Location settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LocationConfig {
     /// If true, --here is default when no location given
     #[serde(default)]
@@ -108,6 +144,104 @@ pub struct ApiKeysConfig {
     pub anu: String,
 }
 
+/// Prefetching entropy pool settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Bytes kept in reserve by `pool:` backends
+    #[serde(default = "default_pool_reserve_bytes")]
+    pub reserve_bytes: usize,
+}
+
+/// Entropy consumption limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageConfig {
+    /// Bytes each backend may serve per UTC day (0 = unlimited)
+    ///
+    /// A backend's `daily_budget_bytes` option overrides this.
+    #[serde(default)]
+    pub daily_budget_bytes: u64,
+}
+
+/// Continuous health test settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Min-entropy in bits per byte assumed of every backend (0-8, 0 = off)
+    ///
+    /// Sets the health test cutoffs. A backend's `health_min_entropy` option
+    /// overrides this.
+    #[serde(default = "default_health_min_entropy")]
+    pub min_entropy: f64,
+}
+
+/// Scheduled entropy quality sampling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorConfig {
+    /// Seconds between samples (0 = off)
+    #[serde(default = "default_monitor_interval_secs")]
+    pub interval_secs: u64,
+
+    /// Bytes drawn from each backend per sample
+    #[serde(default = "default_monitor_sample_bytes")]
+    pub sample_bytes: usize,
+
+    /// Samples kept per backend
+    #[serde(default = "default_monitor_max_samples")]
+    pub max_samples: usize,
+
+    /// Backends to sample; empty turns sampling off
+    ///
+    /// Specs building a `record`, `pool` or `bank` backend are never
+    /// sampled (see `server::monitor`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backends: Vec<String>,
+}
+
+/// Exclusion zone settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExclusionsConfig {
+    /// GeoJSON files holding the zones' polygons
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+/// Settings for a single QRNG backend (`[backends.<name>]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackendConfig {
+    /// Registered backend type to instantiate (defaults to the table name)
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    /// Description shown in backend listings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// API key sent to the entropy service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// Endpoint URL overriding the backend's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    /// Backend-specific options
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub options: HashMap<String, String>,
+}
+
+impl BackendConfig {
+    /// Get a backend-specific option parsed as `T`
+    ///
+    /// Returns `Ok(None)` if the option isn't set.
+    pub fn option<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>> {
+        match self.options.get(key) {
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                Error::Config(format!("Invalid value for backend option {}: {}", key, value))
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
 // Default value functions for serde
 fn default_backend() -> String {
     DEFAULT_BACKEND.to_string()
@@ -127,6 +261,12 @@ fn default_type() -> String {
 fn default_mode() -> String {
     DEFAULT_MODE.to_string()
 }
+fn default_method() -> String {
+    DEFAULT_METHOD.to_string()
+}
+fn default_entropy_check() -> String {
+    DEFAULT_ENTROPY_CHECK.to_string()
+}
 fn default_host() -> String {
     DEFAULT_HOST.to_string()
 }
@@ -136,6 +276,21 @@ fn default_port() -> u16 {
 fn default_shutdown_timeout() -> u64 {
     DEFAULT_SHUTDOWN_TIMEOUT_SECS
 }
+fn default_pool_reserve_bytes() -> usize {
+    DEFAULT_POOL_RESERVE_BYTES
+}
+fn default_health_min_entropy() -> f64 {
+    DEFAULT_HEALTH_MIN_ENTROPY
+}
+fn default_monitor_interval_secs() -> u64 {
+    DEFAULT_MONITOR_INTERVAL_SECS
+}
+fn default_monitor_sample_bytes() -> usize {
+    DEFAULT_MONITOR_SAMPLE_BYTES
+}
+fn default_monitor_max_samples() -> usize {
+    DEFAULT_MONITOR_MAX_SAMPLES
+}
 fn default_url_provider() -> String {
     DEFAULT_URL_PROVIDER.to_string()
 }
@@ -157,18 +312,6 @@ fn default_url_providers() -> HashMap<String, String> {
 }
 
 // Implement Default traits
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            defaults: DefaultsConfig::default(),
-            server: ServerConfig::default(),
-            location: LocationConfig::default(),
-            url: UrlConfig::default(),
-            api_keys: ApiKeysConfig::default(),
-        }
-    }
-}
-
 impl Default for DefaultsConfig {
     fn default() -> Self {
         Self {
@@ -178,6 +321,8 @@ impl Default for DefaultsConfig {
             format: default_format(),
             anomaly_type: default_type(),
             mode: default_mode(),
+            method: default_method(),
+            entropy_check: default_entropy_check(),
         }
     }
 }
@@ -192,9 +337,30 @@ impl Default for ServerConfig {
     }
 }
 
-impl Default for LocationConfig {
+impl Default for PoolConfig {
     fn default() -> Self {
-        Self { default_here: false }
+        Self {
+            reserve_bytes: default_pool_reserve_bytes(),
+        }
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            min_entropy: default_health_min_entropy(),
+        }
+    }
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_monitor_interval_secs(),
+            sample_bytes: default_monitor_sample_bytes(),
+            max_samples: default_monitor_max_samples(),
+            backends: Vec::new(),
+        }
     }
 }
 
@@ -278,6 +444,8 @@ impl Config {
             ["defaults", "format"] => Some(self.defaults.format.clone()),
             ["defaults", "type"] => Some(self.defaults.anomaly_type.clone()),
             ["defaults", "mode"] => Some(self.defaults.mode.clone()),
+            ["defaults", "method"] => Some(self.defaults.method.clone()),
+            ["defaults", "entropy_check"] => Some(self.defaults.entropy_check.clone()),
 
             ["server", "host"] => Some(self.server.host.clone()),
             ["server", "port"] => Some(self.server.port.to_string()),
@@ -291,6 +459,30 @@ impl Config {
 
             ["api_keys", "anu"] => Some(self.api_keys.anu.clone()),
 
+            ["pool", "reserve_bytes"] => Some(self.pool.reserve_bytes.to_string()),
+
+            ["usage", "daily_budget_bytes"] => Some(self.usage.daily_budget_bytes.to_string()),
+
+            ["health", "min_entropy"] => Some(self.health.min_entropy.to_string()),
+
+            ["monitor", "interval_secs"] => Some(self.monitor.interval_secs.to_string()),
+            ["monitor", "sample_bytes"] => Some(self.monitor.sample_bytes.to_string()),
+            ["monitor", "max_samples"] => Some(self.monitor.max_samples.to_string()),
+            ["monitor", "backends"] => Some(self.monitor.backends.join(",")),
+
+            ["exclusions", "files"] => Some(self.exclusions.files.join(",")),
+
+            ["backends", name, field] => {
+                let backend = self.backends.get(*name)?;
+                match *field {
+                    "type" => backend.kind.clone(),
+                    "description" => backend.description.clone(),
+                    "api_key" => backend.api_key.clone(),
+                    "endpoint" => backend.endpoint.clone(),
+                    option => backend.options.get(option).cloned(),
+                }
+            }
+
             _ => None,
         }
     }
@@ -325,6 +517,18 @@ impl Config {
             ["defaults", "mode"] => {
                 self.defaults.mode = value.to_string();
             }
+            ["defaults", "method"] => {
+                value
+                    .parse::<crate::coord::DetectionMethod>()
+                    .map_err(Error::Config)?;
+                self.defaults.method = value.to_lowercase();
+            }
+            ["defaults", "entropy_check"] => {
+                value
+                    .parse::<crate::entropy::EntropyCheck>()
+                    .map_err(Error::Config)?;
+                self.defaults.entropy_check = value.to_lowercase();
+            }
 
             ["server", "host"] => {
                 self.server.host = value.to_string();
@@ -354,6 +558,77 @@ impl Config {
                 self.api_keys.anu = value.to_string();
             }
 
+            ["pool", "reserve_bytes"] => {
+                self.pool.reserve_bytes = value.parse().map_err(|_| {
+                    Error::Config(format!("Invalid reserve size: {}", value))
+                })?;
+            }
+
+            ["usage", "daily_budget_bytes"] => {
+                self.usage.daily_budget_bytes = value.parse().map_err(|_| {
+                    Error::Config(format!("Invalid budget: {}", value))
+                })?;
+            }
+
+            ["health", "min_entropy"] => {
+                let min_entropy: f64 = value.parse().map_err(|_| {
+                    Error::Config(format!("Invalid min-entropy: {}", value))
+                })?;
+                if !(0.0..=8.0).contains(&min_entropy) {
+                    return Err(Error::Config(format!(
+                        "Min-entropy must be between 0 and 8 bits per byte: {}",
+                        value
+                    )));
+                }
+                self.health.min_entropy = min_entropy;
+            }
+
+            ["monitor", "interval_secs"] => {
+                self.monitor.interval_secs = value.parse().map_err(|_| {
+                    Error::Config(format!("Invalid interval value: {}", value))
+                })?;
+            }
+            ["monitor", "sample_bytes"] => {
+                self.monitor.sample_bytes = value.parse().map_err(|_| {
+                    Error::Config(format!("Invalid sample size: {}", value))
+                })?;
+            }
+            ["monitor", "max_samples"] => {
+                self.monitor.max_samples = value.parse().map_err(|_| {
+                    Error::Config(format!("Invalid sample count: {}", value))
+                })?;
+            }
+            ["monitor", "backends"] => {
+                self.monitor.backends = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+
+            ["exclusions", "files"] => {
+                self.exclusions.files = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+
+            ["backends", name, field] => {
+                let backend = self.backends.entry(name.to_string()).or_default();
+                match *field {
+                    "type" => backend.kind = Some(value.to_string()),
+                    "description" => backend.description = Some(value.to_string()),
+                    "api_key" => backend.api_key = Some(value.to_string()),
+                    "endpoint" => backend.endpoint = Some(value.to_string()),
+                    option => {
+                        backend.options.insert(option.to_string(), value.to_string());
+                    }
+                }
+            }
+
             _ => {
                 return Err(Error::Config(format!("Unknown config key: {}", key)));
             }
@@ -371,12 +646,27 @@ impl Config {
             "defaults.format",
             "defaults.type",
             "defaults.mode",
+            "defaults.method",
+            "defaults.entropy_check",
             "server.host",
             "server.port",
             "server.shutdown_timeout_secs",
             "location.default_here",
             "url.default",
             "api_keys.anu",
+            "pool.reserve_bytes",
+            "usage.daily_budget_bytes",
+            "health.min_entropy",
+            "monitor.interval_secs",
+            "monitor.sample_bytes",
+            "monitor.max_samples",
+            "monitor.backends",
+            "exclusions.files",
+            "backends.<name>.type",
+            "backends.<name>.description",
+            "backends.<name>.api_key",
+            "backends.<name>.endpoint",
+            "backends.<name>.<option>",
         ]
     }
 
@@ -421,6 +711,7 @@ mod tests {
         assert_eq!(config.defaults.radius, 3000.0);
         assert_eq!(config.defaults.points, 10_000);
         assert_eq!(config.server.port, 7878);
+        assert_eq!(config.pool.reserve_bytes, 65_536);
     }
 
     #[test]
@@ -457,6 +748,93 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_set_entropy_check() {
+        let mut config = Config::default();
+        assert_eq!(config.get("defaults.entropy_check"), Some("off".to_string()));
+
+        config.set("defaults.entropy_check", "Reject").unwrap();
+        assert_eq!(config.defaults.entropy_check, "reject");
+        assert!(config.set("defaults.entropy_check", "sometimes").is_err());
+    }
+
+    #[test]
+    fn test_set_method() {
+        let mut config = Config::default();
+        assert_eq!(config.get("defaults.method"), Some("grid".to_string()));
+
+        config.set("defaults.method", "KDE").unwrap();
+        assert_eq!(config.defaults.method, "kde");
+        assert!(config.set("defaults.method", "tea_leaves").is_err());
+    }
+
+    #[test]
+    fn test_set_monitor() {
+        let mut config = Config::default();
+        assert_eq!(config.get("monitor.interval_secs"), Some("3600".to_string()));
+        assert_eq!(config.get("monitor.backends"), Some(String::new()));
+
+        config.set("monitor.interval_secs", "0").unwrap();
+        config.set("monitor.backends", "anu, pool:anu,").unwrap();
+        assert_eq!(config.monitor.interval_secs, 0);
+        assert_eq!(config.monitor.backends, vec!["anu", "pool:anu"]);
+        assert!(config.set("monitor.sample_bytes", "lots").is_err());
+    }
+
+    #[test]
+    fn test_set_exclusion_files() {
+        let mut config = Config::default();
+        assert_eq!(config.get("exclusions.files"), Some(String::new()));
+
+        config.set("exclusions.files", "/data/lake.geojson, /data/base.geojson").unwrap();
+        assert_eq!(config.exclusions.files, vec!["/data/lake.geojson", "/data/base.geojson"]);
+        assert_eq!(
+            config.get("exclusions.files"),
+            Some("/data/lake.geojson,/data/base.geojson".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_set_backend_settings() {
+        let mut config = Config::default();
+        assert_eq!(config.get("backends.anu.endpoint"), None);
+
+        config.set("backends.anu.endpoint", "http://localhost:9000").unwrap();
+        config.set("backends.anu.retries", "3").unwrap();
+        assert_eq!(
+            config.get("backends.anu.endpoint"),
+            Some("http://localhost:9000".to_string())
+        );
+        assert_eq!(config.get("backends.anu.retries"), Some("3".to_string()));
+        assert_eq!(config.backends["anu"].option::<u32>("retries").unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_backends_table_parsing() {
+        let config: Config = toml::from_str(
+            r#"
+            [backends.anu]
+            api_key = "secret"
+
+            [backends.lab]
+            type = "anu"
+            endpoint = "http://lab.local/qrng"
+            description = "Lab QRNG"
+
+            [backends.lab.options]
+            retries = "2"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.backends["anu"].api_key.as_deref(), Some("secret"));
+        let lab = &config.backends["lab"];
+        assert_eq!(lab.kind.as_deref(), Some("anu"));
+        assert_eq!(lab.endpoint.as_deref(), Some("http://lab.local/qrng"));
+        assert_eq!(lab.option::<u32>("retries").unwrap(), Some(2));
+        assert!(lab.option::<u32>("missing").unwrap().is_none());
+    }
+
     #[test]
     fn test_format_url() {
         let config = Config::default();
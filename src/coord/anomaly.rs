@@ -1,16 +1,23 @@
 //! Anomaly detection for coordinate generation
 //!
-//! Detects attractors (dense areas), voids (sparse areas), and power anomalies
-//! (most statistically extreme in either direction).
+//! Runs the registered detectors (see `detector`) over generated points:
+//! attractors (dense areas), voids (sparse areas), power anomalies (most
+//! statistically extreme in either direction) and blind spots. Density is
+//! estimated by counting points per grid cell or by kernel density
+//! estimation (see `DetectionMethod`).
 
-use crate::coord::density::{
-    find_densest_cell, find_emptiest_cell, find_most_anomalous_cell, DensityGrid,
-};
+use crate::coord::density::{find_densest_cell, find_emptiest_cell, DensityGrid};
 pub use crate::coord::density::DEFAULT_GRID_RESOLUTION;
-use crate::coord::point::generate_points_in_circle;
-use crate::coord::{AnomalyType, Coordinates, Point};
+use crate::coord::area::{generate_points_in_area, generate_points_in_area_async, Area};
+use crate::coord::detector::{detect_all, detectors, Analysis};
+use crate::coord::kde::{find_peak_and_trough, KernelDensity};
+use crate::coord::point::{
+    generate_points_in_circle, generate_points_in_circle_async,
+    generate_points_in_circle_excluding, generate_points_in_circle_excluding_async,
+};
+use crate::coord::{AnomalyType, Coordinates, DetectionMethod, Point};
 use crate::error::Result;
-use crate::qrng::QrngBackend;
+use crate::qrng::{AsyncQrngBackend, QrngBackend};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -18,9 +25,12 @@ use std::collections::HashMap;
 pub const DEFAULT_POINT_COUNT: usize = 10_000;
 
 /// Results of anomaly detection for a single circle
+///
+/// A polygon search area is analyzed as a single circle with id "area",
+/// whose center and radius are those of the circle enclosing it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CircleResults {
-    /// Circle identifier (e.g., "center", "petal_0")
+    /// Circle identifier (e.g., "center", "petal_0", "area")
     pub id: String,
 
     /// Center of this circle
@@ -44,6 +54,9 @@ pub struct CircleResults {
 /// * `radius` - Radius in meters
 /// * `points` - Pre-generated points to analyze
 /// * `grid_resolution` - Resolution of the density grid
+/// * `method` - How to estimate density (`Kde` still uses the grid's cells
+///   to integrate the expected density over)
+/// * `exclude` - Exclusion zones the points were kept out of
 ///
 /// # Returns
 /// HashMap mapping each anomaly type to its result point
@@ -52,45 +65,62 @@ pub fn find_all_anomalies(
     radius: f64,
     points: &[Coordinates],
     grid_resolution: usize,
+    method: DetectionMethod,
+    exclude: Option<&Area>,
 ) -> HashMap<AnomalyType, Point> {
-    let mut results = HashMap::new();
-
-    // Blind spot is just the first point (or we can pick randomly)
-    // In practice, for blind spot we'd only generate 1 point, but since
-    // we're generating many for analysis, we just use the first one
-    if !points.is_empty() {
-        results.insert(AnomalyType::BlindSpot, Point::new(points[0]));
-    }
-
-    // Build density grid for attractor/void/power analysis
     let mut grid = DensityGrid::new(center, radius, grid_resolution);
-    grid.add_points(points);
-
-    // Find attractor (densest)
-    if let Some(cell) = find_densest_cell(&grid) {
-        results.insert(
-            AnomalyType::Attractor,
-            Point::with_z_score(cell.coords, cell.z_score),
-        );
-    }
-
-    // Find void (emptiest)
-    if let Some(cell) = find_emptiest_cell(&grid) {
-        results.insert(
-            AnomalyType::Void,
-            Point::with_z_score(cell.coords, cell.z_score),
-        );
+    if let Some(zones) = exclude {
+        grid.exclude(zones);
     }
+    anomalies_by_method(grid, points, method)
+}
 
-    // Find power (most anomalous)
-    if let Some((cell, is_attractor)) = find_most_anomalous_cell(&grid) {
-        results.insert(
-            AnomalyType::Power,
-            Point::power(cell.coords, cell.z_score, is_attractor),
-        );
+/// Analyze a set of points generated in a polygon area
+///
+/// Same as `find_all_anomalies`, with the density grid clipped to the area.
+pub fn find_all_anomalies_in_area(
+    area: &Area,
+    points: &[Coordinates],
+    grid_resolution: usize,
+    method: DetectionMethod,
+    exclude: Option<&Area>,
+) -> HashMap<AnomalyType, Point> {
+    let mut grid = DensityGrid::for_area(area, grid_resolution);
+    if let Some(zones) = exclude {
+        grid.exclude(zones);
     }
+    anomalies_by_method(grid, points, method)
+}
 
-    results
+/// Run every registered detector over the area of `grid`
+///
+/// The peak and trough detectors see come from `method`.
+fn anomalies_by_method(
+    mut grid: DensityGrid,
+    points: &[Coordinates],
+    method: DetectionMethod,
+) -> HashMap<AnomalyType, Point> {
+    grid.add_points(points);
+    let (peak, trough) = match method {
+        DetectionMethod::Grid => (
+            find_densest_cell(&grid).map(|cell| Point::with_z_score(cell.coords, cell.z_score)),
+            find_emptiest_cell(&grid).map(|cell| Point::with_z_score(cell.coords, cell.z_score)),
+        ),
+        DetectionMethod::Kde => match find_peak_and_trough(&KernelDensity::new(&grid, points)) {
+            Some((peak, trough)) => (
+                Some(Point::with_z_score(peak.coords, peak.z_score)),
+                Some(Point::with_z_score(trough.coords, trough.z_score)),
+            ),
+            None => (None, None),
+        },
+    };
+
+    detect_all(&Analysis {
+        points,
+        grid: &grid,
+        peak,
+        trough,
+    })
 }
 
 /// Generate points and analyze a single circle
@@ -101,33 +131,149 @@ pub fn find_all_anomalies(
 /// * `radius` - Radius in meters
 /// * `point_count` - Number of points to generate
 /// * `grid_resolution` - Resolution of the density grid
+/// * `method` - How to estimate density
 /// * `include_points` - Whether to include all points in the result
+/// * `exclude` - Exclusion zones to keep points out of
 /// * `rng` - Random number generator backend
 ///
 /// # Returns
 /// CircleResults with all anomaly types
+#[allow(clippy::too_many_arguments)]
 pub fn analyze_circle(
     id: &str,
     center: Coordinates,
     radius: f64,
     point_count: usize,
     grid_resolution: usize,
+    method: DetectionMethod,
     include_points: bool,
+    exclude: Option<&Area>,
     rng: &dyn QrngBackend,
 ) -> Result<CircleResults> {
     // Generate random points
-    let points = generate_points_in_circle(center, radius, point_count, rng)?;
+    let points = match exclude {
+        Some(zones) => generate_points_in_circle_excluding(center, radius, point_count, zones, rng)?,
+        None => generate_points_in_circle(center, radius, point_count, rng)?,
+    };
+
+    Ok(analyze_points(id, center, radius, points, grid_resolution, method, include_points, exclude))
+}
+
+/// Generate points and analyze a single circle without blocking the runtime
+///
+/// Same as `analyze_circle`, drawing entropy from an async backend.
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_circle_async(
+    id: &str,
+    center: Coordinates,
+    radius: f64,
+    point_count: usize,
+    grid_resolution: usize,
+    method: DetectionMethod,
+    include_points: bool,
+    exclude: Option<&Area>,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<CircleResults> {
+    let points = match exclude {
+        Some(zones) => {
+            generate_points_in_circle_excluding_async(center, radius, point_count, zones, rng)
+                .await?
+        }
+        None => generate_points_in_circle_async(center, radius, point_count, rng).await?,
+    };
+
+    Ok(analyze_points(id, center, radius, points, grid_resolution, method, include_points, exclude))
+}
+
+/// Generate points and analyze a polygon area
+///
+/// # Arguments
+/// * `id` - Identifier for the result (e.g., "area")
+/// * `area` - Area to search
+/// * `point_count` - Number of points to generate
+/// * `grid_resolution` - Resolution of the density grid
+/// * `method` - How to estimate density
+/// * `include_points` - Whether to include all points in the result
+/// * `exclude` - Exclusion zones to keep points out of
+/// * `rng` - Random number generator backend
+#[allow(clippy::too_many_arguments)]
+pub fn analyze_area(
+    id: &str,
+    area: &Area,
+    point_count: usize,
+    grid_resolution: usize,
+    method: DetectionMethod,
+    include_points: bool,
+    exclude: Option<&Area>,
+    rng: &dyn QrngBackend,
+) -> Result<CircleResults> {
+    let points = generate_points_in_area(area, point_count, exclude, rng)?;
+
+    Ok(analyze_area_points(id, area, points, grid_resolution, method, include_points, exclude))
+}
+
+/// Generate points and analyze a polygon area without blocking the runtime
+///
+/// Same as `analyze_area`, drawing entropy from an async backend.
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_area_async(
+    id: &str,
+    area: &Area,
+    point_count: usize,
+    grid_resolution: usize,
+    method: DetectionMethod,
+    include_points: bool,
+    exclude: Option<&Area>,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<CircleResults> {
+    let points = generate_points_in_area_async(area, point_count, exclude, rng).await?;
+
+    Ok(analyze_area_points(id, area, points, grid_resolution, method, include_points, exclude))
+}
 
+/// Analyze already-generated points for a polygon area
+fn analyze_area_points(
+    id: &str,
+    area: &Area,
+    points: Vec<Coordinates>,
+    grid_resolution: usize,
+    method: DetectionMethod,
+    include_points: bool,
+    exclude: Option<&Area>,
+) -> CircleResults {
+    let anomalies = find_all_anomalies_in_area(area, &points, grid_resolution, method, exclude);
+
+    CircleResults {
+        id: id.to_string(),
+        center: area.center(),
+        radius: area.radius(),
+        anomalies,
+        points: if include_points { Some(points) } else { None },
+    }
+}
+
+/// Analyze already-generated points for a single circle
+#[allow(clippy::too_many_arguments)]
+fn analyze_points(
+    id: &str,
+    center: Coordinates,
+    radius: f64,
+    points: Vec<Coordinates>,
+    grid_resolution: usize,
+    method: DetectionMethod,
+    include_points: bool,
+    exclude: Option<&Area>,
+) -> CircleResults {
     // Find all anomalies
-    let anomalies = find_all_anomalies(center, radius, &points, grid_resolution);
+    let anomalies = find_all_anomalies(center, radius, &points, grid_resolution, method, exclude);
 
-    Ok(CircleResults {
+    CircleResults {
         id: id.to_string(),
         center,
         radius,
         anomalies,
         points: if include_points { Some(points) } else { None },
-    })
+    }
 }
 
 /// Find the winner for a specific anomaly type across multiple circles
@@ -137,43 +283,19 @@ pub fn analyze_circle(
 /// * `anomaly_type` - Which anomaly type to find the winner for
 ///
 /// # Returns
-/// Tuple of (winning circle id, winning point)
+/// Tuple of (winning circle id, winning point), or `None` if no circle has
+/// the anomaly or no detector is registered for it
 pub fn find_winner(
     circles: &[CircleResults],
     anomaly_type: AnomalyType,
 ) -> Option<(String, Point)> {
+    let detector = anomaly_type.detector()?;
     let mut best: Option<(String, Point)> = None;
 
     for circle in circles {
         if let Some(point) = circle.anomalies.get(&anomaly_type) {
-            let dominated = match anomaly_type {
-                AnomalyType::BlindSpot => {
-                    // For blind spot, just take the first one (or could be random)
-                    best.is_some()
-                }
-                AnomalyType::Attractor => {
-                    // Higher z-score is better (more dense)
-                    best.as_ref().is_some_and(|(_, p)| {
-                        p.z_score.unwrap_or(f64::NEG_INFINITY)
-                            >= point.z_score.unwrap_or(f64::NEG_INFINITY)
-                    })
-                }
-                AnomalyType::Void => {
-                    // Lower z-score is better (more sparse)
-                    best.as_ref().is_some_and(|(_, p)| {
-                        p.z_score.unwrap_or(f64::INFINITY)
-                            <= point.z_score.unwrap_or(f64::INFINITY)
-                    })
-                }
-                AnomalyType::Power => {
-                    // Higher absolute z-score is better
-                    best.as_ref().is_some_and(|(_, p)| {
-                        p.z_score.unwrap_or(0.0).abs() >= point.z_score.unwrap_or(0.0).abs()
-                    })
-                }
-            };
-
-            if !dominated {
+            let better = best.as_ref().is_none_or(|(_, p)| detector.beats(point, p));
+            if better {
                 best = Some((circle.id.clone(), point.clone()));
             }
         }
@@ -186,12 +308,8 @@ pub fn find_winner(
 pub fn find_all_winners(circles: &[CircleResults]) -> HashMap<AnomalyType, (String, Point)> {
     let mut winners = HashMap::new();
 
-    for anomaly_type in [
-        AnomalyType::BlindSpot,
-        AnomalyType::Attractor,
-        AnomalyType::Void,
-        AnomalyType::Power,
-    ] {
+    for detector in detectors() {
+        let anomaly_type = detector.anomaly_type();
         if let Some(winner) = find_winner(circles, anomaly_type) {
             winners.insert(anomaly_type, winner);
         }
@@ -212,34 +330,58 @@ mod tests {
         let radius = 1000.0;
         let points = generate_points_in_circle(center, radius, 10000, &backend).unwrap();
 
-        let anomalies = find_all_anomalies(center, radius, &points, 50);
+        let anomalies = find_all_anomalies(center, radius, &points, 50, DetectionMethod::Grid, None);
 
         // Should have all four anomaly types
-        assert!(anomalies.contains_key(&AnomalyType::BlindSpot));
-        assert!(anomalies.contains_key(&AnomalyType::Attractor));
-        assert!(anomalies.contains_key(&AnomalyType::Void));
-        assert!(anomalies.contains_key(&AnomalyType::Power));
+        assert!(anomalies.contains_key(&AnomalyType::BLIND_SPOT));
+        assert!(anomalies.contains_key(&AnomalyType::ATTRACTOR));
+        assert!(anomalies.contains_key(&AnomalyType::VOID));
+        assert!(anomalies.contains_key(&AnomalyType::POWER));
 
         // Attractor should have positive z-score
-        let attractor = anomalies.get(&AnomalyType::Attractor).unwrap();
+        let attractor = anomalies.get(&AnomalyType::ATTRACTOR).unwrap();
         assert!(attractor.z_score.unwrap() > 0.0);
 
         // Void should have negative z-score
-        let void = anomalies.get(&AnomalyType::Void).unwrap();
+        let void = anomalies.get(&AnomalyType::VOID).unwrap();
         assert!(void.z_score.unwrap() < 0.0);
 
         // Power should have is_attractor set
-        let power = anomalies.get(&AnomalyType::Power).unwrap();
+        let power = anomalies.get(&AnomalyType::POWER).unwrap();
         assert!(power.is_attractor.is_some());
     }
 
+    #[test]
+    fn test_find_all_anomalies_kde() {
+        let backend = SeededPseudoBackend::new(42);
+        let center = Coordinates::new(40.7128, -74.0060);
+        let radius = 1000.0;
+        let points = generate_points_in_circle(center, radius, 10000, &backend).unwrap();
+
+        let anomalies = find_all_anomalies(center, radius, &points, 50, DetectionMethod::Kde, None);
+        let grid = find_all_anomalies(center, radius, &points, 50, DetectionMethod::Grid, None);
+
+        assert_eq!(anomalies.len(), 4);
+        let attractor = anomalies.get(&AnomalyType::ATTRACTOR).unwrap();
+        let void = anomalies.get(&AnomalyType::VOID).unwrap();
+        assert!(attractor.z_score.unwrap() > 0.0);
+        assert!(void.z_score.unwrap() < 0.0);
+        // Found at continuous positions rather than cell centers
+        assert_ne!(attractor.coords, grid[&AnomalyType::ATTRACTOR].coords);
+
+        let power = anomalies.get(&AnomalyType::POWER).unwrap();
+        let strongest = attractor.z_score.unwrap().abs().max(void.z_score.unwrap().abs());
+        assert_eq!(power.z_score.unwrap().abs(), strongest);
+        assert_eq!(power.is_attractor, Some(power.z_score.unwrap() > 0.0));
+    }
+
     #[test]
     fn test_analyze_circle() {
         let backend = SeededPseudoBackend::new(42);
         let center = Coordinates::new(40.7128, -74.0060);
         let radius = 1000.0;
 
-        let result = analyze_circle("center", center, radius, 10000, 50, false, &backend).unwrap();
+        let result = analyze_circle("center", center, radius, 10000, 50, DetectionMethod::Grid, false, None, &backend).unwrap();
 
         assert_eq!(result.id, "center");
         assert_eq!(result.center.lat, center.lat);
@@ -256,13 +398,38 @@ mod tests {
         let center = Coordinates::new(40.7128, -74.0060);
         let radius = 1000.0;
 
-        let result = analyze_circle("center", center, radius, 1000, 50, true, &backend).unwrap();
+        let result = analyze_circle("center", center, radius, 1000, 50, DetectionMethod::Grid, true, None, &backend).unwrap();
 
         // Should include points
         assert!(result.points.is_some());
         assert_eq!(result.points.as_ref().unwrap().len(), 1000);
     }
 
+    #[test]
+    fn test_analyze_area() {
+        let backend = SeededPseudoBackend::new(42);
+        // An L-shaped area, so the enclosing circle has plenty outside it
+        let area = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[
+                [-74.02, 40.70], [-74.00, 40.70], [-74.00, 40.705],
+                [-74.015, 40.705], [-74.015, 40.72], [-74.02, 40.72], [-74.02, 40.70],
+            ]],
+        }))
+        .unwrap();
+
+        let result = analyze_area("area", &area, 5000, 50, DetectionMethod::Grid, true, None, &backend).unwrap();
+
+        assert_eq!(result.id, "area");
+        assert_eq!(result.center, area.center());
+        assert_eq!(result.points.as_ref().unwrap().len(), 5000);
+        assert_eq!(result.anomalies.len(), 4);
+        // Every anomaly is a cell inside the area
+        for point in result.anomalies.values() {
+            assert!(area.contains(point.coords), "{:?} is outside the area", point);
+        }
+    }
+
     #[test]
     fn test_find_winners() {
         let backend = SeededPseudoBackend::new(42);
@@ -270,18 +437,18 @@ mod tests {
         let radius = 1000.0;
 
         // Create two circles
-        let circle1 = analyze_circle("center", center, radius, 5000, 50, false, &backend).unwrap();
+        let circle1 = analyze_circle("center", center, radius, 5000, 50, DetectionMethod::Grid, false, None, &backend).unwrap();
 
         let backend2 = SeededPseudoBackend::new(123);
-        let circle2 = analyze_circle("petal_0", center, radius, 5000, 50, false, &backend2).unwrap();
+        let circle2 = analyze_circle("petal_0", center, radius, 5000, 50, DetectionMethod::Grid, false, None, &backend2).unwrap();
 
         let circles = vec![circle1, circle2];
         let winners = find_all_winners(&circles);
 
         // Should have winners for all types
-        assert!(winners.contains_key(&AnomalyType::BlindSpot));
-        assert!(winners.contains_key(&AnomalyType::Attractor));
-        assert!(winners.contains_key(&AnomalyType::Void));
-        assert!(winners.contains_key(&AnomalyType::Power));
+        assert!(winners.contains_key(&AnomalyType::BLIND_SPOT));
+        assert!(winners.contains_key(&AnomalyType::ATTRACTOR));
+        assert!(winners.contains_key(&AnomalyType::VOID));
+        assert!(winners.contains_key(&AnomalyType::POWER));
     }
 }
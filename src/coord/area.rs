@@ -0,0 +1,623 @@
+//! Polygon search areas
+//!
+//! Lets a generation explore a GeoJSON polygon or multipolygon (a park, a
+//! neighborhood boundary, a hand-drawn shape) instead of a circle. Points are
+//! drawn uniformly over a spherical cap enclosing the area and rejected if
+//! they fall outside it, so they stay uniform on the sphere.
+//!
+//! Areas also describe exclusion zones (lakes, military bases, private
+//! property): points landing in them are rejected the same way, and the
+//! density grid leaves their cells out.
+//!
+//! As in RFC 7946, edges are straight lines in longitude/latitude and a
+//! polygon crossing ±180° must be split into a multipolygon.
+
+use crate::coord::point::{haversine_distance, Sampler, EARTH_RADIUS_METERS};
+use crate::coord::Coordinates;
+use crate::error::{Error, Result};
+use crate::qrng::{AsyncQrngBackend, FloatPrecision, QrngBackend};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::f64::consts::PI;
+use std::path::Path;
+
+/// Points interpolated along each edge when fitting the enclosing cap
+const EDGE_STEPS: usize = 8;
+
+/// A search area of one or more polygons
+///
+/// (De)serializes as a GeoJSON `Polygon` or `MultiPolygon` geometry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Value", into = "Value")]
+pub struct Area {
+    /// Polygons, each an exterior ring followed by any holes (rings closed)
+    polygons: Vec<Vec<Vec<Coordinates>>>,
+    /// Bounding box of each polygon's exterior ring, checked before the rings
+    bounds: Vec<Bounds>,
+    /// Center of the enclosing cap
+    center: Coordinates,
+    /// Radius of the enclosing cap in meters
+    radius: f64,
+    /// Surface area in square meters
+    square_meters: f64,
+}
+
+impl Area {
+    /// Build an area from a GeoJSON object
+    ///
+    /// Accepts `Polygon` and `MultiPolygon` geometries, a `Feature` holding
+    /// one, or a `FeatureCollection`/`GeometryCollection` whose polygons
+    /// together make up the area.
+    pub fn from_geojson(value: &Value) -> Result<Self> {
+        let mut polygons = Vec::new();
+        collect_polygons(value, &mut polygons)?;
+        if polygons.is_empty() {
+            return Err(Error::InvalidArea("GeoJSON holds no polygons".to_string()));
+        }
+        Self::from_polygons(polygons)
+    }
+
+    /// Read an area from a GeoJSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidArea(format!("Can't read {}: {}", path.display(), e))
+        })?;
+        let value: Value = serde_json::from_str(&contents).map_err(|e| {
+            Error::InvalidArea(format!("{} isn't valid JSON: {}", path.display(), e))
+        })?;
+        Self::from_geojson(&value)
+    }
+
+    /// Read and combine the areas in several GeoJSON files
+    ///
+    /// Returns `None` if there are no paths.
+    pub fn load_all<P: AsRef<Path>>(paths: &[P]) -> Result<Option<Self>> {
+        let areas = paths
+            .iter()
+            .map(|path| Self::load(path.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        Self::union(areas)
+    }
+
+    /// Combine areas into one holding all their polygons
+    ///
+    /// Returns `None` if there are no areas.
+    pub fn union(areas: impl IntoIterator<Item = Area>) -> Result<Option<Self>> {
+        let polygons: Vec<_> = areas.into_iter().flat_map(|area| area.polygons).collect();
+        if polygons.is_empty() {
+            return Ok(None);
+        }
+        Self::from_polygons(polygons).map(Some)
+    }
+
+    fn from_polygons(polygons: Vec<Vec<Vec<Coordinates>>>) -> Result<Self> {
+        let square_meters: f64 = polygons
+            .iter()
+            .map(|rings| {
+                let holes: f64 = rings[1..].iter().map(|ring| ring_area(ring)).sum();
+                (ring_area(&rings[0]) - holes).max(0.0)
+            })
+            .sum();
+        if square_meters <= 0.0 {
+            return Err(Error::InvalidArea("Polygons enclose no area".to_string()));
+        }
+
+        let exteriors: Vec<&[Coordinates]> = polygons.iter().map(|rings| rings[0].as_slice()).collect();
+        let center = mean_direction(&exteriors);
+        let radius = exteriors
+            .iter()
+            .flat_map(|ring| densify(ring))
+            .map(|point| haversine_distance(center, point))
+            .fold(0.0, f64::max);
+        // A little slack for edges bowing out between interpolated points
+        let radius = (radius * 1.001 + 1.0).min(PI * EARTH_RADIUS_METERS);
+
+        let bounds = exteriors.iter().map(|ring| Bounds::of(ring)).collect();
+
+        Ok(Self {
+            polygons,
+            bounds,
+            center,
+            radius,
+            square_meters,
+        })
+    }
+
+    /// Center of the circle enclosing the area
+    pub fn center(&self) -> Coordinates {
+        self.center
+    }
+
+    /// Radius in meters of the circle enclosing the area
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Surface area in square meters
+    pub fn square_meters(&self) -> f64 {
+        self.square_meters
+    }
+
+    /// Number of polygons making up the area
+    pub fn polygon_count(&self) -> usize {
+        self.polygons.len()
+    }
+
+    /// Whether a point lies inside the area (and not in a hole)
+    pub fn contains(&self, point: Coordinates) -> bool {
+        self.polygons.iter().zip(&self.bounds).any(|(rings, bounds)| {
+            bounds.contains(point)
+                && ring_contains(&rings[0], point)
+                && !rings[1..].iter().any(|hole| ring_contains(hole, point))
+        })
+    }
+
+    /// The area as a GeoJSON geometry
+    pub fn to_geojson(&self) -> Value {
+        let positions = |ring: &Vec<Coordinates>| -> Vec<[f64; 2]> {
+            ring.iter().map(|c| [c.lng, c.lat]).collect()
+        };
+        let polygon = |rings: &Vec<Vec<Coordinates>>| -> Vec<Vec<[f64; 2]>> {
+            rings.iter().map(positions).collect()
+        };
+        match self.polygons.as_slice() {
+            [only] => json!({ "type": "Polygon", "coordinates": polygon(only) }),
+            all => json!({
+                "type": "MultiPolygon",
+                "coordinates": all.iter().map(polygon).collect::<Vec<_>>(),
+            }),
+        }
+    }
+
+    /// Bytes of entropy generating `points` points outside `exclude`
+    /// usually draws
+    ///
+    /// Rejection sampling makes the exact amount random; this is the first
+    /// round's draw, which covers the whole generation almost every time.
+    /// Fails for areas too sparse to sample (see `point::MIN_ACCEPTANCE`).
+    pub fn entropy_bytes_needed(
+        &self,
+        points: usize,
+        exclude: Option<&Area>,
+        precision: FloatPrecision,
+    ) -> Result<u64> {
+        Ok(Sampler::new(self.center, self.radius, Some(self), exclude)?.entropy_bytes_needed(points, precision))
+    }
+
+    /// Fraction of points drawn over the enclosing cap that land inside
+    pub(crate) fn acceptance(&self) -> f64 {
+        let cap_angle = self.radius / EARTH_RADIUS_METERS;
+        let cap_area = 2.0 * PI * EARTH_RADIUS_METERS.powi(2) * (1.0 - cap_angle.cos());
+        (self.square_meters / cap_area).clamp(f64::MIN_POSITIVE, 1.0)
+    }
+}
+
+impl TryFrom<Value> for Area {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        Self::from_geojson(&value)
+    }
+}
+
+impl From<Area> for Value {
+    fn from(area: Area) -> Self {
+        area.to_geojson()
+    }
+}
+
+/// Generate random points uniformly distributed within an area
+///
+/// Draws candidates over the enclosing cap in rounds until `count` land
+/// inside it (and outside `exclude`), so a given byte stream always gives
+/// the same points.
+pub fn generate_points_in_area(
+    area: &Area,
+    count: usize,
+    exclude: Option<&Area>,
+    rng: &dyn QrngBackend,
+) -> Result<Vec<Coordinates>> {
+    Sampler::new(area.center, area.radius, Some(area), exclude)?.sample(count, rng)
+}
+
+/// Generate random points within an area without blocking the async runtime
+///
+/// Same as `generate_points_in_area`, drawing entropy from an async backend.
+pub async fn generate_points_in_area_async(
+    area: &Area,
+    count: usize,
+    exclude: Option<&Area>,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<Vec<Coordinates>> {
+    Sampler::new(area.center, area.radius, Some(area), exclude)?
+        .sample_async(count, rng)
+        .await
+}
+
+/// Gather the polygons of a GeoJSON object into `polygons`
+fn collect_polygons(value: &Value, polygons: &mut Vec<Vec<Vec<Coordinates>>>) -> Result<()> {
+    let members = |key: &str| -> Result<&Vec<Value>> {
+        value
+            .get(key)
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::InvalidArea(format!("Missing \"{}\" array", key)))
+    };
+
+    match value.get("type").and_then(Value::as_str) {
+        Some("Polygon") => polygons.push(parse_polygon(value.get("coordinates"))?),
+        Some("MultiPolygon") => {
+            for polygon in members("coordinates")? {
+                polygons.push(parse_polygon(Some(polygon))?);
+            }
+        }
+        Some("Feature") => match value.get("geometry") {
+            Some(geometry) if !geometry.is_null() => collect_polygons(geometry, polygons)?,
+            _ => return Err(Error::InvalidArea("Feature has no geometry".to_string())),
+        },
+        Some("FeatureCollection") => {
+            for feature in members("features")? {
+                collect_polygons(feature, polygons)?;
+            }
+        }
+        Some("GeometryCollection") => {
+            for geometry in members("geometries")? {
+                collect_polygons(geometry, polygons)?;
+            }
+        }
+        Some(other) => {
+            return Err(Error::InvalidArea(format!(
+                "{} doesn't enclose an area; use a Polygon or MultiPolygon",
+                other
+            )))
+        }
+        None => return Err(Error::InvalidArea("Not a GeoJSON object".to_string())),
+    }
+    Ok(())
+}
+
+/// Parse a polygon's rings, closing any left open
+fn parse_polygon(coordinates: Option<&Value>) -> Result<Vec<Vec<Coordinates>>> {
+    let rings = coordinates
+        .and_then(Value::as_array)
+        .filter(|rings| !rings.is_empty())
+        .ok_or_else(|| Error::InvalidArea("Polygon has no rings".to_string()))?;
+
+    rings
+        .iter()
+        .map(|ring| {
+            let mut positions = ring
+                .as_array()
+                .ok_or_else(|| Error::InvalidArea("Ring isn't an array of positions".to_string()))?
+                .iter()
+                .map(parse_position)
+                .collect::<Result<Vec<_>>>()?;
+            if positions.first() != positions.last() {
+                positions.push(positions[0]);
+            }
+            if positions.len() < 4 {
+                return Err(Error::InvalidArea(
+                    "Ring needs at least 3 distinct positions".to_string(),
+                ));
+            }
+            Ok(positions)
+        })
+        .collect()
+}
+
+/// Parse a `[lng, lat]` position (any altitude is ignored)
+fn parse_position(value: &Value) -> Result<Coordinates> {
+    let position = value.as_array().filter(|p| p.len() >= 2);
+    let number = |i: usize| position.and_then(|p| p[i].as_f64());
+    match (number(0), number(1)) {
+        (Some(lng), Some(lat)) => {
+            let coords = Coordinates::new(lat, lng);
+            coords.validate()?;
+            Ok(coords)
+        }
+        _ => Err(Error::InvalidArea(format!("Invalid position: {}", value))),
+    }
+}
+
+/// Area of a closed ring in square meters
+///
+/// Chamberlain & Duquette's approximation for lng/lat edges on the sphere.
+fn ring_area(ring: &[Coordinates]) -> f64 {
+    let sum: f64 = ring
+        .windows(2)
+        .map(|edge| {
+            let (a, b) = (edge[0], edge[1]);
+            (b.lng - a.lng).to_radians()
+                * (2.0 + a.lat.to_radians().sin() + b.lat.to_radians().sin())
+        })
+        .sum();
+    (sum * EARTH_RADIUS_METERS.powi(2) / 2.0).abs()
+}
+
+/// Longitude/latitude box around a ring
+///
+/// Edges are straight in lng/lat, so nothing outside the box of a ring's
+/// vertices can be inside the ring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    south: f64,
+    west: f64,
+    north: f64,
+    east: f64,
+}
+
+impl Bounds {
+    fn of(ring: &[Coordinates]) -> Self {
+        ring.iter().fold(
+            Self {
+                south: f64::INFINITY,
+                west: f64::INFINITY,
+                north: f64::NEG_INFINITY,
+                east: f64::NEG_INFINITY,
+            },
+            |b, c| Self {
+                south: b.south.min(c.lat),
+                west: b.west.min(c.lng),
+                north: b.north.max(c.lat),
+                east: b.east.max(c.lng),
+            },
+        )
+    }
+
+    fn contains(&self, point: Coordinates) -> bool {
+        (self.south..=self.north).contains(&point.lat) && (self.west..=self.east).contains(&point.lng)
+    }
+}
+
+/// Even-odd test of a point against a closed ring, in lng/lat
+fn ring_contains(ring: &[Coordinates], point: Coordinates) -> bool {
+    let mut inside = false;
+    for edge in ring.windows(2) {
+        let (a, b) = (edge[0], edge[1]);
+        if (a.lat > point.lat) != (b.lat > point.lat) {
+            let lng = a.lng + (point.lat - a.lat) / (b.lat - a.lat) * (b.lng - a.lng);
+            if point.lng < lng {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Mean direction of the rings' vertices on the unit sphere
+fn mean_direction(rings: &[&[Coordinates]]) -> Coordinates {
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    for ring in rings {
+        // The closing position repeats the first
+        for c in &ring[1..] {
+            let (lat, lng) = (c.lat.to_radians(), c.lng.to_radians());
+            x += lat.cos() * lng.cos();
+            y += lat.cos() * lng.sin();
+            z += lat.sin();
+        }
+    }
+    if x * x + y * y + z * z < 1e-12 {
+        return rings[0][0];
+    }
+    Coordinates::new(
+        z.atan2((x * x + y * y).sqrt()).to_degrees(),
+        y.atan2(x).to_degrees(),
+    )
+}
+
+/// A ring's vertices plus points interpolated along each edge
+fn densify(ring: &[Coordinates]) -> Vec<Coordinates> {
+    ring.windows(2)
+        .flat_map(|edge| {
+            let (a, b) = (edge[0], edge[1]);
+            (0..EDGE_STEPS).map(move |i| {
+                let t = i as f64 / EDGE_STEPS as f64;
+                Coordinates::new(a.lat + t * (b.lat - a.lat), a.lng + t * (b.lng - a.lng))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::point::{draws_for, MIN_ACCEPTANCE};
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::SyncAdapter;
+
+    /// A lng/lat box as a GeoJSON polygon
+    fn square(west: f64, south: f64, east: f64, north: f64) -> Value {
+        json!({
+            "type": "Polygon",
+            "coordinates": [[[west, south], [east, south], [east, north], [west, north], [west, south]]],
+        })
+    }
+
+    #[test]
+    fn test_parse_geojson_forms() {
+        let polygon = square(-0.2, 51.4, -0.1, 51.5);
+        let feature = json!({ "type": "Feature", "properties": {}, "geometry": polygon });
+        let collection = json!({ "type": "FeatureCollection", "features": [feature] });
+        for value in [&polygon, &feature, &collection] {
+            let area = Area::from_geojson(value).unwrap();
+            assert_eq!(area.polygon_count(), 1);
+            assert!(area.contains(Coordinates::new(51.45, -0.15)));
+        }
+
+        let multi = json!({
+            "type": "MultiPolygon",
+            "coordinates": [
+                square(0.0, 0.0, 0.01, 0.01)["coordinates"],
+                square(0.02, 0.0, 0.03, 0.01)["coordinates"],
+            ],
+        });
+        assert_eq!(Area::from_geojson(&multi).unwrap().polygon_count(), 2);
+
+        // Open rings are closed; altitudes are ignored
+        let open = json!({
+            "type": "Polygon",
+            "coordinates": [[[10.0, 10.0, 5.0], [10.1, 10.0, 5.0], [10.0, 10.1, 5.0]]],
+        });
+        assert!(Area::from_geojson(&open).unwrap().contains(Coordinates::new(10.02, 10.02)));
+    }
+
+    #[test]
+    fn test_rejects_invalid_geojson() {
+        let cases = [
+            json!({ "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 1.0]] }),
+            json!({ "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 1.0]]] }),
+            json!({ "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]] }),
+            json!({ "type": "Feature", "geometry": null }),
+            json!({ "type": "FeatureCollection", "features": [] }),
+            json!([1, 2, 3]),
+        ];
+        for value in cases {
+            assert!(
+                matches!(Area::from_geojson(&value), Err(Error::InvalidArea(_))),
+                "{} was accepted",
+                value
+            );
+        }
+        let out_of_range = square(0.0, 89.0, 1.0, 91.0);
+        assert!(matches!(
+            Area::from_geojson(&out_of_range),
+            Err(Error::InvalidCoordinates(_))
+        ));
+    }
+
+    #[test]
+    fn test_contains_respects_holes() {
+        let donut = json!({
+            "type": "Polygon",
+            "coordinates": [
+                [[0.0, 0.0], [3.0, 0.0], [3.0, 3.0], [0.0, 3.0], [0.0, 0.0]],
+                [[1.0, 1.0], [2.0, 1.0], [2.0, 2.0], [1.0, 2.0], [1.0, 1.0]],
+            ],
+        });
+        let area = Area::from_geojson(&donut).unwrap();
+        assert!(area.contains(Coordinates::new(0.5, 0.5)));
+        assert!(!area.contains(Coordinates::new(1.5, 1.5)));
+        assert!(!area.contains(Coordinates::new(3.5, 0.5)));
+        // The hole takes a ninth of the square
+        let outer = Area::from_geojson(&square(0.0, 0.0, 3.0, 3.0)).unwrap();
+        let ratio = area.square_meters() / outer.square_meters();
+        assert!((ratio - 8.0 / 9.0).abs() < 0.001, "ratio {}", ratio);
+    }
+
+    #[test]
+    fn test_square_meters_and_enclosing_circle() {
+        // 0.01° at the equator is about 1113 m either way
+        let area = Area::from_geojson(&square(0.0, 0.0, 0.01, 0.01)).unwrap();
+        let expected = 1113.2 * 1113.2;
+        assert!(
+            (area.square_meters() - expected).abs() / expected < 0.005,
+            "{} m²",
+            area.square_meters()
+        );
+        assert!((area.center().lat - 0.005).abs() < 1e-6);
+        assert!((area.center().lng - 0.005).abs() < 1e-6);
+        let half_diagonal = 1113.2 * std::f64::consts::SQRT_2 / 2.0;
+        assert!(area.radius() >= half_diagonal && area.radius() < half_diagonal * 1.01);
+    }
+
+    #[test]
+    fn test_points_uniform_inside_area() {
+        // High latitude, where a flat lng/lat sampler would visibly skew
+        let south = 60.0_f64;
+        let north = 62.0_f64;
+        let area = Area::from_geojson(&square(10.0, south, 12.0, north)).unwrap();
+        let backend = SeededPseudoBackend::new(42);
+        let count = 20_000;
+        let points = generate_points_in_area(&area, count, None, &backend).unwrap();
+
+        assert_eq!(points.len(), count);
+        assert!(points.iter().all(|&p| area.contains(p)));
+
+        // Area on the sphere grows with sin(lat), so the southern half holds more
+        let middle = (south + north) / 2.0;
+        let sin = |deg: f64| deg.to_radians().sin();
+        let expected = (sin(middle) - sin(south)) / (sin(north) - sin(south));
+        let southern = points.iter().filter(|p| p.lat < middle).count() as f64 / count as f64;
+        assert!(
+            (southern - expected).abs() < 0.015,
+            "{:.3} of points in the southern half, expected {:.3}",
+            southern,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_multipolygon_points_split_by_area() {
+        let multi = json!({
+            "type": "MultiPolygon",
+            "coordinates": [
+                square(0.0, 0.0, 0.01, 0.01)["coordinates"],
+                square(0.03, 0.0, 0.05, 0.01)["coordinates"],
+            ],
+        });
+        let area = Area::from_geojson(&multi).unwrap();
+        let points = generate_points_in_area(&area, 6000, None, &SeededPseudoBackend::new(7)).unwrap();
+
+        assert!(points.iter().all(|p| p.lng < 0.01 || p.lng > 0.03));
+        let west = points.iter().filter(|p| p.lng < 0.01).count() as f64 / 6000.0;
+        assert!((west - 1.0 / 3.0).abs() < 0.03, "{:.3} in the western square", west);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let area = Area::from_geojson(&json!({
+            "type": "Feature",
+            "geometry": square(-74.01, 40.70, -74.00, 40.71),
+        }))
+        .unwrap();
+
+        let json = serde_json::to_value(&area).unwrap();
+        assert_eq!(json["type"], "Polygon");
+        let parsed: Area = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, area);
+    }
+
+    #[test]
+    fn test_entropy_bytes_needed_usually_covers_draw() {
+        let area = Area::from_geojson(&square(0.0, 0.0, 0.01, 0.01)).unwrap();
+        let needed = area.entropy_bytes_needed(1000, None, FloatPrecision::Bits53).unwrap();
+        // A square fills 2/π of its enclosing circle
+        let expected = 1000.0 / (2.0 / PI) * 16.0;
+        assert!(needed as f64 > expected && (needed as f64) < expected * 1.2);
+        assert_eq!(area.entropy_bytes_needed(0, None, FloatPrecision::Bits53).unwrap(), 0);
+
+        // Excluding half the square about doubles it
+        let half = Area::from_geojson(&square(0.005, 0.0, 0.01, 0.01)).unwrap();
+        let excluding = area.entropy_bytes_needed(1000, Some(&half), FloatPrecision::Bits53).unwrap();
+        assert!(excluding as f64 > needed as f64 * 1.8 && (excluding as f64) < needed as f64 * 2.2);
+    }
+
+    #[test]
+    fn test_too_sparse_area_is_rejected() {
+        // Two tiny triangles on opposite sides of the globe
+        let triangle = |lng: f64| vec![vec![[lng, 0.0], [lng + 0.001, 0.0], [lng, 0.001], [lng, 0.0]]];
+        let area = Area::from_geojson(&json!({
+            "type": "MultiPolygon",
+            "coordinates": [triangle(0.0), triangle(179.9)],
+        }))
+        .unwrap();
+        assert!(area.acceptance() < MIN_ACCEPTANCE);
+
+        let err = area.entropy_bytes_needed(10_000, None, FloatPrecision::Bits53).unwrap_err();
+        assert!(matches!(err, Error::InvalidArea(_)), "{}", err);
+        let err = generate_points_in_area(&area, 10_000, None, &SeededPseudoBackend::new(1)).unwrap_err();
+        assert!(matches!(err, Error::InvalidArea(_)), "{}", err);
+
+        assert_eq!(draws_for(10, f64::MIN_POSITIVE), usize::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_generate_points_async_matches_sync() {
+        let area = Area::from_geojson(&square(2.3, 48.85, 2.35, 48.87)).unwrap();
+        let sync_points = generate_points_in_area(&area, 100, None, &SeededPseudoBackend::new(42)).unwrap();
+
+        let adapter = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(42)));
+        let async_points = generate_points_in_area_async(&area, 100, None, &adapter).await.unwrap();
+
+        assert_eq!(sync_points, async_points);
+    }
+}
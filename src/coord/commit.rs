@@ -0,0 +1,440 @@
+//! Commit–reveal generation
+//!
+//! For shared expeditions nobody should be able to regenerate until they like
+//! the result. `Sealed::commit` draws all the entropy a generation will use
+//! up front and publishes a `Commitment`: the parameters plus the SHA-256 of
+//! that entropy. `Sealed::reveal` later runs `flower::generate` on exactly
+//! those bytes and hands out the bytes with the result, so anyone can check
+//! the `Reveal` against the commitment published earlier.
+//!
+//! With exclusion zones the bytes a generation consumes depend on where its
+//! points land, so a commitment draws a margin over the usual amount and the
+//! reveal may leave some of it unused.
+//!
+//! An entropy check tests the draw (up to `ENTROPY_CHECK_MAX_BYTES` of it)
+//! at commit time, so `Reject` refuses to commit rather than fail a reveal
+//! that's already been promised. The revealed result carries the scores for
+//! the bytes it consumed.
+
+use crate::coord::area::Area;
+use crate::coord::flower::{
+    entropy_bytes_needed_excluding, generate, test_entropy_sample, GenerationOptions,
+    GenerationResponse, ENTROPY_CHECK_MAX_BYTES,
+};
+use crate::coord::provenance::{same_winners, sha256_hex};
+use crate::coord::{Coordinates, DetectionMethod, GenerationMode};
+use crate::entropy::EntropyCheck;
+use crate::error::{Error, Result};
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::{AsyncQrngBackend, FloatPrecision, QrngBackend};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Parameters fixed at commit time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitParams {
+    pub lat: f64,
+    pub lng: f64,
+    pub radius: f64,
+    pub points: usize,
+    pub mode: GenerationMode,
+    #[serde(default)]
+    pub method: DetectionMethod,
+    pub grid_resolution: usize,
+    /// Exclusion zones points are kept out of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Area>,
+    /// What to do about the quality of the committed entropy
+    #[serde(default)]
+    pub entropy_check: EntropyCheck,
+}
+
+/// Bytes of entropy to commit for a generation with `params`
+///
+/// Rejection sampling around exclusion zones may need a second round, so
+/// a quarter more than the usual draw is held for it.
+pub fn entropy_bytes_committed(params: &CommitParams, precision: FloatPrecision) -> Result<u64> {
+    let needed = entropy_bytes_needed_excluding(
+        Coordinates::new(params.lat, params.lng),
+        params.radius,
+        params.points,
+        params.mode,
+        params.exclude.as_ref(),
+        precision,
+    )?;
+    Ok(match params.exclude {
+        Some(_) => needed.saturating_add(needed / 4),
+        None => needed,
+    })
+}
+
+/// Published before the result is known
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Commitment {
+    /// ID to reveal by
+    pub id: String,
+    /// When the entropy was drawn
+    pub committed_at: String,
+    pub params: CommitParams,
+    /// Backend the entropy was drawn from
+    pub backend: String,
+    /// Backends that actually supplied it (e.g. every input of a mix)
+    pub entropy_sources: Vec<String>,
+    /// Number of entropy bytes drawn
+    pub entropy_bytes: u64,
+    /// Bits of entropy per float (53 or 32)
+    pub float_bits: u32,
+    /// SHA-256 of the entropy (hex)
+    pub entropy_sha256: String,
+}
+
+/// A revealed generation with the entropy it was derived from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reveal {
+    pub commitment: Commitment,
+    /// The committed entropy (standard base64)
+    pub entropy: String,
+    pub result: GenerationResponse,
+}
+
+impl Reveal {
+    /// Check the result against the commitment
+    ///
+    /// The entropy must hash to the committed digest, the result must use
+    /// the committed parameters and record the digest of the entropy it
+    /// consumed from the start of it, and re-deriving the generation from
+    /// the entropy must give the same winners.
+    pub fn verify(&self) -> Result<bool> {
+        let entropy = base64::engine::general_purpose::STANDARD
+            .decode(&self.entropy)
+            .map_err(|e| Error::Verification(format!("Entropy is not valid base64: {}", e)))?;
+        let commitment = &self.commitment;
+        let params = &commitment.params;
+        let request = &self.result.request;
+
+        let entropy_matches = entropy.len() as u64 == commitment.entropy_bytes
+            && sha256_hex(&entropy) == commitment.entropy_sha256;
+        let provenance_matches = self.result.metadata.provenance.as_ref().is_some_and(|p| {
+            p.bytes <= entropy.len() as u64 && p.sha256 == sha256_hex(&entropy[..p.bytes as usize])
+        });
+        let params_match = request.lat == params.lat
+            && request.lng == params.lng
+            && request.radius == params.radius
+            && request.points == params.points
+            && request.mode == params.mode
+            && request.method == params.method
+            && request.exclude == params.exclude;
+        if !(entropy_matches && provenance_matches && params_match) {
+            return Ok(false);
+        }
+
+        let rerun = derive(commitment, entropy, Vec::new(), None, EntropyCheck::Off)?;
+        Ok(same_winners(&rerun.winners, &self.result.winners))
+    }
+}
+
+/// Committed entropy held until reveal
+pub struct Sealed {
+    commitment: Commitment,
+    entropy: Vec<u8>,
+    extraction: Option<ExtractionStats>,
+    /// Result of the first reveal; later reveals return the same one
+    revealed: Mutex<Option<GenerationResponse>>,
+}
+
+impl Sealed {
+    /// Draw the entropy for a generation and commit to it
+    pub async fn commit(
+        params: CommitParams,
+        backend_name: &str,
+        rng: &dyn AsyncQrngBackend,
+    ) -> Result<Self> {
+        let precision = rng.float_precision();
+        let needed = entropy_bytes_committed(&params, precision)?;
+        let entropy = rng.bytes_async(needed as usize).await?;
+        if params.entropy_check == EntropyCheck::Reject {
+            let sample = entropy[..entropy.len().min(ENTROPY_CHECK_MAX_BYTES)].to_vec();
+            let results = tokio::task::spawn_blocking(move || test_entropy_sample(&sample))
+                .await
+                .map_err(|e| Error::Qrng(format!("Entropy test task failed: {}", e)))?;
+            if let Some(failures) = results.failure_summary() {
+                return Err(Error::EntropyQuality(failures));
+            }
+        }
+
+        let commitment = Commitment {
+            id: uuid::Uuid::new_v4().to_string(),
+            committed_at: chrono::Utc::now().to_rfc3339(),
+            params,
+            backend: backend_name.to_string(),
+            entropy_sources: rng.sources(),
+            entropy_bytes: entropy.len() as u64,
+            float_bits: precision.bits(),
+            entropy_sha256: sha256_hex(&entropy),
+        };
+        Ok(Self {
+            commitment,
+            entropy,
+            extraction: rng.extraction(),
+            revealed: Mutex::new(None),
+        })
+    }
+
+    /// The published commitment
+    pub fn commitment(&self) -> &Commitment {
+        &self.commitment
+    }
+
+    /// Run the generation on the committed entropy
+    pub fn reveal(&self) -> Result<Reveal> {
+        let mut revealed = self.revealed.lock().unwrap();
+        let result = match revealed.as_ref() {
+            Some(result) => result.clone(),
+            None => {
+                // Rejection already happened at commit time
+                let check = match self.commitment.params.entropy_check {
+                    EntropyCheck::Off => EntropyCheck::Off,
+                    _ => EntropyCheck::Warn,
+                };
+                let result = derive(
+                    &self.commitment,
+                    self.entropy.clone(),
+                    self.commitment.entropy_sources.clone(),
+                    self.extraction.clone(),
+                    check,
+                )?;
+                *revealed = Some(result.clone());
+                result
+            }
+        };
+        Ok(Reveal {
+            commitment: self.commitment.clone(),
+            entropy: base64::engine::general_purpose::STANDARD.encode(&self.entropy),
+            result,
+        })
+    }
+}
+
+/// Run the committed generation on `entropy`
+fn derive(
+    commitment: &Commitment,
+    entropy: Vec<u8>,
+    sources: Vec<String>,
+    extraction: Option<ExtractionStats>,
+    entropy_check: EntropyCheck,
+) -> Result<GenerationResponse> {
+    let params = &commitment.params;
+    let rng = Committed {
+        precision: commitment.float_bits.to_string().parse()?,
+        entropy: Mutex::new(entropy.into_iter()),
+        sources,
+        extraction,
+    };
+    let options = GenerationOptions::new(&commitment.backend)
+        .with_points(params.points)
+        .with_grid_resolution(params.grid_resolution)
+        .with_method(params.method)
+        .with_exclude(params.exclude.as_ref())
+        .with_entropy_check(entropy_check);
+    generate(
+        Coordinates::new(params.lat, params.lng),
+        params.radius,
+        params.mode,
+        &options,
+        &rng,
+    )
+}
+
+/// Serves committed bytes in order, reporting the original sources
+struct Committed {
+    precision: FloatPrecision,
+    entropy: Mutex<std::vec::IntoIter<u8>>,
+    sources: Vec<String>,
+    extraction: Option<ExtractionStats>,
+}
+
+impl QrngBackend for Committed {
+    fn name(&self) -> &'static str {
+        "committed"
+    }
+
+    fn description(&self) -> &'static str {
+        "Entropy drawn at commit time"
+    }
+
+    fn sources(&self) -> Vec<String> {
+        if self.sources.is_empty() {
+            vec![self.name().to_string()]
+        } else {
+            self.sources.clone()
+        }
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.precision
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.extraction.clone()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let bytes: Vec<u8> = self.entropy.lock().unwrap().by_ref().take(n).collect();
+        if bytes.len() < n {
+            return Err(Error::Qrng(format!(
+                "Committed entropy exhausted: fewer than {} bytes remaining",
+                n
+            )));
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::AnomalyType;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::SyncAdapter;
+
+    fn params() -> CommitParams {
+        CommitParams {
+            lat: 48.85,
+            lng: 2.35,
+            radius: 5000.0,
+            points: 150,
+            mode: GenerationMode::FlowerPower,
+            method: DetectionMethod::Grid,
+            grid_resolution: 20,
+            exclude: None,
+            entropy_check: EntropyCheck::Off,
+        }
+    }
+
+    async fn sealed(seed: u64) -> Sealed {
+        let rng = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(seed)));
+        Sealed::commit(params(), "pseudo-seeded", &rng).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reveal_uses_committed_entropy() {
+        let sealed = sealed(3).await;
+        let commitment = sealed.commitment().clone();
+        assert_eq!(commitment.entropy_bytes, 7 * 150 * 2 * 8);
+        assert_eq!(commitment.entropy_sources, vec!["pseudo-seeded"]);
+
+        let reveal = sealed.reveal().unwrap();
+        let provenance = reveal.result.metadata.provenance.as_ref().unwrap();
+        assert_eq!(provenance.sha256, commitment.entropy_sha256);
+        assert_eq!(reveal.result.request.backend, "pseudo-seeded");
+        assert!(reveal.verify().unwrap());
+
+        // Revealing again gives the same result, not a fresh one
+        assert_eq!(sealed.reveal().unwrap().result.id, reveal.result.id);
+    }
+
+    #[tokio::test]
+    async fn test_reveal_keeps_out_of_exclusion_zones() {
+        // The eastern half of the search circle
+        let zone = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[2.35, 48.7], [2.6, 48.7], [2.6, 49.0], [2.35, 49.0], [2.35, 48.7]]],
+        }))
+        .unwrap();
+        let params = CommitParams {
+            mode: GenerationMode::Standard,
+            exclude: Some(zone.clone()),
+            ..params()
+        };
+        let rng = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(8)));
+        let sealed = Sealed::commit(params, "pseudo-seeded", &rng).await.unwrap();
+        // About half the candidates are rejected, so about twice the draw
+        assert!(sealed.commitment().entropy_bytes > 2 * 150 * 2 * 8);
+
+        let reveal = sealed.reveal().unwrap();
+        assert_eq!(reveal.result.request.exclude.as_ref(), Some(&zone));
+        assert!(reveal.result.winners.values().all(|w| !zone.contains(w.result.coords)));
+        let provenance = reveal.result.metadata.provenance.as_ref().unwrap();
+        assert!(provenance.bytes <= sealed.commitment().entropy_bytes);
+        assert!(reveal.verify().unwrap());
+
+        // Dropping the zones from the result doesn't verify
+        let mut unzoned = reveal;
+        unzoned.result.request.exclude = None;
+        assert!(!unzoned.verify().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_entropy_check_at_commit() {
+        use crate::entropy::tests::biased_bytes;
+        use crate::qrng::replay::ReplayBackend;
+
+        let params = CommitParams {
+            mode: GenerationMode::Standard,
+            ..params()
+        };
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("biased.bin");
+        std::fs::write(&path, biased_bytes(150 * 2 * 8)).unwrap();
+        let commit = |entropy_check| {
+            let rng = SyncAdapter::from_box(Box::new(ReplayBackend::new(&path)));
+            let params = CommitParams {
+                entropy_check,
+                ..params.clone()
+            };
+            async move { Sealed::commit(params, "replay", &rng).await }
+        };
+
+        assert!(matches!(
+            commit(EntropyCheck::Reject).await,
+            Err(Error::EntropyQuality(_))
+        ));
+        let reveal = commit(EntropyCheck::Warn).await.unwrap().reveal().unwrap();
+        let quality = reveal.result.metadata.entropy_quality.as_ref().unwrap();
+        assert!(!quality.all_passed());
+        assert!(reveal.verify().unwrap());
+        let reveal = commit(EntropyCheck::Off).await.unwrap().reveal().unwrap();
+        assert!(reveal.result.metadata.entropy_quality.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_survives_json_round_trip() {
+        let reveal = sealed(4).await.reveal().unwrap();
+        let json = serde_json::to_string(&reveal).unwrap();
+        let reveal: Reveal = serde_json::from_str(&json).unwrap();
+        assert!(reveal.verify().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_tampering() {
+        let reveal = sealed(5).await.reveal().unwrap();
+
+        // Winners edited after the fact
+        let mut edited = reveal.clone();
+        edited
+            .result
+            .winners
+            .get_mut(&AnomalyType::VOID)
+            .unwrap()
+            .result
+            .coords
+            .lng += 0.01;
+        assert!(!edited.verify().unwrap());
+
+        // Result swapped for one from other entropy
+        let mut swapped = reveal.clone();
+        swapped.result = sealed(6).await.reveal().unwrap().result;
+        assert!(!swapped.verify().unwrap());
+
+        // Entropy swapped to fit a different result
+        let mut regenerated = reveal.clone();
+        regenerated.entropy = sealed(6).await.reveal().unwrap().entropy;
+        assert!(!regenerated.verify().unwrap());
+
+        // Parameters moved
+        let mut moved = reveal;
+        moved.commitment.params.radius = 6000.0;
+        assert!(!moved.verify().unwrap());
+    }
+}
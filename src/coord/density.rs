@@ -1,15 +1,20 @@
 //! Grid-based density analysis
 //!
-//! Divides a circular area into a grid and counts points per cell,
-//! then calculates z-scores for anomaly detection.
+//! Divides a search area (a circle or polygon) into a grid and counts points
+//! per cell, then calculates z-scores for anomaly detection.
+//!
+//! The grid is laid over an azimuthal equal-area projection about the
+//! center, so every cell covers the same ground area wherever the search is,
+//! and points across ±180° bin next to their neighbors.
 
+use crate::coord::area::Area;
+use crate::coord::projection::{planar_distance, AzimuthalEqualArea};
 use crate::coord::Coordinates;
-use std::f64::consts::PI;
 
 /// Default grid resolution (50x50 cells covering the bounding box)
 pub const DEFAULT_GRID_RESOLUTION: usize = 50;
 
-/// A density grid covering a circular area
+/// A density grid covering a search area
 #[derive(Debug)]
 pub struct DensityGrid {
     /// Number of cells in each dimension
@@ -18,13 +23,15 @@ pub struct DensityGrid {
     pub center: Coordinates,
     /// Radius in meters
     pub radius: f64,
+    /// Projection the grid is laid over
+    pub projection: AzimuthalEqualArea,
     /// Point counts per cell [row][col]
     pub cells: Vec<Vec<usize>>,
-    /// Which cells are within the circle
+    /// Which cells are within the search area
     pub in_circle: Vec<Vec<bool>>,
     /// Total number of points added
     pub total_points: usize,
-    /// Size of each cell in meters
+    /// Size of each cell in meters, in the projected plane
     pub cell_size: f64,
 }
 
@@ -36,19 +43,20 @@ impl DensityGrid {
     /// * `radius` - Radius in meters
     /// * `resolution` - Number of cells in each dimension
     pub fn new(center: Coordinates, radius: f64, resolution: usize) -> Self {
-        let cell_size = (2.0 * radius) / resolution as f64;
+        // The circle projects to a circle, a little smaller than `radius`
+        let cell_size = (2.0 * planar_distance(radius)) / resolution as f64;
 
         // Pre-compute which cells are in the circle
         let center_cell = resolution as f64 / 2.0;
         let mut in_circle = vec![vec![false; resolution]; resolution];
 
-        for row in 0..resolution {
-            for col in 0..resolution {
+        for (row, row_cells) in in_circle.iter_mut().enumerate() {
+            for (col, cell) in row_cells.iter_mut().enumerate() {
                 let dx = col as f64 + 0.5 - center_cell;
                 let dy = row as f64 + 0.5 - center_cell;
                 let dist_squared = dx * dx + dy * dy;
                 let max_dist = center_cell;
-                in_circle[row][col] = dist_squared <= max_dist * max_dist;
+                *cell = dist_squared <= max_dist * max_dist;
             }
         }
 
@@ -56,6 +64,7 @@ impl DensityGrid {
             resolution,
             center,
             radius,
+            projection: AzimuthalEqualArea::new(center),
             cells: vec![vec![0; resolution]; resolution],
             in_circle,
             total_points: 0,
@@ -63,28 +72,41 @@ impl DensityGrid {
         }
     }
 
+    /// Create a density grid clipped to a polygon area
+    ///
+    /// The grid spans the area's enclosing circle; only cells whose centers
+    /// fall inside the area count.
+    pub fn for_area(area: &Area, resolution: usize) -> Self {
+        let mut grid = Self::new(area.center(), area.radius(), resolution);
+        for row in 0..resolution {
+            for col in 0..resolution {
+                grid.in_circle[row][col] = area.contains(grid.cell_to_coords(row, col));
+            }
+        }
+        grid
+    }
+
+    /// Leave cells whose centers fall in exclusion zones out of the area
+    ///
+    /// No points can land there, so counting those cells would lower the
+    /// expected count everywhere and show the zones as voids.
+    pub fn exclude(&mut self, zones: &Area) {
+        for row in 0..self.resolution {
+            for col in 0..self.resolution {
+                if self.in_circle[row][col] && zones.contains(self.cell_to_coords(row, col)) {
+                    self.in_circle[row][col] = false;
+                }
+            }
+        }
+    }
+
     /// Add points to the grid
     pub fn add_points(&mut self, points: &[Coordinates]) {
-        const METERS_PER_DEG_LAT: f64 = 111_320.0;
-        let meters_per_deg_lng = METERS_PER_DEG_LAT * (self.center.lat * PI / 180.0).cos();
-
         for point in points {
             // Convert to meters offset from center
-            let dx_meters = (point.lng - self.center.lng) * meters_per_deg_lng;
-            let dy_meters = (point.lat - self.center.lat) * METERS_PER_DEG_LAT;
-
-            // Convert to grid cell
-            let col = ((dx_meters + self.radius) / self.cell_size) as isize;
-            let row = ((dy_meters + self.radius) / self.cell_size) as isize;
-
-            // Bounds check
-            if col >= 0
-                && col < self.resolution as isize
-                && row >= 0
-                && row < self.resolution as isize
-            {
-                let col = col as usize;
-                let row = row as usize;
+            let (dx_meters, dy_meters) = self.projection.forward(*point);
+
+            if let Some((row, col)) = self.cell_at(dx_meters, dy_meters) {
                 if self.in_circle[row][col] {
                     self.cells[row][col] += 1;
                     self.total_points += 1;
@@ -93,6 +115,27 @@ impl DensityGrid {
         }
     }
 
+    /// Cell (row, col) containing a projected position, if it's on the grid
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let half_width = self.half_width();
+
+        // Floor, so points just outside the grid aren't truncated into its
+        // edge cells
+        let col = ((x + half_width) / self.cell_size).floor();
+        let row = ((y + half_width) / self.cell_size).floor();
+
+        let on_grid = |i: f64| i >= 0.0 && i < self.resolution as f64;
+        (on_grid(row) && on_grid(col)).then_some((row as usize, col as usize))
+    }
+
+    /// Projected position of a cell's center, in meters east and north
+    pub fn cell_center(&self, row: usize, col: usize) -> (f64, f64) {
+        (
+            (col as f64 + 0.5) * self.cell_size - self.half_width(),
+            (row as f64 + 0.5) * self.cell_size - self.half_width(),
+        )
+    }
+
     /// Count how many cells are inside the circle
     pub fn cells_in_circle(&self) -> usize {
         self.in_circle
@@ -117,11 +160,11 @@ impl DensityGrid {
 
         let mut scores = vec![vec![None; self.resolution]; self.resolution];
 
-        for row in 0..self.resolution {
-            for col in 0..self.resolution {
+        for (row, row_scores) in scores.iter_mut().enumerate() {
+            for (col, score) in row_scores.iter_mut().enumerate() {
                 if self.in_circle[row][col] {
                     let observed = self.cells[row][col] as f64;
-                    scores[row][col] = Some((observed - expected) / std_dev);
+                    *score = Some((observed - expected) / std_dev);
                 }
             }
         }
@@ -131,18 +174,13 @@ impl DensityGrid {
 
     /// Convert a grid cell back to coordinates (center of cell)
     pub fn cell_to_coords(&self, row: usize, col: usize) -> Coordinates {
-        const METERS_PER_DEG_LAT: f64 = 111_320.0;
-        let meters_per_deg_lng = METERS_PER_DEG_LAT * (self.center.lat * PI / 180.0).cos();
-
-        // Cell center in grid space
-        let cell_center_x = (col as f64 + 0.5) * self.cell_size - self.radius;
-        let cell_center_y = (row as f64 + 0.5) * self.cell_size - self.radius;
-
-        // Convert to lat/lng
-        let lat = self.center.lat + cell_center_y / METERS_PER_DEG_LAT;
-        let lng = self.center.lng + cell_center_x / meters_per_deg_lng;
+        let (x, y) = self.cell_center(row, col);
+        self.projection.inverse(x, y)
+    }
 
-        Coordinates::new(lat, lng)
+    /// Half the grid's width in the projected plane
+    pub(crate) fn half_width(&self) -> f64 {
+        self.cell_size * self.resolution as f64 / 2.0
     }
 }
 
@@ -166,9 +204,9 @@ pub fn find_densest_cell(grid: &DensityGrid) -> Option<CellResult> {
     let scores = grid.calculate_z_scores();
     let mut best: Option<CellResult> = None;
 
-    for row in 0..grid.resolution {
-        for col in 0..grid.resolution {
-            if let Some(z_score) = scores[row][col] {
+    for (row, row_scores) in scores.iter().enumerate() {
+        for (col, &score) in row_scores.iter().enumerate() {
+            if let Some(z_score) = score {
                 let dominated = best.as_ref().is_some_and(|b| b.z_score >= z_score);
                 if !dominated {
                     best = Some(CellResult {
@@ -191,9 +229,9 @@ pub fn find_emptiest_cell(grid: &DensityGrid) -> Option<CellResult> {
     let scores = grid.calculate_z_scores();
     let mut best: Option<CellResult> = None;
 
-    for row in 0..grid.resolution {
-        for col in 0..grid.resolution {
-            if let Some(z_score) = scores[row][col] {
+    for (row, row_scores) in scores.iter().enumerate() {
+        for (col, &score) in row_scores.iter().enumerate() {
+            if let Some(z_score) = score {
                 let dominated = best.as_ref().is_some_and(|b| b.z_score <= z_score);
                 if !dominated {
                     best = Some(CellResult {
@@ -216,9 +254,9 @@ pub fn find_most_anomalous_cell(grid: &DensityGrid) -> Option<(CellResult, bool)
     let scores = grid.calculate_z_scores();
     let mut best: Option<CellResult> = None;
 
-    for row in 0..grid.resolution {
-        for col in 0..grid.resolution {
-            if let Some(z_score) = scores[row][col] {
+    for (row, row_scores) in scores.iter().enumerate() {
+        for (col, &score) in row_scores.iter().enumerate() {
+            if let Some(z_score) = score {
                 let dominated = best.as_ref().is_some_and(|b| b.z_score.abs() >= z_score.abs());
                 if !dominated {
                     best = Some(CellResult {
@@ -242,7 +280,7 @@ pub fn find_most_anomalous_cell(grid: &DensityGrid) -> Option<(CellResult, bool)
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coord::point::generate_points_in_circle;
+    use crate::coord::point::{generate_points_in_circle, haversine_distance};
     use crate::qrng::pseudo::SeededPseudoBackend;
 
     #[test]
@@ -289,12 +327,12 @@ mod tests {
         let scores = grid.calculate_z_scores();
 
         // All cells in circle should have scores
-        for row in 0..50 {
-            for col in 0..50 {
+        for (row, row_scores) in scores.iter().enumerate() {
+            for (col, score) in row_scores.iter().enumerate() {
                 if grid.in_circle[row][col] {
-                    assert!(scores[row][col].is_some());
+                    assert!(score.is_some());
                 } else {
-                    assert!(scores[row][col].is_none());
+                    assert!(score.is_none());
                 }
             }
         }
@@ -322,6 +360,66 @@ mod tests {
         assert!(densest.row != emptiest.row || densest.col != emptiest.col);
     }
 
+    #[test]
+    fn test_grid_clipped_to_area() {
+        // A triangle filling the lower-left half of its bounding box
+        let area = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[-74.02, 40.70], [-74.00, 40.70], [-74.02, 40.72], [-74.02, 40.70]]],
+        }))
+        .unwrap();
+        let backend = SeededPseudoBackend::new(42);
+        let points = crate::coord::area::generate_points_in_area(&area, 5000, None, &backend).unwrap();
+
+        let mut grid = DensityGrid::for_area(&area, 50);
+        grid.add_points(&points);
+
+        for row in 0..50 {
+            for col in 0..50 {
+                let inside = area.contains(grid.cell_to_coords(row, col));
+                assert_eq!(grid.in_circle[row][col], inside);
+                if !inside {
+                    assert_eq!(grid.cells[row][col], 0);
+                }
+            }
+        }
+        assert!(grid.cells_in_circle() > 0);
+        // Only points in boundary cells whose centers fall outside are lost
+        assert!(grid.total_points > 4500, "{} points binned", grid.total_points);
+    }
+
+    #[test]
+    fn test_excluded_cells_are_out_of_area() {
+        use crate::coord::point::generate_points_in_circle_excluding;
+
+        let center = Coordinates::new(40.7128, -74.0060);
+        // A zone over the eastern half of the circle
+        let lake = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[-74.006, 40.70], [-73.99, 40.70], [-73.99, 40.73], [-74.006, 40.73], [-74.006, 40.70]]],
+        }))
+        .unwrap();
+        let backend = SeededPseudoBackend::new(42);
+        let points = generate_points_in_circle_excluding(center, 1000.0, 10000, &lake, &backend).unwrap();
+
+        let mut grid = DensityGrid::new(center, 1000.0, 50);
+        let full = grid.cells_in_circle();
+        grid.exclude(&lake);
+        grid.add_points(&points);
+
+        assert!(grid.cells_in_circle() < full * 6 / 10);
+        for row in 0..50 {
+            for col in 0..50 {
+                if lake.contains(grid.cell_to_coords(row, col)) {
+                    assert!(!grid.in_circle[row][col]);
+                }
+            }
+        }
+        // The zone isn't mistaken for a void
+        let emptiest = find_emptiest_cell(&grid).unwrap();
+        assert!(!lake.contains(emptiest.coords));
+    }
+
     #[test]
     fn test_cell_to_coords() {
         let center = Coordinates::new(40.7128, -74.0060);
@@ -332,4 +430,104 @@ mod tests {
         assert!((center_coords.lat - center.lat).abs() < 0.001);
         assert!((center_coords.lng - center.lng).abs() < 0.001);
     }
+
+    /// Helper to test binning uniform points at a given latitude
+    fn test_grid_at_latitude(lat: f64, lng: f64, name: &str) {
+        let backend = SeededPseudoBackend::new(42);
+        let center = Coordinates::new(lat, lng);
+        let radius = 5000.0; // 5 km
+        let count = 5000;
+        let resolution = 20;
+
+        let points = generate_points_in_circle(center, radius, count, &backend).unwrap();
+        let mut grid = DensityGrid::new(center, radius, resolution);
+        grid.add_points(&points);
+
+        // Cells are equal-area, so the binned share of the points matches
+        // the share of the circle the in-circle cells cover
+        let cell_area = grid.cell_size * grid.cell_size;
+        let disk_area = std::f64::consts::PI * planar_distance(radius).powi(2);
+        let expected = grid.cells_in_circle() as f64 * cell_area / disk_area;
+        let binned = grid.total_points as f64 / count as f64;
+        assert!(
+            (binned - expected).abs() < 0.03,
+            "{}: binned {:.3} of the points, expected {:.3}",
+            name,
+            binned,
+            expected
+        );
+
+        // No cell stands out in uniform points
+        let scores = grid.calculate_z_scores();
+        for (row, row_scores) in scores.iter().enumerate() {
+            for (col, score) in row_scores.iter().enumerate() {
+                if let Some(z) = score {
+                    assert!(z.abs() < 5.0, "{}: cell ({}, {}) has z = {:.2}", name, row, col, z);
+                }
+            }
+        }
+
+        // Each half of the grid gets half the points
+        let west: usize = grid.cells.iter().map(|row| row[..resolution / 2].iter().sum::<usize>()).sum();
+        let south: usize = grid.cells[..resolution / 2].iter().flatten().sum();
+        for (half, n) in [("west", west), ("south", south)] {
+            let share = n as f64 / grid.total_points as f64;
+            assert!((share - 0.5).abs() < 0.05, "{}: {} half has {:.3} of the points", name, half, share);
+        }
+
+        // Cell centers land where they should on the ground
+        for row in 0..resolution {
+            for col in 0..resolution {
+                if grid.in_circle[row][col] {
+                    let coords = grid.cell_to_coords(row, col);
+                    coords.validate().unwrap();
+                    assert!(
+                        haversine_distance(center, coords) <= radius,
+                        "{}: cell ({}, {}) center is outside the circle",
+                        name,
+                        row,
+                        col
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_north_pole() {
+        test_grid_at_latitude(90.0, 0.0, "North Pole");
+        test_grid_at_latitude(89.9, 25.0, "Near North Pole");
+    }
+
+    #[test]
+    fn test_grid_south_pole() {
+        test_grid_at_latitude(-90.0, 0.0, "South Pole");
+        test_grid_at_latitude(-89.9, 0.0, "Near South Pole");
+    }
+
+    #[test]
+    fn test_grid_equator() {
+        test_grid_at_latitude(0.0, 0.0, "Equator (0°, 0°)");
+        test_grid_at_latitude(0.0, 90.0, "Equator (0°, 90°E)");
+        test_grid_at_latitude(0.0, -90.0, "Equator (0°, 90°W)");
+        test_grid_at_latitude(0.0, 180.0, "Equator (0°, 180°)");
+    }
+
+    #[test]
+    fn test_grid_intermediate_latitudes() {
+        test_grid_at_latitude(30.0, -90.0, "30°N (New Orleans)");
+        test_grid_at_latitude(45.0, -122.0, "45°N (Portland)");
+        test_grid_at_latitude(60.0, 10.0, "60°N (Oslo)");
+        test_grid_at_latitude(75.0, -40.0, "75°N (Greenland)");
+        test_grid_at_latitude(-30.0, 151.0, "30°S (Sydney area)");
+        test_grid_at_latitude(-45.0, 170.0, "45°S (New Zealand)");
+        test_grid_at_latitude(-60.0, -60.0, "60°S (Drake Passage)");
+    }
+
+    #[test]
+    fn test_grid_date_line() {
+        test_grid_at_latitude(0.0, 179.9, "Near date line (positive)");
+        test_grid_at_latitude(0.0, -179.9, "Near date line (negative)");
+        test_grid_at_latitude(45.0, 180.0, "On date line");
+    }
 }
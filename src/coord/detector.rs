@@ -0,0 +1,296 @@
+//! Anomaly detectors
+//!
+//! Each anomaly type is found by an `AnomalyDetector`. Every detector in
+//! `DETECTORS` runs on each analyzed circle, picks its winner across circles
+//! and is listed by `/api/types`, `generate --list-types` and the output
+//! formats, so adding an anomaly type only takes implementing the trait here
+//! and adding the detector to that list.
+
+use crate::coord::density::DensityGrid;
+use crate::coord::{AnomalyType, Coordinates, Point};
+use std::collections::HashMap;
+
+/// What a detector sees of one analyzed circle or area
+pub struct Analysis<'a> {
+    /// Generated points, in the order they were drawn
+    pub points: &'a [Coordinates],
+    /// Density grid over the area, holding `points`
+    pub grid: &'a DensityGrid,
+    /// Densest spot found by the detection method, with its z-score
+    pub peak: Option<Point>,
+    /// Emptiest spot found by the detection method, with its z-score
+    pub trough: Option<Point>,
+}
+
+/// Trait for anomaly detectors
+pub trait AnomalyDetector: Send + Sync {
+    /// Name used in requests, results and output (snake_case)
+    fn name(&self) -> &'static str;
+
+    /// Short human-readable description
+    fn description(&self) -> &'static str;
+
+    /// Find the anomaly in one circle or area, if there is one
+    fn detect(&self, analysis: &Analysis) -> Option<Point>;
+
+    /// Whether `candidate` should replace `best` as the winner across circles
+    ///
+    /// Circles are compared in order, so returning `false` on ties keeps the
+    /// earlier one.
+    fn beats(&self, candidate: &Point, best: &Point) -> bool;
+
+    /// The anomaly type this detector finds
+    fn anomaly_type(&self) -> AnomalyType {
+        AnomalyType::new(self.name())
+    }
+
+    /// Waypoint symbol for GPX output
+    fn gpx_symbol(&self) -> &'static str {
+        "flag"
+    }
+}
+
+/// Registered detectors, in listing order
+static DETECTORS: &[&dyn AnomalyDetector] = &[&BlindSpot, &Attractor, &Void, &Power];
+
+/// All registered detectors
+pub fn detectors() -> &'static [&'static dyn AnomalyDetector] {
+    DETECTORS
+}
+
+/// Get a detector by name
+///
+/// Case, `_` and `-` are ignored, so `blind-spot` and `BlindSpot` both find
+/// `blind_spot`.
+pub fn get_detector(name: &str) -> Option<&'static dyn AnomalyDetector> {
+    let key = |s: &str| {
+        s.chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let wanted = key(name);
+    DETECTORS.iter().copied().find(|detector| key(detector.name()) == wanted)
+}
+
+/// Run every detector on an analysis
+pub fn detect_all(analysis: &Analysis) -> HashMap<AnomalyType, Point> {
+    DETECTORS
+        .iter()
+        .filter_map(|detector| Some((detector.anomaly_type(), detector.detect(analysis)?)))
+        .collect()
+}
+
+impl AnomalyType {
+    /// Single random point (no analysis)
+    pub const BLIND_SPOT: Self = Self::new("blind_spot");
+    /// Densest cluster (most points in area)
+    pub const ATTRACTOR: Self = Self::new("attractor");
+    /// Emptiest region (fewest points in area)
+    pub const VOID: Self = Self::new("void");
+    /// Most statistically anomalous (highest absolute z-score)
+    pub const POWER: Self = Self::new("power");
+}
+
+/// The first point drawn
+pub struct BlindSpot;
+
+impl AnomalyDetector for BlindSpot {
+    fn name(&self) -> &'static str {
+        AnomalyType::BLIND_SPOT.name()
+    }
+
+    fn description(&self) -> &'static str {
+        "Random point with no analysis"
+    }
+
+    fn detect(&self, analysis: &Analysis) -> Option<Point> {
+        // In practice, for blind spot we'd only generate 1 point, but since
+        // we're generating many for analysis, we just use the first one
+        analysis.points.first().map(|&coords| Point::new(coords))
+    }
+
+    fn beats(&self, _candidate: &Point, _best: &Point) -> bool {
+        // The first circle's point stands
+        false
+    }
+
+    fn gpx_symbol(&self) -> &'static str {
+        "random"
+    }
+}
+
+/// The densest spot
+pub struct Attractor;
+
+impl AnomalyDetector for Attractor {
+    fn name(&self) -> &'static str {
+        AnomalyType::ATTRACTOR.name()
+    }
+
+    fn description(&self) -> &'static str {
+        "Densest cluster of points"
+    }
+
+    fn detect(&self, analysis: &Analysis) -> Option<Point> {
+        analysis.peak.clone()
+    }
+
+    fn beats(&self, candidate: &Point, best: &Point) -> bool {
+        // Higher z-score is better (more dense)
+        candidate.z_score.unwrap_or(f64::NEG_INFINITY) > best.z_score.unwrap_or(f64::NEG_INFINITY)
+    }
+
+    fn gpx_symbol(&self) -> &'static str {
+        "attraction"
+    }
+}
+
+/// The emptiest spot
+pub struct Void;
+
+impl AnomalyDetector for Void {
+    fn name(&self) -> &'static str {
+        AnomalyType::VOID.name()
+    }
+
+    fn description(&self) -> &'static str {
+        "Emptiest region"
+    }
+
+    fn detect(&self, analysis: &Analysis) -> Option<Point> {
+        analysis.trough.clone()
+    }
+
+    fn beats(&self, candidate: &Point, best: &Point) -> bool {
+        // Lower z-score is better (more sparse)
+        candidate.z_score.unwrap_or(f64::INFINITY) < best.z_score.unwrap_or(f64::INFINITY)
+    }
+
+    fn gpx_symbol(&self) -> &'static str {
+        "void"
+    }
+}
+
+/// Whichever of the peak and trough strays furthest from expected
+pub struct Power;
+
+impl AnomalyDetector for Power {
+    fn name(&self) -> &'static str {
+        AnomalyType::POWER.name()
+    }
+
+    fn description(&self) -> &'static str {
+        "Most statistically anomalous"
+    }
+
+    fn detect(&self, analysis: &Analysis) -> Option<Point> {
+        let strength = |point: &Option<Point>| point.as_ref().and_then(|p| p.z_score).map(f64::abs);
+        // Peaks win ties
+        let strongest = if strength(&analysis.trough) > strength(&analysis.peak) {
+            analysis.trough.as_ref()
+        } else {
+            analysis.peak.as_ref()
+        }?;
+        let z_score = strongest.z_score?;
+        Some(Point::power(strongest.coords, z_score, z_score > 0.0))
+    }
+
+    fn beats(&self, candidate: &Point, best: &Point) -> bool {
+        // Higher absolute z-score is better
+        candidate.z_score.unwrap_or(0.0).abs() > best.z_score.unwrap_or(0.0).abs()
+    }
+
+    fn gpx_symbol(&self) -> &'static str {
+        "star"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(z_score: f64) -> Point {
+        Point::with_z_score(Coordinates::new(0.0, 0.0), z_score)
+    }
+
+    #[test]
+    fn test_registry() {
+        let names: Vec<&str> = detectors().iter().map(|d| d.name()).collect();
+        assert_eq!(names, vec!["blind_spot", "attractor", "void", "power"]);
+
+        assert_eq!(get_detector("attractor").unwrap().name(), "attractor");
+        assert_eq!(get_detector("Blind-Spot").unwrap().name(), "blind_spot");
+        assert_eq!(get_detector("blindspot").unwrap().name(), "blind_spot");
+        assert!(get_detector("wormhole").is_none());
+    }
+
+    #[test]
+    fn test_beats() {
+        let attractor = get_detector("attractor").unwrap();
+        assert!(attractor.beats(&scored(3.0), &scored(2.0)));
+        assert!(!attractor.beats(&scored(2.0), &scored(2.0)));
+
+        let void = get_detector("void").unwrap();
+        assert!(void.beats(&scored(-3.0), &scored(-2.0)));
+        assert!(!void.beats(&scored(-1.0), &scored(-2.0)));
+
+        let power = get_detector("power").unwrap();
+        assert!(power.beats(&scored(-4.0), &scored(3.0)));
+        assert!(!power.beats(&scored(3.0), &scored(-4.0)));
+
+        let blind_spot = get_detector("blind_spot").unwrap();
+        assert!(!blind_spot.beats(&Point::new(Coordinates::new(1.0, 1.0)), &scored(0.0)));
+    }
+
+    #[test]
+    fn test_gpx_symbols() {
+        let symbols: Vec<&str> = detectors().iter().map(|d| d.gpx_symbol()).collect();
+        assert_eq!(symbols, vec!["random", "attraction", "void", "star"]);
+    }
+
+    #[test]
+    fn test_unknown_types_round_trip() {
+        let json = r#"["void","wormhole","ley_line","wormhole"]"#;
+        let types: Vec<AnomalyType> = serde_json::from_str(json).unwrap();
+        assert_eq!(types[0], AnomalyType::VOID);
+        assert_eq!(types[1].name(), "wormhole");
+        assert_ne!(types[1], types[2]);
+        assert_eq!(types[1], types[3]);
+        assert!(types[1].detector().is_none());
+        assert!("wormhole".parse::<AnomalyType>().is_err());
+
+        // Saving again keeps the names
+        assert_eq!(serde_json::to_string(&types).unwrap(), json);
+    }
+
+    #[test]
+    fn test_detect_all() {
+        let grid = DensityGrid::new(Coordinates::new(0.0, 0.0), 1000.0, 10);
+        let points = [Coordinates::new(0.001, 0.001), Coordinates::new(-0.001, 0.002)];
+        let analysis = Analysis {
+            points: &points,
+            grid: &grid,
+            peak: Some(scored(2.5)),
+            trough: Some(scored(-3.5)),
+        };
+
+        let found = detect_all(&analysis);
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[&AnomalyType::BLIND_SPOT].coords, points[0]);
+        assert_eq!(found[&AnomalyType::ATTRACTOR].z_score, Some(2.5));
+        assert_eq!(found[&AnomalyType::VOID].z_score, Some(-3.5));
+        let power = &found[&AnomalyType::POWER];
+        assert_eq!(power.z_score, Some(-3.5));
+        assert_eq!(power.is_attractor, Some(false));
+
+        // Nothing to find without points or a density analysis
+        let empty = Analysis {
+            points: &[],
+            grid: &grid,
+            peak: None,
+            trough: None,
+        };
+        assert!(detect_all(&empty).is_empty());
+    }
+}
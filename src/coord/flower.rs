@@ -3,11 +3,19 @@
 //! Generates 7 overlapping circles in a flower pattern (1 center + 6 petals)
 //! and finds the strongest anomalies across all circles.
 
-use crate::coord::anomaly::{analyze_circle, find_all_winners, CircleResults, DEFAULT_POINT_COUNT};
+use crate::coord::anomaly::{
+    analyze_area, analyze_area_async, analyze_circle, analyze_circle_async, find_all_winners,
+    CircleResults, DEFAULT_POINT_COUNT,
+};
+use crate::coord::area::Area;
 use crate::coord::density::DEFAULT_GRID_RESOLUTION;
-use crate::coord::{AnomalyType, Coordinates, GenerationMode, Point};
-use crate::error::Result;
-use crate::qrng::QrngBackend;
+use crate::coord::point::Sampler;
+use crate::coord::projection::AzimuthalEqualArea;
+use crate::coord::provenance::{Consumption, Digesting, DigestingAsync, Provenance};
+use crate::coord::{AnomalyType, Coordinates, DetectionMethod, GenerationMode, Point};
+use crate::entropy::{run_all_tests, EntropyCheck, EntropyTestResults};
+use crate::error::{Error, Result};
+use crate::qrng::{AsyncQrngBackend, FloatPrecision, QrngBackend};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::f64::consts::PI;
@@ -44,9 +52,21 @@ pub struct GenerationRequest {
     pub lng: f64,
     pub radius: f64,
     pub points: usize,
+    /// Backend that supplied the entropy (the chain member actually used,
+    /// for wrappers like failover and pool)
     pub backend: String,
     pub mode: GenerationMode,
+    /// How anomalies were found (requests from before KDE used the grid)
+    #[serde(default)]
+    pub method: DetectionMethod,
     pub include_points: bool,
+    /// Polygon area searched instead of the circle (`lat`, `lng` and
+    /// `radius` then describe the circle enclosing it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area: Option<Area>,
+    /// Exclusion zones points were kept out of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Area>,
 }
 
 /// Winner result pointing to a specific circle
@@ -63,9 +83,90 @@ pub struct WinnerResult {
 pub struct GenerationMetadata {
     /// When this was generated
     pub timestamp: String,
-    /// Entropy quality scores (if available)
+    /// Entropy quality scores for the bytes consumed (if checked)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub entropy_quality: Option<crate::entropy::EntropyTestResults>,
+    /// Backends that supplied the entropy (e.g. every input of a mix)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entropy_sources: Vec<String>,
+    /// Raw versus delivered bytes when the backend runs an extractor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction: Option<crate::qrng::extract::ExtractionStats>,
+    /// Digest of the entropy consumed, for `provenance::verify`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Settings shared by every kind of generation
+///
+/// `new` gives the defaults; the `with_*` methods override them.
+#[derive(Debug, Clone, Copy)]
+pub struct GenerationOptions<'a> {
+    /// Number of points per circle
+    pub points: usize,
+    /// Resolution of the density grid
+    pub grid_resolution: usize,
+    /// Grid counts or kernel density estimation
+    pub method: DetectionMethod,
+    /// Whether to include all generated points in the response
+    pub include_points: bool,
+    /// Exclusion zones to keep points out of
+    pub exclude: Option<&'a Area>,
+    /// Whether to test the entropy consumed (see `test_entropy_sample`)
+    pub entropy_check: EntropyCheck,
+    /// Name of the QRNG backend, as requested
+    pub backend_name: &'a str,
+}
+
+impl<'a> GenerationOptions<'a> {
+    /// Default options for drawing from `backend_name`
+    pub fn new(backend_name: &'a str) -> Self {
+        Self {
+            points: DEFAULT_POINT_COUNT,
+            grid_resolution: DEFAULT_GRID_RESOLUTION,
+            method: DetectionMethod::Grid,
+            include_points: false,
+            exclude: None,
+            entropy_check: EntropyCheck::Off,
+            backend_name,
+        }
+    }
+
+    /// Set the number of points per circle
+    pub fn with_points(mut self, points: usize) -> Self {
+        self.points = points;
+        self
+    }
+
+    /// Set the density grid resolution
+    pub fn with_grid_resolution(mut self, grid_resolution: usize) -> Self {
+        self.grid_resolution = grid_resolution;
+        self
+    }
+
+    /// Set the anomaly detection method
+    pub fn with_method(mut self, method: DetectionMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set whether to include all generated points
+    pub fn with_include_points(mut self, include_points: bool) -> Self {
+        self.include_points = include_points;
+        self
+    }
+
+    /// Keep points out of `exclude`
+    pub fn with_exclude(mut self, exclude: Option<&'a Area>) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// Set what to do about the quality of the entropy consumed
+    pub fn with_entropy_check(mut self, entropy_check: EntropyCheck) -> Self {
+        self.entropy_check = entropy_check;
+        self
+    }
 }
 
 /// Generate coordinates using the specified mode
@@ -73,11 +174,8 @@ pub struct GenerationMetadata {
 /// # Arguments
 /// * `center` - Center coordinates
 /// * `radius` - Radius in meters
-/// * `points` - Number of points per circle
-/// * `grid_resolution` - Resolution of density grid
-/// * `include_points` - Whether to include all generated points
 /// * `mode` - Standard or FlowerPower
-/// * `backend_name` - Name of the QRNG backend
+/// * `options` - Points, detection method, exclusions and so on
 /// * `rng` - QRNG backend instance
 ///
 /// # Returns
@@ -85,32 +183,260 @@ pub struct GenerationMetadata {
 pub fn generate(
     center: Coordinates,
     radius: f64,
-    points: usize,
-    grid_resolution: usize,
-    include_points: bool,
     mode: GenerationMode,
-    backend_name: &str,
+    options: &GenerationOptions,
+    rng: &dyn QrngBackend,
+) -> Result<GenerationResponse> {
+    let rng = match options.entropy_check {
+        EntropyCheck::Off => Digesting::new(rng),
+        _ => Digesting::new(rng).with_capture(),
+    };
+    let mut circles = Vec::new();
+    for circle in circle_layout(center, radius, mode) {
+        circles.push(analyze_circle(
+            &circle.id,
+            circle.center,
+            circle.radius,
+            options.points,
+            options.grid_resolution,
+            options.method,
+            options.include_points,
+            options.exclude,
+            &rng,
+        )?);
+    }
+
+    let mut consumed = rng.consumption(options.backend_name, options.grid_resolution);
+    test_consumed(&mut consumed, options.entropy_check);
+    finish_response(center, radius, mode, None, options, circles, consumed)
+}
+
+/// Generate coordinates without blocking the async runtime
+///
+/// Same as `generate`, drawing entropy from an async backend. Circles consume
+/// entropy in the same order, so a given byte stream gives the same result
+/// either way.
+pub async fn generate_async(
+    center: Coordinates,
+    radius: f64,
+    mode: GenerationMode,
+    options: &GenerationOptions<'_>,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<GenerationResponse> {
+    let rng = match options.entropy_check {
+        EntropyCheck::Off => DigestingAsync::new(rng),
+        _ => DigestingAsync::new(rng).with_capture(),
+    };
+    let mut circles = Vec::new();
+    for circle in circle_layout(center, radius, mode) {
+        circles.push(
+            analyze_circle_async(
+                &circle.id,
+                circle.center,
+                circle.radius,
+                options.points,
+                options.grid_resolution,
+                options.method,
+                options.include_points,
+                options.exclude,
+                &rng,
+            )
+            .await?,
+        );
+    }
+
+    let consumed = rng.consumption(options.backend_name, options.grid_resolution);
+    let consumed = test_consumed_async(consumed, options.entropy_check).await?;
+    finish_response(center, radius, mode, None, options, circles, consumed)
+}
+
+/// Generate coordinates within a polygon area
+///
+/// Like `generate` in standard mode, with a single result (id "area")
+/// whose points and density grid are confined to `area`.
+pub fn generate_in_area(
+    area: &Area,
+    options: &GenerationOptions,
     rng: &dyn QrngBackend,
 ) -> Result<GenerationResponse> {
+    let rng = match options.entropy_check {
+        EntropyCheck::Off => Digesting::new(rng),
+        _ => Digesting::new(rng).with_capture(),
+    };
+    let circles = vec![analyze_area(
+        "area",
+        area,
+        options.points,
+        options.grid_resolution,
+        options.method,
+        options.include_points,
+        options.exclude,
+        &rng,
+    )?];
+
+    let mut consumed = rng.consumption(options.backend_name, options.grid_resolution);
+    test_consumed(&mut consumed, options.entropy_check);
+    finish_response(
+        area.center(),
+        area.radius(),
+        GenerationMode::Standard,
+        Some(area),
+        options,
+        circles,
+        consumed,
+    )
+}
+
+/// Generate coordinates within a polygon area without blocking the async
+/// runtime
+///
+/// Same as `generate_in_area`, drawing entropy from an async backend.
+pub async fn generate_in_area_async(
+    area: &Area,
+    options: &GenerationOptions<'_>,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<GenerationResponse> {
+    let rng = match options.entropy_check {
+        EntropyCheck::Off => DigestingAsync::new(rng),
+        _ => DigestingAsync::new(rng).with_capture(),
+    };
+    let circles = vec![
+        analyze_area_async(
+            "area",
+            area,
+            options.points,
+            options.grid_resolution,
+            options.method,
+            options.include_points,
+            options.exclude,
+            &rng,
+        )
+        .await?,
+    ];
+
+    let consumed = rng.consumption(options.backend_name, options.grid_resolution);
+    let consumed = test_consumed_async(consumed, options.entropy_check).await?;
+    finish_response(
+        area.center(),
+        area.radius(),
+        GenerationMode::Standard,
+        Some(area),
+        options,
+        circles,
+        consumed,
+    )
+}
+
+/// Bytes of entropy a generation draws
+///
+/// Each circle takes two floats per point; used to check a daily budget
+/// before starting.
+pub fn entropy_bytes_needed(points: usize, mode: GenerationMode, precision: FloatPrecision) -> u64 {
     let circles = match mode {
-        GenerationMode::Standard => generate_standard(
-            center,
-            radius,
-            points,
-            grid_resolution,
-            include_points,
-            rng,
-        )?,
-        GenerationMode::FlowerPower => generate_flower_power(
-            center,
-            radius,
-            points,
-            grid_resolution,
-            include_points,
-            rng,
-        )?,
+        GenerationMode::Standard => 1,
+        GenerationMode::FlowerPower => PETAL_COUNT + 1,
+    };
+    (circles * points * 2 * precision.bytes_per_float()) as u64
+}
+
+/// Bytes of entropy a generation usually draws, allowing for exclusion zones
+///
+/// Points landing in `exclude` are rejected and redrawn, so each circle is
+/// sized by how much of it the zones leave (see `Sampler`). Fails if the
+/// zones leave too little of a circle to sample.
+pub fn entropy_bytes_needed_excluding(
+    center: Coordinates,
+    radius: f64,
+    points: usize,
+    mode: GenerationMode,
+    exclude: Option<&Area>,
+    precision: FloatPrecision,
+) -> Result<u64> {
+    let Some(exclude) = exclude else {
+        return Ok(entropy_bytes_needed(points, mode, precision));
+    };
+    circle_layout(center, radius, mode)
+        .iter()
+        .try_fold(0u64, |total, circle| {
+            let sampler = Sampler::new(circle.center, circle.radius, None, Some(exclude))?;
+            Ok(total.saturating_add(sampler.entropy_bytes_needed(points, precision)))
+        })
+}
+
+/// Most bytes of a generation's entropy that a check tests
+///
+/// A large generation draws megabytes, and the tests' cost grows with their
+/// input, so they run on the first 64 KiB.
+pub const ENTROPY_CHECK_MAX_BYTES: usize = 64 * 1024;
+
+/// Run the `entropy::tests` on up to `ENTROPY_CHECK_MAX_BYTES` of `consumed`
+pub fn test_entropy_sample(consumed: &[u8]) -> EntropyTestResults {
+    run_all_tests(&consumed[..consumed.len().min(ENTROPY_CHECK_MAX_BYTES)])
+}
+
+/// Test the captured entropy unless the check is off
+fn test_consumed(consumed: &mut Consumption, entropy_check: EntropyCheck) {
+    if entropy_check != EntropyCheck::Off {
+        consumed.quality = Some(test_entropy_sample(&consumed.captured));
+    }
+}
+
+/// `test_consumed` on a blocking thread, so the tests don't stall the async
+/// runtime
+async fn test_consumed_async(
+    mut consumed: Consumption,
+    entropy_check: EntropyCheck,
+) -> Result<Consumption> {
+    if entropy_check == EntropyCheck::Off {
+        return Ok(consumed);
+    }
+    tokio::task::spawn_blocking(move || {
+        test_consumed(&mut consumed, entropy_check);
+        consumed
+    })
+    .await
+    .map_err(|e| Error::Qrng(format!("Entropy test task failed: {}", e)))
+}
+
+/// Record the entropy test results
+///
+/// The results go into `metadata.entropy_quality` (and so into history);
+/// `Reject` turns failed tests into an error, while with `Warn` it is up to
+/// the caller to report them.
+fn check_entropy(
+    response: &mut GenerationResponse,
+    results: Option<EntropyTestResults>,
+    entropy_check: EntropyCheck,
+) -> Result<()> {
+    let Some(results) = results else {
+        return Ok(());
     };
+    let failures = results.failure_summary();
+    response.metadata.entropy_quality = Some(results);
 
+    match failures {
+        Some(failures) if entropy_check == EntropyCheck::Reject => {
+            Err(Error::EntropyQuality(failures))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Assemble the response from analyzed circles and what they drew
+///
+/// Sources, extraction stats and provenance go into the metadata, then the
+/// entropy is checked. When exactly one backend supplied it, that backend
+/// becomes `request.backend`, so a failover that fell back to pseudo is
+/// never reported as the backend that was asked for.
+fn finish_response(
+    center: Coordinates,
+    radius: f64,
+    mode: GenerationMode,
+    area: Option<&Area>,
+    options: &GenerationOptions,
+    circles: Vec<CircleResults>,
+    consumed: Consumption,
+) -> Result<GenerationResponse> {
     // Find winners across all circles
     let winner_map = find_all_winners(&circles);
     let winners: HashMap<AnomalyType, WinnerResult> = winner_map
@@ -126,111 +452,95 @@ pub fn generate(
         })
         .collect();
 
-    Ok(GenerationResponse {
+    let backend = match consumed.sources.as_slice() {
+        [only] => only.clone(),
+        _ => options.backend_name.to_string(),
+    };
+    let mut response = GenerationResponse {
         id: uuid::Uuid::new_v4().to_string(),
         request: GenerationRequest {
             lat: center.lat,
             lng: center.lng,
             radius,
-            points,
-            backend: backend_name.to_string(),
+            points: options.points,
+            backend,
             mode,
-            include_points,
+            method: options.method,
+            include_points: options.include_points,
+            area: area.cloned(),
+            exclude: options.exclude.cloned(),
         },
         circles,
         winners,
         metadata: GenerationMetadata {
             timestamp: chrono::Utc::now().to_rfc3339(),
-            entropy_quality: None, // Can be added if we run entropy tests
+            entropy_quality: None,
+            entropy_sources: consumed.sources,
+            extraction: consumed.extraction,
+            provenance: Some(consumed.provenance),
         },
-    })
+    };
+    check_entropy(&mut response, consumed.quality, options.entropy_check)?;
+    Ok(response)
 }
 
-/// Generate using standard mode (single circle)
-fn generate_standard(
+/// A circle to analyze
+struct CircleSpec {
+    id: String,
     center: Coordinates,
     radius: f64,
-    points: usize,
-    grid_resolution: usize,
-    include_points: bool,
-    rng: &dyn QrngBackend,
-) -> Result<Vec<CircleResults>> {
-    let circle = analyze_circle(
-        "center",
-        center,
-        radius,
-        points,
-        grid_resolution,
-        include_points,
-        rng,
-    )?;
-    Ok(vec![circle])
 }
 
-/// Generate using flower power mode (7 circles)
+/// Circles to analyze for a mode, in the order they consume entropy
 ///
-/// Layout: Center circle surrounded by 6 petals at 60-degree intervals.
-/// Each petal is offset by half the radius, creating nice overlap.
-fn generate_flower_power(
-    center: Coordinates,
-    radius: f64,
-    points: usize,
-    grid_resolution: usize,
-    include_points: bool,
-    rng: &dyn QrngBackend,
-) -> Result<Vec<CircleResults>> {
-    // Sub-radius for each circle (half of main radius gives good overlap)
-    let sub_radius = radius / 2.0;
-
-    // Calculate petal centers
-    let petal_centers = calculate_petal_centers(center, sub_radius);
-
-    let mut circles = Vec::with_capacity(7);
-
-    // Center circle
-    circles.push(analyze_circle(
-        "center",
-        center,
-        sub_radius,
-        points,
-        grid_resolution,
-        include_points,
-        rng,
-    )?);
-
-    // Petal circles
-    for (i, &petal_center) in petal_centers.iter().enumerate() {
-        circles.push(analyze_circle(
-            &format!("petal_{}", i),
-            petal_center,
-            sub_radius,
-            points,
-            grid_resolution,
-            include_points,
-            rng,
-        )?);
+/// Standard: a single circle. Flower power: a center circle surrounded by 6
+/// petals at 60-degree intervals, each with half the radius and offset by half
+/// the radius, creating nice overlap.
+fn circle_layout(center: Coordinates, radius: f64, mode: GenerationMode) -> Vec<CircleSpec> {
+    match mode {
+        GenerationMode::Standard => vec![CircleSpec {
+            id: "center".to_string(),
+            center,
+            radius,
+        }],
+        GenerationMode::FlowerPower => {
+            // Sub-radius for each circle (half of main radius gives good overlap)
+            let sub_radius = radius / 2.0;
+
+            let mut circles = Vec::with_capacity(PETAL_COUNT + 1);
+            circles.push(CircleSpec {
+                id: "center".to_string(),
+                center,
+                radius: sub_radius,
+            });
+
+            for (i, &petal_center) in calculate_petal_centers(center, sub_radius).iter().enumerate() {
+                circles.push(CircleSpec {
+                    id: format!("petal_{}", i),
+                    center: petal_center,
+                    radius: sub_radius,
+                });
+            }
+
+            circles
+        }
     }
-
-    Ok(circles)
 }
 
 /// Calculate the centers of the 6 petal circles
 ///
 /// Petals are arranged in a hexagonal pattern around the center,
-/// each offset by `offset_distance` at 60-degree intervals.
+/// each offset by `offset_distance` at 60-degree intervals. At the poles
+/// "north" is toward 180° longitude and the rest follow clockwise.
 fn calculate_petal_centers(center: Coordinates, offset_distance: f64) -> [Coordinates; 6] {
-    const METERS_PER_DEG_LAT: f64 = 111_320.0;
-    let meters_per_deg_lng = METERS_PER_DEG_LAT * (center.lat * PI / 180.0).cos();
+    let projection = AzimuthalEqualArea::new(center);
 
     let mut petals = [center; 6];
 
-    for i in 0..6 {
+    for (i, petal) in petals.iter_mut().enumerate() {
         let angle = (i as f64) * PI / 3.0; // 60 degrees each
 
-        let delta_lat = (offset_distance * angle.cos()) / METERS_PER_DEG_LAT;
-        let delta_lng = (offset_distance * angle.sin()) / meters_per_deg_lng;
-
-        petals[i] = Coordinates::new(center.lat + delta_lat, center.lng + delta_lng);
+        *petal = projection.destination(offset_distance, angle);
     }
 
     petals
@@ -243,16 +553,7 @@ pub fn generate_with_defaults(
     mode: GenerationMode,
     rng: &dyn QrngBackend,
 ) -> Result<GenerationResponse> {
-    generate(
-        center,
-        radius,
-        DEFAULT_POINT_COUNT,
-        DEFAULT_GRID_RESOLUTION,
-        false,
-        mode,
-        rng.name(),
-        rng,
-    )
+    generate(center, radius, mode, &GenerationOptions::new(rng.name()), rng)
 }
 
 #[cfg(test)]
@@ -260,6 +561,7 @@ mod tests {
     use super::*;
     use crate::coord::point::haversine_distance;
     use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::SyncAdapter;
 
     #[test]
     fn test_calculate_petal_centers() {
@@ -296,6 +598,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_petal_centers_at_pole_and_date_line() {
+        let offset = 1000.0;
+        for center in [
+            Coordinates::new(90.0, 0.0),
+            Coordinates::new(-89.99, 45.0),
+            Coordinates::new(0.0, 179.999),
+            Coordinates::new(70.0, -180.0),
+        ] {
+            let petals = calculate_petal_centers(center, offset);
+            for (i, petal) in petals.iter().enumerate() {
+                petal.validate().unwrap();
+                let distance = haversine_distance(center, *petal);
+                assert!((distance - offset).abs() < 1.0, "{:?}: petal {} is {} m away", center, i, distance);
+                let dist = haversine_distance(*petal, petals[(i + 1) % 6]);
+                assert!((dist - offset).abs() < 1.0, "{:?}: petals {} apart", center, dist);
+            }
+        }
+    }
+
     #[test]
     fn test_generate_standard() {
         let backend = SeededPseudoBackend::new(42);
@@ -304,11 +626,10 @@ mod tests {
         let response = generate(
             center,
             1000.0,
-            1000,
-            50,
-            false,
             GenerationMode::Standard,
-            "pseudo",
+            &GenerationOptions::new("pseudo")
+                .with_points(1000)
+                .with_grid_resolution(50),
             &backend,
         )
         .unwrap();
@@ -318,10 +639,241 @@ mod tests {
         assert_eq!(response.circles[0].id, "center");
 
         // Should have all anomaly types in winners
-        assert!(response.winners.contains_key(&AnomalyType::BlindSpot));
-        assert!(response.winners.contains_key(&AnomalyType::Attractor));
-        assert!(response.winners.contains_key(&AnomalyType::Void));
-        assert!(response.winners.contains_key(&AnomalyType::Power));
+        assert!(response.winners.contains_key(&AnomalyType::BLIND_SPOT));
+        assert!(response.winners.contains_key(&AnomalyType::ATTRACTOR));
+        assert!(response.winners.contains_key(&AnomalyType::VOID));
+        assert!(response.winners.contains_key(&AnomalyType::POWER));
+        assert_eq!(response.metadata.entropy_sources, vec!["pseudo-seeded"]);
+    }
+
+    #[test]
+    fn test_generate_reports_failover_backend_used() {
+        use crate::qrng::failover::FailoverBackend;
+        use crate::qrng::retry::RetryPolicy;
+        use crate::qrng::replay::ReplayBackend;
+
+        let no_retry = RetryPolicy::none();
+        let backend = FailoverBackend::new()
+            .with_backend(Box::new(ReplayBackend::new("/nonexistent/capture.bin")), no_retry)
+            .with_backend(Box::new(SeededPseudoBackend::new(42)), no_retry);
+
+        let response = generate(
+            Coordinates::new(40.7128, -74.0060),
+            1000.0,
+            GenerationMode::Standard,
+            &GenerationOptions::new("failover")
+                .with_points(100)
+                .with_grid_resolution(20),
+            &backend,
+        )
+        .unwrap();
+
+        assert_eq!(response.request.backend, "pseudo-seeded");
+        assert_eq!(response.metadata.entropy_sources, vec!["pseudo-seeded"]);
+    }
+
+    #[test]
+    fn test_generate_records_mix_sources() {
+        use crate::qrng::mix::MixBackend;
+
+        let backend = MixBackend::new(vec![
+            Box::new(SeededPseudoBackend::new(1)),
+            Box::new(SeededPseudoBackend::new(2)),
+        ])
+        .unwrap();
+
+        let response = generate(
+            Coordinates::new(40.7128, -74.0060),
+            1000.0,
+            GenerationMode::Standard,
+            &GenerationOptions::new("mix")
+                .with_points(100)
+                .with_grid_resolution(20),
+            &backend,
+        )
+        .unwrap();
+
+        assert_eq!(response.request.backend, "mix");
+        assert_eq!(
+            response.metadata.entropy_sources,
+            vec!["pseudo-seeded", "pseudo-seeded"]
+        );
+    }
+
+    #[test]
+    fn test_generate_records_extraction_ratio() {
+        use crate::qrng::extract::{Extractor, ExtractorBackend};
+
+        let backend = ExtractorBackend::new(
+            Box::new(SeededPseudoBackend::new(42)),
+            Extractor::Sha256 { block: 64 },
+        );
+        let response = generate(
+            Coordinates::new(40.7128, -74.0060),
+            1000.0,
+            GenerationMode::Standard,
+            &GenerationOptions::new("extract")
+                .with_points(100)
+                .with_grid_resolution(20),
+            &backend,
+        )
+        .unwrap();
+
+        let stats = response.metadata.extraction.unwrap();
+        assert_eq!(stats.extractor, "sha256");
+        assert!(stats.output_bytes >= 100 * 2 * 8);
+        assert!(stats.ratio() > 1.9 && stats.ratio() <= 2.0, "ratio {}", stats.ratio());
+    }
+
+    #[test]
+    fn test_entropy_check_tests_consumed_bytes() {
+        let backend = SeededPseudoBackend::new(42);
+        let run = |check| {
+            generate(
+                Coordinates::new(40.7128, -74.0060),
+                1000.0,
+                GenerationMode::Standard,
+                &GenerationOptions::new("pseudo")
+                    .with_points(500)
+                    .with_grid_resolution(20)
+                    .with_entropy_check(check),
+                &backend,
+            )
+            .unwrap()
+        };
+
+        assert!(run(EntropyCheck::Off).metadata.entropy_quality.is_none());
+        let quality = run(EntropyCheck::Warn).metadata.entropy_quality.unwrap();
+        assert_eq!(quality.bytes_analyzed, 500 * 2 * 8);
+        assert!(quality.all_passed());
+        assert!(run(EntropyCheck::Reject).metadata.entropy_quality.is_some());
+    }
+
+    #[test]
+    fn test_entropy_check_on_biased_source() {
+        use crate::entropy::tests::biased_bytes;
+        use crate::qrng::replay::ReplayBackend;
+
+        let biased = biased_bytes(8000);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("biased.bin");
+        std::fs::write(&path, &biased).unwrap();
+
+        let run = |check| {
+            generate(
+                Coordinates::new(40.7128, -74.0060),
+                1000.0,
+                GenerationMode::Standard,
+                &GenerationOptions::new("replay")
+                    .with_points(500)
+                    .with_grid_resolution(20)
+                    .with_entropy_check(check),
+                &ReplayBackend::new(&path),
+            )
+        };
+
+        let warned = run(EntropyCheck::Warn).unwrap();
+        let quality = warned.metadata.entropy_quality.unwrap();
+        assert!(quality.failure_summary().unwrap().contains("balanced"));
+
+        let err = run(EntropyCheck::Reject).unwrap_err();
+        assert!(matches!(err, Error::EntropyQuality(_)), "{}", err);
+    }
+
+    #[test]
+    fn test_entropy_bytes_needed_matches_draw() {
+        use crate::qrng::metered::Metered;
+        use crate::usage::Meter;
+        use std::sync::Arc;
+
+        let meter = Arc::new(Meter::new());
+        let backend = Metered::new(
+            Box::new(SeededPseudoBackend::new(42)) as Box<dyn QrngBackend>,
+            "pseudo",
+            0,
+            Arc::clone(&meter),
+        );
+        let center = Coordinates::new(40.7128, -74.0060);
+        generate(
+            center,
+            4000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("pseudo")
+                .with_points(50)
+                .with_grid_resolution(20),
+            &backend,
+        )
+        .unwrap();
+
+        let needed = entropy_bytes_needed(50, GenerationMode::FlowerPower, FloatPrecision::Bits53);
+        assert_eq!(needed, 7 * 50 * 2 * 8);
+        assert_eq!(meter.process()["pseudo"].bytes, needed);
+    }
+
+    #[test]
+    fn test_generate_in_area() {
+        let area = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[-74.02, 40.70], [-74.00, 40.70], [-74.02, 40.72], [-74.02, 40.70]]],
+        }))
+        .unwrap();
+
+        let response = generate_in_area(
+            &area,
+            &GenerationOptions::new("pseudo")
+                .with_points(2000)
+                .with_grid_resolution(30),
+            &SeededPseudoBackend::new(42),
+        )
+        .unwrap();
+
+        assert_eq!(response.circles.len(), 1);
+        assert_eq!(response.circles[0].id, "area");
+        assert_eq!(response.request.mode, GenerationMode::Standard);
+        assert_eq!(response.request.lat, area.center().lat);
+        assert_eq!(response.winners.len(), 4);
+        for winner in response.winners.values() {
+            assert!(area.contains(winner.result.coords));
+        }
+
+        // The area is kept with the request, through history too
+        let json = serde_json::to_string(&response).unwrap();
+        let parsed: GenerationResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.request.area, Some(area));
+    }
+
+    #[test]
+    fn test_generate_with_exclusion_zone() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        // Covers the middle of the center circle
+        let zone = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[-74.02, 40.705], [-73.99, 40.705], [-73.99, 40.72], [-74.02, 40.72], [-74.02, 40.705]]],
+        }))
+        .unwrap();
+
+        let response = generate(
+            center,
+            4000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("pseudo")
+                .with_points(1000)
+                .with_grid_resolution(20)
+                .with_include_points(true)
+                .with_exclude(Some(&zone)),
+            &SeededPseudoBackend::new(42),
+        )
+        .unwrap();
+
+        assert_eq!(response.request.exclude.as_ref(), Some(&zone));
+        for circle in &response.circles {
+            let points = circle.points.as_ref().unwrap();
+            assert_eq!(points.len(), 1000);
+            assert!(points.iter().all(|&p| !zone.contains(p)), "{}", circle.id);
+        }
+        for (anomaly_type, winner) in &response.winners {
+            assert!(!zone.contains(winner.result.coords), "{} in the zone", anomaly_type);
+        }
     }
 
     #[test]
@@ -331,12 +883,12 @@ mod tests {
 
         let response = generate(
             center,
-            3000.0, // Must be >= FLOWER_POWER_MIN_RADIUS
-            1000,
-            50,
-            false,
+            3000.0,
             GenerationMode::FlowerPower,
-            "pseudo",
+            &GenerationOptions::new("pseudo")
+                .with_points(// Must be >= FLOWER_POWER_MIN_RADIUS
+            1000)
+                .with_grid_resolution(50),
             &backend,
         )
         .unwrap();
@@ -348,10 +900,10 @@ mod tests {
         assert_eq!(response.circles[6].id, "petal_5");
 
         // Should have all anomaly types in winners
-        assert!(response.winners.contains_key(&AnomalyType::BlindSpot));
-        assert!(response.winners.contains_key(&AnomalyType::Attractor));
-        assert!(response.winners.contains_key(&AnomalyType::Void));
-        assert!(response.winners.contains_key(&AnomalyType::Power));
+        assert!(response.winners.contains_key(&AnomalyType::BLIND_SPOT));
+        assert!(response.winners.contains_key(&AnomalyType::ATTRACTOR));
+        assert!(response.winners.contains_key(&AnomalyType::VOID));
+        assert!(response.winners.contains_key(&AnomalyType::POWER));
     }
 
     #[test]
@@ -362,11 +914,12 @@ mod tests {
         let response = generate(
             center,
             1000.0,
-            500,
-            50,
-            true, // Include points
+            // Include points
             GenerationMode::Standard,
-            "pseudo",
+            &GenerationOptions::new("pseudo")
+                .with_points(500)
+                .with_grid_resolution(50)
+                .with_include_points(true),
             &backend,
         )
         .unwrap();
@@ -376,6 +929,65 @@ mod tests {
         assert_eq!(response.circles[0].points.as_ref().unwrap().len(), 500);
     }
 
+    #[tokio::test]
+    async fn test_entropy_check_tests_a_bounded_sample() {
+        let adapter = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(42)));
+        let options = GenerationOptions::new("pseudo")
+            .with_points(1000)
+            .with_grid_resolution(20)
+            .with_entropy_check(EntropyCheck::Warn);
+        let response = generate_async(
+            Coordinates::new(40.7128, -74.0060),
+            3000.0,
+            GenerationMode::FlowerPower,
+            &options,
+            &adapter,
+        )
+        .await
+        .unwrap();
+
+        let provenance = response.metadata.provenance.unwrap();
+        assert_eq!(provenance.bytes, 7 * 1000 * 2 * 8);
+        let quality = response.metadata.entropy_quality.unwrap();
+        assert_eq!(quality.bytes_analyzed, ENTROPY_CHECK_MAX_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_generate_async_matches_sync() {
+        let center = Coordinates::new(40.7128, -74.0060);
+
+        let sync_response = generate(
+            center,
+            3000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("pseudo")
+                .with_points(500)
+                .with_grid_resolution(20),
+            &SeededPseudoBackend::new(42),
+        )
+        .unwrap();
+
+        let adapter = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(42)));
+        let async_response = generate_async(
+            center,
+            3000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("pseudo")
+                .with_points(500)
+                .with_grid_resolution(20),
+            &adapter,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(async_response.circles.len(), 7);
+        for (anomaly_type, winner) in &sync_response.winners {
+            let other = &async_response.winners[anomaly_type];
+            assert_eq!(winner.circle_id, other.circle_id);
+            assert_eq!(winner.result.coords, other.result.coords);
+        }
+    }
+
     #[test]
     fn test_response_serialization() {
         let backend = SeededPseudoBackend::new(42);
@@ -384,11 +996,10 @@ mod tests {
         let response = generate(
             center,
             1000.0,
-            100,
-            50,
-            false,
             GenerationMode::Standard,
-            "pseudo",
+            &GenerationOptions::new("pseudo")
+                .with_points(100)
+                .with_grid_resolution(50),
             &backend,
         )
         .unwrap();
@@ -0,0 +1,336 @@
+//! Kernel density estimation for anomaly detection
+//!
+//! An alternative to counting points per grid cell: a Gaussian kernel is
+//! centered on every point and the summed surface is searched at continuous
+//! positions, so a cluster straddling cell boundaries shows up as one peak
+//! instead of four middling cells. Each position is compared against what
+//! uniform points would give there, with the search area's edges and
+//! exclusion zones accounted for, giving a z-score like the grid's.
+
+use crate::coord::density::DensityGrid;
+use crate::coord::Coordinates;
+
+/// Uniform points put about this many points under each kernel
+///
+/// Comparable to the handful per cell of the default 50×50 grid; wider
+/// kernels would smooth away small clusters.
+pub const KERNEL_POINTS: f64 = 10.0;
+
+/// Kernel weights beyond this many bandwidths are ignored
+const CUTOFF_BANDWIDTHS: f64 = 4.0;
+
+/// Refining a peak or trough stops once steps are this small (meters)
+const MIN_STEP_METERS: f64 = 0.5;
+
+/// Most moves made while refining a peak or trough
+const MAX_MOVES: usize = 200;
+
+/// Directions tried while refining, in the projected plane
+const DIRECTIONS: [(f64, f64); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (1.0, 1.0),
+    (1.0, -1.0),
+    (-1.0, 1.0),
+    (-1.0, -1.0),
+];
+
+/// A projected position (x, y) and its z-score
+type Scored = (f64, f64, f64);
+
+/// Kernel bandwidth in meters for `count` points in a circle of `radius`
+///
+/// A Gaussian kernel of bandwidth h weighs points like a disk of area 4πh²
+/// would, which uniform points fill with `KERNEL_POINTS` when
+/// h = radius·√(KERNEL_POINTS / 4n).
+pub fn bandwidth(radius: f64, count: usize) -> f64 {
+    radius * (KERNEL_POINTS / (4.0 * count.max(1) as f64)).sqrt()
+}
+
+/// Kernel density estimate of points over a search area
+///
+/// The area is that of a `DensityGrid` (its circle or polygon, less any
+/// excluded cells); the grid's cells serve as the raster the expected
+/// density is integrated over. Its counts aren't used.
+pub struct KernelDensity<'a> {
+    grid: &'a DensityGrid,
+    bandwidth: f64,
+    /// Projected points by the grid cell they fall in
+    buckets: Vec<Vec<Vec<(f64, f64)>>>,
+    /// Points per in-area cell
+    rate: f64,
+    /// How many cells a kernel reaches
+    reach: isize,
+    /// Offsets (meters, from a cell's center) the expected density is
+    /// sampled at, spaced no wider than the bandwidth
+    samples: Vec<(f64, f64)>,
+}
+
+/// A density peak or trough
+#[derive(Debug, Clone)]
+pub struct KdeResult {
+    /// Where the estimate peaks or bottoms out
+    pub coords: Coordinates,
+    /// Kernel-weighted point count there
+    pub estimate: f64,
+    /// Kernel-weighted point count uniform points would give there
+    pub expected: f64,
+    /// Z-score (how many std devs from expected)
+    pub z_score: f64,
+}
+
+impl<'a> KernelDensity<'a> {
+    /// Estimate the density of `points` over the area of `grid`
+    ///
+    /// Points outside the area are left out, as `DensityGrid::add_points`
+    /// does.
+    pub fn new(grid: &'a DensityGrid, points: &[Coordinates]) -> Self {
+        let mut buckets = vec![vec![Vec::new(); grid.resolution]; grid.resolution];
+        let mut count = 0;
+        for point in points {
+            let (x, y) = grid.projection.forward(*point);
+            if let Some((row, col)) = grid.cell_at(x, y) {
+                if grid.in_circle[row][col] {
+                    buckets[row][col].push((x, y));
+                    count += 1;
+                }
+            }
+        }
+
+        let bandwidth = bandwidth(grid.radius, count);
+        let cells = grid.cells_in_circle();
+        let per_side = (grid.cell_size / bandwidth).ceil().max(1.0) as usize;
+        let offset = |i: usize| ((i as f64 + 0.5) / per_side as f64 - 0.5) * grid.cell_size;
+        let samples = (0..per_side)
+            .flat_map(|i| (0..per_side).map(move |j| (i, j)))
+            .map(|(i, j)| (offset(i), offset(j)))
+            .collect();
+        Self {
+            grid,
+            bandwidth,
+            buckets,
+            rate: if cells == 0 { 0.0 } else { count as f64 / cells as f64 },
+            reach: (CUTOFF_BANDWIDTHS * bandwidth / grid.cell_size).ceil() as isize,
+            samples,
+        }
+    }
+
+    /// Kernel bandwidth in meters
+    pub fn bandwidth(&self) -> f64 {
+        self.bandwidth
+    }
+
+    /// Estimate, expectation and z-score at a projected position
+    ///
+    /// Uniform points form a Poisson process, so both the mean and the
+    /// variance of the estimate are the rate integrated against the kernel
+    /// (squared, for the variance) over the area.
+    fn evaluate(&self, x: f64, y: f64) -> (f64, f64, f64) {
+        let grid = self.grid;
+        let two_h2 = 2.0 * self.bandwidth * self.bandwidth;
+        let kernel = |px: f64, py: f64| (-((x - px).powi(2) + (y - py).powi(2)) / two_h2).exp();
+
+        let half_width = grid.half_width();
+        let row0 = ((y + half_width) / grid.cell_size).floor() as isize;
+        let col0 = ((x + half_width) / grid.cell_size).floor() as isize;
+        // Cells within reach, clipped to the grid
+        let span = |i: isize| (i - self.reach).max(0)..=(i + self.reach).min(grid.resolution as isize - 1);
+
+        let (mut estimate, mut weight, mut weight_squared) = (0.0, 0.0, 0.0);
+        for row in span(row0) {
+            for col in span(col0) {
+                let (row, col) = (row as usize, col as usize);
+                if !grid.in_circle[row][col] {
+                    continue;
+                }
+                let (cx, cy) = grid.cell_center(row, col);
+                for &(dx, dy) in &self.samples {
+                    let w = kernel(cx + dx, cy + dy);
+                    weight += w;
+                    weight_squared += w * w;
+                }
+                estimate += self.buckets[row][col].iter().map(|&(px, py)| kernel(px, py)).sum::<f64>();
+            }
+        }
+
+        let per_sample = self.rate / self.samples.len() as f64;
+        let expected = per_sample * weight;
+        let variance = per_sample * weight_squared;
+        let z_score = if variance > 0.0 {
+            (estimate - expected) / variance.sqrt()
+        } else {
+            0.0
+        };
+        (estimate, expected, z_score)
+    }
+
+    /// Whether a projected position is in the area
+    fn in_area(&self, x: f64, y: f64) -> bool {
+        self.grid
+            .cell_at(x, y)
+            .is_some_and(|(row, col)| self.grid.in_circle[row][col])
+    }
+
+    /// Highest and lowest z-score positions among the cell centers
+    fn coarse_extremes(&self) -> Option<(Scored, Scored)> {
+        let mut highest: Option<Scored> = None;
+        let mut lowest: Option<Scored> = None;
+        for row in 0..self.grid.resolution {
+            for col in 0..self.grid.resolution {
+                if !self.grid.in_circle[row][col] {
+                    continue;
+                }
+                let (x, y) = self.grid.cell_center(row, col);
+                let (_, _, z) = self.evaluate(x, y);
+                if highest.is_none_or(|(_, _, best)| z > best) {
+                    highest = Some((x, y, z));
+                }
+                if lowest.is_none_or(|(_, _, best)| z < best) {
+                    lowest = Some((x, y, z));
+                }
+            }
+        }
+        highest.zip(lowest)
+    }
+
+    /// Refine a starting position by compass search on `sign` × z-score
+    fn refine(&self, start: Scored, sign: f64) -> KdeResult {
+        let (mut x, mut y, z) = start;
+        let mut score = sign * z;
+        let mut step = self.grid.cell_size / 2.0;
+        let mut moves = 0;
+
+        while step >= MIN_STEP_METERS && moves < MAX_MOVES {
+            let better = DIRECTIONS.iter().find_map(|(dx, dy)| {
+                let (nx, ny) = (x + dx * step, y + dy * step);
+                if !self.in_area(nx, ny) {
+                    return None;
+                }
+                let candidate = sign * self.evaluate(nx, ny).2;
+                (candidate > score).then_some((nx, ny, candidate))
+            });
+            match better {
+                Some((nx, ny, candidate)) => {
+                    (x, y, score) = (nx, ny, candidate);
+                    moves += 1;
+                }
+                None => step /= 2.0,
+            }
+        }
+
+        let (estimate, expected, z_score) = self.evaluate(x, y);
+        KdeResult {
+            coords: self.grid.projection.inverse(x, y),
+            estimate,
+            expected,
+            z_score,
+        }
+    }
+}
+
+/// Find the density peak and trough (highest and lowest z-scores)
+///
+/// Every in-area cell center is tried, then the best ones are refined to
+/// continuous positions. Returns `None` when the area has no cells.
+pub fn find_peak_and_trough(kde: &KernelDensity) -> Option<(KdeResult, KdeResult)> {
+    let (highest, lowest) = kde.coarse_extremes()?;
+    Some((kde.refine(highest, 1.0), kde.refine(lowest, -1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::density::find_densest_cell;
+    use crate::coord::point::{generate_points_in_circle, haversine_distance};
+    use crate::qrng::pseudo::SeededPseudoBackend;
+
+    #[test]
+    fn test_bandwidth() {
+        // 10 000 points put 10 in a disk of radius 1000·√(10/10 000)
+        let h = bandwidth(1000.0, 10_000);
+        assert!((2.0 * h - 1000.0 * 0.001f64.sqrt()).abs() < 1e-9);
+        // More points, narrower kernels
+        assert!(bandwidth(1000.0, 10_000) < bandwidth(1000.0, 1000));
+    }
+
+    #[test]
+    fn test_uniform_points_have_no_strong_anomaly() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let backend = SeededPseudoBackend::new(42);
+        let points = generate_points_in_circle(center, 1000.0, 10000, &backend).unwrap();
+
+        let grid = DensityGrid::new(center, 1000.0, 50);
+        let kde = KernelDensity::new(&grid, &points);
+        let (peak, trough) = find_peak_and_trough(&kde).unwrap();
+
+        assert!(peak.z_score > 0.0 && peak.z_score < 5.0, "peak z = {}", peak.z_score);
+        assert!(trough.z_score < 0.0 && trough.z_score > -5.0, "trough z = {}", trough.z_score);
+        assert!(peak.estimate > peak.expected);
+        assert!(trough.estimate < trough.expected);
+        // The rim isn't mistaken for a void
+        for result in [&peak, &trough] {
+            assert!(haversine_distance(center, result.coords) <= 1000.0);
+        }
+    }
+
+    #[test]
+    fn test_cluster_across_cell_boundaries() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let backend = SeededPseudoBackend::new(42);
+        let mut points = generate_points_in_circle(center, 1000.0, 10000, &backend).unwrap();
+        // The grid's center is a corner shared by four cells; a cluster on
+        // it splits between them
+        let cluster_backend = SeededPseudoBackend::new(7);
+        points.extend(generate_points_in_circle(center, 25.0, 40, &cluster_backend).unwrap());
+
+        let mut grid = DensityGrid::new(center, 1000.0, 50);
+        let kde = KernelDensity::new(&grid, &points);
+        let (peak, _) = find_peak_and_trough(&kde).unwrap();
+        assert!(
+            haversine_distance(center, peak.coords) < kde.bandwidth() / 2.0,
+            "peak {:.1} m from the cluster",
+            haversine_distance(center, peak.coords)
+        );
+
+        grid.add_points(&points);
+        let densest = find_densest_cell(&grid).unwrap();
+        assert!(
+            peak.z_score > densest.z_score,
+            "KDE z = {:.2}, grid z = {:.2}",
+            peak.z_score,
+            densest.z_score
+        );
+    }
+
+    #[test]
+    fn test_excluded_cells_are_not_troughs() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let backend = SeededPseudoBackend::new(42);
+        let points = generate_points_in_circle(center, 1000.0, 10000, &backend).unwrap();
+        // Points fell everywhere, but half the cells are out of the area
+        let mut grid = DensityGrid::new(center, 1000.0, 50);
+        for row in grid.in_circle.iter_mut() {
+            row[..25].fill(false);
+        }
+
+        let kde = KernelDensity::new(&grid, &points);
+        let (peak, trough) = find_peak_and_trough(&kde).unwrap();
+        for result in [&peak, &trough] {
+            assert!(result.coords.lng >= center.lng, "{:?} is in the excluded half", result.coords);
+            assert!(result.z_score.abs() < 5.0, "z = {}", result.z_score);
+        }
+    }
+
+    #[test]
+    fn test_empty_area() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let mut grid = DensityGrid::new(center, 1000.0, 10);
+        for row in grid.in_circle.iter_mut() {
+            row.fill(false);
+        }
+        let kde = KernelDensity::new(&grid, &[center]);
+        assert!(find_peak_and_trough(&kde).is_none());
+    }
+}
@@ -1,17 +1,26 @@
 //! Coordinate generation and analysis
 //!
 //! This module handles:
-//! - Generating random points within a circle
-//! - Density grid analysis
-//! - Anomaly detection (attractor, void, power)
+//! - Generating random points within a circle or polygon area
+//! - Density grid and kernel density analysis
+//! - Anomaly detection (attractor, void, power and any other registered
+//!   detectors)
 //! - Flower power multi-circle generation
 
 pub mod anomaly;
+pub mod area;
+pub mod commit;
 pub mod density;
+pub mod detector;
 pub mod flower;
+pub mod kde;
 pub mod point;
+pub mod projection;
+pub mod provenance;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
 
 /// A geographic coordinate (latitude, longitude)
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -91,21 +100,16 @@ impl Point {
 }
 
 /// Generation mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GenerationMode {
     /// Single circle around the center point
+    #[default]
     Standard,
     /// Seven overlapping circles (flower pattern)
     FlowerPower,
 }
 
-impl Default for GenerationMode {
-    fn default() -> Self {
-        Self::Standard
-    }
-}
-
 impl std::str::FromStr for GenerationMode {
     type Err = String;
 
@@ -118,51 +122,119 @@ impl std::str::FromStr for GenerationMode {
     }
 }
 
-/// Anomaly types that can be detected
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// How point density is estimated when looking for anomalies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub enum AnomalyType {
-    /// Single random point (no analysis)
-    BlindSpot,
-    /// Densest cluster (most points in area)
-    Attractor,
-    /// Emptiest region (fewest points in area)
-    Void,
-    /// Most statistically anomalous (highest absolute z-score)
-    Power,
+pub enum DetectionMethod {
+    /// Count points per cell of a square grid
+    #[default]
+    Grid,
+    /// Gaussian kernel density estimate, searched at continuous positions
+    Kde,
 }
 
-impl std::fmt::Display for AnomalyType {
+impl std::fmt::Display for DetectionMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::BlindSpot => write!(f, "blind_spot"),
-            Self::Attractor => write!(f, "attractor"),
-            Self::Void => write!(f, "void"),
-            Self::Power => write!(f, "power"),
+            Self::Grid => write!(f, "grid"),
+            Self::Kde => write!(f, "kde"),
         }
     }
 }
 
-impl std::str::FromStr for AnomalyType {
+impl std::str::FromStr for DetectionMethod {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "blind_spot" | "blind-spot" | "blindspot" => Ok(Self::BlindSpot),
-            "attractor" => Ok(Self::Attractor),
-            "void" => Ok(Self::Void),
-            "power" => Ok(Self::Power),
-            _ => Err(format!("Unknown anomaly type: {}", s)),
+            "grid" => Ok(Self::Grid),
+            "kde" | "kernel" => Ok(Self::Kde),
+            _ => Err(format!("Unknown detection method: {}", s)),
+        }
+    }
+}
+
+/// An anomaly type: the name of a registered `detector::AnomalyDetector`
+///
+/// The built-in types are constants such as `AnomalyType::ATTRACTOR`;
+/// parsing accepts the name of any registered detector. Deserializing also
+/// keeps names it doesn't know (e.g. from history written by a newer
+/// version), so they survive being saved again; see `AnomalyType::intern`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnomalyType(&'static str);
+
+impl AnomalyType {
+    /// The type found by the detector named `name`
+    pub const fn new(name: &'static str) -> Self {
+        Self(name)
+    }
+
+    /// The detector's name
+    pub const fn name(&self) -> &'static str {
+        self.0
+    }
+
+    /// The type named `name`, whether or not a detector finds it
+    ///
+    /// Registered names are matched as `detector::get_detector` does.
+    ///
+    /// Names without a detector are leaked once each and reused after, so
+    /// memory grows only with the number of distinct unknown names.
+    pub fn intern(name: &str) -> Self {
+        if let Some(detector) = detector::get_detector(name) {
+            return detector.anomaly_type();
+        }
+        static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+        let mut names = NAMES.get_or_init(Default::default).lock().unwrap();
+        match names.get(name) {
+            Some(&interned) => Self(interned),
+            None => {
+                let interned: &'static str = Box::leak(name.into());
+                names.insert(interned);
+                Self(interned)
+            }
         }
     }
+
+    /// The detector that finds this type, if it's registered
+    pub fn detector(&self) -> Option<&'static dyn detector::AnomalyDetector> {
+        detector::get_detector(self.0)
+    }
+}
+
+impl std::fmt::Display for AnomalyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::str::FromStr for AnomalyType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        detector::get_detector(s)
+            .map(|detector| detector.anomaly_type())
+            .ok_or_else(|| format!("Unknown anomaly type: {}", s))
+    }
+}
+
+impl Serialize for AnomalyType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnomalyType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::intern(&name))
+    }
 }
 
 /// List all available anomaly types
 pub fn available_types() -> Vec<AnomalyType> {
-    vec![
-        AnomalyType::BlindSpot,
-        AnomalyType::Attractor,
-        AnomalyType::Void,
-        AnomalyType::Power,
-    ]
+    detector::detectors()
+        .iter()
+        .map(|detector| detector.anomaly_type())
+        .collect()
 }
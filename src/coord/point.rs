@@ -2,14 +2,32 @@
 //!
 //! Generates random points uniformly distributed within a spherical cap.
 //! Uses true spherical geometry for accuracy at all latitudes, including poles.
+//!
+//! Points can also be confined to a polygon area or kept out of exclusion
+//! zones by rejection sampling: candidates are drawn over the cap and those
+//! landing in the wrong place are replaced, which keeps the rest uniform.
 
+use crate::coord::area::Area;
 use crate::coord::Coordinates;
-use crate::error::Result;
-use crate::qrng::QrngBackend;
+use crate::error::{Error, Result};
+use crate::qrng::{AsyncQrngBackend, FloatPrecision, QrngBackend};
 use std::f64::consts::PI;
 
 /// Earth radius in meters (mean radius)
-const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+pub(crate) const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Rounds of drawing before giving up on filling a region
+const MAX_ROUNDS: usize = 32;
+
+/// Points per side of the lattice estimating how much exclusion zones remove
+const LATTICE_SIDE: usize = 64;
+
+/// Smallest fraction of candidates a search may expect to keep
+///
+/// Below this, rejection sampling would draw thousands of times the entropy
+/// the points need (e.g. for tiny polygons on opposite sides of the globe,
+/// whose enclosing cap is enormous).
+pub const MIN_ACCEPTANCE: f64 = 1e-4;
 
 /// Generate a single random point uniformly distributed within a spherical cap
 ///
@@ -47,7 +65,7 @@ pub fn generate_point_in_circle(
 ///
 /// # Returns
 /// A point uniformly distributed within the spherical cap
-fn generate_point_spherical(
+pub(crate) fn generate_point_spherical(
     center: Coordinates,
     radius_meters: f64,
     u1: f64,
@@ -109,14 +127,228 @@ pub fn generate_points_in_circle(
 ) -> Result<Vec<Coordinates>> {
     // Get all random floats at once for efficiency
     let floats = rng.floats(count * 2)?;
-    let mut points = Vec::with_capacity(count);
+    Ok(points_from_floats(center, radius_meters, &floats))
+}
+
+/// Generate many random points without blocking the async runtime
+///
+/// Same as `generate_points_in_circle`, drawing entropy from an async backend.
+pub async fn generate_points_in_circle_async(
+    center: Coordinates,
+    radius_meters: f64,
+    count: usize,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<Vec<Coordinates>> {
+    let floats = rng.floats_async(count * 2).await?;
+    Ok(points_from_floats(center, radius_meters, &floats))
+}
+
+/// Turn pairs of uniform floats into points within the spherical cap
+fn points_from_floats(center: Coordinates, radius_meters: f64, floats: &[f64]) -> Vec<Coordinates> {
+    floats
+        .chunks_exact(2)
+        .map(|pair| generate_point_spherical(center, radius_meters, pair[0], pair[1]))
+        .collect()
+}
+
+/// Generate many random points within a spherical cap, outside exclusion zones
+///
+/// Candidates landing in `exclude` are rejected and more are drawn, so the
+/// points stay uniform over the rest of the cap.
+pub fn generate_points_in_circle_excluding(
+    center: Coordinates,
+    radius_meters: f64,
+    count: usize,
+    exclude: &Area,
+    rng: &dyn QrngBackend,
+) -> Result<Vec<Coordinates>> {
+    Sampler::new(center, radius_meters, None, Some(exclude))?.sample(count, rng)
+}
+
+/// Generate points outside exclusion zones without blocking the async runtime
+///
+/// Same as `generate_points_in_circle_excluding`, drawing entropy from an
+/// async backend.
+pub async fn generate_points_in_circle_excluding_async(
+    center: Coordinates,
+    radius_meters: f64,
+    count: usize,
+    exclude: &Area,
+    rng: &dyn AsyncQrngBackend,
+) -> Result<Vec<Coordinates>> {
+    Sampler::new(center, radius_meters, None, Some(exclude))?
+        .sample_async(count, rng)
+        .await
+}
+
+/// Rejection sampler over a spherical cap
+///
+/// Keeps candidates inside `area` (if any) and outside `exclude` (if any),
+/// drawing in rounds until there are enough. Round sizes depend only on the
+/// geometry and what was kept so far, so a given byte stream always gives
+/// the same points.
+pub(crate) struct Sampler<'a> {
+    center: Coordinates,
+    radius_meters: f64,
+    area: Option<&'a Area>,
+    exclude: Option<&'a Area>,
+    /// Expected fraction of candidates kept
+    acceptance: f64,
+}
+
+impl<'a> Sampler<'a> {
+    /// Sample the cap, narrowed to `area` and minus `exclude`
+    ///
+    /// Fails if the exclusion zones leave nothing to sample, or if fewer
+    /// than `MIN_ACCEPTANCE` of the candidates would be kept.
+    pub(crate) fn new(
+        center: Coordinates,
+        radius_meters: f64,
+        area: Option<&'a Area>,
+        exclude: Option<&'a Area>,
+    ) -> Result<Self> {
+        let mut sampler = Self {
+            center,
+            radius_meters,
+            area,
+            exclude,
+            acceptance: area.map_or(1.0, Area::acceptance),
+        };
+
+        // Zone areas say little about how much of this cap they cover, so
+        // estimate that over a fixed lattice of cap points, unless the
+        // circle enclosing them misses the cap altogether
+        let overlaps = |zones: &Area| {
+            haversine_distance(center, zones.center()) < radius_meters + zones.radius()
+        };
+        if exclude.is_some_and(overlaps) {
+            let u = |i: usize| (i as f64 + 0.5) / LATTICE_SIDE as f64;
+            let (mut inside, mut kept) = (0usize, 0usize);
+            for i in 0..LATTICE_SIDE {
+                for j in 0..LATTICE_SIDE {
+                    let point = generate_point_spherical(center, radius_meters, u(i), u(j));
+                    if area.is_none_or(|area| area.contains(point)) {
+                        inside += 1;
+                        kept += usize::from(sampler.keeps(point));
+                    }
+                }
+            }
+            if inside > 0 && kept == 0 {
+                return Err(Error::InvalidArea(
+                    "Exclusion zones cover the whole search area".to_string(),
+                ));
+            }
+            if inside > 0 {
+                sampler.acceptance *= kept as f64 / inside as f64;
+            }
+        }
+        if sampler.acceptance < MIN_ACCEPTANCE {
+            return Err(Error::InvalidArea(format!(
+                "Too little of the circle enclosing the search area can hold points \
+                 ({:.1e} of it, under {:.0e}); search far-apart polygons separately",
+                sampler.acceptance,
+                MIN_ACCEPTANCE
+            )));
+        }
+        Ok(sampler)
+    }
+
+    /// Bytes of entropy generating `count` points usually draws
+    ///
+    /// Rejection sampling makes the exact amount random; this is the first
+    /// round's draw, which covers the whole generation almost every time.
+    pub(crate) fn entropy_bytes_needed(&self, count: usize, precision: FloatPrecision) -> u64 {
+        (draws_for(count, self.acceptance) as u64).saturating_mul(2 * precision.bytes_per_float() as u64)
+    }
+
+    /// Whether a candidate point is kept
+    fn keeps(&self, point: Coordinates) -> bool {
+        self.area.is_none_or(|area| area.contains(point))
+            && !self.exclude.is_some_and(|zones| zones.contains(point))
+    }
+
+    /// Candidates to draw next, given how many were examined and kept so far
+    fn next_draw(&self, count: usize, examined: usize, kept: usize) -> usize {
+        let rate = if kept > 0 {
+            kept as f64 / examined as f64
+        } else {
+            self.acceptance
+        };
+        draws_for(count - kept, rate)
+    }
+
+    /// Keep candidates from pairs of floats until there are `count`,
+    /// returning how many candidates were examined
+    fn keep(&self, floats: &[f64], count: usize, points: &mut Vec<Coordinates>) -> usize {
+        let mut examined = 0;
+        for pair in floats.chunks_exact(2) {
+            if points.len() == count {
+                break;
+            }
+            examined += 1;
+            let point = generate_point_spherical(self.center, self.radius_meters, pair[0], pair[1]);
+            if self.keeps(point) {
+                points.push(point);
+            }
+        }
+        examined
+    }
+
+    /// Draw `count` points from a sync backend
+    pub(crate) fn sample(&self, count: usize, rng: &dyn QrngBackend) -> Result<Vec<Coordinates>> {
+        let mut points = Vec::with_capacity(count);
+        let mut examined = 0;
+        for _ in 0..MAX_ROUNDS {
+            if points.len() == count {
+                break;
+            }
+            let floats = rng.floats(self.next_draw(count, examined, points.len()).saturating_mul(2))?;
+            examined += self.keep(&floats, count, &mut points);
+        }
+        filled(points, count)
+    }
 
-    for i in 0..count {
-        let u1 = floats[i * 2];
-        let u2 = floats[i * 2 + 1];
-        points.push(generate_point_spherical(center, radius_meters, u1, u2));
+    /// Draw `count` points from an async backend
+    pub(crate) async fn sample_async(
+        &self,
+        count: usize,
+        rng: &dyn AsyncQrngBackend,
+    ) -> Result<Vec<Coordinates>> {
+        let mut points = Vec::with_capacity(count);
+        let mut examined = 0;
+        for _ in 0..MAX_ROUNDS {
+            if points.len() == count {
+                break;
+            }
+            let floats = rng
+                .floats_async(self.next_draw(count, examined, points.len()).saturating_mul(2))
+                .await?;
+            examined += self.keep(&floats, count, &mut points);
+        }
+        filled(points, count)
+    }
+}
+
+/// Candidates to draw for `needed` more points to be kept at `rate`
+///
+/// Three standard deviations over the expected count, so another round is
+/// rarely needed. Saturates at `usize::MAX` for vanishing rates.
+pub(crate) fn draws_for(needed: usize, rate: f64) -> usize {
+    if needed == 0 {
+        return 0;
     }
+    let needed = needed as f64;
+    ((needed + 3.0 * needed.sqrt()) / rate.clamp(f64::MIN_POSITIVE, 1.0)).ceil() as usize
+}
 
+fn filled(points: Vec<Coordinates>, count: usize) -> Result<Vec<Coordinates>> {
+    if points.len() < count {
+        return Err(Error::InvalidArea(format!(
+            "Only {} of {} points landed in the search area; is it too small or thin?",
+            points.len(),
+            count
+        )));
+    }
     Ok(points)
 }
 
@@ -158,6 +390,8 @@ pub fn is_in_circle(point: Coordinates, center: Coordinates, radius_meters: f64)
 mod tests {
     use super::*;
     use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::precision::WithPrecision;
+    use crate::qrng::{FloatPrecision, SyncAdapter};
 
     #[test]
     fn test_generate_point_in_circle() {
@@ -230,6 +464,152 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_uniform_distribution_at_each_float_precision() {
+        // Byte-driven backends build floats from 53 bits by default, 32 when
+        // configured with float_bits; both must stay uniform over the disk
+        let center = Coordinates::new(0.0, 0.0);
+        let radius = 3000.0;
+        let count = 10000;
+
+        for precision in [FloatPrecision::Bits53, FloatPrecision::Bits32] {
+            let backend = WithPrecision::new(
+                Box::new(SeededPseudoBackend::new(12345)) as Box<dyn QrngBackend>,
+                precision,
+            );
+            let points = generate_points_in_circle(center, radius, count, &backend).unwrap();
+
+            let expected_avg_distance = 2.0 * radius / 3.0;
+            let actual_avg_distance: f64 = points
+                .iter()
+                .map(|p| haversine_distance(center, *p))
+                .sum::<f64>()
+                / count as f64;
+            assert!(
+                (actual_avg_distance - expected_avg_distance).abs() < expected_avg_distance * 0.05,
+                "{:?}: average distance {} vs expected {}",
+                precision,
+                actual_avg_distance,
+                expected_avg_distance
+            );
+
+            // Half the points should fall north of the center, half east
+            let north = points.iter().filter(|p| p.lat > center.lat).count();
+            let east = points.iter().filter(|p| p.lng > center.lng).count();
+            for (side, n) in [("north", north), ("east", east)] {
+                let fraction = n as f64 / count as f64;
+                assert!(
+                    (fraction - 0.5).abs() < 0.03,
+                    "{:?}: {:.3} of points {}",
+                    precision,
+                    fraction,
+                    side
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_points_async_matches_sync() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let sync_points =
+            generate_points_in_circle(center, 1000.0, 100, &SeededPseudoBackend::new(42)).unwrap();
+
+        let adapter = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(42)));
+        let async_points = generate_points_in_circle_async(center, 1000.0, 100, &adapter)
+            .await
+            .unwrap();
+
+        assert_eq!(sync_points, async_points);
+    }
+
+    /// A lng/lat box as an exclusion zone
+    fn zone(west: f64, south: f64, east: f64, north: f64) -> Area {
+        Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[west, south], [east, south], [east, north], [west, north], [west, south]]],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_exclusion_zone_points_rejected() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let west_half = zone(-75.0, 40.0, center.lng, 41.0);
+        let backend = SeededPseudoBackend::new(42);
+        let count = 10_000;
+
+        let points =
+            generate_points_in_circle_excluding(center, 1000.0, count, &west_half, &backend).unwrap();
+
+        assert_eq!(points.len(), count);
+        assert!(points.iter().all(|p| p.lng >= center.lng && is_in_circle(*p, center, 1000.0)));
+        // Still uniform over what's left
+        let north = points.iter().filter(|p| p.lat > center.lat).count() as f64 / count as f64;
+        assert!((north - 0.5).abs() < 0.03, "{:.3} of points north", north);
+    }
+
+    #[test]
+    fn test_exclusion_zone_covering_circle() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        let everything = zone(-75.0, 40.0, -73.0, 41.0);
+        let result = generate_points_in_circle_excluding(
+            center,
+            1000.0,
+            10,
+            &everything,
+            &SeededPseudoBackend::new(42),
+        );
+        assert!(matches!(result, Err(Error::InvalidArea(_))));
+
+        // Zones away from the circle change nothing
+        let far = zone(10.0, 10.0, 11.0, 11.0);
+        let backend = SeededPseudoBackend::new(42);
+        let excluding = generate_points_in_circle_excluding(center, 1000.0, 100, &far, &backend).unwrap();
+        let plain = generate_points_in_circle(center, 1000.0, 100, &SeededPseudoBackend::new(42)).unwrap();
+        assert_eq!(excluding, plain);
+    }
+
+    #[test]
+    fn test_exclusion_acceptance_estimate() {
+        let center = Coordinates::new(40.7128, -74.0060);
+        // A 20 x 20 checkerboard of zones over the whole circle, half of it
+        let step = 0.002;
+        let (west, south) = (center.lng - 0.02, center.lat - 0.02);
+        let squares = (0..20)
+            .flat_map(|i| (0..20).map(move |j| (i, j)))
+            .filter(|(i, j)| (i + j) % 2 == 0);
+        let checkerboard = Area::union(squares.map(|(i, j)| {
+            let (w, s) = (west + i as f64 * step, south + j as f64 * step);
+            zone(w, s, w + step, s + step)
+        }))
+        .unwrap()
+        .unwrap();
+        let sampler = Sampler::new(center, 1000.0, None, Some(&checkerboard)).unwrap();
+        assert!((sampler.acceptance - 0.5).abs() < 0.05, "{}", sampler.acceptance);
+
+        // Zones whose enclosing circle misses this one aren't estimated
+        let far = zone(10.0, 10.0, 11.0, 11.0);
+        let sampler = Sampler::new(center, 1000.0, None, Some(&far)).unwrap();
+        assert_eq!(sampler.acceptance, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_generate_points_excluding_async_matches_sync() {
+        let center = Coordinates::new(51.5, -0.12);
+        let park = zone(-0.13, 51.49, -0.11, 51.505);
+        let sync_points =
+            generate_points_in_circle_excluding(center, 2000.0, 500, &park, &SeededPseudoBackend::new(9))
+                .unwrap();
+
+        let adapter = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(9)));
+        let async_points = generate_points_in_circle_excluding_async(center, 2000.0, 500, &park, &adapter)
+            .await
+            .unwrap();
+
+        assert_eq!(sync_points, async_points);
+    }
+
     #[test]
     fn test_haversine_distance() {
         // NYC to nearby point (about 1 degree = ~111km)
@@ -0,0 +1,158 @@
+//! Lambert azimuthal equal-area projection
+//!
+//! Maps the sphere onto a plane centered on a point, in meters east and
+//! north of it. Areas are preserved everywhere and directions from the
+//! center are true, so a circle around the center stays a circle and a
+//! square grid over the plane has cells of equal ground area at any
+//! latitude, poles and ±180° included.
+
+use crate::coord::point::EARTH_RADIUS_METERS;
+use crate::coord::Coordinates;
+
+/// Azimuthal equal-area projection about a center point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AzimuthalEqualArea {
+    center: Coordinates,
+    sin_lat: f64,
+    cos_lat: f64,
+}
+
+impl AzimuthalEqualArea {
+    /// Project about `center`
+    pub fn new(center: Coordinates) -> Self {
+        let lat = center.lat.to_radians();
+        Self {
+            center,
+            sin_lat: lat.sin(),
+            cos_lat: lat.cos(),
+        }
+    }
+
+    /// Center of the projection
+    pub fn center(&self) -> Coordinates {
+        self.center
+    }
+
+    /// Project a point to meters east (x) and north (y) of the center
+    pub fn forward(&self, point: Coordinates) -> (f64, f64) {
+        let lat = point.lat.to_radians();
+        // Sines and cosines absorb any wrap across ±180°
+        let delta_lng = (point.lng - self.center.lng).to_radians();
+        let cos_distance =
+            self.sin_lat * lat.sin() + self.cos_lat * lat.cos() * delta_lng.cos();
+        if 1.0 + cos_distance <= f64::EPSILON {
+            // The antipode maps to the whole rim; pick its southernmost point
+            return (0.0, -2.0 * EARTH_RADIUS_METERS);
+        }
+
+        let k = (2.0 / (1.0 + cos_distance)).sqrt() * EARTH_RADIUS_METERS;
+        let x = k * lat.cos() * delta_lng.sin();
+        let y = k * (self.cos_lat * lat.sin() - self.sin_lat * lat.cos() * delta_lng.cos());
+        (x, y)
+    }
+
+    /// The point projected to `x` meters east and `y` meters north
+    pub fn inverse(&self, x: f64, y: f64) -> Coordinates {
+        let rho = x.hypot(y);
+        if rho == 0.0 {
+            return self.center;
+        }
+
+        // Angular distance from the center
+        let c = 2.0 * (rho / (2.0 * EARTH_RADIUS_METERS)).min(1.0).asin();
+        let (sin_c, cos_c) = c.sin_cos();
+        let lat = (cos_c * self.sin_lat + y * sin_c * self.cos_lat / rho)
+            .clamp(-1.0, 1.0)
+            .asin();
+        let delta_lng = (x * sin_c).atan2(rho * self.cos_lat * cos_c - y * self.sin_lat * sin_c);
+
+        let mut lng = self.center.lng + delta_lng.to_degrees();
+        if lng > 180.0 {
+            lng -= 360.0;
+        } else if lng < -180.0 {
+            lng += 360.0;
+        }
+        Coordinates::new(lat.to_degrees(), lng)
+    }
+
+    /// The point `distance` meters from the center along `bearing` (radians
+    /// clockwise from north)
+    pub fn destination(&self, distance: f64, bearing: f64) -> Coordinates {
+        let rho = planar_distance(distance);
+        self.inverse(rho * bearing.sin(), rho * bearing.cos())
+    }
+}
+
+/// How far from the center in the plane a point `distance` meters away on
+/// the ground lands
+pub fn planar_distance(distance: f64) -> f64 {
+    let half_angle = (distance / (2.0 * EARTH_RADIUS_METERS)).min(std::f64::consts::FRAC_PI_2);
+    2.0 * EARTH_RADIUS_METERS * half_angle.sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::point::haversine_distance;
+
+    #[test]
+    fn test_round_trip() {
+        for (lat, lng) in [(40.7, -74.0), (89.9, 25.0), (90.0, 0.0), (-90.0, 0.0), (0.0, 180.0), (-45.0, -179.95)] {
+            let projection = AzimuthalEqualArea::new(Coordinates::new(lat, lng));
+            for (x, y) in [(0.0, 0.0), (1000.0, 0.0), (-2500.0, 4000.0), (3000.0, -3000.0)] {
+                let point = projection.inverse(x, y);
+                point.validate().unwrap();
+                let (x2, y2) = projection.forward(point);
+                assert!(
+                    (x - x2).abs() < 1e-6 && (y - y2).abs() < 1e-6,
+                    "({}, {}): ({}, {}) came back as ({}, {})",
+                    lat,
+                    lng,
+                    x,
+                    y,
+                    x2,
+                    y2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_distances_from_center() {
+        let center = Coordinates::new(60.0, 10.0);
+        let projection = AzimuthalEqualArea::new(center);
+        for bearing in [0.0, 1.0, 2.5, 4.0] {
+            for distance in [10.0, 5000.0, 500_000.0] {
+                let point = projection.destination(distance, bearing);
+                let error = (haversine_distance(center, point) - distance).abs();
+                assert!(error < distance * 1e-9 + 1e-6, "{} m off at {} m", error, distance);
+                let (x, y) = projection.forward(point);
+                assert!((x.hypot(y) - planar_distance(distance)).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_across_date_line() {
+        let projection = AzimuthalEqualArea::new(Coordinates::new(10.0, 179.99));
+
+        // Just across ±180° is just east, not most of the way around
+        let (x, y) = projection.forward(Coordinates::new(10.0, -179.99));
+        assert!(x > 2000.0 && x < 2300.0, "x = {}", x);
+        assert!(y.abs() < 10.0);
+
+        let east = projection.destination(3000.0, std::f64::consts::FRAC_PI_2);
+        assert!(east.lng < -179.9 && east.lng >= -180.0, "{:?}", east);
+    }
+
+    #[test]
+    fn test_north_pole_directions() {
+        // From the pole every direction is south; bearings follow meridians
+        let projection = AzimuthalEqualArea::new(Coordinates::new(90.0, 0.0));
+        let toward_greenwich = projection.destination(1000.0, std::f64::consts::PI);
+        assert!(toward_greenwich.lng.abs() < 1e-9, "{:?}", toward_greenwich);
+        let toward_90e = projection.destination(1000.0, std::f64::consts::FRAC_PI_2);
+        assert!((toward_90e.lng - 90.0).abs() < 1e-9, "{:?}", toward_90e);
+        assert!((90.0 - toward_90e.lat) * 111_195.0 - 1000.0 < 1.0);
+    }
+}
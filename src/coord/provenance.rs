@@ -0,0 +1,569 @@
+//! Entropy provenance
+//!
+//! Generation draws every float through a digesting wrapper, so the exact
+//! byte stream consumed is hashed with SHA-256. The digest, byte count and
+//! backend go into `GenerationMetadata::provenance` (and so into history).
+//! The wrapper also collects the backends that supplied each draw, so a
+//! generation names every source it used and no other.
+//!
+//! Given a capture of those bytes (from `record:<backend>:<path>`), `verify`
+//! checks the digest and re-runs the generation from the capture to confirm
+//! the stored winners were derived from it unaltered.
+
+use crate::coord::flower::{
+    generate, generate_in_area, GenerationOptions, GenerationResponse, WinnerResult,
+};
+use crate::coord::{AnomalyType, Coordinates};
+use crate::entropy::EntropyTestResults;
+use crate::error::{Error, Result};
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::precision::WithPrecision;
+use crate::qrng::replay::ReplayBackend;
+use crate::qrng::{AsyncQrngBackend, BoxFuture, FloatPrecision, QrngBackend};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Record of the entropy behind a generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// SHA-256 of every entropy byte consumed, in order (hex)
+    pub sha256: String,
+    /// Number of bytes consumed
+    pub bytes: u64,
+    /// Backend spec the generation asked for
+    pub backend: String,
+    /// Bits of entropy per float (53 or 32)
+    pub float_bits: u32,
+    /// Density grid resolution, needed to re-derive the winners
+    pub grid_resolution: usize,
+}
+
+/// What a generation drew, gathered once it's done
+pub struct Consumption {
+    /// Backends that supplied the draws
+    pub sources: Vec<String>,
+    /// Raw versus delivered bytes, if the backend runs an extractor
+    pub extraction: Option<ExtractionStats>,
+    /// Digest of the bytes drawn
+    pub provenance: Provenance,
+    /// The bytes themselves (empty unless built `with_capture`)
+    pub captured: Vec<u8>,
+    /// Test results for the bytes, once a caller has run them
+    pub quality: Option<EntropyTestResults>,
+}
+
+/// Running digest of consumed bytes, optionally keeping the bytes too
+#[derive(Default)]
+struct Digester {
+    state: Mutex<DigestState>,
+}
+
+#[derive(Default)]
+struct DigestState {
+    hasher: Sha256,
+    count: u64,
+    captured: Option<Vec<u8>>,
+    /// Backends that supplied the draws, in first-use order
+    sources: Vec<String>,
+}
+
+impl Digester {
+    fn capturing() -> Self {
+        Self {
+            state: Mutex::new(DigestState {
+                captured: Some(Vec::new()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn update(&self, bytes: &[u8], sources: Vec<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.hasher.update(bytes);
+        state.count += bytes.len() as u64;
+        if let Some(captured) = &mut state.captured {
+            captured.extend_from_slice(bytes);
+        }
+        // A mix can name the same kind of backend twice in one draw, so
+        // only add what this draw has more of than earlier ones did
+        for (i, source) in sources.iter().enumerate() {
+            let in_draw = sources[..=i].iter().filter(|s| *s == source).count();
+            let seen = state.sources.iter().filter(|s| *s == source).count();
+            if in_draw > seen {
+                state.sources.push(source.clone());
+            }
+        }
+    }
+
+    /// Sources of the draws so far, or `None` before the first draw
+    fn sources(&self) -> Option<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        (!state.sources.is_empty()).then(|| state.sources.clone())
+    }
+
+    fn finish(&self) -> (String, u64) {
+        let state = self.state.lock().unwrap();
+        (to_hex(&state.hasher.clone().finalize()), state.count)
+    }
+
+    fn take_captured(&self) -> Vec<u8> {
+        self.state.lock().unwrap().captured.take().unwrap_or_default()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 of `bytes` as hex
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Sync backend wrapper hashing every byte drawn
+///
+/// Floats are always built from bytes here (never the inner backend's own
+/// `floats`), so the digest covers everything a generation consumed.
+pub struct Digesting<'a> {
+    inner: &'a dyn QrngBackend,
+    digest: Digester,
+}
+
+impl<'a> Digesting<'a> {
+    /// Wrap `inner`
+    pub fn new(inner: &'a dyn QrngBackend) -> Self {
+        Self {
+            inner,
+            digest: Digester::default(),
+        }
+    }
+
+    /// Also keep a copy of every byte drawn, for `Consumption::captured`
+    pub fn with_capture(self) -> Self {
+        Self {
+            digest: Digester::capturing(),
+            ..self
+        }
+    }
+
+    /// Everything drawn so far, taking the captured bytes
+    pub fn consumption(&self, backend: &str, grid_resolution: usize) -> Consumption {
+        let (sha256, bytes) = self.digest.finish();
+        Consumption {
+            sources: self.sources(),
+            extraction: self.inner.extraction(),
+            provenance: Provenance {
+                sha256,
+                bytes,
+                backend: backend.to_string(),
+                float_bits: self.inner.float_precision().bits(),
+                grid_resolution,
+            },
+            captured: self.digest.take_captured(),
+            quality: None,
+        }
+    }
+}
+
+impl QrngBackend for Digesting<'_> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    /// Every backend that supplied a draw through this wrapper
+    fn sources(&self) -> Vec<String> {
+        self.digest.sources().unwrap_or_else(|| self.inner.sources())
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let bytes = self.inner.bytes(n)?;
+        self.digest.update(&bytes, self.inner.sources());
+        Ok(bytes)
+    }
+}
+
+/// Async version of `Digesting`
+pub struct DigestingAsync<'a> {
+    inner: &'a dyn AsyncQrngBackend,
+    digest: Digester,
+}
+
+impl<'a> DigestingAsync<'a> {
+    /// Wrap `inner`
+    pub fn new(inner: &'a dyn AsyncQrngBackend) -> Self {
+        Self {
+            inner,
+            digest: Digester::default(),
+        }
+    }
+
+    /// Also keep a copy of every byte drawn, for `Consumption::captured`
+    pub fn with_capture(self) -> Self {
+        Self {
+            digest: Digester::capturing(),
+            ..self
+        }
+    }
+
+    /// Everything drawn so far, taking the captured bytes
+    pub fn consumption(&self, backend: &str, grid_resolution: usize) -> Consumption {
+        let (sha256, bytes) = self.digest.finish();
+        Consumption {
+            sources: self.sources(),
+            extraction: self.inner.extraction(),
+            provenance: Provenance {
+                sha256,
+                bytes,
+                backend: backend.to_string(),
+                float_bits: self.inner.float_precision().bits(),
+                grid_resolution,
+            },
+            captured: self.digest.take_captured(),
+            quality: None,
+        }
+    }
+}
+
+impl AsyncQrngBackend for DigestingAsync<'_> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    /// Every backend that supplied a draw through this wrapper
+    fn sources(&self) -> Vec<String> {
+        self.digest.sources().unwrap_or_else(|| self.inner.sources())
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let bytes = self.inner.bytes_async(n).await?;
+            self.digest.update(&bytes, self.inner.sources());
+            Ok(bytes)
+        })
+    }
+}
+
+/// Outcome of checking a generation against a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    /// Digest of the capture's first `provenance.bytes` bytes
+    pub capture_sha256: String,
+    /// Whether that digest matches the recorded one
+    pub digest_matches: bool,
+    /// Whether re-running the generation gives the recorded winners
+    pub winners_match: bool,
+}
+
+impl Verification {
+    /// Whether the result is confirmed unaltered
+    pub fn passed(&self) -> bool {
+        self.digest_matches && self.winners_match
+    }
+}
+
+/// Check a generation against the capture of the bytes it consumed
+///
+/// Returns an error if the response has no provenance or the capture can't
+/// be read; a capture that doesn't match is reported, not an error.
+pub fn verify(response: &GenerationResponse, capture: &Path) -> Result<Verification> {
+    let provenance = response.metadata.provenance.as_ref().ok_or_else(|| {
+        Error::Config(format!("Generation {} has no provenance record", response.id))
+    })?;
+    let precision: FloatPrecision = provenance.float_bits.to_string().parse()?;
+
+    let data = std::fs::read(capture)?;
+    let consumed = &data[..data.len().min(provenance.bytes as usize)];
+    let capture_sha256 = sha256_hex(consumed);
+    let digest_matches = consumed.len() as u64 == provenance.bytes
+        && capture_sha256 == provenance.sha256;
+
+    let replay = WithPrecision::new(
+        Box::new(ReplayBackend::new(capture)) as Box<dyn QrngBackend>,
+        precision,
+    );
+    let request = &response.request;
+    let options = GenerationOptions::new(&provenance.backend)
+        .with_points(request.points)
+        .with_grid_resolution(provenance.grid_resolution)
+        .with_method(request.method)
+        .with_exclude(request.exclude.as_ref());
+    let rerun = match &request.area {
+        Some(area) => generate_in_area(area, &options, &replay),
+        None => generate(
+            Coordinates::new(request.lat, request.lng),
+            request.radius,
+            request.mode,
+            &options,
+            &replay,
+        ),
+    };
+    let winners_match = match rerun {
+        Ok(rerun) => same_winners(&rerun.winners, &response.winners),
+        // A capture too short to re-run can't confirm anything
+        Err(_) => false,
+    };
+
+    Ok(Verification {
+        capture_sha256,
+        digest_matches,
+        winners_match,
+    })
+}
+
+/// Tolerance for comparing re-derived values, absorbing the last-digit
+/// rounding a JSON round trip of history can introduce
+const TOLERANCE: f64 = 1e-9;
+
+pub(crate) fn same_winners(
+    a: &HashMap<AnomalyType, WinnerResult>,
+    b: &HashMap<AnomalyType, WinnerResult>,
+) -> bool {
+    let close = |x: f64, y: f64| (x - y).abs() <= TOLERANCE;
+    a.len() == b.len()
+        && a.iter().all(|(anomaly_type, x)| {
+            b.get(anomaly_type).is_some_and(|y| {
+                x.circle_id == y.circle_id
+                    && close(x.result.coords.lat, y.result.coords.lat)
+                    && close(x.result.coords.lng, y.result.coords.lng)
+                    && x.result.is_attractor == y.result.is_attractor
+                    && match (x.result.z_score, y.result.z_score) {
+                        (Some(x), Some(y)) => close(x, y),
+                        (x, y) => x.is_none() && y.is_none(),
+                    }
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::{DetectionMethod, GenerationMode};
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::record::RecordingBackend;
+    use tempfile::TempDir;
+
+    fn recorded_generation(path: &Path) -> GenerationResponse {
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(7)), path);
+        generate(
+            Coordinates::new(51.5, -0.12),
+            4000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("record")
+                .with_points(200)
+                .with_grid_resolution(20),
+            &backend,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_provenance_covers_consumed_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let response = recorded_generation(&path);
+
+        let provenance = response.metadata.provenance.unwrap();
+        let capture = std::fs::read(&path).unwrap();
+        assert_eq!(provenance.bytes, capture.len() as u64);
+        assert_eq!(provenance.bytes, 7 * 200 * 2 * 8);
+        assert_eq!(provenance.sha256, sha256_hex(&capture));
+        assert_eq!(provenance.backend, "record");
+        assert_eq!(provenance.float_bits, 53);
+        assert_eq!(provenance.grid_resolution, 20);
+    }
+
+    /// Backend whose draws alternate between two named sources
+    struct Alternating(Mutex<bool>);
+
+    impl QrngBackend for Alternating {
+        fn name(&self) -> &'static str {
+            "alternating"
+        }
+
+        fn description(&self) -> &'static str {
+            "Alternates sources"
+        }
+
+        fn sources(&self) -> Vec<String> {
+            let name = if *self.0.lock().unwrap() { "b" } else { "a" };
+            vec![name.to_string()]
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            let mut flag = self.0.lock().unwrap();
+            *flag = !*flag;
+            Ok(vec![0; n])
+        }
+    }
+
+    #[test]
+    fn test_sources_cover_every_draw_of_a_generation() {
+        let backend = Alternating(Mutex::new(false));
+        backend.bytes(1).unwrap();
+
+        // Draws before the generation don't count
+        let rng = Digesting::new(&backend);
+        rng.bytes(1).unwrap();
+        assert_eq!(rng.sources(), vec!["a"]);
+        rng.bytes(1).unwrap();
+        rng.bytes(1).unwrap();
+        assert_eq!(rng.sources(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_verify_accepts_untouched_result() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let response = recorded_generation(&path);
+
+        // Survives a JSON round trip, as it would through history
+        let json = serde_json::to_string(&response).unwrap();
+        let response: GenerationResponse = serde_json::from_str(&json).unwrap();
+
+        let result = verify(&response, &path).unwrap();
+        assert!(result.passed(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_verify_after_recording_twice_to_one_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        RecordingBackend::new(Box::new(SeededPseudoBackend::new(99)), &path)
+            .bytes(4096)
+            .unwrap();
+
+        let response = recorded_generation(&path);
+        let result = verify(&response, &path).unwrap();
+        assert!(result.passed(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_verify_area_generation_with_exclusions() {
+        use crate::coord::area::Area;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let area = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[-0.15, 51.50], [-0.10, 51.50], [-0.10, 51.52], [-0.15, 51.50]]],
+        }))
+        .unwrap();
+        let zone = Area::from_geojson(&serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[-0.12, 51.50], [-0.10, 51.50], [-0.10, 51.51], [-0.12, 51.50]]],
+        }))
+        .unwrap();
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(7)), &path);
+        let response = generate_in_area(
+            &area,
+            &GenerationOptions::new("record")
+                .with_points(300)
+                .with_grid_resolution(20)
+                .with_exclude(Some(&zone)),
+            &backend,
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&response).unwrap();
+        let parsed: GenerationResponse = serde_json::from_str(&json).unwrap();
+        assert!(verify(&parsed, &path).unwrap().passed());
+
+        // Re-running without the zones or over the circle can't reproduce it
+        let mut unzoned = response.clone();
+        unzoned.request.exclude = None;
+        assert!(!verify(&unzoned, &path).unwrap().winners_match);
+        let mut circle = response;
+        circle.request.area = None;
+        assert!(!verify(&circle, &path).unwrap().winners_match);
+    }
+
+    #[test]
+    fn test_verify_kde_generation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(7)), &path);
+        let response = generate(
+            Coordinates::new(51.5, -0.12),
+            1000.0,
+            GenerationMode::Standard,
+            &GenerationOptions::new("record")
+                .with_points(500)
+                .with_grid_resolution(20)
+                .with_method(DetectionMethod::Kde),
+            &backend,
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&response).unwrap();
+        let parsed: GenerationResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.request.method, DetectionMethod::Kde);
+        assert!(verify(&parsed, &path).unwrap().passed());
+
+        // The grid finds its anomalies elsewhere
+        let mut grid = response;
+        grid.request.method = DetectionMethod::Grid;
+        assert!(!verify(&grid, &path).unwrap().winners_match);
+    }
+
+    #[test]
+    fn test_verify_detects_altered_winner() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let mut response = recorded_generation(&path);
+
+        response
+            .winners
+            .get_mut(&AnomalyType::ATTRACTOR)
+            .unwrap()
+            .result
+            .coords
+            .lat += 0.001;
+
+        let result = verify(&response, &path).unwrap();
+        assert!(result.digest_matches);
+        assert!(!result.winners_match);
+        assert!(!result.passed());
+    }
+
+    #[test]
+    fn test_verify_detects_altered_capture() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let response = recorded_generation(&path);
+
+        let mut capture = std::fs::read(&path).unwrap();
+        capture[100] ^= 1;
+        std::fs::write(&path, &capture).unwrap();
+
+        let result = verify(&response, &path).unwrap();
+        assert!(!result.digest_matches);
+        assert!(!result.passed());
+    }
+}
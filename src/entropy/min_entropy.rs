@@ -0,0 +1,300 @@
+//! NIST SP 800-90B min-entropy estimators
+//!
+//! P-values say whether data looks random; these say how much entropy it
+//! carries, in bits per byte (0-8), so extractor and mixer settings can be
+//! chosen from data. Each estimator takes the 99% upper confidence bound on
+//! the probability it models, so estimates are conservative.
+//!
+//! Most-common-value treats each byte as a sample. The collision, Markov and
+//! compression estimators are defined for binary data in the final SP
+//! 800-90B, so they run on the bit string and are scaled to bits per byte.
+
+use crate::entropy::nist::to_bits;
+use serde::{Deserialize, Serialize};
+
+/// Normal quantile for the 99% confidence bounds used throughout SP 800-90B
+const Z_ALPHA: f64 = 2.576;
+
+/// Block size in bits for the compression estimator
+const COMPRESSION_BLOCK_BITS: usize = 6;
+
+/// Blocks used to prime the compression estimator's dictionary
+const COMPRESSION_DICTIONARY_BLOCKS: usize = 1000;
+
+/// Most blocks the compression estimator reads
+///
+/// Solving for its probability takes a pass over every block per bisection
+/// step, so longer inputs are estimated from their first 600,000 bits.
+pub(crate) const COMPRESSION_MAX_BLOCKS: usize = 100_000;
+
+/// Min-entropy estimates in bits per byte
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MinEntropy {
+    pub most_common_value: f64,
+    pub collision: f64,
+    pub markov: f64,
+    pub compression: f64,
+    /// Smallest estimate: the assessed entropy per byte
+    pub min: f64,
+}
+
+/// Run every estimator on `data`
+///
+/// Inputs too short for an estimator score 0.0 for it.
+pub fn estimate(data: &[u8]) -> MinEntropy {
+    let bits = to_bits(data);
+    let most_common_value = most_common_value(data);
+    let collision = 8.0 * collision(&bits);
+    let markov = 8.0 * markov(&bits);
+    let compression = 8.0 * compression(&bits);
+
+    MinEntropy {
+        most_common_value,
+        collision,
+        markov,
+        compression,
+        min: most_common_value.min(collision).min(markov).min(compression),
+    }
+}
+
+/// Most common value estimate (6.3.1), in bits per sample
+pub(crate) fn most_common_value(samples: &[u8]) -> f64 {
+    let n = samples.len();
+    if n < 2 {
+        return 0.0;
+    }
+
+    let mut counts = [0u64; 256];
+    for &sample in samples {
+        counts[sample as usize] += 1;
+    }
+    let p = *counts.iter().max().unwrap() as f64 / n as f64;
+    let p_upper = (p + Z_ALPHA * (p * (1.0 - p) / (n - 1) as f64).sqrt()).min(1.0);
+
+    -p_upper.log2()
+}
+
+/// Collision estimate (6.3.2), in bits per bit
+pub(crate) fn collision(bits: &[u8]) -> f64 {
+    // Distance to the first repeated value, from each restart point
+    let mut times = Vec::new();
+    let mut i = 0;
+    while i + 1 < bits.len() {
+        if bits[i] == bits[i + 1] {
+            times.push(2.0);
+            i += 2;
+        } else if i + 2 < bits.len() {
+            times.push(3.0);
+            i += 3;
+        } else {
+            break;
+        }
+    }
+    let v = times.len() as f64;
+    if times.len() < 2 {
+        return 0.0;
+    }
+
+    let mean = times.iter().sum::<f64>() / v;
+    let sd = (times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (v - 1.0)).sqrt();
+    let mean_lower = mean - Z_ALPHA * sd / v.sqrt();
+
+    // For binary data the expected collision time is 2 + 2p(1 - p), which
+    // solves in closed form for the most likely bit's probability p
+    let p = if mean_lower >= 2.5 {
+        0.5
+    } else if mean_lower <= 2.0 {
+        1.0
+    } else {
+        (1.0 + (5.0 - 2.0 * mean_lower).sqrt()) / 2.0
+    };
+
+    -p.log2()
+}
+
+/// Markov estimate (6.3.3), in bits per bit
+pub(crate) fn markov(bits: &[u8]) -> f64 {
+    let n = bits.len();
+    if n < 2 {
+        return 0.0;
+    }
+
+    let ones = bits.iter().filter(|&&b| b == 1).count() as f64;
+    let initial = [(n as f64 - ones) / n as f64, ones / n as f64];
+
+    let mut counts = [[0u64; 2]; 2];
+    for pair in bits.windows(2) {
+        counts[pair[0] as usize][pair[1] as usize] += 1;
+    }
+    let mut transition = [[0.0f64; 2]; 2];
+    for from in 0..2 {
+        let total = counts[from][0] + counts[from][1];
+        if total > 0 {
+            for to in 0..2 {
+                transition[from][to] = counts[from][to] as f64 / total as f64;
+            }
+        }
+    }
+
+    // Log-probabilities of the candidate most likely 128-bit sequences
+    let lg = |p: f64| p.log2();
+    let t = transition;
+    let candidates = [
+        lg(initial[0]) + 127.0 * lg(t[0][0]),
+        lg(initial[0]) + 64.0 * lg(t[0][1]) + 63.0 * lg(t[1][0]),
+        lg(initial[0]) + lg(t[0][1]) + 126.0 * lg(t[1][1]),
+        lg(initial[1]) + lg(t[1][0]) + 126.0 * lg(t[0][0]),
+        lg(initial[1]) + 64.0 * lg(t[1][0]) + 63.0 * lg(t[0][1]),
+        lg(initial[1]) + 127.0 * lg(t[1][1]),
+    ];
+    let max = candidates.into_iter().fold(f64::NEG_INFINITY, f64::max);
+
+    (-max / 128.0).min(1.0)
+}
+
+/// Compression estimate (6.3.4), in bits per bit
+///
+/// Reads at most `COMPRESSION_MAX_BLOCKS` blocks.
+pub(crate) fn compression(bits: &[u8]) -> f64 {
+    let blocks: Vec<usize> = bits
+        .chunks_exact(COMPRESSION_BLOCK_BITS)
+        .take(COMPRESSION_MAX_BLOCKS)
+        .map(|block| block.iter().fold(0, |acc, &b| (acc << 1) | b as usize))
+        .collect();
+    let l = blocks.len();
+    if l <= COMPRESSION_DICTIONARY_BLOCKS + 1 {
+        return 0.0;
+    }
+    let v = (l - COMPRESSION_DICTIONARY_BLOCKS) as f64;
+
+    // Distance (in blocks) back to each block value's previous occurrence,
+    // 1-based as in the standard
+    let mut last_seen = [0usize; 1 << COMPRESSION_BLOCK_BITS];
+    for (i, &block) in blocks[..COMPRESSION_DICTIONARY_BLOCKS].iter().enumerate() {
+        last_seen[block] = i + 1;
+    }
+    // log2 of every possible distance, shared by the bisection below
+    let logs: Vec<f64> = (0..=l).map(|t| (t as f64).log2()).collect();
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for i in COMPRESSION_DICTIONARY_BLOCKS + 1..=l {
+        let block = blocks[i - 1];
+        let distance = i - last_seen[block];
+        last_seen[block] = i;
+        let lg = logs[distance];
+        sum += lg;
+        sum_sq += lg * lg;
+    }
+    let mean = sum / v;
+    let sd = 0.5907 * (sum_sq / (v - 1.0) - mean * mean).max(0.0).sqrt();
+    let mean_lower = mean - Z_ALPHA * sd / v.sqrt();
+
+    // Expected mean log-distance when one block value has probability p and
+    // the rest share the remainder evenly
+    let others = ((1 << COMPRESSION_BLOCK_BITS) - 1) as f64;
+    let expected = |p: f64| {
+        let g = |z: f64| {
+            let (mut total, mut partial, mut power) = (0.0, 0.0, 1.0);
+            for (t, &lg) in logs.iter().enumerate().skip(1) {
+                if t > COMPRESSION_DICTIONARY_BLOCKS {
+                    total += partial + lg * z * power;
+                }
+                partial += lg * z * z * power;
+                power *= 1.0 - z;
+            }
+            total / v
+        };
+        g(p) + others * g((1.0 - p) / others)
+    };
+
+    // The expectation falls as p rises; bisect for the p that matches
+    let (mut low, mut high) = (1.0 / (others + 1.0), 1.0);
+    if mean_lower >= expected(low) {
+        return 1.0;
+    }
+    for _ in 0..60 {
+        let mid = (low + high) / 2.0;
+        if expected(mid) > mean_lower {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (-high.log2() / COMPRESSION_BLOCK_BITS as f64).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy::tests::biased_bytes;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::QrngBackend;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_compression_reads_bounded_prefix() {
+        let data = SeededPseudoBackend::new(5).bytes(1_120_000).unwrap();
+        let bits = to_bits(&data);
+        let prefix = &bits[..COMPRESSION_MAX_BLOCKS * COMPRESSION_BLOCK_BITS];
+
+        let start = std::time::Instant::now();
+        let estimate = compression(&bits);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(estimate, compression(prefix));
+    }
+
+    #[test]
+    fn test_most_common_value_example() {
+        // SP 800-90B section 6.3.1 example
+        let samples = [0, 1, 1, 2, 0, 1, 2, 2, 0, 1, 0, 1, 1, 0, 2, 2, 1, 0, 2, 1];
+        assert_abs_diff_eq!(most_common_value(&samples), 0.5363, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_random_data_near_full_entropy() {
+        let data = SeededPseudoBackend::new(42).bytes(20_000).unwrap();
+        let estimate = estimate(&data);
+
+        assert!(estimate.most_common_value > 6.8, "{:?}", estimate);
+        assert!(estimate.collision > 6.0, "{:?}", estimate);
+        assert!(estimate.markov > 7.8, "{:?}", estimate);
+        assert!(estimate.compression > 5.0, "{:?}", estimate);
+        assert_eq!(
+            estimate.min,
+            estimate
+                .most_common_value
+                .min(estimate.collision)
+                .min(estimate.markov)
+                .min(estimate.compression)
+        );
+        assert!(estimate.min <= 8.0);
+    }
+
+    #[test]
+    fn test_biased_data_estimates() {
+        let estimate = estimate(&biased_bytes(20_000));
+        let true_entropy = -8.0 * 0.625f64.log2();
+
+        // Models that fit a biased source land close to its true entropy
+        assert_abs_diff_eq!(estimate.most_common_value, true_entropy, epsilon = 0.5);
+        assert_abs_diff_eq!(estimate.markov, true_entropy, epsilon = 0.3);
+        // and none should credit it with much more
+        assert!(estimate.collision < true_entropy + 0.3, "{:?}", estimate);
+        assert!(estimate.compression < true_entropy + 0.3, "{:?}", estimate);
+    }
+
+    #[test]
+    fn test_constant_data_has_no_entropy() {
+        let estimate = estimate(&[0u8; 5000]);
+        assert!(estimate.most_common_value < 0.01, "{:?}", estimate);
+        assert!(estimate.collision < 0.01, "{:?}", estimate);
+        assert!(estimate.markov < 0.01, "{:?}", estimate);
+        assert!(estimate.compression < 0.01, "{:?}", estimate);
+    }
+
+    #[test]
+    fn test_short_input() {
+        assert_eq!(estimate(&[]), MinEntropy::default());
+        assert_eq!(compression(&to_bits(&[0x55; 100])), 0.0);
+    }
+}
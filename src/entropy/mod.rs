@@ -1,7 +1,16 @@
 //! Entropy quality testing
 //!
-//! Statistical tests to verify randomness quality of QRNG data.
+//! Statistical tests to verify randomness quality of QRNG data: three quick
+//! tests always run, plus optional extended NIST SP 800-22 tests (`nist`).
+//! SP 800-90B min-entropy estimates (`min_entropy`) are always reported too.
+//! The server keeps a rolling history of results per backend (`series`).
 
+pub mod min_entropy;
+pub mod nist;
+pub mod series;
 pub mod tests;
 
-pub use tests::{run_all_tests, EntropyTestResults};
+pub use min_entropy::MinEntropy;
+pub use nist::NistTest;
+pub use series::{EntropySample, EntropySeries, Trend};
+pub use tests::{run_all_tests, run_tests, EntropyCheck, EntropyTestResults};
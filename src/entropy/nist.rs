@@ -0,0 +1,579 @@
+//! Extended NIST SP 800-22 statistical tests
+//!
+//! The remaining practical tests from SP 800-22 rev. 1a, each returning the
+//! test's p-value (0-1; below `PASS_THRESHOLD` fails). Bits are taken most
+//! significant first, as in the basic tests. Where the standard reports two
+//! p-values (serial, cumulative sums) the smaller is returned.
+//!
+//! Parameters follow the standard's recommendations for the input length;
+//! inputs too short for a test score 0.0.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Block length for the block frequency test
+const BLOCK_FREQUENCY_M: usize = 128;
+
+/// Template for the non-overlapping template test (the standard's first
+/// aperiodic 9-bit template)
+const TEMPLATE: [u8; 9] = [0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+/// Number of blocks for the non-overlapping template test
+const TEMPLATE_BLOCKS: usize = 8;
+
+/// An extended SP 800-22 test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NistTest {
+    BlockFrequency,
+    LongestRun,
+    Serial,
+    ApproximateEntropy,
+    CumulativeSums,
+    Spectral,
+    NonOverlappingTemplate,
+}
+
+impl NistTest {
+    /// All extended tests
+    pub const ALL: [NistTest; 7] = [
+        NistTest::BlockFrequency,
+        NistTest::LongestRun,
+        NistTest::Serial,
+        NistTest::ApproximateEntropy,
+        NistTest::CumulativeSums,
+        NistTest::Spectral,
+        NistTest::NonOverlappingTemplate,
+    ];
+
+    /// Name used on the command line and in results
+    pub fn name(self) -> &'static str {
+        match self {
+            NistTest::BlockFrequency => "block-frequency",
+            NistTest::LongestRun => "longest-run",
+            NistTest::Serial => "serial",
+            NistTest::ApproximateEntropy => "approximate-entropy",
+            NistTest::CumulativeSums => "cumulative-sums",
+            NistTest::Spectral => "spectral",
+            NistTest::NonOverlappingTemplate => "non-overlapping-template",
+        }
+    }
+
+    /// Run the test on `data`, returning its p-value
+    pub fn run(self, data: &[u8]) -> f64 {
+        let bits = to_bits(data);
+        match self {
+            NistTest::BlockFrequency => block_frequency(&bits, BLOCK_FREQUENCY_M),
+            NistTest::LongestRun => longest_run(&bits),
+            NistTest::Serial => {
+                let m = log2(bits.len()).saturating_sub(3).clamp(2, 16);
+                let (p1, p2) = serial(&bits, m);
+                p1.min(p2)
+            }
+            NistTest::ApproximateEntropy => {
+                let m = log2(bits.len()).saturating_sub(6).clamp(2, 10);
+                approximate_entropy(&bits, m)
+            }
+            NistTest::CumulativeSums => {
+                let (forward, backward) = cumulative_sums(&bits);
+                forward.min(backward)
+            }
+            NistTest::Spectral => spectral(&bits),
+            NistTest::NonOverlappingTemplate => {
+                non_overlapping_template(&bits, &TEMPLATE, TEMPLATE_BLOCKS)
+            }
+        }
+    }
+}
+
+impl fmt::Display for NistTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for NistTest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NistTest::ALL
+            .into_iter()
+            .find(|test| test.name() == s.to_lowercase().replace('_', "-"))
+            .ok_or_else(|| {
+                let names: Vec<_> = NistTest::ALL.iter().map(|t| t.name()).collect();
+                format!("Unknown test: {} (expected one of {})", s, names.join(", "))
+            })
+    }
+}
+
+/// Bits of `data`, most significant first
+pub(crate) fn to_bits(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+        .collect()
+}
+
+/// floor(log2(n)), 0 for n = 0
+fn log2(n: usize) -> usize {
+    n.checked_ilog2().unwrap_or(0) as usize
+}
+
+/// Frequency test within a block
+pub(crate) fn block_frequency(bits: &[u8], m: usize) -> f64 {
+    let blocks = bits.len() / m;
+    if blocks == 0 {
+        return 0.0;
+    }
+
+    let chi_sq: f64 = bits
+        .chunks_exact(m)
+        .map(|block| {
+            let pi = block.iter().map(|&b| b as f64).sum::<f64>() / m as f64;
+            (pi - 0.5).powi(2)
+        })
+        .sum::<f64>()
+        * 4.0
+        * m as f64;
+
+    igamc(blocks as f64 / 2.0, chi_sq / 2.0)
+}
+
+/// Test for the longest run of ones in a block
+pub(crate) fn longest_run(bits: &[u8]) -> f64 {
+    // (block length, shortest class, class probabilities), with the
+    // probabilities unrounded as in the reference implementation
+    let (m, min_run, pi): (usize, usize, &[f64]) = match bits.len() {
+        0..=127 => return 0.0,
+        128..=6271 => (8, 1, &[0.21484375, 0.3671875, 0.23046875, 0.1875]),
+        6272..=749_999 => (
+            128,
+            4,
+            &[0.1174035788, 0.242955959, 0.249363483, 0.17517706, 0.102701071, 0.112398847],
+        ),
+        _ => (
+            10_000,
+            10,
+            &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727],
+        ),
+    };
+    let classes = pi.len();
+
+    let mut counts = vec![0u64; classes];
+    for block in bits.chunks_exact(m) {
+        let mut longest = 0;
+        let mut run = 0;
+        for &bit in block {
+            run = if bit == 1 { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+        counts[longest.clamp(min_run, min_run + classes - 1) - min_run] += 1;
+    }
+
+    let blocks = (bits.len() / m) as f64;
+    let chi_sq: f64 = counts
+        .iter()
+        .zip(pi)
+        .map(|(&count, &p)| (count as f64 - blocks * p).powi(2) / (blocks * p))
+        .sum();
+
+    igamc((classes - 1) as f64 / 2.0, chi_sq / 2.0)
+}
+
+/// Counts of each overlapping `m`-bit pattern, wrapping around the end
+fn pattern_counts(bits: &[u8], m: usize) -> Vec<u64> {
+    let mut counts = vec![0u64; 1 << m];
+    let mask = (1usize << m) - 1;
+    let mut pattern = 0usize;
+    // Prime with the first m - 1 bits, then slide over every start position
+    for &bit in bits.iter().chain(bits).take(m - 1) {
+        pattern = (pattern << 1) | bit as usize;
+    }
+    for &bit in bits.iter().chain(bits).skip(m - 1).take(bits.len()) {
+        pattern = ((pattern << 1) | bit as usize) & mask;
+        counts[pattern] += 1;
+    }
+    counts
+}
+
+/// Serial test: both p-values for pattern length `m`
+pub(crate) fn serial(bits: &[u8], m: usize) -> (f64, f64) {
+    let n = bits.len();
+    if m < 2 || n < m {
+        return (0.0, 0.0);
+    }
+
+    let psi_sq = |m: usize| -> f64 {
+        if m == 0 {
+            return 0.0;
+        }
+        let sum: f64 = pattern_counts(bits, m)
+            .iter()
+            .map(|&c| (c as f64).powi(2))
+            .sum();
+        sum * (1u64 << m) as f64 / n as f64 - n as f64
+    };
+    let (psi_m, psi_m1, psi_m2) = (psi_sq(m), psi_sq(m - 1), psi_sq(m - 2));
+
+    let del1 = psi_m - psi_m1;
+    let del2 = psi_m - 2.0 * psi_m1 + psi_m2;
+    (
+        igamc(2f64.powi(m as i32 - 2), del1 / 2.0),
+        igamc(2f64.powi(m as i32 - 3), del2 / 2.0),
+    )
+}
+
+/// Approximate entropy test with block length `m`
+pub(crate) fn approximate_entropy(bits: &[u8], m: usize) -> f64 {
+    let n = bits.len();
+    if m == 0 || n <= m {
+        return 0.0;
+    }
+
+    let phi = |m: usize| -> f64 {
+        pattern_counts(bits, m)
+            .iter()
+            .filter(|&&c| c > 0)
+            .map(|&c| {
+                let p = c as f64 / n as f64;
+                p * p.ln()
+            })
+            .sum()
+    };
+    let ap_en = phi(m) - phi(m + 1);
+    let chi_sq = 2.0 * n as f64 * (std::f64::consts::LN_2 - ap_en);
+
+    igamc(2f64.powi(m as i32 - 1), chi_sq / 2.0)
+}
+
+/// Cumulative sums test: forward and backward p-values
+pub(crate) fn cumulative_sums(bits: &[u8]) -> (f64, f64) {
+    if bits.is_empty() {
+        return (0.0, 0.0);
+    }
+    let forward = cusum_p_value(bits.len(), max_excursion(bits.iter()));
+    let backward = cusum_p_value(bits.len(), max_excursion(bits.iter().rev()));
+    (forward, backward)
+}
+
+/// Largest absolute partial sum of the ±1 walk
+fn max_excursion<'a>(bits: impl Iterator<Item = &'a u8>) -> i64 {
+    let mut sum = 0i64;
+    let mut max = 0i64;
+    for &bit in bits {
+        sum += if bit == 1 { 1 } else { -1 };
+        max = max.max(sum.abs());
+    }
+    max
+}
+
+fn cusum_p_value(n: usize, z: i64) -> f64 {
+    let n = n as f64;
+    let z = z as f64;
+    let sqrt_n = n.sqrt();
+
+    // Bounds truncate toward zero, as in the reference implementation
+    let mut sum1 = 0.0;
+    let mut k = ((-n / z + 1.0) / 4.0) as i64;
+    while k <= ((n / z - 1.0) / 4.0) as i64 {
+        let k4 = 4.0 * k as f64;
+        sum1 += normal_cdf((k4 + 1.0) * z / sqrt_n) - normal_cdf((k4 - 1.0) * z / sqrt_n);
+        k += 1;
+    }
+    let mut sum2 = 0.0;
+    let mut k = ((-n / z - 3.0) / 4.0) as i64;
+    while k <= ((n / z - 1.0) / 4.0) as i64 {
+        let k4 = 4.0 * k as f64;
+        sum2 += normal_cdf((k4 + 3.0) * z / sqrt_n) - normal_cdf((k4 + 1.0) * z / sqrt_n);
+        k += 1;
+    }
+
+    (1.0 - sum1 + sum2).clamp(0.0, 1.0)
+}
+
+/// Discrete Fourier transform (spectral) test
+///
+/// Uses the longest power-of-two prefix of the input.
+pub(crate) fn spectral(bits: &[u8]) -> f64 {
+    if bits.len() < 1000 {
+        return 0.0;
+    }
+    let n = 1usize << log2(bits.len());
+
+    let mut re: Vec<f64> = bits[..n]
+        .iter()
+        .map(|&b| if b == 1 { 1.0 } else { -1.0 })
+        .collect();
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+
+    let n_f = n as f64;
+    let threshold = ((1.0 / 0.05f64).ln() * n_f).sqrt();
+    let expected = 0.95 * n_f / 2.0;
+    let below = (0..n / 2)
+        .filter(|&j| re[j].hypot(im[j]) < threshold)
+        .count() as f64;
+    let d = (below - expected) / (n_f * 0.95 * 0.05 / 4.0).sqrt();
+
+    erfc(d.abs() / std::f64::consts::SQRT_2)
+}
+
+/// In-place iterative radix-2 FFT; `re.len()` must be a power of two
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Non-overlapping template matching test over `blocks` blocks
+pub(crate) fn non_overlapping_template(bits: &[u8], template: &[u8], blocks: usize) -> f64 {
+    let m = template.len();
+    let block_len = bits.len() / blocks;
+    if block_len <= m {
+        return 0.0;
+    }
+
+    let mean = (block_len - m + 1) as f64 / 2f64.powi(m as i32);
+    let variance =
+        block_len as f64 * (1.0 / 2f64.powi(m as i32) - (2 * m - 1) as f64 / 2f64.powi(2 * m as i32));
+
+    let chi_sq: f64 = bits
+        .chunks_exact(block_len)
+        .take(blocks)
+        .map(|block| {
+            let mut hits = 0u64;
+            let mut j = 0;
+            while j + m <= block.len() {
+                if &block[j..j + m] == template {
+                    hits += 1;
+                    j += m;
+                } else {
+                    j += 1;
+                }
+            }
+            (hits as f64 - mean).powi(2) / variance
+        })
+        .sum();
+
+    igamc(blocks as f64 / 2.0, chi_sq / 2.0)
+}
+
+/// Standard normal cumulative distribution
+fn normal_cdf(z: f64) -> f64 {
+    if z >= 0.0 {
+        1.0 - 0.5 * erfc(z / std::f64::consts::SQRT_2)
+    } else {
+        0.5 * erfc(-z / std::f64::consts::SQRT_2)
+    }
+}
+
+/// Complementary error function for x >= 0
+fn erfc(x: f64) -> f64 {
+    igamc(0.5, x * x)
+}
+
+/// Natural log of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, &c)| acc + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// Regularized upper incomplete gamma function Q(a, x)
+fn igamc(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const MAX_ITERATIONS: usize = 1000;
+
+    if x <= 0.0 || a <= 0.0 {
+        return 1.0;
+    }
+    let log_prefix = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        // Series for P(a, x)
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (1.0 - sum * log_prefix.exp()).clamp(0.0, 1.0)
+    } else {
+        // Continued fraction for Q(a, x) (modified Lentz)
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        (log_prefix.exp() * h).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy::tests::{biased_bytes, PASS_THRESHOLD};
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::QrngBackend;
+    use approx::assert_abs_diff_eq;
+
+    fn bits(s: &str) -> Vec<u8> {
+        s.bytes().map(|c| c - b'0').collect()
+    }
+
+    // Worked examples from SP 800-22 rev. 1a, section 2
+
+    #[test]
+    fn test_block_frequency_example() {
+        assert_abs_diff_eq!(block_frequency(&bits("0110011010"), 3), 0.801252, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_longest_run_example() {
+        let eps = bits(concat!(
+            "11001100000101010110110001001100111000000000001001",
+            "00110101010001000100111101011010000000110101111100",
+            "1100111001101101100010110010"
+        ));
+        assert_abs_diff_eq!(longest_run(&eps), 0.180609, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_serial_example() {
+        let (p1, p2) = serial(&bits("0011011101"), 3);
+        assert_abs_diff_eq!(p1, 0.808792, epsilon = 1e-6);
+        assert_abs_diff_eq!(p2, 0.670320, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_approximate_entropy_example() {
+        assert_abs_diff_eq!(approximate_entropy(&bits("0100110101"), 3), 0.261961, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_cumulative_sums_example() {
+        let (forward, _) = cumulative_sums(&bits("1011010111"));
+        assert_abs_diff_eq!(forward, 0.4116588, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_non_overlapping_template_example() {
+        let p = non_overlapping_template(&bits("10100100101110010110"), &bits("001"), 2);
+        assert_abs_diff_eq!(p, 0.344154, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_fft_matches_naive_dft() {
+        let input: Vec<f64> = (0..16).map(|i| ((i * 7) % 5) as f64 - 2.0).collect();
+        let (mut re, mut im) = (input.clone(), vec![0.0; 16]);
+        fft(&mut re, &mut im);
+
+        for k in 0..16 {
+            let (mut expected_re, mut expected_im) = (0.0, 0.0);
+            for (t, &x) in input.iter().enumerate() {
+                let angle = -2.0 * std::f64::consts::PI * (k * t) as f64 / 16.0;
+                expected_re += x * angle.cos();
+                expected_im += x * angle.sin();
+            }
+            assert_abs_diff_eq!(re[k], expected_re, epsilon = 1e-9);
+            assert_abs_diff_eq!(im[k], expected_im, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_all_pass_good_data() {
+        let data = SeededPseudoBackend::new(42).bytes(20_000).unwrap();
+        for test in NistTest::ALL {
+            let p = test.run(&data);
+            assert!(p >= PASS_THRESHOLD, "{} failed on good data: {}", test, p);
+        }
+    }
+
+    #[test]
+    fn test_all_fail_biased_data() {
+        let data = biased_bytes(20_000);
+        for test in NistTest::ALL {
+            let p = test.run(&data);
+            assert!(p < PASS_THRESHOLD, "{} passed biased data: {}", test, p);
+        }
+    }
+
+    #[test]
+    fn test_parse_names() {
+        for test in NistTest::ALL {
+            assert_eq!(test.name().parse::<NistTest>().unwrap(), test);
+        }
+        assert_eq!("Block_Frequency".parse::<NistTest>().unwrap(), NistTest::BlockFrequency);
+        assert!("poker".parse::<NistTest>().is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! Entropy quality time series
+//!
+//! The server samples each monitored backend on an interval (see
+//! `server::monitor`) and records the test results here, so quality can be
+//! followed over time without drawing fresh entropy on every status request.
+//! Each backend keeps its most recent `monitor.max_samples` samples. A series
+//! loaded from a file with `EntropySeries::load` is saved back to it through
+//! `snapshot`, which the caller writes once it has let go of the series
+//! (`entropy.json` in the data directory, next to the history).
+
+use crate::entropy::{run_all_tests, EntropyTestResults};
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::qrng::AsyncQrngBackend;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+
+const SERIES_FILE_NAME: &str = "entropy.json";
+
+/// One scheduled test of a backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntropySample {
+    /// When the bytes were drawn (RFC 3339)
+    pub timestamp: String,
+    /// Test results, if the draw succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results: Option<EntropyTestResults>,
+    /// Why the draw failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl EntropySample {
+    /// Draw `bytes` from `backend` and test them
+    pub async fn take(backend: &dyn AsyncQrngBackend, bytes: usize) -> Self {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        match backend.bytes_async(bytes).await {
+            Ok(data) => Self {
+                timestamp,
+                results: Some(run_all_tests(&data)),
+                error: None,
+            },
+            Err(e) => Self {
+                timestamp,
+                results: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// A sample whose backend couldn't be drawn from
+    pub fn failed(error: &Error) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            results: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// Summary of a backend's samples
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trend {
+    /// Samples held
+    pub samples: usize,
+    /// Samples whose draw failed
+    pub errors: usize,
+    /// Fraction of tested samples that passed every test
+    pub pass_rate: f64,
+    /// Mean overall score of tested samples
+    pub mean_overall: f64,
+    /// Mean assessed min-entropy (bits per byte) of tested samples
+    pub mean_min_entropy: f64,
+    /// Least-squares change in overall score per day (0 with fewer than two
+    /// tested samples)
+    pub overall_per_day: f64,
+    /// Timestamp of the newest sample
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+}
+
+impl Trend {
+    /// Summarize `samples`, oldest first
+    pub fn of<'a>(samples: impl IntoIterator<Item = &'a EntropySample>) -> Self {
+        let mut trend = Trend::default();
+        // (days since the first tested sample, overall score)
+        let mut points = Vec::new();
+        let mut start = None;
+        let mut passed = 0;
+        let mut min_entropy = 0.0;
+
+        for sample in samples {
+            trend.samples += 1;
+            trend.latest = Some(sample.timestamp.clone());
+            let Some(results) = &sample.results else {
+                trend.errors += 1;
+                continue;
+            };
+            if results.all_passed() {
+                passed += 1;
+            }
+            min_entropy += results.min_entropy.min;
+
+            let time = chrono::DateTime::parse_from_rfc3339(&sample.timestamp)
+                .map(|t| t.timestamp() as f64 / 86_400.0)
+                .unwrap_or(0.0);
+            let start = *start.get_or_insert(time);
+            points.push((time - start, results.overall));
+        }
+
+        let tested = points.len();
+        if tested == 0 {
+            return trend;
+        }
+        let n = tested as f64;
+        trend.pass_rate = passed as f64 / n;
+        trend.mean_min_entropy = min_entropy / n;
+
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        trend.mean_overall = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let spread: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if spread > 0.0 {
+            let covariance: f64 = points
+                .iter()
+                .map(|(t, y)| (t - mean_t) * (y - trend.mean_overall))
+                .sum();
+            trend.overall_per_day = covariance / spread;
+        }
+        trend
+    }
+}
+
+/// Rolling per-backend entropy samples
+#[derive(Debug, Default)]
+pub struct EntropySeries {
+    /// Samples kept per backend
+    max_samples: usize,
+    /// Samples per backend spec, oldest first
+    samples: BTreeMap<String, VecDeque<EntropySample>>,
+    /// Where the series is saved, if anywhere
+    path: Option<PathBuf>,
+}
+
+impl EntropySeries {
+    /// Create an in-memory series keeping `max_samples` per backend
+    pub fn new(max_samples: usize) -> Self {
+        Self {
+            max_samples,
+            ..Self::default()
+        }
+    }
+
+    /// Default series file path
+    pub fn series_path() -> Result<PathBuf> {
+        Ok(History::data_dir()?.join(SERIES_FILE_NAME))
+    }
+
+    /// Save to `path` from now on (see `snapshot`)
+    pub fn with_path(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Load the series saved at `path` (empty if the file doesn't exist);
+    /// later snapshots are saved back to it
+    pub fn load(path: impl Into<PathBuf>, max_samples: usize) -> Result<Self> {
+        let path = path.into();
+        let mut series = Self::new(max_samples);
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| Error::Config(format!("Failed to read entropy series: {}", e)))?;
+            series.samples = serde_json::from_str(&content)
+                .map_err(|e| Error::Config(format!("Failed to parse entropy series: {}", e)))?;
+            for samples in series.samples.values_mut() {
+                trim(samples, max_samples);
+            }
+        }
+        Ok(series.with_path(path))
+    }
+
+    /// Add a sample for `backend`, dropping its oldest beyond the limit
+    pub fn record(&mut self, backend: &str, sample: EntropySample) {
+        let samples = self.samples.entry(backend.to_string()).or_default();
+        samples.push_back(sample);
+        trim(samples, self.max_samples);
+    }
+
+    /// The series as it should be saved, if it has a file
+    ///
+    /// Serializing is quick; writing is left to `Snapshot::save` so it can
+    /// happen without holding the series.
+    pub fn snapshot(&self) -> Result<Option<Snapshot>> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let content = serde_json::to_string_pretty(&self.samples)
+            .map_err(|e| Error::Config(format!("Failed to serialize entropy series: {}", e)))?;
+        Ok(Some(Snapshot {
+            path: path.clone(),
+            content,
+        }))
+    }
+
+    /// Backends with samples, sorted by name
+    pub fn backends(&self) -> Vec<&str> {
+        self.samples.keys().map(String::as_str).collect()
+    }
+
+    /// Samples for `backend`, oldest first
+    pub fn samples(&self, backend: &str) -> Vec<&EntropySample> {
+        self.samples
+            .get(backend)
+            .map(|samples| samples.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Newest sample for `backend`
+    pub fn latest(&self, backend: &str) -> Option<&EntropySample> {
+        self.samples.get(backend).and_then(|samples| samples.back())
+    }
+
+    /// Trend across `backend`'s samples
+    pub fn trend(&self, backend: &str) -> Trend {
+        Trend::of(self.samples(backend))
+    }
+}
+
+fn trim(samples: &mut VecDeque<EntropySample>, max_samples: usize) {
+    while samples.len() > max_samples {
+        samples.pop_front();
+    }
+}
+
+/// A serialized series waiting to be written
+pub struct Snapshot {
+    path: PathBuf,
+    content: String,
+}
+
+impl Snapshot {
+    /// Write the series file
+    ///
+    /// Writes a temporary file and renames it over the old one, so a crash
+    /// mid-write never leaves a truncated series behind.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| Error::Config(format!("Failed to create data directory: {}", e)))?;
+        }
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, &self.content)
+            .and_then(|()| fs::rename(&temp, &self.path))
+            .map_err(|e| Error::Config(format!("Failed to write entropy series: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::{QrngBackend, SyncAdapter};
+    use approx::assert_abs_diff_eq;
+    use tempfile::TempDir;
+
+    fn sample(timestamp: &str, overall: f64, passed: bool) -> EntropySample {
+        let mut results = run_all_tests(&SeededPseudoBackend::new(1).bytes(5000).unwrap());
+        results.overall = overall;
+        if !passed {
+            results.balanced = 0.0;
+        }
+        EntropySample {
+            timestamp: timestamp.to_string(),
+            results: Some(results),
+            error: None,
+        }
+    }
+
+    fn failed(timestamp: &str) -> EntropySample {
+        EntropySample {
+            timestamp: timestamp.to_string(),
+            results: None,
+            error: Some("QRNG error: offline".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_take_sample() {
+        let backend = SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(5)));
+        let sample = EntropySample::take(&backend, 5000).await;
+        assert_eq!(sample.results.unwrap().bytes_analyzed, 5000);
+        assert!(sample.error.is_none());
+    }
+
+    #[test]
+    fn test_trend() {
+        let samples = [
+            sample("2026-01-01T00:00:00Z", 0.4, true),
+            failed("2026-01-01T12:00:00Z"),
+            sample("2026-01-02T00:00:00Z", 0.5, true),
+            sample("2026-01-03T00:00:00Z", 0.6, false),
+        ];
+        let trend = Trend::of(&samples);
+
+        assert_eq!(trend.samples, 4);
+        assert_eq!(trend.errors, 1);
+        assert_abs_diff_eq!(trend.pass_rate, 2.0 / 3.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(trend.mean_overall, 0.5, epsilon = 1e-12);
+        assert_abs_diff_eq!(trend.overall_per_day, 0.1, epsilon = 1e-9);
+        let min_entropy = samples[0].results.as_ref().unwrap().min_entropy.min;
+        assert_abs_diff_eq!(trend.mean_min_entropy, min_entropy, epsilon = 1e-12);
+        assert_eq!(trend.latest.as_deref(), Some("2026-01-03T00:00:00Z"));
+
+        assert_eq!(Trend::of(&[failed("2026-01-01T00:00:00Z")]).pass_rate, 0.0);
+        assert_eq!(Trend::of(&samples[..1]).overall_per_day, 0.0);
+    }
+
+    #[test]
+    fn test_rolling_and_persisted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("entropy.json");
+
+        let mut series = EntropySeries::load(&path, 3).unwrap();
+        for day in 1..=5 {
+            let timestamp = format!("2026-01-0{}T00:00:00Z", day);
+            series.record("anu", sample(&timestamp, 0.5, true));
+        }
+        series.record("pseudo", failed("2026-01-05T00:00:00Z"));
+        series.snapshot().unwrap().unwrap().save().unwrap();
+
+        assert_eq!(series.samples("anu").len(), 3);
+        assert_eq!(series.samples("anu")[0].timestamp, "2026-01-03T00:00:00Z");
+        assert_eq!(series.backends(), vec!["anu", "pseudo"]);
+
+        // Reloading with a smaller limit keeps the newest
+        let reloaded = EntropySeries::load(&path, 2).unwrap();
+        assert_eq!(reloaded.samples("anu").len(), 2);
+        assert_eq!(
+            reloaded.latest("anu").unwrap().timestamp,
+            "2026-01-05T00:00:00Z"
+        );
+        assert!(reloaded.latest("pseudo").unwrap().error.is_some());
+        assert!(reloaded.latest("device").is_none());
+        assert_eq!(reloaded.trend("device"), Trend::default());
+    }
+}
@@ -4,8 +4,13 @@
 //! - Balanced (Monobit): Checks if 0s and 1s are roughly equal
 //! - Uniform (Chi-Square): Checks if byte values are uniformly distributed
 //! - Scattered (Runs): Checks for patterns/clusters in the bit sequence
+//!
+//! Extended SP 800-22 tests (`entropy::nist`) can be run alongside them.
 
+use crate::entropy::min_entropy::{self, MinEntropy};
+use crate::entropy::nist::NistTest;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Threshold for considering a test "passed"
 /// Values closer to 1.0 indicate better randomness
@@ -26,9 +31,17 @@ pub struct EntropyTestResults {
     /// Measures randomness of bit transitions
     pub scattered: f64,
 
+    /// Extended SP 800-22 p-values, keyed by test name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extended: BTreeMap<String, f64>,
+
     /// Overall quality (average of all tests)
     pub overall: f64,
 
+    /// SP 800-90B min-entropy estimates (bits per byte)
+    #[serde(default)]
+    pub min_entropy: MinEntropy,
+
     /// Number of bytes analyzed
     pub bytes_analyzed: usize,
 }
@@ -36,9 +49,65 @@ pub struct EntropyTestResults {
 impl EntropyTestResults {
     /// Check if all tests pass the threshold
     pub fn all_passed(&self) -> bool {
-        self.balanced >= PASS_THRESHOLD
-            && self.uniform >= PASS_THRESHOLD
-            && self.scattered >= PASS_THRESHOLD
+        self.failures().is_empty()
+    }
+
+    /// Tests scoring below the threshold, with their scores
+    pub fn failures(&self) -> Vec<(String, f64)> {
+        [
+            ("balanced", self.balanced),
+            ("uniform", self.uniform),
+            ("scattered", self.scattered),
+        ]
+        .into_iter()
+        .map(|(name, p)| (name.to_string(), p))
+        .chain(self.extended.iter().map(|(name, &p)| (name.clone(), p)))
+        .filter(|&(_, p)| p < PASS_THRESHOLD)
+        .collect()
+    }
+
+    /// One-line description of the failed tests, if any failed
+    pub fn failure_summary(&self) -> Option<String> {
+        let failures = self.failures();
+        if failures.is_empty() {
+            return None;
+        }
+        let tests: Vec<String> = failures
+            .iter()
+            .map(|(name, p)| format!("{} {:.4}", name, p))
+            .collect();
+        Some(format!(
+            "{} below {} over {} bytes",
+            tests.join(", "),
+            PASS_THRESHOLD,
+            self.bytes_analyzed
+        ))
+    }
+}
+
+/// What a generation does about the quality of the entropy it consumed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntropyCheck {
+    /// Don't test the entropy
+    #[default]
+    Off,
+    /// Test it and record the results; callers warn if it failed
+    Warn,
+    /// Test it and refuse the generation if it failed
+    Reject,
+}
+
+impl std::str::FromStr for EntropyCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(EntropyCheck::Off),
+            "warn" => Ok(EntropyCheck::Warn),
+            "reject" => Ok(EntropyCheck::Reject),
+            _ => Err(format!("Unknown entropy check: {} (expected off, warn or reject)", s)),
+        }
     }
 }
 
@@ -50,17 +119,30 @@ impl EntropyTestResults {
 /// # Returns
 /// EntropyTestResults with scores for each test (0-1, higher is better)
 pub fn run_all_tests(data: &[u8]) -> EntropyTestResults {
+    run_tests(data, &[])
+}
+
+/// Run the basic tests plus the given extended SP 800-22 tests
+pub fn run_tests(data: &[u8], extended: &[NistTest]) -> EntropyTestResults {
     let balanced = monobit_test(data);
     let uniform = chi_square_test(data);
     let scattered = runs_test(data);
 
-    let overall = (balanced + uniform + scattered) / 3.0;
+    let extended: BTreeMap<String, f64> = extended
+        .iter()
+        .map(|test| (test.name().to_string(), test.run(data)))
+        .collect();
+
+    let overall = (balanced + uniform + scattered + extended.values().sum::<f64>())
+        / (3 + extended.len()) as f64;
 
     EntropyTestResults {
         balanced,
         uniform,
         scattered,
+        extended,
         overall,
+        min_entropy: min_entropy::estimate(data),
         bytes_analyzed: data.len(),
     }
 }
@@ -170,7 +252,7 @@ pub fn runs_test(data: &[u8]) -> f64 {
     let pi = ones as f64 / n;
 
     // Check if proportions are too extreme for the test
-    if pi < 0.01 || pi > 0.99 {
+    if !(0.01..=0.99).contains(&pi) {
         return 0.0;
     }
 
@@ -214,7 +296,24 @@ fn erf(x: f64) -> f64 {
     sign * y
 }
 
+/// Deterministic bytes with about 62.5% ones (-log2(0.625) = 0.678 bits of
+/// min-entropy per bit), for checking that tests catch bias
 #[cfg(test)]
+pub(crate) fn biased_bytes(len: usize) -> Vec<u8> {
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::QrngBackend;
+
+    let backend = SeededPseudoBackend::new(42);
+    let (a, b, c) = (
+        backend.bytes(len).unwrap(),
+        backend.bytes(len).unwrap(),
+        backend.bytes(len).unwrap(),
+    );
+    (0..len).map(|i| a[i] | (b[i] & c[i])).collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use super::*;
     use crate::qrng::pseudo::SeededPseudoBackend;
@@ -313,5 +412,21 @@ mod tests {
         assert!(results.all_passed());
         assert_eq!(results.bytes_analyzed, 10000);
         assert!(results.overall > 0.0 && results.overall <= 1.0);
+        assert!(results.extended.is_empty());
+        assert!(results.min_entropy.min > 5.0 && results.min_entropy.min <= 8.0);
+    }
+
+    #[test]
+    fn test_run_tests_with_extended() {
+        let data: Vec<u8> = (0..10000).map(|i| if i % 2 == 0 { 0x0F } else { 0x33 }).collect();
+
+        let results = run_tests(&data, &[NistTest::Serial, NistTest::Spectral]);
+        assert_eq!(
+            results.extended.keys().collect::<Vec<_>>(),
+            vec!["serial", "spectral"]
+        );
+        assert!(!results.all_passed());
+        let failed: Vec<String> = results.failures().into_iter().map(|(name, _)| name).collect();
+        assert!(failed.contains(&"serial".to_string()), "{:?}", failed);
     }
 }
@@ -8,12 +8,43 @@ pub enum Error {
     #[error("QRNG error: {0}")]
     Qrng(String),
 
+    /// The entropy service refused the request for quota/rate-limit reasons
+    #[error("QRNG quota exceeded: {message}")]
+    QrngQuota {
+        message: String,
+        /// How long the service asked us to wait, if it said
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// The entropy service couldn't be reached or failed transiently
+    #[error("QRNG network error: {0}")]
+    QrngNetwork(String),
+
+    /// Drawing more entropy would exceed the configured daily budget
+    #[error("Entropy budget exceeded: {0}")]
+    EntropyBudget(String),
+
+    /// A backend's byte stream failed a continuous health test
+    #[error("Entropy health test failed: {0}")]
+    EntropyHealth(String),
+
+    /// The entropy a generation consumed failed the quality tests
+    #[error("Entropy quality check failed: {0}")]
+    EntropyQuality(String),
+
+    /// A saved result doesn't match the entropy it claims to come from
+    #[error("Verification failed: {0}")]
+    Verification(String),
+
     #[error("Invalid coordinates: {0}")]
     InvalidCoordinates(String),
 
     #[error("Invalid radius: {0}")]
     InvalidRadius(String),
 
+    #[error("Invalid search area: {0}")]
+    InvalidArea(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -80,12 +80,7 @@ impl OutputFormatter for GpxFormatter {
             }
 
             // Add symbol based on type
-            let symbol = match anomaly_type {
-                AnomalyType::Attractor => "attraction",
-                AnomalyType::Void => "void",
-                AnomalyType::Power => "star",
-                AnomalyType::BlindSpot => "random",
-            };
+            let symbol = anomaly_type.detector().map_or("flag", |detector| detector.gpx_symbol());
             gpx.push_str(&format!("    <sym>{}</sym>\n", symbol));
 
             gpx.push_str("  </wpt>\n");
@@ -99,15 +94,15 @@ impl OutputFormatter for GpxFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coord::flower::generate;
+    use crate::coord::flower::{generate, GenerationOptions};
     use crate::coord::{Coordinates, GenerationMode};
     use crate::qrng::pseudo::SeededPseudoBackend;
 
     fn create_test_response() -> GenerationResponse {
         let backend = SeededPseudoBackend::new(12345);
         let center = Coordinates::new(40.7128, -74.0060);
-        generate(center, 1000.0, 100, 10, false, GenerationMode::Standard, "test", &backend)
-            .unwrap()
+        let options = GenerationOptions::new("test").with_points(100).with_grid_resolution(10);
+        generate(center, 1000.0, GenerationMode::Standard, &options, &backend).unwrap()
     }
 
     #[test]
@@ -117,7 +112,7 @@ mod tests {
         let config = Config::default();
 
         let output = formatter
-            .format(&response, AnomalyType::Attractor, &config)
+            .format(&response, AnomalyType::ATTRACTOR, &config)
             .unwrap();
 
         // Verify GPX structure
@@ -127,6 +122,7 @@ mod tests {
         assert!(output.contains("<name>"));
         assert!(output.contains("</gpx>"));
         assert!(output.contains("Center"));
+        assert!(output.contains("<sym>attraction</sym>"));
     }
 
     #[test]
@@ -31,15 +31,15 @@ impl OutputFormatter for JsonFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coord::flower::generate;
+    use crate::coord::flower::{generate, GenerationOptions};
     use crate::coord::{Coordinates, GenerationMode};
     use crate::qrng::pseudo::SeededPseudoBackend;
 
     fn create_test_response() -> GenerationResponse {
         let backend = SeededPseudoBackend::new(12345);
         let center = Coordinates::new(40.7128, -74.0060);
-        generate(center, 1000.0, 100, 10, false, GenerationMode::Standard, "test", &backend)
-            .unwrap()
+        let options = GenerationOptions::new("test").with_points(100).with_grid_resolution(10);
+        generate(center, 1000.0, GenerationMode::Standard, &options, &backend).unwrap()
     }
 
     #[test]
@@ -49,7 +49,7 @@ mod tests {
         let config = Config::default();
 
         let output = formatter
-            .format(&response, AnomalyType::Attractor, &config)
+            .format(&response, AnomalyType::ATTRACTOR, &config)
             .unwrap();
 
         // Verify it's valid JSON
@@ -32,9 +32,36 @@ impl OutputFormatter for TextFormatter {
             "Center: ({:.6}, {:.6})\n",
             response.request.lat, response.request.lng
         ));
-        output.push_str(&format!("Radius: {}m\n", response.request.radius));
+        match &response.request.area {
+            Some(area) => output.push_str(&format!(
+                "Area: {:.3} km² in {} polygon(s)\n",
+                area.square_meters() / 1e6,
+                area.polygon_count()
+            )),
+            None => output.push_str(&format!("Radius: {}m\n", response.request.radius)),
+        }
+        if let Some(zones) = &response.request.exclude {
+            output.push_str(&format!("Excluding: {} polygon(s)\n", zones.polygon_count()));
+        }
         output.push_str(&format!("Mode: {:?}\n", response.request.mode));
-        output.push_str(&format!("Backend: {}\n\n", response.request.backend));
+        output.push_str(&format!("Method: {}\n", response.request.method));
+        output.push_str(&format!("Backend: {}\n", response.request.backend));
+        if let Some(stats) = &response.metadata.extraction {
+            output.push_str(&format!(
+                "Extractor: {} ({} raw bytes for {} output, {:.2}:1)\n",
+                stats.extractor,
+                stats.input_bytes,
+                stats.output_bytes,
+                stats.ratio()
+            ));
+        }
+        if let Some(provenance) = &response.metadata.provenance {
+            output.push_str(&format!(
+                "Entropy: {} bytes, SHA-256 {}\n",
+                provenance.bytes, provenance.sha256
+            ));
+        }
+        output.push('\n');
 
         // Results
         output.push_str("Results:\n");
@@ -56,6 +83,10 @@ impl OutputFormatter for TextFormatter {
             output.push_str(&format!("  Balanced: {:.2}\n", quality.balanced));
             output.push_str(&format!("  Uniform: {:.2}\n", quality.uniform));
             output.push_str(&format!("  Scattered: {:.2}\n", quality.scattered));
+            output.push_str(&format!(
+                "  Min-entropy: {:.2} bits/byte\n",
+                quality.min_entropy.min
+            ));
         }
 
         Ok(output)
@@ -65,15 +96,15 @@ impl OutputFormatter for TextFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coord::flower::generate;
+    use crate::coord::flower::{generate, GenerationOptions};
     use crate::coord::{Coordinates, GenerationMode};
     use crate::qrng::pseudo::SeededPseudoBackend;
 
     fn create_test_response() -> GenerationResponse {
         let backend = SeededPseudoBackend::new(12345);
         let center = Coordinates::new(40.7128, -74.0060);
-        generate(center, 1000.0, 100, 10, false, GenerationMode::Standard, "test", &backend)
-            .unwrap()
+        let options = GenerationOptions::new("test").with_points(100).with_grid_resolution(10);
+        generate(center, 1000.0, GenerationMode::Standard, &options, &backend).unwrap()
     }
 
     #[test]
@@ -83,7 +114,7 @@ mod tests {
         let config = Config::default();
 
         let output = formatter
-            .format(&response, AnomalyType::Attractor, &config)
+            .format(&response, AnomalyType::ATTRACTOR, &config)
             .unwrap();
 
         assert!(output.contains("q-explore generation"));
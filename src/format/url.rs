@@ -56,15 +56,15 @@ impl OutputFormatter for UrlFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coord::flower::generate;
+    use crate::coord::flower::{generate, GenerationOptions};
     use crate::coord::{Coordinates, GenerationMode};
     use crate::qrng::pseudo::SeededPseudoBackend;
 
     fn create_test_response() -> GenerationResponse {
         let backend = SeededPseudoBackend::new(12345);
         let center = Coordinates::new(40.7128, -74.0060);
-        generate(center, 1000.0, 100, 10, false, GenerationMode::Standard, "test", &backend)
-            .unwrap()
+        let options = GenerationOptions::new("test").with_points(100).with_grid_resolution(10);
+        generate(center, 1000.0, GenerationMode::Standard, &options, &backend).unwrap()
     }
 
     #[test]
@@ -74,7 +74,7 @@ mod tests {
         let config = Config::default();
 
         let output = formatter
-            .format(&response, AnomalyType::Attractor, &config)
+            .format(&response, AnomalyType::ATTRACTOR, &config)
             .unwrap();
 
         // Default provider is Google
@@ -88,7 +88,7 @@ mod tests {
         let config = Config::default();
 
         let output = formatter
-            .format_with_provider(&response, AnomalyType::Attractor, &config, Some("openstreetmap"))
+            .format_with_provider(&response, AnomalyType::ATTRACTOR, &config, Some("openstreetmap"))
             .unwrap();
 
         assert!(output.contains("openstreetmap.org"));
@@ -101,7 +101,7 @@ mod tests {
         let config = Config::default();
 
         // Blind spot should work
-        let result = formatter.format(&response, AnomalyType::BlindSpot, &config);
+        let result = formatter.format(&response, AnomalyType::BLIND_SPOT, &config);
         assert!(result.is_ok());
     }
 
@@ -40,6 +40,7 @@ pub mod geo;
 pub mod history;
 pub mod qrng;
 pub mod server;
+pub mod usage;
 
 // Re-export commonly used types
 pub use config::Config;
@@ -9,12 +9,22 @@
 //!
 //! Both tiers support hex16 format with size=10 for maximum throughput:
 //! 1024 values × 20 bytes = 20KB per request.
+//!
+//! Transient failures (network errors, 5xx, 429) are retried with exponential
+//! backoff, honoring `Retry-After`. The free tier allows one request per
+//! minute, so requests to it are paced client-side across every `AnuBackend`
+//! in the process.
 
 use crate::error::{Error, Result};
-use crate::qrng::QrngBackend;
+use crate::qrng::http::{decode_hex, JsonRequest};
+use crate::qrng::registry::BackendContext;
+use crate::qrng::retry::RetryPolicy;
+use crate::qrng::{AsyncQrngBackend, BoxFuture, QrngBackend};
 use serde::Deserialize;
-use std::sync::mpsc;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
 const ANU_FREE_URL: &str = "https://qrng.anu.edu.au/API/jsonI.php";
 const ANU_PAID_URL: &str = "https://api.quantumnumbers.anu.edu.au";
@@ -25,13 +35,23 @@ const HEX16_BLOCK_SIZE: usize = 10; // Max allowed by API
 const BYTES_PER_HEX16_ELEMENT: usize = HEX16_BLOCK_SIZE * 2; // 20 bytes (each block = 4 hex chars = 2 bytes)
 const BYTES_PER_REQUEST: usize = MAX_ARRAY_LENGTH * BYTES_PER_HEX16_ELEMENT; // 20,480 bytes
 
+/// Free tier allows one request per minute
+pub const FREE_TIER_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
 /// ANU QRNG backend
 ///
-/// Note: This backend runs HTTP requests in a separate thread to avoid
-/// conflicts with tokio's async runtime.
+/// Note: The sync `QrngBackend` path runs HTTP requests in a separate thread to
+/// avoid conflicts with tokio's async runtime. The `AsyncQrngBackend` path uses
+/// an async client directly.
 #[derive(Debug)]
 pub struct AnuBackend {
     api_key: Option<String>,
+    /// Overrides the tier's default endpoint (e.g. a proxy or local mock)
+    endpoint: Option<String>,
+    retry: RetryPolicy,
+    timeout: Duration,
+    /// Minimum time between requests; `None` uses the tier's default
+    min_interval: Option<Duration>,
 }
 
 /// Which API tier is being used
@@ -61,16 +81,74 @@ struct AnuResponse {
 impl AnuBackend {
     /// Create a new ANU backend
     pub fn new() -> Self {
-        Self { api_key: None }
+        Self {
+            api_key: None,
+            endpoint: None,
+            retry: RetryPolicy::default(),
+            timeout: crate::qrng::http::REQUEST_TIMEOUT,
+            min_interval: None,
+        }
     }
 
     /// Create a new ANU backend with an API key
     pub fn with_api_key(api_key: impl Into<String>) -> Self {
         Self {
             api_key: Some(api_key.into()),
+            ..Self::new()
         }
     }
 
+    /// Set how failed requests are retried
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set the per-request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the minimum time between requests
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = Some(interval);
+        self
+    }
+
+    /// Use a custom endpoint URL instead of the tier's default
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Build from registry context
+    ///
+    /// The backend's own `api_key` takes precedence over `[api_keys] anu`.
+    /// Options: `retries`, `backoff_ms`, `timeout_secs`, `min_interval_secs`.
+    pub fn from_context(ctx: &BackendContext) -> Result<Self> {
+        let settings = ctx.settings;
+        let api_key = [settings.api_key.as_deref(), Some(ctx.config.api_keys.anu.as_str())]
+            .into_iter()
+            .flatten()
+            .find(|key| !key.is_empty())
+            .map(str::to_string);
+        let mut backend = Self {
+            api_key,
+            endpoint: settings.endpoint.clone(),
+            ..Self::new()
+        }
+        .with_retry(RetryPolicy::from_settings(settings, RetryPolicy::default())?);
+
+        if let Some(secs) = settings.option("timeout_secs")? {
+            backend = backend.with_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = settings.option("min_interval_secs")? {
+            backend = backend.with_min_interval(Duration::from_secs(secs));
+        }
+        Ok(backend)
+    }
+
     /// Get which API tier is being used
     pub fn tier(&self) -> AnuTier {
         match &self.api_key {
@@ -79,119 +157,168 @@ impl AnuBackend {
         }
     }
 
-    /// Fetch random bytes from the ANU API
+    /// Build the request for a batch of `count` bytes
     ///
     /// Uses hex16 with size=10 for maximum throughput (20,480 bytes per request).
-    /// Runs HTTP request in a separate thread to avoid tokio runtime conflicts.
-    fn fetch_bytes(&self, count: usize) -> Result<Vec<u8>> {
+    fn request_for(&self, count: usize) -> JsonRequest {
         // Calculate how many hex16 elements we need (each gives us 20 bytes with size=10)
         // Request up to MAX_ARRAY_LENGTH elements
-        let element_count = ((count + BYTES_PER_HEX16_ELEMENT - 1) / BYTES_PER_HEX16_ELEMENT)
-            .min(MAX_ARRAY_LENGTH);
+        let element_count = count.div_ceil(BYTES_PER_HEX16_ELEMENT).min(MAX_ARRAY_LENGTH);
 
         // Select endpoint based on whether we have an API key
-        let (url, api_key) = match &self.api_key {
-            Some(key) if !key.is_empty() => {
-                // Paid endpoint uses header auth
-                (
-                    format!(
-                        "{}?length={}&type=hex16&size={}",
-                        ANU_PAID_URL, element_count, HEX16_BLOCK_SIZE
-                    ),
-                    Some(key.clone()),
-                )
-            }
-            _ => {
-                // Free endpoint, no auth needed
-                (
-                    format!(
-                        "{}?length={}&type=hex16&size={}",
-                        ANU_FREE_URL, element_count, HEX16_BLOCK_SIZE
-                    ),
-                    None,
-                )
-            }
+        let (base_url, api_key) = match &self.api_key {
+            // Paid endpoint uses header auth
+            Some(key) if !key.is_empty() => (ANU_PAID_URL, Some(key.clone())),
+            // Free endpoint, no auth needed
+            _ => (ANU_FREE_URL, None),
         };
+        let url = format!(
+            "{}?length={}&type=hex16&size={}",
+            self.endpoint.as_deref().unwrap_or(base_url),
+            element_count,
+            HEX16_BLOCK_SIZE
+        );
+
+        let request = JsonRequest::get("ANU API", url).timeout(self.timeout);
+        match api_key {
+            Some(key) => request.header("x-api-key", key),
+            // Free tier has an expired SSL cert, so we need to disable verification
+            None => request.accept_invalid_certs(true),
+        }
+    }
 
-        // Run the HTTP request in a separate thread to avoid tokio runtime conflicts
-        let (tx, rx) = mpsc::channel();
-        let is_free_tier = api_key.is_none();
-
-        thread::spawn(move || {
-            let result = (|| -> Result<Vec<u8>> {
-                let mut client_builder = reqwest::blocking::Client::builder()
-                    .timeout(std::time::Duration::from_secs(30));
+    /// Minimum time between requests to this backend's endpoint
+    ///
+    /// Defaults to one minute for the public free-tier endpoint, and no
+    /// pacing for the paid tier or a custom endpoint.
+    fn min_interval(&self) -> Duration {
+        self.min_interval.unwrap_or(match (self.tier(), &self.endpoint) {
+            (AnuTier::Free, None) => FREE_TIER_MIN_INTERVAL,
+            _ => Duration::ZERO,
+        })
+    }
 
-                // Free tier has an expired SSL cert, so we need to disable verification
-                if is_free_tier {
-                    client_builder = client_builder.danger_accept_invalid_certs(true);
-                }
+    /// Endpoint this backend sends requests to (the pacing key)
+    fn base_url(&self) -> String {
+        let default = match self.tier() {
+            AnuTier::Free => ANU_FREE_URL,
+            AnuTier::Paid => ANU_PAID_URL,
+        };
+        self.endpoint.as_deref().unwrap_or(default).to_string()
+    }
 
-                let client = client_builder
-                    .build()
-                    .map_err(|e| Error::Qrng(format!("Failed to build HTTP client: {}", e)))?;
+    /// Claim the next request slot, returning how long to wait for it
+    fn reserve_slot(&self) -> Duration {
+        let interval = self.min_interval();
+        if interval.is_zero() {
+            return Duration::ZERO;
+        }
+        let mut slots = request_slots().lock().unwrap();
+        let now = Instant::now();
+        let slot = slots.entry(self.base_url()).or_insert(now);
+        let at = (*slot).max(now);
+        *slot = at + interval;
+        at - now
+    }
 
-                let mut request = client.get(&url);
+    /// Keep every backend on this endpoint away until `Retry-After` passes
+    fn note_quota(&self, result: &Result<Vec<u8>>) {
+        if let Err(Error::QrngQuota {
+            retry_after: Some(wait),
+            ..
+        }) = result
+        {
+            let until = Instant::now() + *wait;
+            let mut slots = request_slots().lock().unwrap();
+            let slot = slots.entry(self.base_url()).or_insert(until);
+            *slot = (*slot).max(until);
+        }
+    }
 
-                // Add API key header for paid endpoint
-                if let Some(key) = api_key {
-                    request = request.header("x-api-key", key);
-                }
+    /// Fetch random bytes from the ANU API, retrying transient failures
+    fn fetch_bytes(&self, count: usize) -> Result<Vec<u8>> {
+        self.retry.run(|| {
+            thread::sleep(self.reserve_slot());
+            let result = self
+                .request_for(count)
+                .send_blocking()
+                .and_then(parse_response);
+            self.note_quota(&result);
+            result
+        })
+    }
 
-                let response = request
+    /// Fetch random bytes from the ANU API without blocking the runtime
+    async fn fetch_bytes_async(&self, count: usize) -> Result<Vec<u8>> {
+        self.retry
+            .run_async(|| async move {
+                tokio::time::sleep(self.reserve_slot()).await;
+                let result = self
+                    .request_for(count)
                     .send()
-                    .map_err(|e| Error::Qrng(format!("ANU API request failed: {}", e)))?;
-
-                if !response.status().is_success() {
-                    return Err(Error::Qrng(format!(
-                        "ANU API returned status: {}",
-                        response.status()
-                    )));
-                }
-
-                let anu_response: AnuResponse = response
-                    .json()
-                    .map_err(|e| Error::Qrng(format!("Failed to parse ANU response: {}", e)))?;
-
-                if !anu_response.success {
-                    let msg = anu_response
-                        .message
-                        .unwrap_or_else(|| "Unknown error".to_string());
-                    return Err(Error::Qrng(format!("ANU API error: {}", msg)));
-                }
-
-                // Convert hex strings to bytes
-                // Each element is 40 hex chars = 20 bytes (with size=10)
-                let hex_data = anu_response
-                    .data
-                    .ok_or_else(|| Error::Qrng("ANU API returned no data".to_string()))?;
-
-                let mut bytes = Vec::with_capacity(hex_data.len() * BYTES_PER_HEX16_ELEMENT);
-                for hex_str in hex_data {
-                    // Parse pairs of hex characters into bytes
-                    for i in (0..hex_str.len()).step_by(2) {
-                        if i + 2 <= hex_str.len() {
-                            let byte = u8::from_str_radix(&hex_str[i..i + 2], 16).map_err(|e| {
-                                Error::Qrng(format!(
-                                    "Failed to parse hex '{}': {}",
-                                    &hex_str[i..i + 2],
-                                    e
-                                ))
-                            })?;
-                            bytes.push(byte);
-                        }
-                    }
-                }
-
-                Ok(bytes)
-            })();
-
-            let _ = tx.send(result);
-        });
+                    .await
+                    .and_then(parse_response);
+                self.note_quota(&result);
+                result
+            })
+            .await
+    }
+
+    /// Fetch `count` bytes asynchronously, batching across requests
+    async fn bytes_async_impl(&self, count: usize) -> Result<Vec<u8>> {
+        let mut result = Vec::with_capacity(count);
+        let mut remaining = count;
+
+        while remaining > 0 {
+            let batch_size = remaining.min(BYTES_PER_REQUEST);
+            let bytes = self.fetch_bytes_async(batch_size).await?;
+            result.extend(&bytes[..bytes.len().min(remaining)]);
+            remaining = remaining.saturating_sub(bytes.len());
+        }
+
+        result.truncate(count);
+        Ok(result)
+    }
+}
+
+/// Next permitted request time per endpoint, shared by every `AnuBackend`
+fn request_slots() -> &'static Mutex<HashMap<String, Instant>> {
+    static SLOTS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    SLOTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-        rx.recv()
-            .map_err(|_| Error::Qrng("Failed to receive response from HTTP thread".to_string()))?
+/// Extract the random bytes from a parsed ANU response
+fn parse_response(anu_response: AnuResponse) -> Result<Vec<u8>> {
+    if !anu_response.success {
+        let msg = anu_response
+            .message
+            .unwrap_or_else(|| "Unknown error".to_string());
+        // The free tier reports its rate limit in the body, not as a 429
+        if msg.to_lowercase().contains("limit") {
+            return Err(Error::QrngQuota {
+                message: format!("ANU API: {}", msg),
+                retry_after: None,
+            });
+        }
+        return Err(Error::Qrng(format!("ANU API error: {}", msg)));
     }
+
+    // Convert hex strings to bytes
+    // Each element is 40 hex chars = 20 bytes (with size=10)
+    let hex_data = anu_response
+        .data
+        .ok_or_else(|| Error::Qrng("ANU API returned no data".to_string()))?;
+
+    let mut bytes = Vec::with_capacity(hex_data.len() * BYTES_PER_HEX16_ELEMENT);
+    for hex_str in hex_data {
+        bytes.extend(decode_hex(&hex_str)?);
+    }
+    // Batching loops until it has enough, so an empty batch would spin
+    if bytes.is_empty() {
+        return Err(Error::Qrng("ANU API returned no data".to_string()));
+    }
+
+    Ok(bytes)
 }
 
 impl Default for AnuBackend {
@@ -209,6 +336,10 @@ impl QrngBackend for AnuBackend {
         "Australian National University Quantum Random Number Generator"
     }
 
+    fn requests_for(&self, n: usize) -> u64 {
+        n.div_ceil(BYTES_PER_REQUEST) as u64
+    }
+
     fn bytes(&self, count: usize) -> Result<Vec<u8>> {
         if count == 0 {
             return Ok(Vec::new());
@@ -230,34 +361,36 @@ impl QrngBackend for AnuBackend {
         result.truncate(count);
         Ok(result)
     }
+}
 
-    fn floats(&self, count: usize) -> Result<Vec<f64>> {
-        if count == 0 {
-            return Ok(Vec::new());
-        }
+impl AsyncQrngBackend for AnuBackend {
+    fn name(&self) -> &'static str {
+        QrngBackend::name(self)
+    }
 
-        // Need 8 bytes per f64
-        let bytes = self.bytes(count * 8)?;
-        let mut floats = Vec::with_capacity(count);
+    fn description(&self) -> &'static str {
+        QrngBackend::description(self)
+    }
 
-        for chunk in bytes.chunks_exact(8) {
-            let value = u64::from_le_bytes(chunk.try_into().unwrap());
-            let float = (value as f64) / (u64::MAX as f64);
-            floats.push(float);
-        }
+    fn requests_for(&self, n: usize) -> u64 {
+        QrngBackend::requests_for(self, n)
+    }
 
-        Ok(floats)
+    fn bytes_async(&self, count: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(self.bytes_async_impl(count))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::qrng::mock::{MockResponse, MockServer};
+    use crate::qrng::{floats_from_bytes, FloatPrecision};
 
     #[test]
     fn test_anu_backend_creation() {
         let backend = AnuBackend::new();
-        assert_eq!(backend.name(), "anu");
+        assert_eq!(QrngBackend::name(&backend), "anu");
         assert!(backend.api_key.is_none());
     }
 
@@ -271,7 +404,7 @@ mod tests {
     #[test]
     fn test_anu_backend_description() {
         let backend = AnuBackend::new();
-        assert!(backend.description().contains("Australian National University"));
+        assert!(QrngBackend::description(&backend).contains("Australian National University"));
     }
 
     #[test]
@@ -290,6 +423,175 @@ mod tests {
         assert_eq!(backend.tier(), AnuTier::Paid);
     }
 
+    #[test]
+    fn test_anu_fetch_from_mock_server() {
+        let server = crate::qrng::mock::MockServer::anu();
+        let backend = AnuBackend::new().with_endpoint(server.url());
+
+        let bytes = backend.bytes(25).unwrap();
+        assert_eq!(bytes, (0..25).collect::<Vec<u8>>());
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[test]
+    fn test_anu_api_error_from_mock_server() {
+        let server = crate::qrng::mock::MockServer::start(|_| {
+            crate::qrng::mock::MockResponse::json(r#"{"success": false, "message": "quota"}"#)
+        });
+        let backend = AnuBackend::new().with_endpoint(server.url());
+
+        let err = backend.bytes(10).unwrap_err();
+        assert!(err.to_string().contains("quota"));
+    }
+
+    #[tokio::test]
+    async fn test_anu_empty_batch_is_error() {
+        let empty = |_: &_| MockResponse::json(r#"{"success": true, "data": []}"#);
+
+        let server = MockServer::start(empty);
+        let backend = AnuBackend::new().with_endpoint(server.url());
+        assert!(matches!(backend.bytes(10), Err(Error::Qrng(_))));
+        assert_eq!(server.request_count(), 1);
+
+        let server = MockServer::start(empty);
+        let backend = AnuBackend::new().with_endpoint(server.url());
+        assert!(matches!(backend.bytes_async(10).await, Err(Error::Qrng(_))));
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_anu_async_fetch_from_mock_server() {
+        let server = crate::qrng::mock::MockServer::anu();
+        let backend = AnuBackend::new().with_endpoint(server.url());
+
+        // Spans two requests (20,480 bytes each)
+        let bytes = backend.bytes_async(25_000).await.unwrap();
+        assert_eq!(bytes.len(), 25_000);
+        assert_eq!(&bytes[..4], &[0, 1, 2, 3]);
+        assert_eq!(server.request_count(), 2);
+    }
+
+    /// Retry policy with no backoff so tests run fast
+    fn fast_retry(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::ZERO,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_anu_retries_server_errors() {
+        let server = MockServer::anu_after(vec![MockResponse::status(503), MockResponse::status(502)]);
+        let backend = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_retry(fast_retry(2));
+
+        assert_eq!(backend.bytes(4).unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(server.request_count(), 3);
+    }
+
+    #[test]
+    fn test_anu_network_error_after_retries() {
+        let server = MockServer::start(|_| MockResponse::status(503));
+        let backend = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_retry(fast_retry(1));
+
+        let err = backend.bytes(4).unwrap_err();
+        assert!(matches!(err, Error::QrngNetwork(_)), "{:?}", err);
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[test]
+    fn test_anu_honors_retry_after() {
+        let server = MockServer::anu_after(vec![MockResponse::status(429).with_header("Retry-After", "1")]);
+        let backend = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_retry(fast_retry(1));
+
+        let started = Instant::now();
+        assert_eq!(backend.bytes(2).unwrap(), vec![0, 1]);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[test]
+    fn test_anu_quota_error_is_distinct() {
+        let server = MockServer::start(|_| MockResponse::status(429).with_header("Retry-After", "3600"));
+        let backend = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_retry(fast_retry(3));
+
+        // Retry-After beyond max_wait: give up immediately
+        let err = backend.bytes(4).unwrap_err();
+        assert!(
+            matches!(err, Error::QrngQuota { retry_after: Some(d), .. } if d == Duration::from_secs(3600)),
+            "{:?}",
+            err
+        );
+        assert_eq!(server.request_count(), 1);
+
+        // Free tier reports its limit in a 200 body
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"success": false, "message": "The QRNG API is limited to 1 requests per minute"}"#,
+            )
+        });
+        let backend = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_retry(RetryPolicy::none());
+        assert!(matches!(backend.bytes(4), Err(Error::QrngQuota { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_anu_paces_requests() {
+        let server = MockServer::anu();
+        let backend = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_min_interval(Duration::from_millis(300));
+
+        // Two requests: the second has to wait for its slot
+        let started = Instant::now();
+        let bytes = backend.bytes_async(BYTES_PER_REQUEST + 10).await.unwrap();
+        assert_eq!(bytes.len(), BYTES_PER_REQUEST + 10);
+        assert_eq!(server.request_count(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_anu_free_tier_pacing_defaults() {
+        assert_eq!(AnuBackend::new().min_interval(), FREE_TIER_MIN_INTERVAL);
+        assert_eq!(AnuBackend::with_api_key("key").min_interval(), Duration::ZERO);
+        assert_eq!(
+            AnuBackend::new().with_endpoint("http://localhost").min_interval(),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_anu_options_from_config() {
+        let mut config = crate::config::Config::default();
+        config.set("backends.anu.retries", "5").unwrap();
+        config.set("backends.anu.min_interval_secs", "2").unwrap();
+
+        let registry = crate::qrng::registry::BackendRegistry::builtin();
+        assert!(registry.create("anu", &config).is_ok());
+
+        config.set("backends.anu.timeout_secs", "forever").unwrap();
+        assert!(registry.create("anu", &config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_anu_async_floats_match_sync_layout() {
+        let server = crate::qrng::mock::MockServer::anu();
+        let backend = AnuBackend::new().with_endpoint(server.url());
+
+        let floats = backend.floats_async(3).await.unwrap();
+        let expected = floats_from_bytes(&(0..24).collect::<Vec<u8>>(), FloatPrecision::Bits53);
+        assert_eq!(floats, expected);
+    }
+
     // Integration tests - these actually call the ANU API
     // Run with: cargo test qrng::anu -- --ignored
     // Set ANU_API_KEY env var to use paid tier (avoids rate limiting)
@@ -0,0 +1,381 @@
+//! Offline entropy bank
+//!
+//! `q-explore entropy fetch` draws bytes from a backend while online and
+//! deposits them in a bank directory (`bank/` in the data directory, or the
+//! `bank:<dir>` spec / `dir` option). The `bank` backend spends them later,
+//! e.g. in the field without connectivity.
+//!
+//! Bytes are never served twice. `index.json` is the record of what's
+//! unspent; a withdrawal marks its bytes spent there before handing them out
+//! and only then trims them off the end of `entropy.bin`. A crash in between
+//! leaves the file longer than the index, and the excess is discarded on the
+//! next access. A lock file serializes access between processes.
+
+use crate::config::BackendConfig;
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::qrng::QrngBackend;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const BANK_DIR_NAME: &str = "bank";
+const ENTROPY_FILE_NAME: &str = "entropy.bin";
+const INDEX_FILE_NAME: &str = "index.json";
+const LOCK_FILE_NAME: &str = "lock";
+
+/// Bytes fetched from one backend in one go
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deposit {
+    /// When the bytes were fetched (RFC 3339)
+    pub fetched_at: String,
+    /// Backend spec they were fetched with
+    pub backend: String,
+    /// Backends that actually supplied them
+    pub sources: Vec<String>,
+    /// Bytes deposited
+    pub bytes: u64,
+    /// Bytes not yet spent
+    pub remaining: u64,
+}
+
+/// What the bank holds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BankIndex {
+    /// Deposits in the order they were made
+    ///
+    /// `entropy.bin` holds each deposit's unspent bytes, in this order.
+    /// Withdrawals take from the end, so the newest deposit is spent first.
+    pub deposits: Vec<Deposit>,
+    /// Bytes withdrawn over the bank's lifetime
+    pub spent: u64,
+}
+
+impl BankIndex {
+    /// Unspent bytes
+    pub fn remaining(&self) -> u64 {
+        self.deposits.iter().map(|d| d.remaining).sum()
+    }
+}
+
+/// An entropy bank directory
+pub struct Bank {
+    dir: PathBuf,
+}
+
+impl Bank {
+    /// Use the bank in `dir` (created on first deposit)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default bank directory
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(History::data_dir()?.join(BANK_DIR_NAME))
+    }
+
+    /// The bank named by a backend table's `dir` option, or the default
+    pub fn from_settings(settings: Option<&BackendConfig>) -> Result<Self> {
+        let dir = match settings {
+            Some(settings) => settings.option::<PathBuf>("dir")?,
+            None => None,
+        };
+        Ok(Self::new(match dir {
+            Some(dir) => dir,
+            None => Self::default_dir()?,
+        }))
+    }
+
+    /// Bank directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Current contents
+    pub fn index(&self) -> Result<BankIndex> {
+        if !self.dir.exists() {
+            return Ok(BankIndex::default());
+        }
+        let _lock = self.lock()?;
+        self.load()
+    }
+
+    /// Add `bytes` fetched with `backend` from `sources`
+    pub fn deposit(&self, backend: &str, sources: Vec<String>, bytes: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| bank_error("create the bank directory", e))?;
+        let _lock = self.lock()?;
+        let mut index = self.load()?;
+
+        // Bytes first: until the index lists them they're discarded as
+        // leftovers, so an interrupted deposit is simply lost
+        let mut file = self.open_entropy()?;
+        file.seek(SeekFrom::End(0))
+            .and_then(|_| file.write_all(bytes))
+            .and_then(|_| file.sync_all())
+            .map_err(|e| bank_error("write to the bank", e))?;
+
+        index.deposits.push(Deposit {
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+            backend: backend.to_string(),
+            sources,
+            bytes: bytes.len() as u64,
+            remaining: bytes.len() as u64,
+        });
+        self.save(&index)
+    }
+
+    /// Take `n` unspent bytes, returning them with the backends that
+    /// supplied them
+    ///
+    /// Takes nothing if the bank holds fewer than `n`.
+    pub fn withdraw(&self, n: usize) -> Result<(Vec<u8>, Vec<String>)> {
+        if !self.dir.exists() {
+            return Err(insufficient(0, n));
+        }
+        let _lock = self.lock()?;
+        let mut index = self.load()?;
+        let remaining = index.remaining();
+        if (remaining as usize) < n {
+            return Err(insufficient(remaining, n));
+        }
+
+        let start = remaining - n as u64;
+        let mut file = self.open_entropy()?;
+        let mut bytes = vec![0u8; n];
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .map_err(|e| bank_error("read from the bank", e))?;
+
+        // Mark spent, newest deposit first
+        let mut sources = Vec::new();
+        let mut owed = n as u64;
+        for deposit in index.deposits.iter_mut().rev() {
+            if owed == 0 {
+                break;
+            }
+            let taken = owed.min(deposit.remaining);
+            if taken > 0 {
+                deposit.remaining -= taken;
+                owed -= taken;
+                for source in &deposit.sources {
+                    if !sources.contains(source) {
+                        sources.push(source.clone());
+                    }
+                }
+            }
+        }
+        index.spent += n as u64;
+        self.save(&index)?;
+
+        // The bytes are spent as of the save; a failed trim is cleaned up on
+        // the next access
+        let _ = file.set_len(start);
+        Ok((bytes, sources))
+    }
+
+    /// Hold the bank's lock until the returned file is dropped
+    fn lock(&self) -> Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(LOCK_FILE_NAME))
+            .map_err(|e| bank_error("open the bank lock", e))?;
+        file.lock().map_err(|e| bank_error("lock the bank", e))?;
+        Ok(file)
+    }
+
+    fn open_entropy(&self) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(self.dir.join(ENTROPY_FILE_NAME))
+            .map_err(|e| bank_error("open the bank", e))
+    }
+
+    /// Load the index, discarding bytes it doesn't list; the lock must be held
+    fn load(&self) -> Result<BankIndex> {
+        let path = self.dir.join(INDEX_FILE_NAME);
+        let index: BankIndex = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| bank_error("read the bank index", e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| Error::Qrng(format!("Failed to parse the bank index: {}", e)))?
+        } else {
+            BankIndex::default()
+        };
+
+        let file = self.open_entropy()?;
+        let len = file
+            .metadata()
+            .map_err(|e| bank_error("read the bank", e))?
+            .len();
+        let remaining = index.remaining();
+        if len < remaining {
+            return Err(Error::Qrng(format!(
+                "Entropy bank {} is damaged: index lists {} unspent bytes but only {} are stored",
+                self.dir.display(),
+                remaining,
+                len
+            )));
+        }
+        if len > remaining {
+            file.set_len(remaining)
+                .map_err(|e| bank_error("trim spent bytes from the bank", e))?;
+        }
+        Ok(index)
+    }
+
+    /// Replace the index atomically; the lock must be held
+    fn save(&self, index: &BankIndex) -> Result<()> {
+        let content = serde_json::to_string_pretty(index)
+            .map_err(|e| Error::Qrng(format!("Failed to serialize the bank index: {}", e)))?;
+        let path = self.dir.join(INDEX_FILE_NAME);
+        let temp = self.dir.join(format!("{}.tmp", INDEX_FILE_NAME));
+        File::create(&temp)
+            .and_then(|mut file| file.write_all(content.as_bytes()).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&temp, &path))
+            .map_err(|e| bank_error("write the bank index", e))
+    }
+}
+
+fn bank_error(action: &str, e: std::io::Error) -> Error {
+    Error::Qrng(format!("Failed to {}: {}", action, e))
+}
+
+fn insufficient(available: u64, requested: usize) -> Error {
+    Error::Qrng(format!(
+        "Entropy bank has {} bytes left, {} requested; run `q-explore entropy fetch` while online",
+        available, requested
+    ))
+}
+
+/// Backend that spends bytes from an entropy bank
+pub struct BankBackend {
+    bank: Bank,
+    /// Backends that supplied the most recent withdrawal
+    sources: Mutex<Vec<String>>,
+}
+
+impl BankBackend {
+    /// Spend from `bank`
+    pub fn new(bank: Bank) -> Self {
+        Self {
+            bank,
+            sources: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl QrngBackend for BankBackend {
+    fn name(&self) -> &'static str {
+        "bank"
+    }
+
+    fn description(&self) -> &'static str {
+        "Entropy fetched earlier into the offline bank"
+    }
+
+    /// The backends the last withdrawal's bytes were fetched from
+    fn sources(&self) -> Vec<String> {
+        let sources = self.sources.lock().unwrap();
+        if sources.is_empty() {
+            vec![self.name().to_string()]
+        } else {
+            sources.clone()
+        }
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let (bytes, sources) = self.bank.withdraw(n)?;
+        *self.sources.lock().unwrap() = sources;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn bank() -> (TempDir, Bank) {
+        let dir = TempDir::new().unwrap();
+        let bank = Bank::new(dir.path().join("bank"));
+        (dir, bank)
+    }
+
+    #[test]
+    fn test_empty_bank() {
+        let (_dir, bank) = bank();
+        assert_eq!(bank.index().unwrap(), BankIndex::default());
+
+        let err = bank.withdraw(1).unwrap_err();
+        assert!(err.to_string().contains("0 bytes left"), "{}", err);
+        assert!(!bank.dir().exists());
+    }
+
+    #[test]
+    fn test_bytes_are_spent_once() {
+        let (_dir, bank) = bank();
+        let first: Vec<u8> = (0..100).collect();
+        let second: Vec<u8> = (100..150).collect();
+        bank.deposit("anu", vec!["anu".to_string()], &first).unwrap();
+        bank.deposit("lab", vec!["lab".to_string()], &second).unwrap();
+
+        // Newest deposit first, spanning into the older one
+        let (bytes, sources) = bank.withdraw(60).unwrap();
+        assert_eq!(bytes, (90..150).collect::<Vec<u8>>());
+        assert_eq!(sources, vec!["lab", "anu"]);
+
+        let (bytes, sources) = bank.withdraw(90).unwrap();
+        assert_eq!(bytes, (0..90).collect::<Vec<u8>>());
+        assert_eq!(sources, vec!["anu"]);
+
+        let index = bank.index().unwrap();
+        assert_eq!(index.remaining(), 0);
+        assert_eq!(index.spent, 150);
+        assert_eq!(index.deposits[0].bytes, 100);
+        assert!(bank.withdraw(1).is_err());
+    }
+
+    #[test]
+    fn test_short_bank_takes_nothing() {
+        let (_dir, bank) = bank();
+        bank.deposit("anu", vec!["anu".to_string()], &[7; 10]).unwrap();
+
+        assert!(bank.withdraw(11).is_err());
+        assert_eq!(bank.index().unwrap().remaining(), 10);
+        assert_eq!(bank.withdraw(10).unwrap().0, vec![7; 10]);
+    }
+
+    #[test]
+    fn test_unlisted_bytes_are_never_served() {
+        let (_dir, bank) = bank();
+        bank.deposit("anu", vec!["anu".to_string()], &[1; 10]).unwrap();
+
+        // Spent bytes left behind by an interrupted withdrawal
+        let mut file = bank.open_entropy().unwrap();
+        file.seek(SeekFrom::End(0)).unwrap();
+        file.write_all(&[9; 5]).unwrap();
+
+        assert_eq!(bank.withdraw(10).unwrap().0, vec![1; 10]);
+        assert!(bank.withdraw(1).is_err());
+    }
+
+    #[test]
+    fn test_backend_reports_bank_sources() {
+        let (_dir, bank) = bank();
+        bank.deposit("anu", vec!["anu".to_string()], &[3; 64]).unwrap();
+        let backend = BankBackend::new(Bank::new(bank.dir()));
+
+        assert_eq!(backend.sources(), vec!["bank"]);
+        assert_eq!(backend.floats(4).unwrap().len(), 4);
+        assert_eq!(backend.sources(), vec!["anu"]);
+        assert_eq!(bank.index().unwrap().remaining(), 32);
+    }
+}
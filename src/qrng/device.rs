@@ -0,0 +1,272 @@
+//! Device/file entropy backend
+//!
+//! Reads raw bytes from a local path: a hardware RNG character device
+//! (`/dev/hwrng`), a named pipe fed by a USB TRNG, or a plain file of
+//! pre-collected entropy.
+//!
+//! Reads happen on a dedicated thread so a device that stops producing (or a
+//! FIFO with no writer) surfaces as a timeout error instead of hanging the
+//! caller. Bytes that arrive after a timeout are kept for the next call.
+
+use crate::error::{Error, Result};
+use crate::qrng::QrngBackend;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a read may block before giving up
+pub const DEFAULT_DEVICE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Backend reading raw bytes from a device, FIFO or file
+pub struct DeviceBackend {
+    path: PathBuf,
+    timeout: Duration,
+    state: Mutex<DeviceState>,
+}
+
+struct DeviceState {
+    // Started lazily on first draw so construction never fails
+    reader: Option<ReaderHandle>,
+    /// Bytes read but not yet handed out (e.g. late arrivals after a timeout)
+    buffer: Vec<u8>,
+    /// Set once the source hits end-of-file
+    exhausted: bool,
+}
+
+/// Channel pair to the reader thread
+struct ReaderHandle {
+    requests: Sender<usize>,
+    responses: Receiver<io::Result<Vec<u8>>>,
+    /// Size of the request the thread is currently serving, if any
+    pending: Option<usize>,
+}
+
+impl DeviceBackend {
+    /// Create a backend reading from `path` with the default timeout
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            timeout: DEFAULT_DEVICE_TIMEOUT,
+            state: Mutex::new(DeviceState {
+                reader: None,
+                buffer: Vec::new(),
+                exhausted: false,
+            }),
+        }
+    }
+
+    /// Set how long a read may block before returning an error
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Path being read
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn spawn_reader(&self) -> ReaderHandle {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (response_tx, response_rx) = mpsc::channel();
+        let path = self.path.clone();
+        thread::spawn(move || read_loop(path, request_rx, response_tx));
+        ReaderHandle {
+            requests: request_tx,
+            responses: response_rx,
+            pending: None,
+        }
+    }
+}
+
+impl QrngBackend for DeviceBackend {
+    fn name(&self) -> &'static str {
+        "device"
+    }
+
+    fn description(&self) -> &'static str {
+        "Raw bytes from a local device, FIFO or file"
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let deadline = Instant::now() + self.timeout;
+
+        while state.buffer.len() < n && !state.exhausted {
+            let wanted = n - state.buffer.len();
+            if state.reader.is_none() {
+                state.reader = Some(self.spawn_reader());
+            }
+            let reader = state.reader.as_mut().expect("reader started above");
+
+            if reader.pending.is_none() {
+                if reader.requests.send(wanted).is_err() {
+                    state.reader = None;
+                    return Err(Error::Qrng(format!(
+                        "Reader for {} stopped unexpectedly",
+                        self.path.display()
+                    )));
+                }
+                reader.pending = Some(wanted);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match reader.responses.recv_timeout(remaining) {
+                Ok(Ok(bytes)) => {
+                    let requested = reader.pending.take().unwrap_or(0);
+                    if bytes.len() < requested {
+                        state.exhausted = true;
+                    }
+                    state.buffer.extend(bytes);
+                }
+                Ok(Err(e)) => {
+                    state.reader = None;
+                    return Err(Error::Qrng(format!(
+                        "Failed to read entropy source {}: {}",
+                        self.path.display(),
+                        e
+                    )));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(Error::Qrng(format!(
+                        "Timed out after {:.1}s waiting for {} bytes from {}",
+                        self.timeout.as_secs_f64(),
+                        wanted,
+                        self.path.display()
+                    )));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    state.reader = None;
+                    return Err(Error::Qrng(format!(
+                        "Reader for {} stopped unexpectedly",
+                        self.path.display()
+                    )));
+                }
+            }
+        }
+
+        if state.buffer.len() < n {
+            return Err(Error::Qrng(format!(
+                "Entropy source {} exhausted: {} of {} bytes available",
+                self.path.display(),
+                state.buffer.len(),
+                n
+            )));
+        }
+
+        Ok(state.buffer.drain(..n).collect())
+    }
+}
+
+/// Serve read requests until the backend is dropped
+///
+/// Each request is answered with up to `n` bytes; a short answer means the
+/// source reached end-of-file. Opening happens here too, since opening a FIFO
+/// blocks until a writer appears.
+fn read_loop(path: PathBuf, requests: Receiver<usize>, responses: Sender<io::Result<Vec<u8>>>) {
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            let _ = responses.send(Err(e));
+            return;
+        }
+    };
+
+    for n in requests {
+        let result = read_up_to(&mut file, n);
+        let failed = result.is_err();
+        if responses.send(result).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Read `n` bytes, or fewer if end-of-file comes first
+fn read_up_to(file: &mut File, n: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; n];
+    let mut filled = 0;
+    while filled < n {
+        match file.read(&mut bytes[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    bytes.truncate(filled);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_reads_urandom() {
+        let backend = DeviceBackend::new("/dev/urandom");
+        let bytes = backend.bytes(4096).unwrap();
+        assert_eq!(bytes.len(), 4096);
+        assert!(bytes.iter().any(|&b| b != 0));
+
+        let floats = backend.floats(100).unwrap();
+        assert!(floats.iter().all(|f| (0.0..1.0).contains(f)));
+    }
+
+    #[test]
+    fn test_reads_file_in_order_then_exhausts() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("entropy.bin");
+        std::fs::write(&path, (0..100u8).collect::<Vec<u8>>()).unwrap();
+
+        let backend = DeviceBackend::new(&path);
+        assert_eq!(backend.bytes(60).unwrap(), (0..60u8).collect::<Vec<u8>>());
+
+        let err = backend.bytes(60).unwrap_err();
+        assert!(err.to_string().contains("exhausted"), "{}", err);
+
+        // The remaining bytes are still available to a smaller request
+        assert_eq!(backend.bytes(40).unwrap(), (60..100u8).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_missing_path_is_error() {
+        let backend = DeviceBackend::new("/nonexistent/hwrng");
+        let err = backend.bytes(8).unwrap_err();
+        assert!(err.to_string().contains("Failed to read entropy source"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_fifo_without_writer_times_out() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("trng.fifo");
+        let status = std::process::Command::new("mkfifo").arg(&path).status();
+        if !matches!(status, Ok(s) if s.success()) {
+            return; // mkfifo unavailable
+        }
+
+        let backend = DeviceBackend::new(&path).with_timeout(Duration::from_millis(200));
+        let started = Instant::now();
+        let err = backend.bytes(16).unwrap_err();
+        assert!(err.to_string().contains("Timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // Bytes written after the timeout are picked up by the next call
+        let writer_path = path.clone();
+        let writer = thread::spawn(move || {
+            use std::io::Write;
+            let mut fifo = std::fs::OpenOptions::new().write(true).open(writer_path).unwrap();
+            fifo.write_all(&[7u8; 16]).unwrap();
+        });
+        writer.join().unwrap();
+
+        let backend = backend.with_timeout(Duration::from_secs(5));
+        assert_eq!(backend.bytes(16).unwrap(), vec![7u8; 16]);
+    }
+}
@@ -0,0 +1,348 @@
+//! Randomness extractor backend
+//!
+//! Cheap hardware RNGs are often biased. This wrapper runs the raw bytes from
+//! its inner backend through an extractor before handing them out:
+//!
+//! - `von-neumann`: reads bit pairs, emitting 0 for `01`, 1 for `10` and
+//!   nothing for `00`/`11`. Removes bias from independent bits at a cost of
+//!   at least 4 raw bytes per output byte.
+//! - `sha256`: hashes each block of raw bytes (64 by default) down to 32
+//!   bytes, the vetted SHA-256 conditioning function of NIST SP 800-90B.
+//!
+//! `extraction()` reports the raw bytes consumed against bytes delivered, so
+//! users can see how much entropy the whitening cost.
+
+use crate::error::{Error, Result};
+use crate::qrng::{FloatPrecision, QrngBackend};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Default raw bytes hashed into each 32-byte SHA-256 output block
+pub const DEFAULT_SHA256_BLOCK: usize = 64;
+
+/// SHA-256 output size
+const SHA256_OUTPUT: usize = 32;
+
+/// Give up on von Neumann after this many raw bytes in a row yield nothing
+const MAX_BARREN_BYTES: usize = 4096;
+
+/// Extraction method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extractor {
+    /// Von Neumann debiasing
+    VonNeumann,
+    /// SHA-256 conditioning over blocks of `block` raw bytes
+    Sha256 { block: usize },
+}
+
+impl Extractor {
+    /// SHA-256 conditioning with a custom block size
+    ///
+    /// Returns an error if the block is smaller than the 32-byte output,
+    /// which would stretch rather than condense the input.
+    pub fn sha256(block: usize) -> Result<Self> {
+        if block < SHA256_OUTPUT {
+            return Err(Error::Config(format!(
+                "SHA-256 block must be at least {} bytes, got {}",
+                SHA256_OUTPUT, block
+            )));
+        }
+        Ok(Extractor::Sha256 { block })
+    }
+
+    /// Name used in specs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extractor::VonNeumann => "von-neumann",
+            Extractor::Sha256 { .. } => "sha256",
+        }
+    }
+}
+
+impl FromStr for Extractor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "von-neumann" | "vn" => Ok(Extractor::VonNeumann),
+            "sha256" => Ok(Extractor::Sha256 {
+                block: DEFAULT_SHA256_BLOCK,
+            }),
+            other => Err(Error::Config(format!(
+                "Unknown extractor: {} (expected von-neumann or sha256)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Raw bytes consumed versus extracted bytes delivered
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtractionStats {
+    /// Extractor that produced the output
+    pub extractor: String,
+    /// Raw bytes drawn from the source
+    pub input_bytes: u64,
+    /// Extracted bytes handed out
+    pub output_bytes: u64,
+}
+
+impl ExtractionStats {
+    /// Raw bytes consumed per output byte (0 before any output)
+    pub fn ratio(&self) -> f64 {
+        if self.output_bytes == 0 {
+            0.0
+        } else {
+            self.input_bytes as f64 / self.output_bytes as f64
+        }
+    }
+}
+
+/// Mutable extraction state
+#[derive(Default)]
+struct State {
+    /// Extracted bytes not yet handed out
+    surplus: Vec<u8>,
+    input_bytes: u64,
+    output_bytes: u64,
+}
+
+/// Backend whitening another backend's output
+pub struct ExtractorBackend {
+    inner: Box<dyn QrngBackend>,
+    extractor: Extractor,
+    state: Mutex<State>,
+}
+
+impl ExtractorBackend {
+    /// Wrap `inner` with `extractor`
+    pub fn new(inner: Box<dyn QrngBackend>, extractor: Extractor) -> Self {
+        Self {
+            inner,
+            extractor,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Extract at least `n` bytes into `state.surplus`
+    fn fill(&self, state: &mut State, n: usize) -> Result<()> {
+        let mut barren = 0;
+        while state.surplus.len() < n {
+            let needed = n - state.surplus.len();
+            let (raw, out) = match self.extractor {
+                Extractor::VonNeumann => {
+                    // Unbiased input yields one bit per 4 raw bits
+                    let raw = self.draw(needed * 4)?;
+                    let out = von_neumann(&raw);
+                    barren = if out.is_empty() { barren + raw.len() } else { 0 };
+                    if barren >= MAX_BARREN_BYTES {
+                        return Err(Error::Qrng(format!(
+                            "Von Neumann extractor got no output from {} bytes of {}; \
+                             the source looks stuck",
+                            barren,
+                            self.inner.name()
+                        )));
+                    }
+                    (raw, out)
+                }
+                Extractor::Sha256 { block } => {
+                    let blocks = needed.div_ceil(SHA256_OUTPUT);
+                    let raw = self.draw(blocks * block)?;
+                    let out = raw
+                        .chunks_exact(block)
+                        .flat_map(|chunk| Sha256::digest(chunk).to_vec())
+                        .collect();
+                    (raw, out)
+                }
+            };
+            state.input_bytes += raw.len() as u64;
+            state.surplus.extend(out);
+        }
+        Ok(())
+    }
+
+    /// Draw exactly `n` raw bytes from the inner backend
+    fn draw(&self, n: usize) -> Result<Vec<u8>> {
+        let raw = self.inner.bytes(n)?;
+        if raw.len() != n {
+            return Err(Error::Qrng(format!(
+                "Extractor source {} returned {} bytes, expected {}",
+                self.inner.name(),
+                raw.len(),
+                n
+            )));
+        }
+        Ok(raw)
+    }
+}
+
+/// Von Neumann debiasing over the bit pairs of `raw`
+///
+/// Bits that don't complete an output byte are dropped.
+fn von_neumann(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() / 4);
+    let mut byte = 0u8;
+    let mut bits = 0;
+    for &b in raw {
+        for shift in (0..8).step_by(2).rev() {
+            match (b >> shift) & 0b11 {
+                0b01 => byte <<= 1,
+                0b10 => byte = (byte << 1) | 1,
+                _ => continue,
+            }
+            bits += 1;
+            if bits == 8 {
+                out.push(byte);
+                byte = 0;
+                bits = 0;
+            }
+        }
+    }
+    out
+}
+
+impl QrngBackend for ExtractorBackend {
+    fn name(&self) -> &'static str {
+        "extract"
+    }
+
+    fn description(&self) -> &'static str {
+        "Randomness extractor over another backend"
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        let state = self.state.lock().unwrap();
+        Some(ExtractionStats {
+            extractor: self.extractor.name().to_string(),
+            input_bytes: state.input_bytes,
+            output_bytes: state.output_bytes,
+        })
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        self.fill(&mut state, n)?;
+        let rest = state.surplus.split_off(n);
+        let out = std::mem::replace(&mut state.surplus, rest);
+        state.output_bytes += n as u64;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy::tests::monobit_test;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+
+    /// Backend with each bit set with probability 3/4
+    struct Biased(SeededPseudoBackend);
+
+    impl QrngBackend for Biased {
+        fn name(&self) -> &'static str {
+            "biased"
+        }
+
+        fn description(&self) -> &'static str {
+            "Ones-heavy test source"
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            let a = self.0.bytes(n)?;
+            let b = self.0.bytes(n)?;
+            Ok(a.iter().zip(b).map(|(x, y)| x | y).collect())
+        }
+    }
+
+    /// Backend returning the same byte forever
+    struct Constant;
+
+    impl QrngBackend for Constant {
+        fn name(&self) -> &'static str {
+            "constant"
+        }
+
+        fn description(&self) -> &'static str {
+            "Stuck test source"
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            Ok(vec![0xFF; n])
+        }
+    }
+
+    #[test]
+    fn test_von_neumann_pairs() {
+        // 01 10 00 11 -> 0 1; 10 10 10 10 -> 1 1 1 1; 01 01 -> 0 0
+        assert_eq!(von_neumann(&[0b0110_0011, 0b1010_1010, 0b0101_0000]), vec![0b0111_1100]);
+        assert!(von_neumann(&[0x00, 0xFF]).is_empty());
+    }
+
+    #[test]
+    fn test_von_neumann_removes_bias() {
+        let biased = Biased(SeededPseudoBackend::new(3));
+        assert!(monobit_test(&biased.bytes(4096).unwrap()) < 0.01);
+
+        let backend = ExtractorBackend::new(
+            Box::new(Biased(SeededPseudoBackend::new(3))),
+            Extractor::VonNeumann,
+        );
+        let out = backend.bytes(1024).unwrap();
+        assert_eq!(out.len(), 1024);
+        assert!(monobit_test(&out) > 0.01);
+
+        // p(01 or 10) = 3/8 per pair, so about 5.3 raw bytes per output byte
+        let stats = backend.extraction().unwrap();
+        assert_eq!(stats.extractor, "von-neumann");
+        assert_eq!(stats.output_bytes, 1024);
+        assert!(stats.ratio() > 4.0, "ratio {}", stats.ratio());
+    }
+
+    #[test]
+    fn test_sha256_conditioning() {
+        let backend = ExtractorBackend::new(
+            Box::new(SeededPseudoBackend::new(9)),
+            Extractor::from_str("sha256").unwrap(),
+        );
+        let out = backend.bytes(40).unwrap();
+
+        let raw = SeededPseudoBackend::new(9).bytes(128).unwrap();
+        assert_eq!(&out[..32], Sha256::digest(&raw[..64]).as_slice());
+        assert_eq!(&out[32..], &Sha256::digest(&raw[64..])[..8]);
+
+        // Surplus from the second block is served before drawing more
+        assert_eq!(backend.bytes(24).unwrap(), &Sha256::digest(&raw[64..])[8..]);
+        let stats = backend.extraction().unwrap();
+        assert_eq!((stats.input_bytes, stats.output_bytes), (128, 64));
+        assert_eq!(stats.ratio(), 2.0);
+    }
+
+    #[test]
+    fn test_stuck_source_is_error() {
+        let backend = ExtractorBackend::new(Box::new(Constant), Extractor::VonNeumann);
+        let err = backend.bytes(16).unwrap_err().to_string();
+        assert!(err.contains("stuck"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_extractor() {
+        assert_eq!(Extractor::from_str("vn").unwrap(), Extractor::VonNeumann);
+        assert!(Extractor::from_str("md5").is_err());
+        assert!(Extractor::sha256(16).is_err());
+        assert_eq!(Extractor::sha256(128).unwrap(), Extractor::Sha256 { block: 128 });
+    }
+}
@@ -0,0 +1,291 @@
+//! Failover chain backend
+//!
+//! Tries an ordered list of backends (e.g. paid ANU, free ANU, a local
+//! device, pseudo), moving on to the next as soon as one fails. Transient
+//! failures (network, quota) may be retried under the link's policy first;
+//! anything else (a bad request, a missing file) fails over straight away.
+//! `sources()` reports the backend that supplied the last draw, so a
+//! generation that fell back to pseudo says so.
+
+use crate::error::{Error, Result};
+use crate::qrng::retry::RetryPolicy;
+use crate::qrng::{FloatPrecision, QrngBackend};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A backend in the chain with its retry policy
+struct Link {
+    backend: Box<dyn QrngBackend>,
+    policy: RetryPolicy,
+}
+
+/// Backend falling through an ordered chain until one succeeds
+pub struct FailoverBackend {
+    chain: Vec<Link>,
+    /// Sources of the most recent successful draw
+    last: Mutex<Vec<String>>,
+}
+
+impl FailoverBackend {
+    /// Create an empty chain
+    pub fn new() -> Self {
+        Self {
+            chain: Vec::new(),
+            last: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Append a backend to the end of the chain
+    pub fn with_backend(mut self, backend: Box<dyn QrngBackend>, policy: RetryPolicy) -> Self {
+        self.chain.push(Link { backend, policy });
+        self
+    }
+
+    /// Number of backends in the chain
+    pub fn len(&self) -> usize {
+        self.chain.len()
+    }
+
+    /// Whether the chain is empty
+    pub fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+}
+
+impl Default for FailoverBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Combine the failures of every link into one error
+///
+/// The error keeps the most actionable kind seen: a quota refusal (with
+/// the shortest wait asked for), then a network failure, then a plain
+/// QRNG error.
+fn exhausted(failures: Vec<(String, Error)>) -> Error {
+    let message = format!(
+        "All backends in failover chain failed: {}",
+        failures
+            .iter()
+            .map(|(name, e)| format!("{}: {}", name, e))
+            .collect::<Vec<_>>()
+            .join("; ")
+    );
+    let mut quota = false;
+    let mut retry_after: Option<Duration> = None;
+    let mut network = false;
+    for (_, e) in &failures {
+        match e {
+            Error::QrngQuota { retry_after: wait, .. } => {
+                quota = true;
+                if let Some(wait) = wait {
+                    retry_after = Some(retry_after.map_or(*wait, |r| r.min(*wait)));
+                }
+            }
+            Error::QrngNetwork(_) => network = true,
+            _ => {}
+        }
+    }
+    if quota {
+        Error::QrngQuota { message, retry_after }
+    } else if network {
+        Error::QrngNetwork(message)
+    } else {
+        Error::Qrng(message)
+    }
+}
+
+impl QrngBackend for FailoverBackend {
+    fn name(&self) -> &'static str {
+        "failover"
+    }
+
+    fn description(&self) -> &'static str {
+        "Ordered fallback chain of backends"
+    }
+
+    /// The backend that supplied the most recent draw
+    fn sources(&self) -> Vec<String> {
+        self.last.lock().unwrap().clone()
+    }
+
+    /// Any link may end up supplying the bytes, so the cheapest setting wins
+    fn float_precision(&self) -> FloatPrecision {
+        self.chain
+            .iter()
+            .map(|link| link.backend.float_precision())
+            .min()
+            .unwrap_or_default()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let mut failures = Vec::new();
+
+        for link in &self.chain {
+            let result = link.policy.run(|| {
+                let bytes = link.backend.bytes(n)?;
+                if bytes.len() != n {
+                    return Err(Error::Qrng(format!(
+                        "returned {} of {} bytes",
+                        bytes.len(),
+                        n
+                    )));
+                }
+                Ok(bytes)
+            });
+            match result {
+                Ok(bytes) => {
+                    *self.last.lock().unwrap() = link.backend.sources();
+                    return Ok(bytes);
+                }
+                Err(e) => failures.push((link.backend.name().to_string(), e)),
+            }
+        }
+
+        Err(exhausted(failures))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Backend that fails its first `failures` calls with `error()`
+    struct Flaky {
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+        error: fn() -> Error,
+    }
+
+    impl Flaky {
+        /// Fails with a transient network error
+        fn network(failures: usize, calls: &Arc<AtomicUsize>) -> Self {
+            Self {
+                failures,
+                calls: Arc::clone(calls),
+                error: || Error::QrngNetwork("connection reset".to_string()),
+            }
+        }
+
+        /// Fails with an error retrying can't fix
+        fn permanent(calls: &Arc<AtomicUsize>) -> Self {
+            Self {
+                failures: usize::MAX,
+                calls: Arc::clone(calls),
+                error: || Error::Qrng("HTTP 400: bad request".to_string()),
+            }
+        }
+    }
+
+    impl QrngBackend for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn description(&self) -> &'static str {
+            "Fails a few times"
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.error)());
+            }
+            Ok(vec![1; n])
+        }
+    }
+
+    fn no_wait(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::ZERO,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_retries_before_failing_over() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = FailoverBackend::new()
+            .with_backend(Box::new(Flaky::network(2, &calls)), no_wait(2))
+            .with_backend(Box::new(SeededPseudoBackend::new(1)), no_wait(0));
+
+        assert_eq!(chain.bytes(4).unwrap(), vec![1; 4]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(chain.sources(), vec!["flaky"]);
+    }
+
+    #[test]
+    fn test_permanent_errors_fail_over_at_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = FailoverBackend::new()
+            .with_backend(Box::new(Flaky::permanent(&calls)), no_wait(3))
+            .with_backend(Box::new(SeededPseudoBackend::new(1)), no_wait(0));
+
+        assert_eq!(chain.bytes(4).unwrap().len(), 4);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_falls_through_and_reports_truthfully() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = FailoverBackend::new()
+            .with_backend(Box::new(Flaky::network(usize::MAX, &calls)), no_wait(1))
+            .with_backend(Box::new(SeededPseudoBackend::new(1)), no_wait(0));
+
+        assert!(chain.sources().is_empty());
+        assert_eq!(chain.bytes(8).unwrap(), SeededPseudoBackend::new(1).bytes(8).unwrap());
+        assert_eq!(chain.sources(), vec!["pseudo-seeded"]);
+    }
+
+    #[test]
+    fn test_sources_cover_only_the_last_draw() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = FailoverBackend::new()
+            .with_backend(Box::new(Flaky::network(1, &calls)), no_wait(0))
+            .with_backend(Box::new(SeededPseudoBackend::new(1)), no_wait(0));
+
+        chain.bytes(4).unwrap();
+        assert_eq!(chain.sources(), vec!["pseudo-seeded"]);
+        // Recovered: the next draw comes from the first link alone
+        chain.bytes(4).unwrap();
+        assert_eq!(chain.sources(), vec!["flaky"]);
+    }
+
+    #[test]
+    fn test_all_failing_lists_every_error() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = FailoverBackend::new()
+            .with_backend(Box::new(Flaky::network(usize::MAX, &calls)), no_wait(1))
+            .with_backend(Box::new(Flaky::permanent(&calls)), no_wait(1));
+
+        let err = chain.bytes(4).unwrap_err();
+        // The transient failure's kind survives, so callers can back off
+        assert!(matches!(err, Error::QrngNetwork(_)), "{:?}", err);
+        let err = err.to_string();
+        assert!(err.contains("connection reset"), "{}", err);
+        assert!(err.contains("bad request"), "{}", err);
+    }
+
+    #[test]
+    fn test_quota_errors_keep_their_wait() {
+        let quota = |secs| Error::QrngQuota {
+            message: "slow down".to_string(),
+            retry_after: Some(Duration::from_secs(secs)),
+        };
+        let err = exhausted(vec![
+            ("a".to_string(), Error::QrngNetwork("timeout".to_string())),
+            ("b".to_string(), quota(60)),
+            ("c".to_string(), quota(30)),
+        ]);
+        match err {
+            Error::QrngQuota { retry_after, .. } => {
+                assert_eq!(retry_after, Some(Duration::from_secs(30)))
+            }
+            other => panic!("expected a quota error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! Continuous health tests
+//!
+//! A hardware source can get stuck partway through a run and keep serving
+//! bytes that look fine to everything downstream. The registry wraps every
+//! backend it builds in `Health`, which runs the two SP 800-90B (section
+//! 4.4) continuous tests over each byte it serves:
+//!
+//! - Repetition count: the same byte too many times in a row.
+//! - Adaptive proportion: the first byte of a 512-byte window recurring too
+//!   often within it.
+//!
+//! Cutoffs follow from the entropy the source is assumed to carry
+//! (`health.min_entropy` bits per byte, or a backend's own
+//! `health_min_entropy` option; 0 turns the tests off) and a false-alarm
+//! probability of 2^-40. A failure is latched: the draw that tripped it and
+//! every later one return `Error::EntropyHealth`, so nothing built on the
+//! failing stream is reported. `QrngBackend::health_failure` exposes the
+//! latch, e.g. for long-lived pools in `/api/status`.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::{AsyncQrngBackend, BoxFuture, FloatPrecision, QrngBackend};
+use std::sync::Mutex;
+
+/// -log2 of the false-alarm probability per test
+const ALPHA_BITS: f64 = 40.0;
+
+/// Adaptive proportion test window for 8-bit samples
+const WINDOW: usize = 512;
+
+/// Assumed min-entropy per byte for a backend
+///
+/// `name` is the backend name as written in the spec or config table.
+pub fn min_entropy(config: &Config, name: &str) -> Result<f64> {
+    let own = match config.backends.get(name) {
+        Some(settings) => settings.option::<f64>("health_min_entropy")?,
+        None => None,
+    };
+    let h = own.unwrap_or(config.health.min_entropy);
+    if !(0.0..=8.0).contains(&h) {
+        return Err(Error::Config(format!(
+            "Health test min-entropy must be between 0 and 8 bits per byte, got {}",
+            h
+        )));
+    }
+    Ok(h)
+}
+
+/// Repetition count cutoff for `h` bits of entropy per byte
+///
+/// A run this long has probability at most 2^-40 from such a source.
+pub fn repetition_cutoff(h: f64) -> usize {
+    repetition_cutoff_at(h, ALPHA_BITS)
+}
+
+/// Adaptive proportion cutoff for `h` bits of entropy per byte
+///
+/// A window's first byte recurs this often with probability at most 2^-40.
+pub fn proportion_cutoff(h: f64) -> usize {
+    proportion_cutoff_at(h, ALPHA_BITS)
+}
+
+fn repetition_cutoff_at(h: f64, alpha_bits: f64) -> usize {
+    if h <= 0.0 {
+        return usize::MAX;
+    }
+    1 + (alpha_bits / h).ceil() as usize
+}
+
+/// 1 + CRITBINOM(512, 2^-h, 1 - 2^-alpha_bits)
+fn proportion_cutoff_at(h: f64, alpha_bits: f64) -> usize {
+    if h <= 0.0 {
+        return usize::MAX;
+    }
+    let p = 2f64.powf(-h);
+    let alpha = 2f64.powf(-alpha_bits);
+
+    // Binomial(WINDOW, p) probabilities, via log factorials
+    let mut ln_factorial = vec![0.0; WINDOW + 1];
+    for k in 1..=WINDOW {
+        ln_factorial[k] = ln_factorial[k - 1] + (k as f64).ln();
+    }
+    let pmf = |k: usize| {
+        (ln_factorial[WINDOW] - ln_factorial[k] - ln_factorial[WINDOW - k]
+            + k as f64 * p.ln()
+            + (WINDOW - k) as f64 * (1.0 - p).ln())
+        .exp()
+    };
+
+    // Walk down from the top while the tail above k stays within alpha
+    let mut tail = 0.0;
+    let mut k = WINDOW;
+    while k > 0 {
+        let next = tail + pmf(k);
+        if next > alpha {
+            break;
+        }
+        tail = next;
+        k -= 1;
+    }
+    1 + k
+}
+
+/// Running state of both tests over one byte stream
+struct Monitor {
+    repetition_cutoff: usize,
+    proportion_cutoff: usize,
+    /// Bytes seen so far
+    offset: u64,
+    last: u8,
+    run: usize,
+    window_first: u8,
+    window_seen: usize,
+    window_count: usize,
+    failure: Option<String>,
+}
+
+impl Monitor {
+    fn new(h: f64) -> Self {
+        Self {
+            repetition_cutoff: repetition_cutoff(h),
+            proportion_cutoff: proportion_cutoff(h),
+            offset: 0,
+            last: 0,
+            run: 0,
+            window_first: 0,
+            window_seen: 0,
+            window_count: 0,
+            failure: None,
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> std::result::Result<(), String> {
+        if let Some(failure) = &self.failure {
+            return Err(failure.clone());
+        }
+        for &byte in bytes {
+            if self.offset > 0 && byte == self.last {
+                self.run += 1;
+            } else {
+                self.last = byte;
+                self.run = 1;
+            }
+
+            if self.window_seen == 0 {
+                self.window_first = byte;
+                self.window_count = 1;
+            } else if byte == self.window_first {
+                self.window_count += 1;
+            }
+            self.window_seen = (self.window_seen + 1) % WINDOW;
+
+            let failure = if self.run >= self.repetition_cutoff {
+                Some(format!(
+                    "repetition count test: byte {:#04x} repeated {} times at offset {}",
+                    byte, self.run, self.offset
+                ))
+            } else if self.window_count >= self.proportion_cutoff {
+                Some(format!(
+                    "adaptive proportion test: byte {:#04x} seen {} times in a {}-byte window at offset {}",
+                    self.window_first, self.window_count, WINDOW, self.offset
+                ))
+            } else {
+                None
+            };
+            self.offset += 1;
+
+            if let Some(failure) = failure {
+                self.failure = Some(failure.clone());
+                return Err(failure);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Backend whose byte stream is health-tested as it's served
+pub struct Health<B> {
+    inner: B,
+    /// Name failures are reported under (the spec or config table name)
+    key: String,
+    monitor: Mutex<Monitor>,
+}
+
+impl<B> Health<B> {
+    /// Test `inner` assuming `min_entropy` bits per byte (0 = untested)
+    pub fn new(inner: B, key: impl Into<String>, min_entropy: f64) -> Self {
+        Self {
+            inner,
+            key: key.into(),
+            monitor: Mutex::new(Monitor::new(min_entropy)),
+        }
+    }
+
+    /// The latched failure, if the tests have tripped
+    fn failure(&self) -> Option<String> {
+        self.monitor
+            .lock()
+            .unwrap()
+            .failure
+            .as_ref()
+            .map(|failure| format!("{}: {}", self.key, failure))
+    }
+
+    fn check(&self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        self.monitor
+            .lock()
+            .unwrap()
+            .feed(&bytes)
+            .map_err(|failure| Error::EntropyHealth(format!("{}: {}", self.key, failure)))?;
+        Ok(bytes)
+    }
+}
+
+/// Floats are left to the default implementation so they're built from
+/// tested bytes
+impl QrngBackend for Health<Box<dyn QrngBackend>> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        self.check(self.inner.bytes(n)?)
+    }
+}
+
+impl AsyncQrngBackend for Health<Box<dyn AsyncQrngBackend>> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(async move { self.check(self.inner.bytes_async(n).await?) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::flower::{generate, GenerationOptions};
+    use crate::coord::{Coordinates, GenerationMode};
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::SyncAdapter;
+
+    /// Serves good bytes, then gets stuck on one value
+    struct StuckAfter {
+        good: SeededPseudoBackend,
+        remaining: Mutex<usize>,
+    }
+
+    impl StuckAfter {
+        fn new(good_bytes: usize) -> Self {
+            Self {
+                good: SeededPseudoBackend::new(7),
+                remaining: Mutex::new(good_bytes),
+            }
+        }
+    }
+
+    impl QrngBackend for StuckAfter {
+        fn name(&self) -> &'static str {
+            "stuck"
+        }
+
+        fn description(&self) -> &'static str {
+            "Gets stuck partway through"
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            let mut remaining = self.remaining.lock().unwrap();
+            let good = n.min(*remaining);
+            *remaining -= good;
+            let mut bytes = self.good.bytes(good)?;
+            bytes.resize(n, 0xA5);
+            Ok(bytes)
+        }
+    }
+
+    fn tested(inner: impl QrngBackend + 'static) -> Health<Box<dyn QrngBackend>> {
+        Health::new(Box::new(inner) as Box<dyn QrngBackend>, "test", 2.0)
+    }
+
+    #[test]
+    fn test_cutoffs_match_sp800_90b_tables() {
+        // Table 2 (alpha = 2^-20, W = 512) and the 4.4.1 formula
+        let table = [(0.5, 410), (1.0, 311), (2.0, 177), (4.0, 62), (8.0, 13)];
+        for (h, cutoff) in table {
+            assert_eq!(proportion_cutoff_at(h, 20.0), cutoff, "H = {}", h);
+        }
+        assert_eq!(repetition_cutoff_at(2.0, 20.0), 11);
+
+        assert_eq!(repetition_cutoff(2.0), 21);
+        assert_eq!(proportion_cutoff(2.0), 201);
+        assert_eq!(repetition_cutoff(0.0), usize::MAX);
+        assert_eq!(proportion_cutoff(0.0), usize::MAX);
+    }
+
+    #[test]
+    fn test_random_stream_passes() {
+        let backend = tested(SeededPseudoBackend::new(1));
+        for _ in 0..64 {
+            backend.bytes(16_384).unwrap();
+        }
+        assert_eq!(backend.floats(1000).unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn test_stuck_source_fails_and_stays_failed() {
+        let backend = tested(StuckAfter::new(1000));
+        backend.bytes(1000).unwrap();
+
+        let err = backend.bytes(100).unwrap_err();
+        assert!(matches!(err, Error::EntropyHealth(_)), "{}", err);
+        assert!(err.to_string().contains("repetition count"), "{}", err);
+        assert!(err.to_string().contains("offset 1020"), "{}", err);
+
+        // Latched even if the draw itself would look fine
+        let err = backend.bytes(1).unwrap_err();
+        assert!(matches!(err, Error::EntropyHealth(_)), "{}", err);
+    }
+
+    #[test]
+    fn test_adaptive_proportion_catches_frequent_value() {
+        // Every other byte is zero: no long runs, but far too many zeros
+        let good = SeededPseudoBackend::new(3).bytes(512).unwrap();
+        let mut data = Vec::new();
+        for byte in good {
+            data.extend([0, byte | 1]);
+        }
+        let path = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(path.path(), &data).unwrap();
+
+        let backend = tested(crate::qrng::replay::ReplayBackend::new(path.path()));
+        let err = backend.bytes(data.len()).unwrap_err();
+        assert!(err.to_string().contains("adaptive proportion"), "{}", err);
+    }
+
+    #[test]
+    fn test_zero_min_entropy_disables_tests() {
+        let backend = Health::new(
+            Box::new(StuckAfter::new(0)) as Box<dyn QrngBackend>,
+            "test",
+            0.0,
+        );
+        assert_eq!(backend.bytes(10_000).unwrap(), vec![0xA5; 10_000]);
+    }
+
+    #[test]
+    fn test_generation_stops_on_stuck_source() {
+        let backend = tested(StuckAfter::new(5000));
+        let result = generate(
+            Coordinates::new(51.5, -0.12),
+            2000.0,
+            GenerationMode::Standard,
+            &GenerationOptions::new("stuck")
+                .with_points(500)
+                .with_grid_resolution(20),
+            &backend,
+        );
+        assert!(matches!(result, Err(Error::EntropyHealth(_))));
+    }
+
+    #[tokio::test]
+    async fn test_async_stream_is_tested() {
+        let inner = SyncAdapter::from_box(Box::new(StuckAfter::new(100)));
+        let backend = Health::new(Box::new(inner) as Box<dyn AsyncQrngBackend>, "test", 2.0);
+        backend.bytes_async(100).await.unwrap();
+        assert_eq!(backend.health_failure(), None);
+        let err = backend.floats_async(10).await.unwrap_err();
+        assert!(matches!(err, Error::EntropyHealth(_)), "{}", err);
+
+        let failure = backend.health_failure().unwrap();
+        assert!(failure.starts_with("test: repetition count"), "{}", failure);
+    }
+
+    #[tokio::test]
+    async fn test_sync_adapter_reports_latched_failure() {
+        let backend = SyncAdapter::from_box(Box::new(tested(StuckAfter::new(0))));
+        assert_eq!(backend.health_failure(), None);
+        backend.bytes_async(100).await.unwrap_err();
+        assert!(backend.health_failure().is_some());
+    }
+
+    #[test]
+    fn test_min_entropy_from_config() {
+        let mut config = Config::default();
+        assert_eq!(min_entropy(&config, "anu").unwrap(), 2.0);
+
+        config.set("health.min_entropy", "4").unwrap();
+        config.set("backends.lab.health_min_entropy", "0.5").unwrap();
+        assert_eq!(min_entropy(&config, "anu").unwrap(), 4.0);
+        assert_eq!(min_entropy(&config, "lab").unwrap(), 0.5);
+
+        config.set("backends.lab.health_min_entropy", "9").unwrap();
+        assert!(matches!(min_entropy(&config, "lab"), Err(Error::Config(_))));
+        assert!(config.set("health.min_entropy", "-1").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Shared HTTP request flow for network entropy backends
+//!
+//! Both the ANU backend and the generic HTTP JSON backend fetch a JSON
+//! document with a GET request and pull random bytes out of it. This module
+//! owns the transport half: building the client, sending, checking the status
+//! and parsing the body as JSON.
+//!
+//! The blocking path runs on its own thread to avoid conflicts with tokio's
+//! runtime; the async path uses `reqwest::Client` directly.
+
+use crate::error::{Error, Result};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::de::DeserializeOwned;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Default per-request timeout
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A JSON GET request to an entropy service
+#[derive(Debug, Clone)]
+pub struct JsonRequest {
+    /// Service name used in error messages (e.g. "ANU API")
+    pub service: String,
+    pub url: String,
+    /// Extra request headers (e.g. auth)
+    pub headers: Vec<(String, String)>,
+    /// Skip TLS certificate verification (ANU's free tier has an expired cert)
+    pub accept_invalid_certs: bool,
+    pub timeout: Duration,
+}
+
+impl JsonRequest {
+    /// GET `url`, labelling errors with `service`
+    pub fn get(service: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            url: url.into(),
+            headers: Vec::new(),
+            accept_invalid_certs: false,
+            timeout: REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Add a request header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Skip TLS certificate verification
+    pub fn accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Set the per-request timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send the request and parse the response body
+    ///
+    /// Runs in a separate thread to avoid tokio runtime conflicts.
+    pub fn send_blocking<T: DeserializeOwned + Send + 'static>(self) -> Result<T> {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = (|| -> Result<T> {
+                let client = reqwest::blocking::Client::builder()
+                    .timeout(self.timeout)
+                    .danger_accept_invalid_certs(self.accept_invalid_certs)
+                    .build()
+                    .map_err(|e| Error::Qrng(format!("Failed to build HTTP client: {}", e)))?;
+
+                let mut request = client.get(&self.url);
+                for (name, value) in &self.headers {
+                    request = request.header(name, value);
+                }
+
+                let response = request.send().map_err(|e| self.request_failed(e))?;
+                self.check_status(response.status(), response.headers())?;
+                response.json().map_err(|e| self.parse_failed(e))
+            })();
+
+            let _ = tx.send(result);
+        });
+
+        rx.recv()
+            .map_err(|_| Error::Qrng("Failed to receive response from HTTP thread".to_string()))?
+    }
+
+    /// Send the request without blocking the runtime and parse the response
+    pub async fn send<T: DeserializeOwned>(&self) -> Result<T> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .build()
+            .map_err(|e| Error::Qrng(format!("Failed to build HTTP client: {}", e)))?;
+
+        let mut request = client.get(&self.url);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await.map_err(|e| self.request_failed(e))?;
+        self.check_status(response.status(), response.headers())?;
+        response.json().await.map_err(|e| self.parse_failed(e))
+    }
+
+    /// Turn a non-2xx HTTP status into an error
+    ///
+    /// 429 is a quota error (with the server's `Retry-After`, if any) and 5xx
+    /// a transient network error; both are retryable. Anything else is
+    /// permanent.
+    fn check_status(&self, status: reqwest::StatusCode, headers: &HeaderMap) -> Result<()> {
+        if status.is_success() {
+            return Ok(());
+        }
+        let message = format!("{} returned status: {}", self.service, status);
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::QrngQuota {
+                message,
+                retry_after: headers
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after),
+            });
+        }
+        if status.is_server_error() {
+            return Err(Error::QrngNetwork(message));
+        }
+        Err(Error::Qrng(message))
+    }
+
+    fn request_failed(&self, e: reqwest::Error) -> Error {
+        Error::QrngNetwork(format!("{} request failed: {}", self.service, e))
+    }
+
+    fn parse_failed(&self, e: reqwest::Error) -> Error {
+        Error::Qrng(format!("Failed to parse {} response: {}", self.service, e))
+    }
+}
+
+/// Parse a `Retry-After` value: delay seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// Decode a hex string into bytes
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(Error::Qrng(format!("Hex string has odd length: {}", hex.len())));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            let pair = hex.get(i..i + 2).unwrap_or_default();
+            u8::from_str_radix(pair, 16)
+                .map_err(|e| Error::Qrng(format!("Failed to parse hex '{}': {}", pair, e)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::mock::{MockResponse, MockServer};
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("00ff7a").unwrap(), vec![0x00, 0xff, 0x7a]);
+        assert_eq!(decode_hex("").unwrap(), Vec::<u8>::new());
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+    }
+
+    #[test]
+    fn test_sends_headers_and_parses_json() {
+        let server = MockServer::start(|req| {
+            let auth = req.headers.get("x-token").cloned().unwrap_or_default();
+            MockResponse::json(serde_json::json!({ "auth": auth }).to_string())
+        });
+
+        let value: serde_json::Value = JsonRequest::get("Mock", server.url())
+            .header("X-Token", "secret")
+            .send_blocking()
+            .unwrap();
+        assert_eq!(value["auth"], "secret");
+    }
+
+    #[tokio::test]
+    async fn test_status_error_names_service() {
+        let server = MockServer::start(|_| MockResponse::status(503));
+
+        let err = JsonRequest::get("Mock QRNG", server.url())
+            .send::<serde_json::Value>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::QrngNetwork(_)), "{:?}", err);
+        assert!(err.to_string().contains("Mock QRNG returned status: 503"), "{}", err);
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/limited" => MockResponse::status(429).with_header("Retry-After", "7"),
+            _ => MockResponse::status(403),
+        });
+
+        let err = JsonRequest::get("Mock", format!("{}/limited", server.url()))
+            .send_blocking::<serde_json::Value>()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::QrngQuota { retry_after: Some(d), .. } if d == Duration::from_secs(7)),
+            "{:?}",
+            err
+        );
+
+        let err = JsonRequest::get("Mock", format!("{}/forbidden", server.url()))
+            .send_blocking::<serde_json::Value>()
+            .unwrap_err();
+        assert!(matches!(err, Error::Qrng(_)), "{:?}", err);
+
+        // Nothing listening: connection refused
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let err = JsonRequest::get("Mock", url)
+            .send_blocking::<serde_json::Value>()
+            .unwrap_err();
+        assert!(matches!(err, Error::QrngNetwork(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -0,0 +1,411 @@
+//! Generic HTTP JSON entropy backend
+//!
+//! For QRNG services with ANU-like JSON APIs. Everything service-specific is
+//! configuration:
+//!
+//! ```toml
+//! [backends.lab]
+//! type = "http"
+//! endpoint = "https://qrng.example.com/random?bytes={bytes}"
+//! api_key = "..."
+//!
+//! [backends.lab.options]
+//! auth_header = "Authorization"  # header carrying api_key (default x-api-key)
+//! auth_prefix = "Bearer "        # prepended to api_key
+//! json_path = "result.data"      # dot path to the random data
+//! encoding = "base64"            # hex, base64 or uint8
+//! max_bytes = "1024"             # largest {bytes} per request
+//! ```
+//!
+//! `{bytes}` in the URL template is replaced with the number of bytes wanted
+//! from each request. The value at `json_path` may be a single string, an
+//! array of strings (concatenated), or for `uint8` an array of numbers.
+
+use crate::error::{Error, Result};
+use crate::qrng::http::{decode_hex, JsonRequest};
+use crate::qrng::registry::BackendContext;
+use crate::qrng::{AsyncQrngBackend, BoxFuture, QrngBackend};
+use base64::Engine;
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Default largest request size
+pub const DEFAULT_MAX_BYTES_PER_REQUEST: usize = 1024;
+
+/// Default header used to send the API key
+pub const DEFAULT_AUTH_HEADER: &str = "x-api-key";
+
+/// How random data is encoded in the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Hex string(s), two characters per byte
+    Hex,
+    /// Standard base64 string(s)
+    Base64,
+    /// Array of integers 0-255
+    Uint8,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            "uint8" => Ok(Encoding::Uint8),
+            _ => Err(format!("Unknown encoding: {} (expected hex, base64 or uint8)", s)),
+        }
+    }
+}
+
+/// Backend fetching bytes from a configurable JSON API
+#[derive(Debug, Clone)]
+pub struct HttpJsonBackend {
+    url_template: String,
+    /// Header name and value sent with every request
+    auth: Option<(String, String)>,
+    json_path: String,
+    encoding: Encoding,
+    max_bytes_per_request: usize,
+    accept_invalid_certs: bool,
+}
+
+impl HttpJsonBackend {
+    /// Create a backend for `url_template` expecting hex data at the root
+    pub fn new(url_template: impl Into<String>) -> Self {
+        Self {
+            url_template: url_template.into(),
+            auth: None,
+            json_path: String::new(),
+            encoding: Encoding::Hex,
+            max_bytes_per_request: DEFAULT_MAX_BYTES_PER_REQUEST,
+            accept_invalid_certs: false,
+        }
+    }
+
+    /// Send `value` in header `name` with every request
+    pub fn with_auth_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.auth = Some((name.into(), value.into()));
+        self
+    }
+
+    /// Dot-separated path to the random data (e.g. `result.data` or `data.0`)
+    pub fn with_json_path(mut self, path: impl Into<String>) -> Self {
+        self.json_path = path.into();
+        self
+    }
+
+    /// Set how the random data is encoded
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Set the largest `{bytes}` value sent in one request
+    pub fn with_max_bytes_per_request(mut self, max: usize) -> Self {
+        self.max_bytes_per_request = max.max(1);
+        self
+    }
+
+    /// Skip TLS certificate verification
+    pub fn accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Build from registry context
+    ///
+    /// The URL template comes from the spec argument (`http:<url>`) or the
+    /// backend's `endpoint`. Specs from HTTP clients can only use the
+    /// endpoint, so the server can't be pointed (with its API key) at any
+    /// URL a caller likes.
+    pub fn from_context(ctx: &BackendContext) -> Result<Self> {
+        let settings = ctx.settings;
+        let url = ctx
+            .path_arg()?
+            .or(settings.endpoint.as_deref())
+            .ok_or_else(|| {
+                Error::Config("HTTP backend needs an endpoint (or spec http:<url>)".to_string())
+            })?;
+
+        let mut backend = Self::new(url);
+        if let Some(key) = settings.api_key.as_deref().filter(|k| !k.is_empty()) {
+            let header = settings
+                .option("auth_header")?
+                .unwrap_or_else(|| DEFAULT_AUTH_HEADER.to_string());
+            let prefix: String = settings.option("auth_prefix")?.unwrap_or_default();
+            backend = backend.with_auth_header(header, format!("{}{}", prefix, key));
+        }
+        if let Some(path) = settings.option::<String>("json_path")? {
+            backend = backend.with_json_path(path);
+        }
+        if let Some(encoding) = settings.options.get("encoding") {
+            backend = backend.with_encoding(encoding.parse().map_err(Error::Config)?);
+        }
+        if let Some(max) = settings.option("max_bytes")? {
+            backend = backend.with_max_bytes_per_request(max);
+        }
+        if let Some(accept) = settings.option("accept_invalid_certs")? {
+            backend = backend.accept_invalid_certs(accept);
+        }
+        Ok(backend)
+    }
+
+    /// Build the request for a batch of `count` bytes
+    fn request_for(&self, count: usize) -> JsonRequest {
+        let url = self.url_template.replace("{bytes}", &count.to_string());
+        let mut request =
+            JsonRequest::get("HTTP entropy API", url).accept_invalid_certs(self.accept_invalid_certs);
+        if let Some((name, value)) = &self.auth {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    /// Pull the bytes out of a response document
+    fn parse_response(&self, response: &Value) -> Result<Vec<u8>> {
+        let data = lookup(response, &self.json_path).ok_or_else(|| {
+            Error::Qrng(format!(
+                "HTTP entropy API response has no value at '{}'",
+                self.json_path
+            ))
+        })?;
+        decode(data, self.encoding)
+    }
+
+    /// Fetch `count` bytes, batching across requests
+    fn bytes_blocking(&self, count: usize) -> Result<Vec<u8>> {
+        let mut result = Vec::with_capacity(count);
+        while result.len() < count {
+            let batch = (count - result.len()).min(self.max_bytes_per_request);
+            let response: Value = self.request_for(batch).send_blocking()?;
+            extend_batch(&mut result, self.parse_response(&response)?)?;
+        }
+        result.truncate(count);
+        Ok(result)
+    }
+
+    /// Fetch `count` bytes asynchronously, batching across requests
+    async fn bytes_async_impl(&self, count: usize) -> Result<Vec<u8>> {
+        let mut result = Vec::with_capacity(count);
+        while result.len() < count {
+            let batch = (count - result.len()).min(self.max_bytes_per_request);
+            let response: Value = self.request_for(batch).send().await?;
+            extend_batch(&mut result, self.parse_response(&response)?)?;
+        }
+        result.truncate(count);
+        Ok(result)
+    }
+}
+
+/// Append a batch, refusing empty ones so a misbehaving API can't loop forever
+fn extend_batch(result: &mut Vec<u8>, batch: Vec<u8>) -> Result<()> {
+    if batch.is_empty() {
+        return Err(Error::Qrng("HTTP entropy API returned no data".to_string()));
+    }
+    result.extend(batch);
+    Ok(())
+}
+
+/// Follow a dot-separated path; numeric segments index arrays
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => current.get(segment),
+        })
+}
+
+/// Decode the data value according to `encoding`
+fn decode(data: &Value, encoding: Encoding) -> Result<Vec<u8>> {
+    match (data, encoding) {
+        (Value::String(s), Encoding::Hex) => decode_hex(s),
+        (Value::String(s), Encoding::Base64) => base64::engine::general_purpose::STANDARD
+            .decode(s)
+            .map_err(|e| Error::Qrng(format!("Failed to parse base64: {}", e))),
+        (Value::Array(items), Encoding::Uint8) => items
+            .iter()
+            .map(|item| {
+                item.as_u64()
+                    .and_then(|n| u8::try_from(n).ok())
+                    .ok_or_else(|| Error::Qrng(format!("Not a uint8 value: {}", item)))
+            })
+            .collect(),
+        (Value::Array(items), _) => {
+            let mut bytes = Vec::new();
+            for item in items {
+                bytes.extend(decode(item, encoding)?);
+            }
+            Ok(bytes)
+        }
+        _ => Err(Error::Qrng(format!(
+            "Unexpected {:?} data in HTTP entropy API response: {}",
+            encoding, data
+        ))),
+    }
+}
+
+impl QrngBackend for HttpJsonBackend {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn description(&self) -> &'static str {
+        "Configurable HTTP JSON entropy API"
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        n.div_ceil(self.max_bytes_per_request) as u64
+    }
+
+    fn bytes(&self, count: usize) -> Result<Vec<u8>> {
+        self.bytes_blocking(count)
+    }
+}
+
+impl AsyncQrngBackend for HttpJsonBackend {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn description(&self) -> &'static str {
+        "Configurable HTTP JSON entropy API"
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        QrngBackend::requests_for(self, n)
+    }
+
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(self.bytes_async_impl(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::qrng::mock::{MockRequest, MockResponse, MockServer};
+    use crate::qrng::registry::BackendRegistry;
+
+    /// Mock service returning `bytes` counting up from zero
+    fn counting_server(encode: fn(&[u8]) -> Value) -> MockServer {
+        MockServer::start(move |req: &MockRequest| {
+            let n: usize = req.query.get("n").and_then(|v| v.parse().ok()).unwrap_or(0);
+            let bytes: Vec<u8> = (0..n).map(|i| i as u8).collect();
+            MockResponse::json(serde_json::json!({ "result": { "data": encode(&bytes) } }).to_string())
+        })
+    }
+
+    fn hex(bytes: &[u8]) -> Value {
+        Value::String(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn b64(bytes: &[u8]) -> Value {
+        Value::String(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    fn uint8(bytes: &[u8]) -> Value {
+        serde_json::json!(bytes)
+    }
+
+    #[test]
+    fn test_encodings() {
+        for (encode, encoding) in [
+            (hex as fn(&[u8]) -> Value, Encoding::Hex),
+            (b64, Encoding::Base64),
+            (uint8, Encoding::Uint8),
+        ] {
+            let server = counting_server(encode);
+            let backend = HttpJsonBackend::new(format!("{}/random?n={{bytes}}", server.url()))
+                .with_json_path("result.data")
+                .with_encoding(encoding);
+
+            assert_eq!(
+                backend.bytes(32).unwrap(),
+                (0..32u8).collect::<Vec<u8>>(),
+                "{:?}",
+                encoding
+            );
+        }
+    }
+
+    #[test]
+    fn test_batches_large_requests() {
+        let server = counting_server(hex);
+        let backend = HttpJsonBackend::new(format!("{}/?n={{bytes}}", server.url()))
+            .with_json_path("result.data")
+            .with_max_bytes_per_request(100);
+
+        let bytes = backend.bytes(250).unwrap();
+        assert_eq!(bytes.len(), 250);
+        assert_eq!(server.request_count(), 3);
+    }
+
+    #[test]
+    fn test_array_of_strings_and_index_path() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(r#"{"batches": [{"values": ["0001", "0203"]}]}"#)
+        });
+        let backend = HttpJsonBackend::new(server.url()).with_json_path("batches.0.values");
+
+        assert_eq!(backend.bytes(4).unwrap(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_missing_path_and_empty_data_are_errors() {
+        let server = MockServer::start(|_| MockResponse::json(r#"{"data": ""}"#));
+
+        let backend = HttpJsonBackend::new(server.url()).with_json_path("random");
+        assert!(backend.bytes(4).unwrap_err().to_string().contains("no value at 'random'"));
+
+        let backend = HttpJsonBackend::new(server.url()).with_json_path("data");
+        assert!(backend.bytes(4).unwrap_err().to_string().contains("returned no data"));
+    }
+
+    #[tokio::test]
+    async fn test_configured_from_registry_with_auth() {
+        let server = MockServer::start(|req| {
+            if req.headers.get("authorization").map(String::as_str) != Some("Bearer s3cret") {
+                return MockResponse::status(401);
+            }
+            let n: usize = req.query.get("count").and_then(|v| v.parse().ok()).unwrap_or(0);
+            MockResponse::json(serde_json::json!({ "bytes": vec![9u8; n] }).to_string())
+        });
+
+        let mut config = Config::default();
+        config.set("backends.lab.type", "http").unwrap();
+        config
+            .set("backends.lab.endpoint", &format!("{}/qrng?count={{bytes}}", server.url()))
+            .unwrap();
+        config.set("backends.lab.api_key", "s3cret").unwrap();
+        config.set("backends.lab.auth_header", "Authorization").unwrap();
+        config.set("backends.lab.auth_prefix", "Bearer ").unwrap();
+        config.set("backends.lab.json_path", "bytes").unwrap();
+        config.set("backends.lab.encoding", "uint8").unwrap();
+
+        let registry = BackendRegistry::builtin();
+        let backend = registry.create_async("lab", &config).unwrap();
+        assert_eq!(backend.name(), "http");
+        assert_eq!(backend.bytes_async(16).await.unwrap(), vec![9u8; 16]);
+
+        let sync = registry.create("lab", &config).unwrap();
+        assert_eq!(sync.bytes(8).unwrap(), vec![9u8; 8]);
+
+        // Clients can use the configured endpoint, but not another URL
+        assert!(registry.create_async_remote("lab", &config).is_ok());
+        assert!(registry.create_async_remote("lab:http://169.254.169.254/", &config).is_err());
+        assert!(registry.create_remote("http:http://169.254.169.254/", &config).is_err());
+
+        config.set("backends.lab.encoding", "morse").unwrap();
+        assert!(registry.create("lab", &config).is_err());
+    }
+
+    #[test]
+    fn test_requires_endpoint() {
+        let registry = BackendRegistry::builtin();
+        assert!(registry.create("http", &Config::default()).is_err());
+    }
+}
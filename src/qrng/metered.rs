@@ -0,0 +1,232 @@
+//! Usage-metering wrapper
+//!
+//! The registry wraps every backend it builds so the bytes and upstream
+//! requests it serves are counted in `usage::meter()` under the name used in
+//! the spec, and its daily budget is enforced before each draw. The draw's
+//! bytes are reserved against the budget while it runs, so concurrent draws
+//! can't overshoot it together.
+
+use crate::error::Result;
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::{AsyncQrngBackend, BoxFuture, FloatPrecision, QrngBackend};
+use crate::usage::{Meter, Reservation, Usage};
+use std::sync::Arc;
+
+/// Backend whose draws are counted against a meter
+pub struct Metered<B> {
+    inner: B,
+    /// Name usage is recorded under (the spec or config table name)
+    key: String,
+    /// Daily byte budget (0 = unlimited)
+    budget: u64,
+    meter: Arc<Meter>,
+}
+
+impl<B> Metered<B> {
+    /// Meter `inner` under `key`
+    pub fn new(inner: B, key: impl Into<String>, budget: u64, meter: Arc<Meter>) -> Self {
+        Self {
+            inner,
+            key: key.into(),
+            budget,
+            meter,
+        }
+    }
+
+    fn reserve(&self, bytes: usize) -> Result<Reservation<'_>> {
+        self.meter.reserve(&self.key, self.budget, bytes as u64)
+    }
+}
+
+/// Charge a finished draw to its reservation
+fn record(reservation: Reservation, bytes: usize, requests: u64) {
+    reservation.record(Usage {
+        bytes: bytes as u64,
+        requests,
+    });
+}
+
+impl QrngBackend for Metered<Box<dyn QrngBackend>> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let reservation = self.reserve(n)?;
+        let bytes = self.inner.bytes(n)?;
+        record(reservation, bytes.len(), self.inner.requests_for(n));
+        Ok(bytes)
+    }
+
+    fn float(&self) -> Result<f64> {
+        let n = self.inner.float_precision().bytes_per_float();
+        let reservation = self.reserve(n)?;
+        let value = self.inner.float()?;
+        record(reservation, n, self.inner.requests_for(n));
+        Ok(value)
+    }
+
+    /// Delegates so backends keep their own float generation; usage is
+    /// counted as the bytes those floats would take
+    fn floats(&self, n: usize) -> Result<Vec<f64>> {
+        let bytes = n * self.inner.float_precision().bytes_per_float();
+        let reservation = self.reserve(bytes)?;
+        let floats = self.inner.floats(n)?;
+        record(reservation, bytes, self.inner.requests_for(bytes));
+        Ok(floats)
+    }
+}
+
+impl AsyncQrngBackend for Metered<Box<dyn AsyncQrngBackend>> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let reservation = self.reserve(n)?;
+            let bytes = self.inner.bytes_async(n).await?;
+            record(reservation, bytes.len(), self.inner.requests_for(n));
+            Ok(bytes)
+        })
+    }
+
+    fn floats_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<f64>>> {
+        Box::pin(async move {
+            let bytes = n * self.inner.float_precision().bytes_per_float();
+            let reservation = self.reserve(bytes)?;
+            let floats = self.inner.floats_async(n).await?;
+            record(reservation, bytes, self.inner.requests_for(bytes));
+            Ok(floats)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::SyncAdapter;
+
+    #[test]
+    fn test_counts_bytes_and_floats() {
+        let meter = Arc::new(Meter::new());
+        let backend = Metered::new(
+            Box::new(SeededPseudoBackend::new(1)) as Box<dyn QrngBackend>,
+            "lab",
+            0,
+            Arc::clone(&meter),
+        );
+
+        let reference = SeededPseudoBackend::new(1);
+        assert_eq!(backend.bytes(10).unwrap(), reference.bytes(10).unwrap());
+        assert_eq!(backend.floats(4).unwrap(), reference.floats(4).unwrap());
+        assert_eq!(meter.process()["lab"], Usage { bytes: 42, requests: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_budget_refuses_before_drawing() {
+        let meter = Arc::new(Meter::new());
+        let backend = Metered::new(
+            Box::new(SyncAdapter::from_box(Box::new(SeededPseudoBackend::new(1))))
+                as Box<dyn AsyncQrngBackend>,
+            "pseudo",
+            100,
+            Arc::clone(&meter),
+        );
+
+        backend.bytes_async(96).await.unwrap();
+        let err = backend.floats_async(1).await.unwrap_err();
+        assert!(matches!(err, Error::EntropyBudget(_)), "{}", err);
+        assert_eq!(meter.today()["pseudo"].bytes, 96);
+    }
+
+    /// Backend that takes a while to draw, so draws overlap
+    struct Slow;
+
+    impl QrngBackend for Slow {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        fn description(&self) -> &'static str {
+            "Draws slowly"
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            Ok(vec![0; n])
+        }
+    }
+
+    #[test]
+    fn test_concurrent_draws_stay_within_budget() {
+        let meter = Arc::new(Meter::new());
+        let backend = Arc::new(Metered::new(
+            Box::new(Slow) as Box<dyn QrngBackend>,
+            "slow",
+            100,
+            Arc::clone(&meter),
+        ));
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let backend = Arc::clone(&backend);
+                std::thread::spawn(move || backend.bytes(40).is_ok())
+            })
+            .collect();
+        let served = threads.into_iter().filter_map(|t| t.join().unwrap().then_some(())).count();
+
+        assert_eq!(served, 2);
+        assert_eq!(meter.today()["slow"].bytes, 80);
+    }
+}
@@ -0,0 +1,133 @@
+//! Entropy mixing backend
+//!
+//! Draws the same number of bytes from every child backend and XORs them
+//! together. As long as one child is truly random and independent of the
+//! others, the output is too, so combining a remote QRNG with a local
+//! hardware device means neither has to be trusted alone.
+
+use crate::error::{Error, Result};
+use crate::qrng::{FloatPrecision, QrngBackend};
+
+/// Backend XOR-combining two or more children
+pub struct MixBackend {
+    children: Vec<Box<dyn QrngBackend>>,
+}
+
+impl MixBackend {
+    /// Mix the given backends
+    ///
+    /// Returns an error with fewer than two children, since mixing one
+    /// source adds nothing.
+    pub fn new(children: Vec<Box<dyn QrngBackend>>) -> Result<Self> {
+        if children.len() < 2 {
+            return Err(Error::Config(
+                "Mix backend needs at least two sources".to_string(),
+            ));
+        }
+        Ok(Self { children })
+    }
+
+    /// The backends being mixed
+    pub fn children(&self) -> &[Box<dyn QrngBackend>] {
+        &self.children
+    }
+}
+
+impl QrngBackend for MixBackend {
+    fn name(&self) -> &'static str {
+        "mix"
+    }
+
+    fn description(&self) -> &'static str {
+        "XOR of several backends"
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.children.iter().flat_map(|child| child.sources()).collect()
+    }
+
+    /// Every child supplies the full byte count, so the cheapest setting wins
+    fn float_precision(&self) -> FloatPrecision {
+        self.children
+            .iter()
+            .map(|child| child.float_precision())
+            .min()
+            .unwrap_or_default()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let mut mixed = vec![0u8; n];
+        for child in &self.children {
+            let bytes = child.bytes(n)?;
+            if bytes.len() != n {
+                return Err(Error::Qrng(format!(
+                    "Mix source {} returned {} bytes, expected {}",
+                    child.name(),
+                    bytes.len(),
+                    n
+                )));
+            }
+            for (out, b) in mixed.iter_mut().zip(bytes) {
+                *out ^= b;
+            }
+        }
+        Ok(mixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+
+    /// Backend returning the same byte forever
+    struct Constant(u8);
+
+    impl QrngBackend for Constant {
+        fn name(&self) -> &'static str {
+            "constant"
+        }
+
+        fn description(&self) -> &'static str {
+            "Constant bytes"
+        }
+
+        fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+            Ok(vec![self.0; n])
+        }
+    }
+
+    #[test]
+    fn test_xors_children() {
+        let mix = MixBackend::new(vec![Box::new(Constant(0b1100)), Box::new(Constant(0b1010))])
+            .unwrap();
+        assert_eq!(mix.bytes(3).unwrap(), vec![0b0110; 3]);
+    }
+
+    #[test]
+    fn test_constant_child_does_not_weaken_output() {
+        let seeded = SeededPseudoBackend::new(99).bytes(64).unwrap();
+        let mix = MixBackend::new(vec![
+            Box::new(SeededPseudoBackend::new(99)),
+            Box::new(Constant(0)),
+        ])
+        .unwrap();
+        assert_eq!(mix.bytes(64).unwrap(), seeded);
+    }
+
+    #[test]
+    fn test_reports_all_sources() {
+        let mix = MixBackend::new(vec![
+            Box::new(SeededPseudoBackend::new(1)),
+            Box::new(Constant(7)),
+        ])
+        .unwrap();
+        assert_eq!(mix.name(), "mix");
+        assert_eq!(mix.sources(), vec!["pseudo-seeded", "constant"]);
+    }
+
+    #[test]
+    fn test_needs_two_children() {
+        assert!(MixBackend::new(vec![Box::new(Constant(1))]).is_err());
+    }
+}
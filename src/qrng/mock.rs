@@ -0,0 +1,210 @@
+//! Local mock HTTP server for backend tests
+//!
+//! Stands in for remote entropy APIs (ANU and friends) so network backends can
+//! be exercised without leaving the machine. Each connection is answered by a
+//! handler closure and then closed.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Header names are lowercased
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// A response to send back
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    /// 200 response with a JSON body
+    pub fn json(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.into(),
+        }
+    }
+
+    /// Empty response with the given status
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// Add a header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Mock HTTP server running on a background thread
+pub struct MockServer {
+    url: String,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    /// Start a server answering every request with `handler`
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                if let Some(request) = read_request(&mut stream) {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    write_response(&mut stream, &handler(&request));
+                }
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Start a server that mimics the ANU hex16 API
+    ///
+    /// Bytes are a counter (0, 1, 2, ... wrapping) continuing across requests,
+    /// so tests can check ordering.
+    pub fn anu() -> Self {
+        Self::anu_after(Vec::new())
+    }
+
+    /// Like `anu`, but answers the first requests with `failures` in order
+    pub fn anu_after(failures: Vec<MockResponse>) -> Self {
+        let next = AtomicUsize::new(0);
+        let calls = AtomicUsize::new(0);
+        Self::start(move |req| {
+            if let Some(failure) = failures.get(calls.fetch_add(1, Ordering::SeqCst)) {
+                return failure.clone();
+            }
+            let length: usize = req.query.get("length").and_then(|v| v.parse().ok()).unwrap_or(1);
+            let size: usize = req.query.get("size").and_then(|v| v.parse().ok()).unwrap_or(1);
+            let data: Vec<String> = (0..length)
+                .map(|_| {
+                    (0..size * 2)
+                        .map(|_| format!("{:02x}", next.fetch_add(1, Ordering::SeqCst) as u8))
+                        .collect()
+                })
+                .collect();
+            MockResponse::json(
+                serde_json::json!({
+                    "success": true,
+                    "type": "hex16",
+                    "length": length.to_string(),
+                    "data": data,
+                })
+                .to_string(),
+            )
+        })
+    }
+
+    /// Base URL of the server (e.g. `http://127.0.0.1:12345`)
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Number of requests handled so far
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let n = stream.read(&mut chunk).ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[header_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    let (path, query_str) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    let query = query_str
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                urlencoding::decode(v).map(|v| v.into_owned()).unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    Some(MockRequest {
+        method,
+        path: path.to_string(),
+        query,
+        headers,
+        body,
+    })
+}
+
+fn write_response(stream: &mut TcpStream, response: &MockResponse) {
+    let mut out = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    out.push_str(&response.body);
+    let _ = stream.write_all(out.as_bytes());
+    let _ = stream.flush();
+}
@@ -7,13 +7,111 @@
 //! Adding a new QRNG backend requires:
 //! 1. Create `src/qrng/{backend_name}.rs` implementing `QrngBackend`
 //! 2. Add `pub mod {backend_name};` below
-//! 3. Register in the backend registry (TODO: implement in config)
+//! 3. Register a constructor in `BackendRegistry::builtin()` (`registry.rs`)
+//!
+//! ## Async
+//! `AsyncQrngBackend` is the async companion trait used by the server and CLI.
+//! Backends with a native async source (ANU) implement it directly; any sync
+//! backend can be used through `SyncAdapter`, which runs it on tokio's
+//! blocking pool so a slow source never stalls the runtime.
+//!
+//! ## Other HTTP services
+//! `http` talks to any ANU-like JSON API; the URL template, auth header, JSON
+//! path and encoding come from its `[backends.<name>]` table (see
+//! `http_json.rs`). It shares the request flow in `http.rs` with ANU. The
+//! `http:<url>` shorthand isn't accepted from HTTP clients.
+//!
+//! ## Local devices
+//! `device:<path>` reads raw bytes from a hardware RNG device, named pipe or
+//! file, with a read timeout (`timeout_secs` option) and an error once the
+//! source is exhausted. HTTP clients can only use devices configured as a
+//! `[backends.<name>]` table with a `path` option.
+//!
+//! ## Offline bank
+//! `q-explore entropy fetch --bytes N` stores bytes from an online backend in
+//! an on-disk bank; `bank` (or `bank:<dir>`) spends them, each byte at most
+//! once, so generations work without connectivity. Generation metadata names
+//! the backends the bytes were originally fetched from. HTTP clients can't
+//! pick the directory; it comes from the backend's `dir` option.
+//!
+//! ## Wrappers
+//! `pool:<inner>` keeps a reserve of bytes from `<inner>` prefetched on a
+//! background thread.
+//!
+//! `mix:<a>,<b>,...` XORs equal byte counts from each listed backend, so the
+//! output is at least as random as the best input. `sources()` on the mix
+//! reports every contributor.
+//!
+//! `extract:<method>:<inner>` whitens a biased source with von Neumann
+//! debiasing (`von-neumann`) or SHA-256 conditioning (`sha256`, `block_bytes`
+//! option). The raw-to-output byte ratio is reported in generation metadata.
+//!
+//! `failover:<a>,<b>,...` tries each backend in order, moving on when one
+//! fails. Transient failures can be retried with exponential backoff first
+//! (`retries`, `backoff_ms` options on the failover's table); network
+//! backends already retry under their own policy, so by default the chain
+//! doesn't. Its `sources()` names only the backend that supplied the draw.
+//!
+//! ## Float precision
+//! `float`/`floats` build each float from 53 bits (8 bytes), filling the f64
+//! mantissa. Set `float_bits = "32"` in a backend's `[backends.<name>]` table
+//! to draw only 4 bytes per float instead, e.g. for a rate-limited free tier;
+//! wrappers (pool, record) inherit their inner backend's precision, and mix
+//! and failover use the coarsest of their members. Give a `replay` the same
+//! setting as the recording so it reproduces the same floats.
+//!
+//! ## Usage accounting
+//! Backends built by the registry are wrapped in `metered::Metered`, which
+//! counts bytes and upstream requests per backend name in `usage::meter()`
+//! and refuses draws beyond the backend's daily budget.
+//!
+//! ## Health tests
+//! Registry-built backends are also wrapped in `health::Health`, which runs
+//! the SP 800-90B repetition count and adaptive proportion tests on every
+//! byte served and fails with `Error::EntropyHealth` once a source looks
+//! stuck, so a generation stops instead of reporting a corrupted grid.
+//!
+//! ## Record and replay
+//! `record:<inner>:<path>` tees every byte drawn from `<inner>` into the capture
+//! file at `<path>`; `replay:<path>` serves those bytes back so a generation can
+//! be reproduced exactly. Each generation also records a SHA-256 digest of
+//! the bytes it consumed (`coord::provenance`), which `q-explore verify`
+//! checks against a capture. A `[backends.<name>]` table of either type can
+//! set the capture with `path` (and `source` for record); that's the only way
+//! HTTP clients can use them.
+//! A recording replaces any earlier capture at its path unless its table
+//! sets `append = "true"`.
 
 pub mod anu;
+pub mod bank;
+pub mod device;
+pub mod extract;
+pub mod failover;
+pub mod health;
+pub mod http;
+pub mod http_json;
+pub mod metered;
+pub mod mix;
+#[cfg(test)]
+#[allow(dead_code)]
+pub(crate) mod mock;
+pub mod pool;
+pub mod precision;
 pub mod pseudo;
+pub mod record;
+pub mod registry;
+pub mod replay;
+pub mod retry;
 
-use crate::error::Result;
+use crate::config::Config;
+use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+
+/// Boxed future returned by `AsyncQrngBackend` methods
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 /// Trait for quantum random number generator backends
 ///
@@ -25,6 +123,33 @@ pub trait QrngBackend: Send + Sync {
     /// Returns a human-readable description of this backend
     fn description(&self) -> &'static str;
 
+    /// Names of the backends that supplied the most recent draw
+    ///
+    /// Wrappers report what they wrap; composites report every contributor
+    /// to that draw. Generations collect these across all their draws
+    /// (`coord::provenance::Digesting`).
+    fn sources(&self) -> Vec<String> {
+        vec![self.name().to_string()]
+    }
+
+    /// Raw-to-extracted byte counts, if an extractor sits in this backend
+    fn extraction(&self) -> Option<extract::ExtractionStats> {
+        None
+    }
+
+    /// Upstream requests needed to serve `n` bytes, for usage accounting
+    fn requests_for(&self, _n: usize) -> u64 {
+        1
+    }
+
+    /// The continuous health test failure latched in this backend, if any
+    ///
+    /// Single-backend wrappers report their inner backend's; a latched
+    /// backend fails every draw until it is rebuilt (see `health`).
+    fn health_failure(&self) -> Option<String> {
+        None
+    }
+
     /// Generate n random bytes
     ///
     /// # Arguments
@@ -34,33 +159,262 @@ pub trait QrngBackend: Send + Sync {
     /// Vec of n random bytes (values 0-255)
     fn bytes(&self, n: usize) -> Result<Vec<u8>>;
 
+    /// Bits of entropy behind each float from `float`/`floats`
+    fn float_precision(&self) -> FloatPrecision {
+        FloatPrecision::default()
+    }
+
     /// Generate a single random float uniformly distributed in [0.0, 1.0)
     ///
-    /// Default implementation uses 4 bytes to create a u32, then divides by 2^32.
+    /// Default implementation draws `float_precision().bytes_per_float()` bytes.
     fn float(&self) -> Result<f64> {
-        let bytes = self.bytes(4)?;
-        let u = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        Ok(u as f64 / 4_294_967_296.0)
+        let precision = self.float_precision();
+        let bytes = self.bytes(precision.bytes_per_float())?;
+        Ok(floats_from_bytes(&bytes, precision)[0])
     }
 
     /// Generate n random floats, each uniformly distributed in [0.0, 1.0)
     ///
-    /// Default implementation calls float() n times. Backends may override
-    /// for efficiency (e.g., batch API calls).
+    /// Default implementation fetches the bytes for all n floats in one call.
+    /// Backends may override for efficiency.
     fn floats(&self, n: usize) -> Result<Vec<f64>> {
-        let bytes = self.bytes(n * 4)?;
-        let mut result = Vec::with_capacity(n);
-        for i in 0..n {
-            let offset = i * 4;
-            let u = u32::from_be_bytes([
-                bytes[offset],
-                bytes[offset + 1],
-                bytes[offset + 2],
-                bytes[offset + 3],
-            ]);
-            result.push(u as f64 / 4_294_967_296.0);
+        let precision = self.float_precision();
+        let bytes = self.bytes(n * precision.bytes_per_float())?;
+        Ok(floats_from_bytes(&bytes, precision))
+    }
+}
+
+/// How many bits of entropy go into each float
+///
+/// 53 bits fill an f64's mantissa, so floats are as fine-grained as the type
+/// allows. 32 bits halve the bytes drawn per float, for rate-limited sources
+/// where that matters more than the coarser 2^-32 lattice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FloatPrecision {
+    /// 4 bytes per float
+    Bits32,
+    /// 8 bytes per float, top 53 bits used
+    #[default]
+    Bits53,
+}
+
+impl FloatPrecision {
+    /// Bytes drawn from the backend per float
+    pub fn bytes_per_float(self) -> usize {
+        match self {
+            FloatPrecision::Bits32 => 4,
+            FloatPrecision::Bits53 => 8,
+        }
+    }
+
+    /// Bits of entropy per float
+    pub fn bits(self) -> u32 {
+        match self {
+            FloatPrecision::Bits32 => 32,
+            FloatPrecision::Bits53 => 53,
+        }
+    }
+}
+
+impl std::str::FromStr for FloatPrecision {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "32" => Ok(FloatPrecision::Bits32),
+            "53" => Ok(FloatPrecision::Bits53),
+            other => Err(Error::Config(format!(
+                "Invalid float_bits: {} (expected 32 or 53)",
+                other
+            ))),
         }
-        Ok(result)
+    }
+}
+
+/// Convert bytes to floats in [0.0, 1.0), big-endian, at the given precision
+///
+/// Trailing bytes that don't make up a full float are ignored.
+pub fn floats_from_bytes(bytes: &[u8], precision: FloatPrecision) -> Vec<f64> {
+    bytes
+        .chunks_exact(precision.bytes_per_float())
+        .map(|chunk| match precision {
+            FloatPrecision::Bits32 => {
+                let u = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                u as f64 / 4_294_967_296.0
+            }
+            FloatPrecision::Bits53 => {
+                let u = u64::from_be_bytes(chunk.try_into().unwrap()) >> 11;
+                u as f64 / 9_007_199_254_740_992.0
+            }
+        })
+        .collect()
+}
+
+/// Async companion to `QrngBackend`
+///
+/// Methods return boxed futures so the trait stays object-safe and backends can
+/// be chosen at runtime as `Box<dyn AsyncQrngBackend>`.
+pub trait AsyncQrngBackend: Send + Sync {
+    /// Returns the backend name (e.g., "pseudo", "anu", "rndo")
+    fn name(&self) -> &'static str;
+
+    /// Returns a human-readable description of this backend
+    fn description(&self) -> &'static str;
+
+    /// Names of the backends that supplied the most recent draw
+    fn sources(&self) -> Vec<String> {
+        vec![self.name().to_string()]
+    }
+
+    /// Bits of entropy behind each float from `floats_async`
+    fn float_precision(&self) -> FloatPrecision {
+        FloatPrecision::default()
+    }
+
+    /// Raw-to-extracted byte counts, if an extractor sits in this backend
+    fn extraction(&self) -> Option<extract::ExtractionStats> {
+        None
+    }
+
+    /// Upstream requests needed to serve `n` bytes, for usage accounting
+    fn requests_for(&self, _n: usize) -> u64 {
+        1
+    }
+
+    /// The continuous health test failure latched in this backend, if any
+    ///
+    /// See `QrngBackend::health_failure`.
+    fn health_failure(&self) -> Option<String> {
+        None
+    }
+
+    /// Generate n random bytes
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>>;
+
+    /// Generate n random floats, each uniformly distributed in [0.0, 1.0)
+    ///
+    /// Default implementation uses the same byte layout as `QrngBackend::floats`.
+    fn floats_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<f64>>> {
+        Box::pin(async move {
+            let precision = self.float_precision();
+            let bytes = self.bytes_async(n * precision.bytes_per_float()).await?;
+            Ok(floats_from_bytes(&bytes, precision))
+        })
+    }
+}
+
+/// Runs a sync backend on tokio's blocking thread pool
+///
+/// Delegates `floats_async` to the inner backend's own `floats`, so a backend
+/// produces the same values whether it's driven sync or async.
+pub struct SyncAdapter {
+    inner: Arc<dyn QrngBackend>,
+}
+
+impl SyncAdapter {
+    /// Wrap a sync backend
+    pub fn new(inner: Arc<dyn QrngBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// Wrap a boxed sync backend
+    pub fn from_box(inner: Box<dyn QrngBackend>) -> Self {
+        Self {
+            inner: Arc::from(inner),
+        }
+    }
+
+    /// Run a blocking call against the inner backend
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn QrngBackend) -> Result<T> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || f(inner.as_ref()))
+            .await
+            .map_err(|e| Error::Qrng(format!("Backend task failed: {}", e)))?
+    }
+}
+
+impl AsyncQrngBackend for SyncAdapter {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<extract::ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(self.run(move |b| b.bytes(n)))
+    }
+
+    fn floats_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<f64>>> {
+        Box::pin(self.run(move |b| b.floats(n)))
+    }
+}
+
+/// Shared backends (e.g. a long-lived pool) can be handed out as trait objects
+impl<T: QrngBackend + ?Sized> QrngBackend for Arc<T> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn description(&self) -> &'static str {
+        (**self).description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        (**self).sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        (**self).float_precision()
+    }
+
+    fn extraction(&self) -> Option<extract::ExtractionStats> {
+        (**self).extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        (**self).health_failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        (**self).requests_for(n)
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        (**self).bytes(n)
+    }
+
+    fn float(&self) -> Result<f64> {
+        (**self).float()
+    }
+
+    fn floats(&self, n: usize) -> Result<Vec<f64>> {
+        (**self).floats(n)
     }
 }
 
@@ -73,44 +427,32 @@ pub struct BackendInfo {
     pub description: String,
 }
 
-/// Get a backend by name
+/// The built-in backend registry
+pub fn registry() -> &'static registry::BackendRegistry {
+    static REGISTRY: OnceLock<registry::BackendRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(registry::BackendRegistry::builtin)
+}
+
+/// Get a backend by spec (e.g. `anu`, `pool:anu`, or a configured instance)
 ///
-/// Returns the pseudo backend as default if name is not recognized
-pub fn get_backend(name: &str) -> Box<dyn QrngBackend> {
-    match name {
-        "pseudo" => Box::new(pseudo::PseudoBackend::new()),
-        "anu" => Box::new(anu::AnuBackend::new()),
-        _ => Box::new(pseudo::PseudoBackend::new()), // Default to pseudo
-    }
-}
-
-/// Get a backend by name with optional API key
-pub fn get_backend_with_key(name: &str, api_key: Option<&str>) -> Box<dyn QrngBackend> {
-    match name {
-        "pseudo" => Box::new(pseudo::PseudoBackend::new()),
-        "anu" => {
-            if let Some(key) = api_key {
-                Box::new(anu::AnuBackend::with_api_key(key))
-            } else {
-                Box::new(anu::AnuBackend::new())
-            }
-        }
-        _ => Box::new(pseudo::PseudoBackend::new()),
-    }
+/// Returns an error if the name is not registered.
+pub fn get_backend(spec: &str, config: &Config) -> Result<Box<dyn QrngBackend>> {
+    registry().create(spec, config)
+}
+
+/// Get an async backend by spec
+///
+/// Backends with a native async implementation (ANU) are driven directly;
+/// every other backend runs through `SyncAdapter`.
+pub fn get_async_backend(spec: &str, config: &Config) -> Result<Box<dyn AsyncQrngBackend>> {
+    registry().create_async(spec, config)
 }
 
 /// List all available backends with their info
-pub fn available_backends() -> Vec<BackendInfo> {
-    vec![
-        BackendInfo {
-            name: "pseudo".to_string(),
-            description: "Pseudo-random number generator (for testing)".to_string(),
-        },
-        BackendInfo {
-            name: "anu".to_string(),
-            description: "Australian National University Quantum Random Number Generator".to_string(),
-        },
-    ]
+///
+/// Includes instances defined under `[backends.<name>]` in config.
+pub fn available_backends(config: &Config) -> Vec<BackendInfo> {
+    registry().list(config)
 }
 
 #[cfg(test)]
@@ -180,6 +522,136 @@ mod tests {
         test_uniform_distribution_for_backend(&backend, "SeededPseudoBackend");
     }
 
+    #[test]
+    fn test_get_backend_record_and_replay() {
+        let config = Config::default();
+        let backend = get_backend("record:pseudo:/tmp/capture.bin", &config).unwrap();
+        assert_eq!(backend.name(), "record");
+
+        let backend = get_backend("replay:/tmp/capture.bin", &config).unwrap();
+        assert_eq!(backend.name(), "replay");
+
+        // Paths may themselves contain colons
+        let backend = get_backend("replay:C:/captures/run.bin", &config).unwrap();
+        assert_eq!(backend.name(), "replay");
+    }
+
+    #[test]
+    fn test_get_backend_pool() {
+        let backend = get_backend("pool:pseudo", &Config::default()).unwrap();
+        assert_eq!(backend.name(), "pool");
+        assert_eq!(backend.bytes(100).unwrap().len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_sync_adapter_matches_sync_backend() {
+        let adapter = SyncAdapter::from_box(Box::new(pseudo::SeededPseudoBackend::new(42)));
+        let sync = pseudo::SeededPseudoBackend::new(42);
+
+        assert_eq!(adapter.name(), "pseudo-seeded");
+        assert_eq!(adapter.bytes_async(16).await.unwrap(), sync.bytes(16).unwrap());
+        assert_eq!(adapter.floats_async(16).await.unwrap(), sync.floats(16).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_async_backend() {
+        let config = Config::default();
+        assert_eq!(get_async_backend("anu", &config).unwrap().name(), "anu");
+
+        let backend = get_async_backend("pseudo", &config).unwrap();
+        assert_eq!(backend.name(), "pseudo");
+        assert_eq!(backend.floats_async(10).await.unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_get_backend_unknown_name() {
+        let config = Config::default();
+        assert!(get_backend("quantum-foam", &config).is_err());
+        assert!(available_backends(&config).iter().any(|b| b.name == "anu"));
+    }
+
+    #[test]
+    fn test_floats_from_bytes() {
+        let floats = floats_from_bytes(&[0, 0, 0, 0, 0x80, 0, 0, 0, 0xFF], FloatPrecision::Bits32);
+        assert_eq!(floats, vec![0.0, 0.5]);
+
+        let mut bytes = vec![0x80, 0, 0, 0, 0, 0, 0, 0];
+        bytes.extend([0xFF; 8]);
+        let floats = floats_from_bytes(&bytes, FloatPrecision::Bits53);
+        assert_eq!(floats, vec![0.5, 1.0 - f64::EPSILON / 2.0]);
+        assert!(floats[1] < 1.0);
+    }
+
+    /// Seeded bytes through the default `floats` path at a given precision
+    fn bytes_backend(precision: FloatPrecision) -> precision::WithPrecision<Box<dyn QrngBackend>> {
+        precision::WithPrecision::new(Box::new(pseudo::SeededPseudoBackend::new(2024)), precision)
+    }
+
+    /// Whether a float is a multiple of 2^-32
+    fn on_32_bit_lattice(f: f64) -> bool {
+        let scaled = f * 4_294_967_296.0;
+        scaled == scaled.trunc()
+    }
+
+    #[test]
+    fn test_53_bit_floats_uniform_distribution() {
+        let backend = bytes_backend(FloatPrecision::Bits53);
+        test_uniform_distribution_for_backend(&backend, "53-bit floats");
+
+        // Nearly every float should use bits below 2^-32
+        let floats = backend.floats(1000).unwrap();
+        let coarse = floats.iter().filter(|f| on_32_bit_lattice(**f)).count();
+        assert!(coarse < 5, "{} of 1000 floats on the 32-bit lattice", coarse);
+    }
+
+    #[test]
+    fn test_32_bit_floats_uniform_distribution() {
+        let backend = bytes_backend(FloatPrecision::Bits32);
+        test_uniform_distribution_for_backend(&backend, "32-bit floats");
+        assert!(backend.floats(1000).unwrap().into_iter().all(on_32_bit_lattice));
+    }
+
+    #[test]
+    fn test_float_bits_from_config() {
+        let mut config = Config::default();
+        assert_eq!(
+            get_backend("pseudo", &config).unwrap().float_precision(),
+            FloatPrecision::Bits53
+        );
+
+        config.set("backends.pseudo.float_bits", "32").unwrap();
+        let backend = get_backend("pseudo", &config).unwrap();
+        assert_eq!(backend.float_precision(), FloatPrecision::Bits32);
+        assert_eq!(
+            get_backend("pool:pseudo", &config).unwrap().float_precision(),
+            FloatPrecision::Bits32
+        );
+        assert_eq!(
+            get_async_backend("pseudo", &config).unwrap().float_precision(),
+            FloatPrecision::Bits32
+        );
+
+        config.set("backends.pseudo.float_bits", "64").unwrap();
+        assert!(get_backend("pseudo", &config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_float_bits_on_native_async_backend() {
+        let mut config = Config::default();
+        config.set("backends.anu.float_bits", "32").unwrap();
+        let backend = get_async_backend("anu", &config).unwrap();
+        assert_eq!(backend.name(), "anu");
+        assert_eq!(backend.float_precision(), FloatPrecision::Bits32);
+    }
+
+    #[test]
+    fn test_arc_backend_delegates() {
+        let shared: Arc<dyn QrngBackend> = Arc::new(pseudo::SeededPseudoBackend::new(42));
+        let boxed: Box<dyn QrngBackend> = Box::new(Arc::clone(&shared));
+        assert_eq!(boxed.name(), "pseudo-seeded");
+        assert_eq!(boxed.bytes(8).unwrap().len(), 8);
+    }
+
     #[test]
     #[ignore = "Requires network access to ANU API"]
     fn test_anu_backend_uniform_distribution() {
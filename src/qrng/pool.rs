@@ -0,0 +1,471 @@
+//! Prefetching entropy pool
+//!
+//! Wraps any backend and keeps a reserve of bytes topped up on a background
+//! thread, so callers are served from memory instead of waiting on a network
+//! round trip. Requests larger than what's buffered drain the reserve and
+//! fetch the remainder directly from the inner backend.
+//!
+//! The pool remembers which backends supplied each buffered stretch, so
+//! `sources()` names the ones behind the bytes actually served. A pool shared
+//! between concurrent callers hands each one a `PoolHandle` to keep those
+//! answers apart.
+
+use crate::config::{BackendConfig, Config};
+use crate::error::Result;
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::{FloatPrecision, QrngBackend};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Maximum bytes fetched from the inner backend per refill
+///
+/// Matches one ANU hex16 request so a refill never costs more than one call.
+pub const POOL_REFILL_CHUNK: usize = 20_480;
+
+/// How long the refill thread waits after a failed fetch before retrying
+const REFILL_ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Reserve size for a pool: the `reserve_bytes` option of its
+/// `[backends.<name>]` table, falling back to `[pool] reserve_bytes`
+pub fn reserve_bytes(settings: Option<&BackendConfig>, config: &Config) -> Result<usize> {
+    let configured = match settings {
+        Some(settings) => settings.option("reserve_bytes")?,
+        None => None,
+    };
+    Ok(configured.unwrap_or(config.pool.reserve_bytes))
+}
+
+/// Fill level of a pool, as reported by `/api/status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStatus {
+    /// Name of the wrapped backend
+    pub backend: String,
+    /// Target reserve size in bytes
+    pub capacity: usize,
+    /// Bytes currently buffered
+    pub available: usize,
+    /// available / capacity (0-1)
+    pub fill: f64,
+    /// Most recent refill error, cleared on the next successful refill
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Continuous health test failure latched in the wrapped backend
+    ///
+    /// Every draw fails until the pool is rebuilt, which for the server's
+    /// own backend means a restart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_failure: Option<String>,
+}
+
+/// State shared between the pool and its refill thread
+struct PoolShared {
+    inner: Box<dyn QrngBackend>,
+    capacity: usize,
+    state: Mutex<PoolState>,
+    /// Signalled when bytes are taken or the pool is shut down
+    wake: Condvar,
+    /// Held across each call to `inner`, so its `sources()` answers for
+    /// the fetch just made
+    fetch: Mutex<()>,
+}
+
+impl PoolShared {
+    /// Fetch `n` bytes from the inner backend along with their sources
+    fn fetch(&self, n: usize) -> Result<(Vec<u8>, Vec<String>)> {
+        let _guard = self.fetch.lock().unwrap();
+        let bytes = self.inner.bytes(n)?;
+        Ok((bytes, self.inner.sources()))
+    }
+}
+
+struct PoolState {
+    buffer: VecDeque<u8>,
+    /// Length and sources of each stretch of `buffer`, oldest first
+    segments: VecDeque<(usize, Vec<String>)>,
+    last_error: Option<String>,
+    shutdown: bool,
+}
+
+impl PoolState {
+    /// Take up to `n` buffered bytes, adding their sources to `sources`
+    fn take(&mut self, n: usize, sources: &mut Vec<String>) -> Vec<u8> {
+        let take = n.min(self.buffer.len());
+        let mut left = take;
+        while left > 0 {
+            let Some((len, segment)) = self.segments.front_mut() else {
+                break;
+            };
+            for source in segment.iter() {
+                if !sources.contains(source) {
+                    sources.push(source.clone());
+                }
+            }
+            if *len > left {
+                *len -= left;
+                break;
+            }
+            left -= *len;
+            self.segments.pop_front();
+        }
+        self.buffer.drain(..take).collect()
+    }
+}
+
+/// Backend serving bytes from a background-refilled reserve
+pub struct PoolBackend {
+    shared: Arc<PoolShared>,
+    /// Sources of the most recent draw through the pool itself
+    last: Mutex<Vec<String>>,
+}
+
+impl PoolBackend {
+    /// Create a pool around `inner` keeping `capacity` bytes in reserve
+    ///
+    /// Starts the refill thread immediately.
+    pub fn new(inner: Box<dyn QrngBackend>, capacity: usize) -> Self {
+        let shared = Arc::new(PoolShared {
+            inner,
+            capacity,
+            state: Mutex::new(PoolState {
+                buffer: VecDeque::with_capacity(capacity),
+                segments: VecDeque::new(),
+                last_error: None,
+                shutdown: false,
+            }),
+            wake: Condvar::new(),
+            fetch: Mutex::new(()),
+        });
+
+        let worker = Arc::clone(&shared);
+        thread::spawn(move || refill_loop(worker));
+
+        Self {
+            shared,
+            last: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Take `n` bytes along with the backends that supplied them
+    pub fn draw(&self, n: usize) -> Result<(Vec<u8>, Vec<String>)> {
+        let mut sources = Vec::new();
+        let mut bytes = self.shared.state.lock().unwrap().take(n, &mut sources);
+        self.shared.wake.notify_all();
+
+        // Anything the reserve couldn't cover comes straight from the source
+        if bytes.len() < n {
+            let (rest, rest_sources) = self.shared.fetch(n - bytes.len())?;
+            bytes.extend(rest);
+            for source in rest_sources {
+                if !sources.contains(&source) {
+                    sources.push(source);
+                }
+            }
+        }
+
+        Ok((bytes, sources))
+    }
+
+    /// Current fill level
+    pub fn status(&self) -> PoolStatus {
+        let state = self.shared.state.lock().unwrap();
+        let available = state.buffer.len();
+        PoolStatus {
+            backend: self.shared.inner.name().to_string(),
+            capacity: self.shared.capacity,
+            available,
+            fill: if self.shared.capacity == 0 {
+                0.0
+            } else {
+                available as f64 / self.shared.capacity as f64
+            },
+            last_error: state.last_error.clone(),
+            health_failure: self.shared.inner.health_failure(),
+        }
+    }
+
+    /// The backend being buffered
+    pub fn inner(&self) -> &dyn QrngBackend {
+        self.shared.inner.as_ref()
+    }
+}
+
+impl Drop for PoolBackend {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.wake.notify_all();
+    }
+}
+
+impl QrngBackend for PoolBackend {
+    fn name(&self) -> &'static str {
+        "pool"
+    }
+
+    fn description(&self) -> &'static str {
+        "Prefetching pool in front of another backend"
+    }
+
+    /// Backends behind the bytes of the most recent draw
+    fn sources(&self) -> Vec<String> {
+        let last = self.last.lock().unwrap();
+        if last.is_empty() {
+            self.shared.inner.sources()
+        } else {
+            last.clone()
+        }
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.shared.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.shared.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.shared.inner.health_failure()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let (bytes, sources) = self.draw(n)?;
+        *self.last.lock().unwrap() = sources;
+        Ok(bytes)
+    }
+}
+
+/// One caller's view of a shared pool
+///
+/// Reports the sources of its own draws, whatever other holders of the pool
+/// are drawing at the same time.
+pub struct PoolHandle {
+    pool: Arc<PoolBackend>,
+    last: Mutex<Vec<String>>,
+}
+
+impl PoolHandle {
+    /// Draw from `pool`
+    pub fn new(pool: Arc<PoolBackend>) -> Self {
+        Self {
+            pool,
+            last: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl QrngBackend for PoolHandle {
+    fn name(&self) -> &'static str {
+        self.pool.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.pool.description()
+    }
+
+    /// Backends behind the bytes of this handle's most recent draw
+    fn sources(&self) -> Vec<String> {
+        let last = self.last.lock().unwrap();
+        if last.is_empty() {
+            self.pool.inner().sources()
+        } else {
+            last.clone()
+        }
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.pool.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.pool.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.pool.health_failure()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let (bytes, sources) = self.pool.draw(n)?;
+        *self.last.lock().unwrap() = sources;
+        Ok(bytes)
+    }
+}
+
+/// Keep the reserve topped up until the pool is dropped
+fn refill_loop(shared: Arc<PoolShared>) {
+    loop {
+        let wanted = {
+            let mut state = shared.state.lock().unwrap();
+            while !state.shutdown && state.buffer.len() >= shared.capacity {
+                state = shared.wake.wait(state).unwrap();
+            }
+            if state.shutdown {
+                return;
+            }
+            (shared.capacity - state.buffer.len()).min(POOL_REFILL_CHUNK)
+        };
+
+        // Fetch without holding the lock so readers aren't blocked
+        match shared.fetch(wanted) {
+            Ok((bytes, sources)) => {
+                let mut state = shared.state.lock().unwrap();
+                if !bytes.is_empty() {
+                    state.segments.push_back((bytes.len(), sources));
+                }
+                state.buffer.extend(bytes);
+                state.last_error = None;
+            }
+            Err(e) => {
+                let mut state = shared.state.lock().unwrap();
+                state.last_error = Some(e.to_string());
+                let (state, _) = shared
+                    .wake
+                    .wait_timeout_while(state, REFILL_ERROR_BACKOFF, |s| !s.shutdown)
+                    .unwrap();
+                if state.shutdown {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::anu::AnuBackend;
+    use crate::qrng::mock::MockServer;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use std::time::Instant;
+
+    /// Wait until the pool reports at least `bytes` available
+    fn wait_for_fill(pool: &PoolBackend, bytes: usize) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while pool.status().available < bytes {
+            assert!(Instant::now() < deadline, "pool never filled: {:?}", pool.status());
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_pool_fills_in_background() {
+        let pool = PoolBackend::new(Box::new(SeededPseudoBackend::new(42)), 1000);
+        wait_for_fill(&pool, 1000);
+
+        let status = pool.status();
+        assert_eq!(status.capacity, 1000);
+        assert_eq!(status.available, 1000);
+        assert_eq!(status.fill, 1.0);
+        assert_eq!(status.backend, "pseudo-seeded");
+    }
+
+    #[test]
+    fn test_pool_preserves_byte_order() {
+        let pool = PoolBackend::new(Box::new(SeededPseudoBackend::new(42)), 100);
+        wait_for_fill(&pool, 100);
+
+        // The first 100 bytes come from the reserve, in source order; the
+        // rest is topped up by the refill thread or fetched directly
+        let bytes = pool.bytes(250).unwrap();
+        let expected = SeededPseudoBackend::new(42).bytes(250).unwrap();
+        assert_eq!(bytes.len(), 250);
+        assert_eq!(&bytes[..100], &expected[..100]);
+    }
+
+    #[test]
+    fn test_pool_larger_than_capacity() {
+        let pool = PoolBackend::new(Box::new(SeededPseudoBackend::new(1)), 16);
+        let bytes = pool.bytes(1000).unwrap();
+        assert_eq!(bytes.len(), 1000);
+    }
+
+    #[test]
+    fn test_pool_floats_in_range() {
+        let pool = PoolBackend::new(Box::new(SeededPseudoBackend::new(7)), 4096);
+        let floats = pool.floats(1000).unwrap();
+        assert_eq!(floats.len(), 1000);
+        assert!(floats.iter().all(|f| (0.0..1.0).contains(f)));
+    }
+
+    #[test]
+    fn test_pool_serves_from_reserve_without_new_requests() {
+        let server = MockServer::anu();
+        let inner = AnuBackend::new().with_endpoint(server.url());
+        let pool = PoolBackend::new(Box::new(inner), 4000);
+        wait_for_fill(&pool, 4000);
+
+        let requests_before = server.request_count();
+        let bytes = pool.bytes(1000).unwrap();
+
+        // Mock ANU bytes count up from zero
+        assert_eq!(bytes, (0..1000).map(|i| i as u8).collect::<Vec<u8>>());
+        assert_eq!(requests_before, 1);
+
+        // The refill thread tops the reserve back up
+        wait_for_fill(&pool, 4000);
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[test]
+    fn test_pool_reports_sources_of_served_bytes() {
+        use crate::qrng::failover::FailoverBackend;
+        use crate::qrng::retry::RetryPolicy;
+
+        // ANU is down, so the reserve fills from the fallback
+        let down = MockServer::start(|_| crate::qrng::mock::MockResponse::status(503));
+        let chain = FailoverBackend::new()
+            .with_backend(
+                Box::new(
+                    AnuBackend::new()
+                        .with_endpoint(down.url())
+                        .with_retry(RetryPolicy::none()),
+                ),
+                RetryPolicy::none(),
+            )
+            .with_backend(Box::new(SeededPseudoBackend::new(3)), RetryPolicy::none());
+        let pool = Arc::new(PoolBackend::new(Box::new(chain), 100));
+        wait_for_fill(&pool, 100);
+
+        let handle = PoolHandle::new(Arc::clone(&pool));
+        handle.bytes(50).unwrap();
+        assert_eq!(handle.sources(), vec!["pseudo-seeded"]);
+        pool.bytes(500).unwrap();
+        assert_eq!(pool.sources(), vec!["pseudo-seeded"]);
+    }
+
+    #[test]
+    fn test_pool_reports_latched_health_failure() {
+        let inner = crate::qrng::registry()
+            .create("device:/dev/zero", &crate::config::Config::default())
+            .unwrap();
+        let pool = PoolBackend::new(inner, 4096);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while pool.status().health_failure.is_none() {
+            assert!(Instant::now() < deadline, "health failure never reported");
+            thread::sleep(Duration::from_millis(10));
+        }
+        let failure = pool.status().health_failure.unwrap();
+        assert!(failure.contains("repetition count"), "{}", failure);
+        assert!(pool.bytes(4096 + 1).is_err());
+    }
+
+    #[test]
+    fn test_pool_reports_refill_errors() {
+        let server = MockServer::start(|_| crate::qrng::mock::MockResponse::status(503));
+        let inner = AnuBackend::new()
+            .with_endpoint(server.url())
+            .with_retry(crate::qrng::retry::RetryPolicy::none());
+        let pool = PoolBackend::new(Box::new(inner), 100);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while pool.status().last_error.is_none() {
+            assert!(Instant::now() < deadline, "refill error never reported");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(pool.bytes(10).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Float precision override
+//!
+//! Applied by the registry when a backend's table sets `float_bits`. Bytes
+//! pass straight through; only the number of bytes drawn per float changes.
+
+use crate::error::Result;
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::{AsyncQrngBackend, BoxFuture, FloatPrecision, QrngBackend};
+
+/// Backend whose floats use a fixed precision
+pub struct WithPrecision<B> {
+    inner: B,
+    precision: FloatPrecision,
+}
+
+impl<B> WithPrecision<B> {
+    /// Wrap `inner`, building its floats at `precision`
+    pub fn new(inner: B, precision: FloatPrecision) -> Self {
+        Self { inner, precision }
+    }
+}
+
+impl QrngBackend for WithPrecision<Box<dyn QrngBackend>> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.precision
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        self.inner.bytes(n)
+    }
+}
+
+impl AsyncQrngBackend for WithPrecision<Box<dyn AsyncQrngBackend>> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.precision
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn requests_for(&self, n: usize) -> u64 {
+        self.inner.requests_for(n)
+    }
+
+    fn bytes_async(&self, n: usize) -> BoxFuture<'_, Result<Vec<u8>>> {
+        self.inner.bytes_async(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::floats_from_bytes;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+
+    #[test]
+    fn test_floats_use_wrapped_precision() {
+        let backend = WithPrecision::new(
+            Box::new(SeededPseudoBackend::new(7)) as Box<dyn QrngBackend>,
+            FloatPrecision::Bits32,
+        );
+        let bytes = SeededPseudoBackend::new(7).bytes(12).unwrap();
+
+        assert_eq!(backend.name(), "pseudo-seeded");
+        assert_eq!(
+            backend.floats(3).unwrap(),
+            floats_from_bytes(&bytes, FloatPrecision::Bits32)
+        );
+    }
+}
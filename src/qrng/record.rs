@@ -0,0 +1,197 @@
+//! Recording backend
+//!
+//! Wraps another backend and tees every byte it draws into a capture file.
+//! The capture can later be served back by `ReplayBackend` to reproduce a
+//! generation bit-for-bit.
+//!
+//! A recorder starts its capture afresh, replacing any earlier file at the
+//! path, unless it's set to append.
+//!
+//! Floats are always derived from the recorded bytes (never from the inner
+//! backend's own `floats`), so a replay of the capture yields identical values.
+
+use crate::error::{Error, Result};
+use crate::qrng::extract::ExtractionStats;
+use crate::qrng::{FloatPrecision, QrngBackend};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Backend that records all bytes drawn from an inner backend
+pub struct RecordingBackend {
+    inner: Box<dyn QrngBackend>,
+    path: PathBuf,
+    append: bool,
+    // Opened lazily on first draw so construction never fails
+    file: Mutex<Option<File>>,
+}
+
+impl RecordingBackend {
+    /// Create a recording backend that writes the capture file at `path`
+    ///
+    /// An existing file there is truncated on the first draw.
+    pub fn new(inner: Box<dyn QrngBackend>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            append: false,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Append to an existing capture instead of replacing it
+    ///
+    /// A replay of such a capture starts with the earlier bytes, so only the
+    /// first recording in it can be verified.
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Path of the capture file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The backend whose bytes are being recorded
+    pub fn inner(&self) -> &dyn QrngBackend {
+        self.inner.as_ref()
+    }
+
+    /// Append bytes to the capture file
+    fn record(&self, bytes: &[u8]) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+
+        if file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                if !parent.as_os_str().is_empty() {
+                    std::fs::create_dir_all(parent)?;
+                }
+            }
+            let opened = OpenOptions::new()
+                .create(true)
+                .append(self.append)
+                .write(true)
+                .truncate(!self.append)
+                .open(&self.path)
+                .map_err(|e| {
+                    Error::Qrng(format!(
+                        "Failed to open capture file {}: {}",
+                        self.path.display(),
+                        e
+                    ))
+                })?;
+            *file = Some(opened);
+        }
+
+        let file = file.as_mut().expect("capture file opened above");
+        file.write_all(bytes)
+            .and_then(|_| file.flush())
+            .map_err(|e| {
+                Error::Qrng(format!(
+                    "Failed to write capture file {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })
+    }
+}
+
+impl QrngBackend for RecordingBackend {
+    fn name(&self) -> &'static str {
+        "record"
+    }
+
+    fn description(&self) -> &'static str {
+        "Records bytes from another backend to a capture file"
+    }
+
+    fn sources(&self) -> Vec<String> {
+        self.inner.sources()
+    }
+
+    fn float_precision(&self) -> FloatPrecision {
+        self.inner.float_precision()
+    }
+
+    fn extraction(&self) -> Option<ExtractionStats> {
+        self.inner.extraction()
+    }
+
+    fn health_failure(&self) -> Option<String> {
+        self.inner.health_failure()
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let bytes = self.inner.bytes(n)?;
+        self.record(&bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qrng::floats_from_bytes;
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recording_tees_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(42)), &path);
+        let first = backend.bytes(16).unwrap();
+        let second = backend.bytes(8).unwrap();
+
+        let captured = std::fs::read(&path).unwrap();
+        assert_eq!(captured.len(), 24);
+        assert_eq!(&captured[..16], first.as_slice());
+        assert_eq!(&captured[16..], second.as_slice());
+    }
+
+    #[test]
+    fn test_recording_floats_come_from_recorded_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(42)), &path);
+        let floats = backend.floats(10).unwrap();
+
+        // Floats use 8 bytes each, all of which must be in the capture
+        let captured = std::fs::read(&path).unwrap();
+        assert_eq!(captured.len(), 80);
+        assert_eq!(floats, floats_from_bytes(&captured, FloatPrecision::Bits53));
+    }
+
+    #[test]
+    fn test_recording_replaces_earlier_capture() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        std::fs::write(&path, [0u8; 32]).unwrap();
+
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(42)), &path);
+        let first = backend.bytes(16).unwrap();
+        let second = backend.bytes(8).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), [first.clone(), second].concat());
+
+        let backend =
+            RecordingBackend::new(Box::new(SeededPseudoBackend::new(7)), &path).with_append(true);
+        let appended = backend.bytes(4).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[..16], first);
+        assert_eq!(std::fs::read(&path).unwrap()[24..], appended);
+    }
+
+    #[test]
+    fn test_recording_creates_parent_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("capture.bin");
+
+        let backend = RecordingBackend::new(Box::new(SeededPseudoBackend::new(1)), &path);
+        backend.bytes(4).unwrap();
+
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,875 @@
+//! QRNG backend registry
+//!
+//! Maps backend names to constructors. Backends are selected with a spec of
+//! the form `name` or `name:arg`, where `arg` is passed to the constructor
+//! (e.g. the inner backend of `pool:anu`).
+//!
+//! Each backend can be configured with a `[backends.<name>]` table in the
+//! config. A table whose name isn't a registered backend defines a new
+//! instance of the backend named by its `type`:
+//!
+//! ```toml
+//! [backends.lab]
+//! type = "anu"
+//! endpoint = "http://qrng.lab.local/API/jsonI.php"
+//! ```
+//!
+//! Specs sent by HTTP clients are built with `create_remote`, which refuses
+//! spec arguments naming files, directories or URLs (`record`, `replay`,
+//! `device`, `http`, `bank`);
+//! those backends are only reachable remotely through config tables, e.g.
+//! `type = "replay"` with a `path` option.
+
+use crate::config::{BackendConfig, Config};
+use crate::error::{Error, Result};
+use crate::qrng::{
+    anu, bank, device, extract, failover, http_json, mix, pool, pseudo, record, replay, AsyncQrngBackend, BackendInfo,
+    FloatPrecision, QrngBackend, SyncAdapter,
+};
+use crate::qrng::health::{self, Health};
+use crate::qrng::metered::Metered;
+use crate::qrng::precision::WithPrecision;
+use crate::qrng::retry::RetryPolicy;
+use crate::usage;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The backend or instance name of a spec (`pool` for `pool:anu`)
+pub fn spec_name(spec: &str) -> &str {
+    spec.split_once(':').map_or(spec, |(name, _)| name)
+}
+
+/// The name a spec's draws are budgeted under
+///
+/// The server keeps one unmetered pool per `pool:` spec, so a pooled spec is
+/// charged to the backend it wraps (`anu` for `pool:anu`).
+pub fn budget_name(spec: &str) -> &str {
+    let mut spec = spec;
+    while let Some(inner) = spec.strip_prefix("pool:") {
+        spec = inner;
+    }
+    spec_name(spec)
+}
+
+/// Deepest wrapper nesting `BackendRegistry::kinds` follows
+const MAX_NESTING: usize = 16;
+
+/// Extractor method and inner spec of an `extract` backend
+fn extract_parts(ctx: &BackendContext) -> Result<(String, String)> {
+    let usage = "extract:<von-neumann|sha256>:<backend>";
+    Ok(match ctx.arg.filter(|arg| !arg.is_empty()) {
+        Some(arg) => {
+            let (method, inner) = arg
+                .split_once(':')
+                .ok_or_else(|| Error::Config(format!("Backend spec must be {}", usage)))?;
+            (method.to_string(), inner.to_string())
+        }
+        None => (
+            ctx.settings
+                .option::<String>("method")?
+                .unwrap_or_else(|| "sha256".to_string()),
+            ctx.settings
+                .option::<String>("source")?
+                .ok_or_else(|| Error::Config(format!("Backend spec must be {}", usage)))?,
+        ),
+    })
+}
+
+/// Inner spec and capture path of a `record` backend
+fn record_parts(ctx: &BackendContext) -> Result<(String, String)> {
+    match ctx.path_arg()? {
+        Some(arg) => arg
+            .split_once(':')
+            .map(|(inner, path)| (inner.to_string(), path.to_string())),
+        None => ctx
+            .settings
+            .option::<String>("source")?
+            .zip(ctx.settings.option::<String>("path")?),
+    }
+    .ok_or_else(|| Error::Config("Backend spec must be record:<backend>:<path>".to_string()))
+}
+
+/// Everything a constructor needs to build a backend
+pub struct BackendContext<'a> {
+    /// Registry, for wrappers that build inner backends
+    pub registry: &'a BackendRegistry,
+    /// Full application config
+    pub config: &'a Config,
+    /// This backend's `[backends.<name>]` table (empty if not configured)
+    pub settings: &'a BackendConfig,
+    /// Text after the first `:` in the spec, if any
+    pub arg: Option<&'a str>,
+    /// Whether `arg` came from an HTTP client rather than the operator
+    pub remote: bool,
+}
+
+impl BackendContext<'_> {
+    /// The spec argument, or an error naming what was expected
+    pub fn require_arg(&self, usage: &str) -> Result<&str> {
+        self.arg
+            .filter(|arg| !arg.is_empty())
+            .ok_or_else(|| Error::Config(format!("Backend spec must be {}", usage)))
+    }
+
+    /// The spec argument, where it names a file, directory or URL
+    ///
+    /// Refused for remote specs: any caller could otherwise read, write or
+    /// fetch whatever the server can.
+    pub fn path_arg(&self) -> Result<Option<&str>> {
+        let arg = self.arg.filter(|arg| !arg.is_empty());
+        if self.remote && arg.is_some() {
+            return Err(Error::Config(
+                "Backend specs in requests can't name files, directories or URLs; \
+                 configure the backend under [backends] instead"
+                    .to_string(),
+            ));
+        }
+        Ok(arg)
+    }
+
+    /// Comma-separated backend specs from the spec argument, or from the
+    /// `option` setting when there's no argument
+    pub fn spec_list(&self, option: &str, usage: &str) -> Result<Vec<String>> {
+        let list = match self.arg.filter(|arg| !arg.is_empty()) {
+            Some(list) => list.to_string(),
+            None => self
+                .settings
+                .option::<String>(option)?
+                .ok_or_else(|| Error::Config(format!("Backend spec must be {}", usage)))?,
+        };
+        Ok(list
+            .split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Build another backend from a spec (for wrappers)
+    ///
+    /// Inner specs taken from a remote spec argument are remote too; those
+    /// from the backend's config table aren't.
+    pub fn create(&self, spec: &str) -> Result<Box<dyn QrngBackend>> {
+        let remote = self.remote && self.arg.is_some_and(|arg| !arg.is_empty());
+        self.registry.build(spec, self.config, remote)
+    }
+}
+
+/// Builds a sync backend
+pub type BackendConstructor = fn(&BackendContext) -> Result<Box<dyn QrngBackend>>;
+
+/// Builds a native async backend
+pub type AsyncBackendConstructor = fn(&BackendContext) -> Result<Box<dyn AsyncQrngBackend>>;
+
+/// A registered backend
+pub struct BackendRegistration {
+    /// Name used in specs and config
+    pub name: &'static str,
+    /// Human-readable description
+    pub description: &'static str,
+    /// Sync constructor
+    pub constructor: BackendConstructor,
+    /// Native async constructor; sync backends are wrapped in `SyncAdapter`
+    /// when this is `None`
+    pub async_constructor: Option<AsyncBackendConstructor>,
+}
+
+/// Registry of available QRNG backends
+pub struct BackendRegistry {
+    registrations: Vec<BackendRegistration>,
+}
+
+impl BackendRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Create a registry with all built-in backends
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+
+        registry.register(
+            "pseudo",
+            "Pseudo-random number generator (for testing)",
+            |_| Ok(Box::new(pseudo::PseudoBackend::new())),
+        );
+        registry.register_async(
+            "anu",
+            "Australian National University Quantum Random Number Generator",
+            |ctx| Ok(Box::new(anu::AnuBackend::from_context(ctx)?)),
+            |ctx| Ok(Box::new(anu::AnuBackend::from_context(ctx)?)),
+        );
+        registry.register_async(
+            "http",
+            "Configurable HTTP JSON entropy API (set endpoint, json_path, encoding)",
+            |ctx| Ok(Box::new(http_json::HttpJsonBackend::from_context(ctx)?)),
+            |ctx| Ok(Box::new(http_json::HttpJsonBackend::from_context(ctx)?)),
+        );
+        registry.register(
+            "device",
+            "Raw bytes from a local device, FIFO or file (device:<path>)",
+            |ctx| {
+                let path = match ctx.path_arg()? {
+                    Some(path) => path.to_string(),
+                    None => ctx
+                        .settings
+                        .option::<String>("path")?
+                        .ok_or_else(|| Error::Config("Backend spec must be device:<path>".to_string()))?,
+                };
+                let mut backend = device::DeviceBackend::new(path);
+                if let Some(secs) = ctx.settings.option::<f64>("timeout_secs")? {
+                    let timeout = Duration::try_from_secs_f64(secs).map_err(|_| {
+                        Error::Config(format!("Invalid device timeout: {}", secs))
+                    })?;
+                    backend = backend.with_timeout(timeout);
+                }
+                Ok(Box::new(backend))
+            },
+        );
+        registry.register(
+            "bank",
+            "Entropy fetched earlier into the offline bank (bank or bank:<dir>)",
+            |ctx| {
+                let bank = match ctx.path_arg()? {
+                    Some(dir) => bank::Bank::new(dir),
+                    None => bank::Bank::from_settings(Some(ctx.settings))?,
+                };
+                Ok(Box::new(bank::BankBackend::new(bank)))
+            },
+        );
+        registry.register(
+            "pool",
+            "Prefetching pool in front of another backend (pool:<backend>)",
+            |ctx| {
+                let inner = ctx.create(ctx.require_arg("pool:<backend>")?)?;
+                let reserve = pool::reserve_bytes(Some(ctx.settings), ctx.config)?;
+                Ok(Box::new(pool::PoolBackend::new(inner, reserve)))
+            },
+        );
+        registry.register(
+            "mix",
+            "XOR of several backends (mix:<backend>,<backend>,...)",
+            |ctx| {
+                let children = ctx
+                    .spec_list("sources", "mix:<backend>,<backend>,...")?
+                    .iter()
+                    .map(|spec| ctx.create(spec))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Box::new(mix::MixBackend::new(children)?))
+            },
+        );
+        registry.register(
+            "extract",
+            "Whitens another backend (extract:<von-neumann|sha256>:<backend>)",
+            |ctx| {
+                let (method, inner) = extract_parts(ctx)?;
+                let mut extractor: extract::Extractor = method.parse()?;
+                if let Some(block) = ctx.settings.option::<usize>("block_bytes")? {
+                    if !matches!(extractor, extract::Extractor::Sha256 { .. }) {
+                        return Err(Error::Config(
+                            "block_bytes only applies to the sha256 extractor".to_string(),
+                        ));
+                    }
+                    extractor = extract::Extractor::sha256(block)?;
+                }
+                Ok(Box::new(extract::ExtractorBackend::new(ctx.create(&inner)?, extractor)))
+            },
+        );
+        registry.register(
+            "failover",
+            "Ordered fallback chain (failover:<backend>,<backend>,...)",
+            |ctx| {
+                // Network members retry under their own policy; the chain
+                // only adds retries when its table asks for them
+                let policy = RetryPolicy::from_settings(ctx.settings, RetryPolicy::none())?;
+                let mut chain = failover::FailoverBackend::new();
+                for spec in ctx.spec_list("chain", "failover:<backend>,<backend>,...")? {
+                    chain = chain.with_backend(ctx.create(&spec)?, policy);
+                }
+                if chain.is_empty() {
+                    return Err(Error::Config("Failover chain is empty".to_string()));
+                }
+                Ok(Box::new(chain))
+            },
+        );
+        registry.register(
+            "record",
+            "Records bytes from another backend (record:<backend>:<path>)",
+            |ctx| {
+                let (inner, path) = record_parts(ctx)?;
+                let append = ctx.settings.option::<bool>("append")?.unwrap_or(false);
+                Ok(Box::new(
+                    record::RecordingBackend::new(ctx.create(&inner)?, path).with_append(append),
+                ))
+            },
+        );
+        registry.register(
+            "replay",
+            "Replays bytes from a capture file (replay:<path>)",
+            |ctx| {
+                let path = match ctx.path_arg()? {
+                    Some(path) => path.to_string(),
+                    None => ctx
+                        .settings
+                        .option::<String>("path")?
+                        .ok_or_else(|| Error::Config("Backend spec must be replay:<path>".to_string()))?,
+                };
+                Ok(Box::new(replay::ReplayBackend::new(path)))
+            },
+        );
+
+        registry
+    }
+
+    /// Register a sync backend
+    ///
+    /// Replaces any existing registration with the same name.
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        constructor: BackendConstructor,
+    ) {
+        self.insert(BackendRegistration {
+            name,
+            description,
+            constructor,
+            async_constructor: None,
+        });
+    }
+
+    /// Register a backend with both sync and native async constructors
+    pub fn register_async(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        constructor: BackendConstructor,
+        async_constructor: AsyncBackendConstructor,
+    ) {
+        self.insert(BackendRegistration {
+            name,
+            description,
+            constructor,
+            async_constructor: Some(async_constructor),
+        });
+    }
+
+    fn insert(&mut self, registration: BackendRegistration) {
+        self.registrations.retain(|r| r.name != registration.name);
+        self.registrations.push(registration);
+    }
+
+    /// Look up a registration by name
+    pub fn get(&self, name: &str) -> Option<&BackendRegistration> {
+        self.registrations.iter().find(|r| r.name == name)
+    }
+
+    /// Resolve a spec to its registration, settings and argument
+    fn resolve<'a>(
+        &'a self,
+        spec: &'a str,
+        config: &'a Config,
+    ) -> Result<(&'a BackendRegistration, &'a BackendConfig, Option<&'a str>)> {
+        static EMPTY: std::sync::OnceLock<BackendConfig> = std::sync::OnceLock::new();
+
+        let name = spec_name(spec);
+        let arg = spec.split_once(':').map(|(_, arg)| arg);
+
+        let settings = config.backends.get(name);
+        let kind = settings.and_then(|s| s.kind.as_deref()).unwrap_or(name);
+
+        let registration = self
+            .get(kind)
+            .ok_or_else(|| Error::Config(format!("Unknown QRNG backend: {}", name)))?;
+
+        Ok((
+            registration,
+            settings.unwrap_or_else(|| EMPTY.get_or_init(BackendConfig::default)),
+            arg,
+        ))
+    }
+
+    /// Create a sync backend from a spec
+    pub fn create(&self, spec: &str, config: &Config) -> Result<Box<dyn QrngBackend>> {
+        self.build(spec, config, false)
+    }
+
+    /// Create a sync backend from a spec sent by an HTTP client
+    ///
+    /// See `BackendContext::path_arg` for what such specs can't do.
+    pub fn create_remote(&self, spec: &str, config: &Config) -> Result<Box<dyn QrngBackend>> {
+        self.build(spec, config, true)
+    }
+
+    fn build(&self, spec: &str, config: &Config, remote: bool) -> Result<Box<dyn QrngBackend>> {
+        let (registration, settings, arg) = self.resolve(spec, config)?;
+        let backend = (registration.constructor)(&BackendContext {
+            registry: self,
+            config,
+            settings,
+            arg,
+            remote,
+        })?;
+        let name = spec_name(spec);
+        let backend: Box<dyn QrngBackend> =
+            Box::new(Health::new(backend, name, health::min_entropy(config, name)?));
+        let backend: Box<dyn QrngBackend> = match settings.option::<FloatPrecision>("float_bits")? {
+            Some(precision) => Box::new(WithPrecision::new(backend, precision)),
+            None => backend,
+        };
+        let budget = usage::daily_budget(config, name)?;
+        Ok(Box::new(Metered::new(backend, name, budget, Arc::clone(usage::meter()))))
+    }
+
+    /// Create an async backend from a spec
+    ///
+    /// Uses the native async constructor when registered, otherwise runs the
+    /// sync backend through `SyncAdapter`.
+    pub fn create_async(&self, spec: &str, config: &Config) -> Result<Box<dyn AsyncQrngBackend>> {
+        self.build_async(spec, config, false)
+    }
+
+    /// Create an async backend from a spec sent by an HTTP client
+    pub fn create_async_remote(&self, spec: &str, config: &Config) -> Result<Box<dyn AsyncQrngBackend>> {
+        self.build_async(spec, config, true)
+    }
+
+    fn build_async(&self, spec: &str, config: &Config, remote: bool) -> Result<Box<dyn AsyncQrngBackend>> {
+        let (registration, settings, arg) = self.resolve(spec, config)?;
+        let Some(constructor) = registration.async_constructor else {
+            return Ok(Box::new(SyncAdapter::from_box(self.build(spec, config, remote)?)));
+        };
+        let backend = constructor(&BackendContext {
+            registry: self,
+            config,
+            settings,
+            arg,
+            remote,
+        })?;
+        let name = spec_name(spec);
+        let backend: Box<dyn AsyncQrngBackend> =
+            Box::new(Health::new(backend, name, health::min_entropy(config, name)?));
+        let backend: Box<dyn AsyncQrngBackend> = match settings.option::<FloatPrecision>("float_bits")? {
+            Some(precision) => Box::new(WithPrecision::new(backend, precision)),
+            None => backend,
+        };
+        let budget = usage::daily_budget(config, name)?;
+        Ok(Box::new(Metered::new(backend, name, budget, Arc::clone(usage::meter()))))
+    }
+
+    /// Registered types a spec builds, its own first, then those of every
+    /// backend it wraps, depth first
+    ///
+    /// Reads the spec and config only, so nothing is opened, fetched or
+    /// started. Lets callers spot e.g. a bank behind `pool:` or `mix:`.
+    pub fn kinds(&self, spec: &str, config: &Config) -> Result<Vec<&'static str>> {
+        let mut kinds = Vec::new();
+        self.walk(spec, config, 0, &mut kinds)?;
+        Ok(kinds)
+    }
+
+    fn walk(
+        &self,
+        spec: &str,
+        config: &Config,
+        depth: usize,
+        kinds: &mut Vec<&'static str>,
+    ) -> Result<()> {
+        // Deeper than any sensible nesting: a table that wraps itself
+        if depth > MAX_NESTING {
+            return Err(Error::Config(format!("Backend spec nests too deeply: {}", spec)));
+        }
+        let (registration, settings, arg) = self.resolve(spec, config)?;
+        kinds.push(registration.name);
+        let ctx = BackendContext {
+            registry: self,
+            config,
+            settings,
+            arg,
+            remote: false,
+        };
+        let inner = match registration.name {
+            "pool" => vec![ctx.require_arg("pool:<backend>")?.to_string()],
+            "mix" => ctx.spec_list("sources", "mix:<backend>,<backend>,...")?,
+            "failover" => ctx.spec_list("chain", "failover:<backend>,<backend>,...")?,
+            "extract" => vec![extract_parts(&ctx)?.1],
+            "record" => vec![record_parts(&ctx)?.0],
+            _ => Vec::new(),
+        };
+        for spec in inner {
+            self.walk(&spec, config, depth + 1, kinds)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a spec resolves to a registered backend
+    pub fn contains(&self, spec: &str, config: &Config) -> bool {
+        self.resolve(spec, config).is_ok()
+    }
+
+    /// List registered backends plus instances defined in config
+    pub fn list(&self, config: &Config) -> Vec<BackendInfo> {
+        let mut backends: Vec<BackendInfo> = self
+            .registrations
+            .iter()
+            .map(|r| BackendInfo {
+                name: r.name.to_string(),
+                description: config
+                    .backends
+                    .get(r.name)
+                    .and_then(|s| s.description.clone())
+                    .unwrap_or_else(|| r.description.to_string()),
+            })
+            .collect();
+
+        let mut instances: Vec<(&String, &BackendConfig)> = config
+            .backends
+            .iter()
+            .filter(|(name, _)| self.get(name).is_none())
+            .collect();
+        instances.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, settings) in instances {
+            let Some(registration) = settings.kind.as_deref().and_then(|k| self.get(k)) else {
+                continue;
+            };
+            backends.push(BackendInfo {
+                name: name.clone(),
+                description: settings
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| format!("{} ({})", registration.description, registration.name)),
+            });
+        }
+
+        backends
+    }
+}
+
+impl Default for BackendRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_backends() {
+        let registry = BackendRegistry::builtin();
+        let config = Config::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = dir.path().join("capture.bin");
+        let capture = capture.to_str().unwrap();
+
+        assert_eq!(registry.create("pseudo", &config).unwrap().name(), "pseudo");
+        assert_eq!(registry.create("anu", &config).unwrap().name(), "anu");
+        assert_eq!(registry.create("pool:pseudo", &config).unwrap().name(), "pool");
+        assert_eq!(
+            registry
+                .create(&format!("record:pseudo:{}", capture), &config)
+                .unwrap()
+                .name(),
+            "record"
+        );
+        assert_eq!(
+            registry.create(&format!("replay:{}", capture), &config).unwrap().name(),
+            "replay"
+        );
+        assert_eq!(registry.create("device:/dev/urandom", &config).unwrap().name(), "device");
+        assert_eq!(registry.create("mix:pseudo,anu", &config).unwrap().name(), "mix");
+    }
+
+    #[test]
+    fn test_mix_sources_from_config() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        config.set("backends.trusted.type", "mix").unwrap();
+        config.set("backends.trusted.sources", "anu, pool:pseudo").unwrap();
+
+        let backend = registry.create("trusted", &config).unwrap();
+        assert_eq!(backend.sources(), vec!["anu", "pseudo"]);
+
+        assert!(registry.create("mix:pseudo", &config).is_err());
+        assert!(registry.create("mix:pseudo,nonexistent", &config).is_err());
+    }
+
+    #[test]
+    fn test_extract_from_spec_and_config() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+
+        let backend = registry.create("extract:von-neumann:pool:pseudo", &config).unwrap();
+        assert_eq!(backend.name(), "extract");
+        assert_eq!(backend.bytes(16).unwrap().len(), 16);
+        assert_eq!(backend.extraction().unwrap().extractor, "von-neumann");
+        assert!(registry.create("extract:pseudo", &config).is_err());
+
+        config.set("backends.whitened.type", "extract").unwrap();
+        config.set("backends.whitened.source", "pseudo").unwrap();
+        config.set("backends.whitened.block_bytes", "96").unwrap();
+        let backend = registry.create("whitened", &config).unwrap();
+        backend.bytes(32).unwrap();
+        assert_eq!(backend.extraction().unwrap().input_bytes, 96);
+
+        config.set("backends.whitened.method", "von-neumann").unwrap();
+        assert!(registry.create("whitened", &config).is_err());
+    }
+
+    #[test]
+    fn test_device_from_config() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        assert!(registry.create("device", &config).is_err());
+
+        config.set("backends.dongle.type", "device").unwrap();
+        config.set("backends.dongle.path", "/dev/urandom").unwrap();
+        config.set("backends.dongle.timeout_secs", "0.5").unwrap();
+
+        let backend = registry.create("dongle", &config).unwrap();
+        assert_eq!(backend.name(), "device");
+        // Clients can use the configured device, but not point it elsewhere
+        assert!(registry.create_remote("dongle", &config).is_ok());
+        assert!(registry.create_remote("dongle:/dev/zero", &config).is_err());
+
+        config.set("backends.dongle.timeout_secs", "soon").unwrap();
+        assert!(registry.create("dongle", &config).is_err());
+    }
+
+    #[test]
+    fn test_unknown_backend_is_error() {
+        let registry = BackendRegistry::builtin();
+        let config = Config::default();
+
+        let err = registry.create("nonexistent", &config).err().unwrap();
+        assert!(err.to_string().contains("Unknown QRNG backend: nonexistent"));
+        assert!(registry.create_async("nonexistent", &config).is_err());
+        assert!(registry.create("pool:nonexistent", &config).is_err());
+    }
+
+    #[test]
+    fn test_missing_spec_argument_is_error() {
+        let registry = BackendRegistry::builtin();
+        let config = Config::default();
+
+        assert!(registry.create("pool", &config).is_err());
+        assert!(registry.create("replay", &config).is_err());
+        assert!(registry.create("record:pseudo", &config).is_err());
+    }
+
+    #[test]
+    fn test_remote_specs_cant_name_paths() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = dir.path().join("capture.bin");
+        let capture = capture.to_str().unwrap();
+
+        for spec in [
+            format!("record:pseudo:{}", capture),
+            format!("replay:{}", capture),
+            format!("mix:pseudo,replay:{}", capture),
+        ] {
+            let err = registry.create_remote(&spec, &config).err().unwrap();
+            assert!(err.to_string().contains("can't name files"), "{}: {}", spec, err);
+        }
+        assert!(registry.create_async_remote(&format!("replay:{}", capture), &config).is_err());
+        assert!(registry.create_remote("pool:pseudo", &config).is_ok());
+        assert!(registry.create_remote("device:/etc/shadow", &config).is_err());
+        assert!(registry.create_remote("failover:device:/dev/zero,pseudo", &config).is_err());
+
+        // Paths from config tables are fine, but can't be overridden
+        config.set("backends.capture.type", "replay").unwrap();
+        config.set("backends.capture.path", capture).unwrap();
+        config.set("backends.resilient.type", "failover").unwrap();
+        config
+            .set("backends.resilient.chain", &format!("replay:{}, pseudo", capture))
+            .unwrap();
+        assert_eq!(registry.create_remote("capture", &config).unwrap().name(), "replay");
+        assert_eq!(registry.create_remote("resilient", &config).unwrap().name(), "failover");
+        assert!(registry.create_remote("capture:/etc/passwd", &config).is_err());
+    }
+
+    #[test]
+    fn test_record_from_config() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = dir.path().join("capture.bin");
+        config.set("backends.taped.type", "record").unwrap();
+        config.set("backends.taped.source", "pseudo").unwrap();
+        assert!(registry.create("taped", &config).is_err());
+
+        config.set("backends.taped.path", &capture.display().to_string()).unwrap();
+        let bytes = registry.create("taped", &config).unwrap().bytes(32).unwrap();
+        assert_eq!(std::fs::read(&capture).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_configured_instance() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        config.set("backends.lab.type", "anu").unwrap();
+        config.set("backends.lab.description", "Lab QRNG").unwrap();
+
+        assert_eq!(registry.create("lab", &config).unwrap().name(), "anu");
+
+        let listed = registry.list(&config);
+        let lab = listed.iter().find(|b| b.name == "lab").unwrap();
+        assert_eq!(lab.description, "Lab QRNG");
+    }
+
+    #[test]
+    fn test_instance_with_unknown_type_is_error() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        config.set("backends.lab.type", "nope").unwrap();
+
+        assert!(registry.create("lab", &config).is_err());
+        assert!(registry.list(&config).iter().all(|b| b.name != "lab"));
+    }
+
+    #[test]
+    fn test_custom_registration() {
+        let mut registry = BackendRegistry::new();
+        registry.register("seeded", "Seeded pseudo-random", |ctx| {
+            let seed = ctx.require_arg("seeded:<seed>")?.parse().map_err(|_| {
+                Error::Config("Seed must be a number".to_string())
+            })?;
+            Ok(Box::new(pseudo::SeededPseudoBackend::new(seed)))
+        });
+        let config = Config::default();
+
+        let a = registry.create("seeded:42", &config).unwrap().bytes(8).unwrap();
+        let b = registry.create("seeded:42", &config).unwrap().bytes(8).unwrap();
+        assert_eq!(a, b);
+
+        let listed = registry.list(&config);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "seeded");
+    }
+
+    #[test]
+    fn test_kinds_walk_wrapped_specs() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = dir.path().join("capture.bin");
+        config.set("backends.field.type", "bank").unwrap();
+        config.set("backends.resilient.type", "failover").unwrap();
+        config.set("backends.resilient.chain", "anu, pool:field").unwrap();
+
+        assert_eq!(registry.kinds("pseudo", &config).unwrap(), vec!["pseudo"]);
+        assert_eq!(
+            registry.kinds("mix:anu,extract:sha256:bank", &config).unwrap(),
+            vec!["mix", "anu", "extract", "bank"]
+        );
+        assert_eq!(
+            registry.kinds("resilient", &config).unwrap(),
+            vec!["failover", "anu", "pool", "bank"]
+        );
+        assert_eq!(
+            registry
+                .kinds(&format!("record:pseudo:{}", capture.display()), &config)
+                .unwrap(),
+            vec!["record", "pseudo"]
+        );
+        assert!(registry.kinds("mix:anu,nonexistent", &config).is_err());
+
+        // A table wrapping itself is an error, not a stack overflow
+        config.set("backends.cycle.type", "mix").unwrap();
+        config.set("backends.cycle.sources", "cycle").unwrap();
+        assert!(registry.kinds("cycle", &config).is_err());
+    }
+
+    #[test]
+    fn test_failover_from_config() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        config.set("backends.resilient.type", "failover").unwrap();
+        config.set("backends.resilient.chain", "replay:/nonexistent.bin, pseudo").unwrap();
+        config.set("backends.resilient.retries", "0").unwrap();
+
+        let backend = registry.create("resilient", &config).unwrap();
+        assert_eq!(backend.name(), "failover");
+        assert_eq!(backend.bytes(16).unwrap().len(), 16);
+        assert_eq!(backend.sources(), vec!["pseudo"]);
+
+        assert!(registry.create("failover:pseudo,nonexistent", &config).is_err());
+        config.set("backends.resilient.backoff_ms", "soon").unwrap();
+        assert!(registry.create("resilient", &config).is_err());
+    }
+
+    #[test]
+    fn test_bank_from_spec_and_config() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        let dir = tempfile::TempDir::new().unwrap();
+        let entropy = pseudo::SeededPseudoBackend::new(4).bytes(256).unwrap();
+        bank::Bank::new(dir.path())
+            .deposit("anu", vec!["anu".to_string()], &entropy)
+            .unwrap();
+
+        let backend = registry
+            .create(&format!("bank:{}", dir.path().display()), &config)
+            .unwrap();
+        assert_eq!(backend.name(), "bank");
+        assert_eq!(backend.bytes(128).unwrap(), entropy[128..]);
+        assert_eq!(backend.sources(), vec!["anu"]);
+
+        config.set("backends.field.type", "bank").unwrap();
+        config
+            .set("backends.field.dir", &dir.path().display().to_string())
+            .unwrap();
+        let backend = registry.create("field", &config).unwrap();
+        assert_eq!(backend.bytes(128).unwrap(), entropy[..128]);
+        assert!(backend.bytes(1).is_err());
+
+        // Clients can spend from the configured bank, but not name a directory
+        let elsewhere = tempfile::TempDir::new().unwrap();
+        assert!(registry.create_remote("field", &config).is_ok());
+        let spec = format!("bank:{}", elsewhere.path().display());
+        assert!(registry.create_remote(&spec, &config).is_err());
+        assert!(registry.create_remote(&format!("pool:{}", spec), &config).is_err());
+        assert_eq!(std::fs::read_dir(elsewhere.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_backends_are_health_tested() {
+        let registry = BackendRegistry::builtin();
+        let mut config = Config::default();
+        let capture = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(capture.path(), [0u8; 1024]).unwrap();
+        let replay = format!("replay:{}", capture.path().display());
+
+        let err = registry.create(&replay, &config).unwrap().bytes(64).unwrap_err();
+        assert!(matches!(err, Error::EntropyHealth(_)), "{}", err);
+
+        // A failover chain moves past the stuck member
+        let chain = format!("failover:{},pseudo", replay);
+        let backend = registry.create(&chain, &config).unwrap();
+        assert_eq!(backend.bytes(64).unwrap().len(), 64);
+        assert_eq!(backend.sources(), vec!["pseudo"]);
+
+        // Turned off for a backend that's known to be low-entropy
+        config.set("backends.replay.health_min_entropy", "0").unwrap();
+        assert_eq!(registry.create(&replay, &config).unwrap().bytes(64).unwrap(), vec![0; 64]);
+    }
+
+    #[tokio::test]
+    async fn test_create_async() {
+        let registry = BackendRegistry::builtin();
+        let config = Config::default();
+
+        let backend = registry.create_async("pseudo", &config).unwrap();
+        assert_eq!(backend.name(), "pseudo");
+        assert_eq!(backend.bytes_async(32).await.unwrap().len(), 32);
+
+        assert_eq!(registry.create_async("anu", &config).unwrap().name(), "anu");
+    }
+}
@@ -0,0 +1,160 @@
+//! Replay backend
+//!
+//! Serves bytes back, in order, from a capture file written by
+//! `RecordingBackend`. Running a generation against a replay of the capture
+//! reproduces the original result exactly.
+
+use crate::error::{Error, Result};
+use crate::qrng::QrngBackend;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Backend that replays bytes from a capture file
+pub struct ReplayBackend {
+    path: PathBuf,
+    // Opened lazily on first draw so construction never fails
+    reader: Mutex<Option<BufReader<File>>>,
+}
+
+impl ReplayBackend {
+    /// Create a replay backend reading from the capture file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            reader: Mutex::new(None),
+        }
+    }
+
+    /// Path of the capture file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl QrngBackend for ReplayBackend {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn description(&self) -> &'static str {
+        "Replays bytes from a capture file"
+    }
+
+    fn bytes(&self, n: usize) -> Result<Vec<u8>> {
+        let mut reader = self.reader.lock().unwrap();
+
+        if reader.is_none() {
+            let file = File::open(&self.path).map_err(|e| {
+                Error::Qrng(format!(
+                    "Failed to open capture file {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })?;
+            *reader = Some(BufReader::new(file));
+        }
+
+        let reader = reader.as_mut().expect("capture file opened above");
+        let mut bytes = vec![0u8; n];
+        reader.read_exact(&mut bytes).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                Error::Qrng(format!(
+                    "Capture file {} exhausted: fewer than {} bytes remaining",
+                    self.path.display(),
+                    n
+                ))
+            } else {
+                Error::Qrng(format!(
+                    "Failed to read capture file {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            }
+        })?;
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::flower::{generate, GenerationOptions};
+    use crate::coord::{Coordinates, GenerationMode};
+    use crate::qrng::pseudo::SeededPseudoBackend;
+    use crate::qrng::record::RecordingBackend;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_replay_returns_captured_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        std::fs::write(&path, [1u8, 2, 3, 4, 5, 6]).unwrap();
+
+        let backend = ReplayBackend::new(&path);
+        assert_eq!(backend.bytes(4).unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(backend.bytes(2).unwrap(), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_replay_exhausted() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        std::fs::write(&path, [1u8, 2, 3]).unwrap();
+
+        let backend = ReplayBackend::new(&path);
+        let result = backend.bytes(4);
+        assert!(matches!(result, Err(Error::Qrng(msg)) if msg.contains("exhausted")));
+    }
+
+    #[test]
+    fn test_replay_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = ReplayBackend::new(temp_dir.path().join("missing.bin"));
+        assert!(backend.bytes(1).is_err());
+    }
+
+    #[test]
+    fn test_record_then_replay_reproduces_generation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.bin");
+        let center = Coordinates::new(40.7128, -74.0060);
+
+        let recorder = RecordingBackend::new(Box::new(SeededPseudoBackend::new(7)), &path);
+        let original = generate(
+            center,
+            3000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("record")
+                .with_points(500)
+                .with_grid_resolution(20),
+            &recorder,
+        )
+        .unwrap();
+
+        let replay = ReplayBackend::new(&path);
+        let replayed = generate(
+            center,
+            3000.0,
+            GenerationMode::FlowerPower,
+            &GenerationOptions::new("replay")
+                .with_points(500)
+                .with_grid_resolution(20),
+            &replay,
+        )
+        .unwrap();
+
+        assert_eq!(original.winners.len(), replayed.winners.len());
+        for (anomaly_type, winner) in &original.winners {
+            let other = &replayed.winners[anomaly_type];
+            assert_eq!(winner.circle_id, other.circle_id);
+            assert_eq!(winner.result.coords, other.result.coords);
+            assert_eq!(winner.result.z_score, other.result.z_score);
+        }
+
+        // The whole capture should have been consumed
+        assert!(replay.bytes(1).is_err());
+    }
+}
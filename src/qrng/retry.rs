@@ -0,0 +1,181 @@
+//! Retry with exponential backoff
+//!
+//! Shared by network backends (ANU) and the failover chain. Only transient
+//! failures are retried: `Error::QrngNetwork` and `Error::QrngQuota`. A quota
+//! error carrying a `Retry-After` waits at least that long, unless it exceeds
+//! `max_wait`, in which case the error is returned straight away.
+
+use crate::config::BackendConfig;
+use crate::error::{Error, Result};
+use std::future::Future;
+use std::thread;
+use std::time::Duration;
+
+/// Default retries after the first attempt
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// Default delay before the first retry; doubles on each further retry
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest single wait a retry will accept (including `Retry-After`)
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(120);
+
+/// How hard to try an operation before giving up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub retries: u32,
+    /// Delay before the first retry
+    pub backoff: Duration,
+    /// Give up instead of waiting longer than this
+    pub max_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            max_wait: DEFAULT_MAX_WAIT,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Read `retries` and `backoff_ms` options, falling back to `defaults`
+    pub fn from_settings(settings: &BackendConfig, defaults: RetryPolicy) -> Result<Self> {
+        Ok(Self {
+            retries: settings.option("retries")?.unwrap_or(defaults.retries),
+            backoff: settings
+                .option("backoff_ms")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.backoff),
+            max_wait: defaults.max_wait,
+        })
+    }
+
+    /// Delay before retry number `attempt` (0-based)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt))
+    }
+
+    /// How long to wait before retrying after `err`, or `None` to give up
+    fn wait_after(&self, attempt: u32, err: &Error) -> Option<Duration> {
+        if attempt >= self.retries {
+            return None;
+        }
+        let wait = match err {
+            Error::QrngNetwork(_) => self.delay(attempt),
+            Error::QrngQuota { retry_after, .. } => {
+                self.delay(attempt).max(retry_after.unwrap_or_default())
+            }
+            _ => return None,
+        };
+        (wait <= self.max_wait).then_some(wait)
+    }
+
+    /// Run `op` until it succeeds, fails permanently or runs out of retries
+    pub fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Ok(value) => return Ok(value),
+                Err(e) => match self.wait_after(attempt, &e) {
+                    Some(wait) => thread::sleep(wait),
+                    None => return Err(e),
+                },
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Async version of `run`
+    pub async fn run_async<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) => match self.wait_after(attempt, &e) {
+                    Some(wait) => tokio::time::sleep(wait).await,
+                    None => return Err(e),
+                },
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn fast(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::ZERO,
+            max_wait: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy {
+            backoff: Duration::from_millis(100),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_retries_transient_errors() {
+        let calls = Cell::new(0);
+        let result = fast(2).run(|| {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err(Error::QrngNetwork("connection reset".to_string()))
+            } else {
+                Ok(calls.get())
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_does_not_retry_permanent_errors() {
+        let calls = Cell::new(0);
+        let result: Result<()> = fast(5).run(|| {
+            calls.set(calls.get() + 1);
+            Err(Error::Qrng("bad response".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_gives_up_when_retry_after_too_long() {
+        let calls = Cell::new(0);
+        let result: Result<()> = fast(5).run(|| {
+            calls.set(calls.get() + 1);
+            Err(Error::QrngQuota {
+                message: "slow down".to_string(),
+                retry_after: Some(Duration::from_secs(3600)),
+            })
+        });
+        assert!(matches!(result, Err(Error::QrngQuota { .. })));
+        assert_eq!(calls.get(), 1);
+    }
+}
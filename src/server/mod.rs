@@ -2,10 +2,12 @@
 //!
 //! Provides REST API endpoints for coordinate generation.
 
+pub mod monitor;
 pub mod routes;
 pub mod state;
 
 use crate::config::Config;
+use crate::entropy::EntropySeries;
 use crate::error::Result;
 use routes::create_router;
 use state::AppState;
@@ -26,7 +28,7 @@ pub async fn run(config: Config) -> Result<()> {
         crate::error::Error::Server(format!("Invalid server address: {}", e))
     })?;
 
-    let state = Arc::new(AppState::new(config));
+    let state = start(config).await?;
     let app = create_router(state);
 
     info!("Starting server on {}", addr);
@@ -50,7 +52,7 @@ pub async fn run_on(addr: &str, config: Config) -> Result<()> {
         crate::error::Error::Server(format!("Invalid server address: {}", e))
     })?;
 
-    let state = Arc::new(AppState::new(config));
+    let state = start(config).await?;
     let app = create_router(state);
 
     info!("Starting server on {}", addr);
@@ -65,3 +67,22 @@ pub async fn run_on(addr: &str, config: Config) -> Result<()> {
 
     Ok(())
 }
+
+/// Build the server state with the persisted entropy series and start
+/// scheduled sampling
+///
+/// An unreadable series file is logged and replaced by an empty series
+/// rather than keeping the server from starting.
+async fn start(config: Config) -> Result<Arc<AppState>> {
+    let path = EntropySeries::series_path()?;
+    let max_samples = config.monitor.max_samples;
+    let series = EntropySeries::load(&path, max_samples).unwrap_or_else(|e| {
+        tracing::warn!("Starting with an empty entropy series: {}", e);
+        EntropySeries::new(max_samples).with_path(path)
+    });
+    let state = Arc::new(AppState::new(config).with_entropy_series(series));
+    // Read the exclusion zones now, so a bad file stops startup
+    state.configured_exclusions().await?;
+    monitor::spawn(Arc::clone(&state)).await;
+    Ok(state)
+}
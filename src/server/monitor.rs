@@ -0,0 +1,213 @@
+//! Scheduled entropy sampling
+//!
+//! Every `monitor.interval_secs` the server draws `monitor.sample_bytes`
+//! from each backend listed in `monitor.backends`, runs the entropy tests on
+//! them and records the results in `AppState::entropy`. `/api/status` reports
+//! the latest sample and `/api/entropy` the whole series, so neither draws
+//! entropy of its own.
+//!
+//! Nothing is sampled unless backends are listed, since every sample spends
+//! entropy (and quota). Specs that build a `record`, `pool` or `bank` backend
+//! anywhere inside are never sampled: that would write to a capture, drain a
+//! shared reserve or spend the offline bank.
+
+use crate::entropy::EntropySample;
+use crate::error::Error;
+use crate::qrng::registry;
+use crate::server::state::AppState;
+use crate::usage;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Backend types sampling would disturb
+pub const UNSAMPLED_KINDS: [&str; 3] = ["record", "pool", "bank"];
+
+/// Start sampling in the background
+///
+/// Returns `None` when sampling is turned off (`monitor.interval_secs = 0`
+/// or no `monitor.backends`). The first sample is taken one interval after
+/// start, not straight away.
+pub async fn spawn(state: Arc<AppState>) -> Option<JoinHandle<()>> {
+    let (interval_secs, listed) = {
+        let config = state.config.read().await;
+        (config.monitor.interval_secs, !config.monitor.backends.is_empty())
+    };
+    if interval_secs == 0 || !listed {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            sample_all(&state).await;
+        }
+    }))
+}
+
+/// Why `spec` mustn't be sampled, if it mustn't
+fn unsampled_reason(spec: &str, config: &crate::config::Config) -> Option<String> {
+    let kinds = registry().kinds(spec, config).ok()?;
+    let kind = kinds.iter().find(|kind| UNSAMPLED_KINDS.contains(kind))?;
+    Some(format!(
+        "{} builds a {} backend, which the monitor never samples",
+        spec, kind
+    ))
+}
+
+/// Take one sample from every monitored backend
+pub async fn sample_all(state: &AppState) {
+    let (names, sample_bytes, refused) = {
+        let config = state.config.read().await;
+        let mut names = Vec::new();
+        let mut refused = Vec::new();
+        for name in &config.monitor.backends {
+            match unsampled_reason(name, &config) {
+                Some(reason) => refused.push((name.clone(), reason)),
+                None => names.push(name.clone()),
+            }
+        }
+        (names, config.monitor.sample_bytes, refused)
+    };
+
+    for (name, reason) in refused {
+        tracing::warn!("Not sampling entropy: {}", reason);
+        state
+            .entropy
+            .lock()
+            .await
+            .record(&name, EntropySample::failed(&Error::Config(reason)));
+    }
+
+    for name in names {
+        let sample = match state.backend(&name).await {
+            Ok(backend) => EntropySample::take(backend.as_ref(), sample_bytes).await,
+            Err(e) => EntropySample::failed(&e),
+        };
+        if let Some(error) = &sample.error {
+            tracing::warn!("Entropy sample from {} failed: {}", name, error);
+        }
+        state.entropy.lock().await.record(&name, sample);
+    }
+
+    // Write outside the lock so requests reading the series aren't held up
+    let snapshot = state.entropy.lock().await.snapshot();
+    let saved = match snapshot {
+        Ok(Some(snapshot)) => tokio::task::spawn_blocking(move || snapshot.save())
+            .await
+            .unwrap_or_else(|e| Err(Error::Server(format!("Save task failed: {}", e)))),
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        tracing::warn!("Failed to save entropy series: {}", e);
+    }
+
+    if let Err(e) = usage::meter().flush() {
+        tracing::warn!("Failed to save entropy usage: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_samples_listed_backends_only() {
+        let mut config = Config::default();
+        config.set("backends.lab.type", "pseudo").unwrap();
+        config.set("backends.spare.type", "pseudo").unwrap();
+        config.set("monitor.backends", "lab,pseudo").unwrap();
+        config.set("monitor.sample_bytes", "2000").unwrap();
+        let state = AppState::new(config);
+
+        sample_all(&state).await;
+        sample_all(&state).await;
+
+        let series = state.entropy.lock().await;
+        assert_eq!(series.backends(), vec!["lab", "pseudo"]);
+        assert_eq!(series.samples("pseudo").len(), 2);
+        let results = series.latest("lab").unwrap().results.as_ref().unwrap();
+        assert_eq!(results.bytes_analyzed, 2000);
+        assert_eq!(series.trend("pseudo").errors, 0);
+    }
+
+    #[tokio::test]
+    async fn test_never_samples_record_pool_or_bank() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = dir.path().join("capture.bin");
+        let mut config = Config::default();
+        config.set("backends.field.type", "bank").unwrap();
+        config.set("backends.field.dir", dir.path().to_str().unwrap()).unwrap();
+        config.set("backends.tape.type", "record").unwrap();
+        config.set("backends.tape.source", "pseudo").unwrap();
+        config.set("backends.tape.path", capture.to_str().unwrap()).unwrap();
+        config.monitor.backends = [
+            "mix:pseudo,lab",
+            "pool:pseudo",
+            "field",
+            "failover:pseudo,field",
+            "mix:bank,pseudo",
+            "tape",
+        ]
+        .map(String::from)
+        .to_vec();
+        config.set("backends.lab.type", "pseudo").unwrap();
+        let state = AppState::new(config);
+
+        sample_all(&state).await;
+
+        let series = state.entropy.lock().await;
+        assert!(series.latest("mix:pseudo,lab").unwrap().results.is_some());
+        for name in ["pool:pseudo", "field", "failover:pseudo,field", "mix:bank,pseudo", "tape"] {
+            let error = series.latest(name).unwrap().error.clone().unwrap();
+            assert!(error.contains("never samples"), "{}: {}", name, error);
+        }
+        assert!(!capture.exists());
+        assert!(state.pool_statuses().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_listed_backend_failures_are_recorded() {
+        let mut config = Config::default();
+        config.set("monitor.backends", "pseudo,nonexistent").unwrap();
+        let state = AppState::new(config);
+
+        sample_all(&state).await;
+
+        let series = state.entropy.lock().await;
+        assert!(series.latest("pseudo").unwrap().results.is_some());
+        let failed = series.latest("nonexistent").unwrap();
+        assert!(failed.error.as_ref().unwrap().contains("Unknown QRNG backend"));
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        // Off until backends are listed
+        assert!(spawn(Arc::new(AppState::new(Config::default()))).await.is_none());
+
+        let mut config = Config::default();
+        config.set("monitor.backends", "pseudo").unwrap();
+        config.set("monitor.interval_secs", "0").unwrap();
+        assert!(spawn(Arc::new(AppState::new(config))).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_first_sample_waits_an_interval() {
+        let mut config = Config::default();
+        config.set("monitor.backends", "pseudo").unwrap();
+        config.set("monitor.interval_secs", "1").unwrap();
+        config.set("monitor.sample_bytes", "1000").unwrap();
+        let state = Arc::new(AppState::new(config));
+
+        let task = spawn(Arc::clone(&state)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(state.entropy.lock().await.latest("pseudo").is_none());
+        task.abort();
+    }
+}
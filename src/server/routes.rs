@@ -2,24 +2,32 @@
 //!
 //! Defines all REST API endpoints for the server.
 
-use crate::coord::flower::{generate, GenerationResponse};
-use crate::coord::{available_types, AnomalyType, Coordinates, GenerationMode};
-use crate::entropy::run_all_tests;
+use crate::coord::commit::{entropy_bytes_committed, CommitParams, Commitment, Reveal, Sealed};
+use crate::coord::area::Area;
+use crate::coord::flower::{
+    entropy_bytes_needed_excluding, generate_async, generate_in_area_async, GenerationOptions, GenerationResponse,
+};
+use crate::coord::detector::detectors;
+use crate::coord::{Coordinates, DetectionMethod, GenerationMode};
+use crate::entropy::{EntropyCheck, EntropySample, Trend};
 use crate::error::Error;
 use crate::format::available_formats;
 use crate::geo::{get_ip_locator, GeoLocation};
 use crate::history::{History, HistoryEntry};
-use crate::qrng::{available_backends, get_backend_with_key, BackendInfo};
+use crate::qrng::pool::PoolStatus;
+use crate::qrng::{available_backends, BackendInfo};
 use crate::server::state::AppState;
+use crate::usage::{self, Usage};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tower_http::services::ServeDir;
 
@@ -47,6 +55,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/api/generate", post(generate_handler))
         .route("/api/status", get(status_handler))
+        .route("/api/entropy", get(entropy_handler))
         .route("/api/backends", get(backends_handler))
         .route("/api/types", get(types_handler))
         .route("/api/formats", get(formats_handler))
@@ -54,6 +63,9 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/api/history", get(history_handler))
         .route("/api/history/:id", get(history_entry_handler).delete(history_delete_handler).patch(history_update_handler))
         .route("/api/share", post(create_share_handler))
+        .route("/api/commit", post(commit_handler))
+        .route("/api/commit/:id", get(commitment_handler))
+        .route("/api/reveal/:id", get(reveal_handler))
         .nest_service("/", ServeDir::new(&static_path).append_index_html_on_directories(true))
         .with_state(state)
 }
@@ -61,10 +73,14 @@ pub fn create_router(state: Arc<AppState>) -> Router {
 /// Generate request body
 #[derive(Debug, Deserialize)]
 pub struct GenerateRequest {
-    /// Latitude
-    pub lat: f64,
-    /// Longitude
-    pub lng: f64,
+    /// Latitude (not needed with `area`)
+    pub lat: Option<f64>,
+    /// Longitude (not needed with `area`)
+    pub lng: Option<f64>,
+    /// GeoJSON polygon or multipolygon to search instead of a circle
+    pub area: Option<serde_json::Value>,
+    /// GeoJSON exclusion zones, on top of those in config
+    pub exclude: Option<serde_json::Value>,
     /// Search radius in meters
     #[serde(default = "default_radius")]
     pub radius: f64,
@@ -82,6 +98,10 @@ pub struct GenerateRequest {
     /// Grid resolution for density analysis
     #[serde(default = "default_grid_resolution")]
     pub grid_resolution: usize,
+    /// Anomaly detection method: grid or kde (default from config)
+    pub method: Option<DetectionMethod>,
+    /// Test the entropy consumed: off, warn or reject (default from config)
+    pub entropy_check: Option<EntropyCheck>,
 }
 
 fn default_radius() -> f64 {
@@ -94,6 +114,69 @@ fn default_grid_resolution() -> usize {
     50
 }
 
+/// Most points per circle a generation or commitment may ask for
+///
+/// Bounds the entropy one request draws and, for a commitment, holds in
+/// memory until the reveal (about 11 MB for flower power).
+pub const MAX_POINTS: usize = 100_000;
+
+/// Finest density grid a generation or commitment may ask for
+pub const MAX_GRID_RESOLUTION: usize = 1000;
+
+/// Refuse point counts and grids outside `MAX_POINTS` and
+/// `MAX_GRID_RESOLUTION`
+fn validate_size(points: usize, grid_resolution: usize) -> Result<(), ApiError> {
+    if !(1..=MAX_POINTS).contains(&points) {
+        return Err(ApiError {
+            error: format!("Points must be between 1 and {}", MAX_POINTS),
+            code: "INVALID_POINTS".to_string(),
+        });
+    }
+    if !(1..=MAX_GRID_RESOLUTION).contains(&grid_resolution) {
+        return Err(ApiError {
+            error: format!("Grid resolution must be between 1 and {}", MAX_GRID_RESOLUTION),
+            code: "INVALID_GRID_RESOLUTION".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The requested detection method, or the configured default
+async fn default_method(
+    state: &AppState,
+    method: Option<DetectionMethod>,
+) -> Result<DetectionMethod, ApiError> {
+    match method {
+        Some(method) => Ok(method),
+        None => state
+            .config
+            .read()
+            .await
+            .defaults
+            .method
+            .parse()
+            .map_err(|e| ApiError::from(Error::Config(e))),
+    }
+}
+
+/// Entropy check from the request, or the configured default
+async fn default_entropy_check(
+    state: &AppState,
+    entropy_check: Option<EntropyCheck>,
+) -> Result<EntropyCheck, ApiError> {
+    match entropy_check {
+        Some(entropy_check) => Ok(entropy_check),
+        None => state
+            .config
+            .read()
+            .await
+            .defaults
+            .entropy_check
+            .parse()
+            .map_err(|e| ApiError::from(Error::Config(e))),
+    }
+}
+
 /// API error response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiError {
@@ -112,7 +195,13 @@ impl From<Error> for ApiError {
         let code = match &err {
             Error::InvalidCoordinates(_) => "INVALID_COORDINATES",
             Error::InvalidRadius(_) => "INVALID_RADIUS",
+            Error::InvalidArea(_) => "INVALID_AREA",
             Error::Qrng(_) => "QRNG_ERROR",
+            Error::QrngQuota { .. } => "QRNG_QUOTA_EXCEEDED",
+            Error::QrngNetwork(_) => "QRNG_NETWORK_ERROR",
+            Error::EntropyBudget(_) => "ENTROPY_BUDGET_EXCEEDED",
+            Error::EntropyQuality(_) => "ENTROPY_QUALITY_FAILED",
+            Error::EntropyHealth(_) => "ENTROPY_HEALTH_FAILED",
             Error::Config(_) => "CONFIG_ERROR",
             _ => "INTERNAL_ERROR",
         };
@@ -130,47 +219,234 @@ async fn generate_handler(
     State(state): State<Arc<AppState>>,
     Json(req): Json<GenerateRequest>,
 ) -> Result<Json<GenerationResponse>, ApiError> {
+    let area = req
+        .area
+        .as_ref()
+        .map(Area::from_geojson)
+        .transpose()
+        .map_err(ApiError::from)?;
+    let configured = state.configured_exclusions().await.map_err(ApiError::from)?;
+    let requested = req
+        .exclude
+        .as_ref()
+        .map(Area::from_geojson)
+        .transpose()
+        .map_err(ApiError::from)?;
+    let exclude = Area::union(configured.into_iter().chain(requested)).map_err(ApiError::from)?;
+    if area.is_some() && req.mode != GenerationMode::Standard {
+        return Err(ApiError::from(Error::InvalidArea(
+            "Only standard mode can search an area".to_string(),
+        )));
+    }
+
     // Validate coordinates
-    let center = Coordinates::new(req.lat, req.lng);
+    let center = match (&area, req.lat, req.lng) {
+        (Some(area), _, _) => area.center(),
+        (None, Some(lat), Some(lng)) => Coordinates::new(lat, lng),
+        (None, _, _) => {
+            return Err(ApiError::from(Error::InvalidCoordinates(
+                "lat and lng are required without an area".to_string(),
+            )))
+        }
+    };
     center.validate().map_err(ApiError::from)?;
 
     // Validate radius
-    if req.radius <= 0.0 {
+    if area.is_none() && req.radius <= 0.0 {
         return Err(ApiError {
             error: "Radius must be positive".to_string(),
             code: "INVALID_RADIUS".to_string(),
         });
     }
+    validate_size(req.points, req.grid_resolution)?;
+
+    // Get the requested backend (API keys and paths come from config)
+    let (backend_name, backend) = state
+        .requested_backend(req.backend.as_deref())
+        .await
+        .map_err(ApiError::from)?;
+
+    // Refuse before drawing anything if the daily budget can't cover it
+    let needed = match &area {
+        Some(area) => area.entropy_bytes_needed(req.points, exclude.as_ref(), backend.float_precision()),
+        None => entropy_bytes_needed_excluding(
+            center,
+            req.radius,
+            req.points,
+            req.mode,
+            exclude.as_ref(),
+            backend.float_precision(),
+        ),
+    }
+    .map_err(ApiError::from)?;
+    usage::check_budget(&*state.config.read().await, &backend_name, needed)
+        .map_err(ApiError::from)?;
 
-    // Get backend with API key if configured
-    let backend_name = match &req.backend {
-        Some(name) => name.clone(),
-        None => state.backend_name().await,
-    };
-    let config = state.config.read().await;
-    let api_key = if backend_name == "anu" && !config.api_keys.anu.is_empty() {
-        Some(config.api_keys.anu.as_str())
-    } else {
-        None
-    };
-    let backend = get_backend_with_key(&backend_name, api_key);
+    let entropy_check = default_entropy_check(&state, req.entropy_check).await?;
+    let method = default_method(&state, req.method).await?;
 
     // Generate
-    let response = generate(
-        center,
-        req.radius,
-        req.points,
-        req.grid_resolution,
-        req.include_points,
-        req.mode,
-        backend.name(),
-        backend.as_ref(),
-    )
-    .map_err(ApiError::from)?;
+    let options = GenerationOptions::new(backend.name())
+        .with_points(req.points)
+        .with_grid_resolution(req.grid_resolution)
+        .with_method(method)
+        .with_include_points(req.include_points)
+        .with_exclude(exclude.as_ref())
+        .with_entropy_check(entropy_check);
+    let response = match &area {
+        Some(area) => generate_in_area_async(area, &options, backend.as_ref()).await,
+        None => generate_async(center, req.radius, req.mode, &options, backend.as_ref()).await,
+    };
+    if let Err(e) = usage::meter().flush() {
+        tracing::warn!("Failed to save entropy usage: {}", e);
+    }
+    let response = response.map_err(ApiError::from)?;
+
+    if let Some(failures) = response
+        .metadata
+        .entropy_quality
+        .as_ref()
+        .and_then(|quality| quality.failure_summary())
+    {
+        tracing::warn!("Generation {} entropy failed quality tests: {}", response.id, failures);
+    }
 
     Ok(Json(response))
 }
 
+/// Commit request body
+#[derive(Debug, Deserialize)]
+pub struct CommitRequest {
+    /// Latitude
+    pub lat: f64,
+    /// Longitude
+    pub lng: f64,
+    /// Search radius in meters
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+    /// Number of points for analysis
+    #[serde(default = "default_points")]
+    pub points: usize,
+    /// QRNG backend to draw the entropy from
+    pub backend: Option<String>,
+    /// Generation mode (standard or flower_power)
+    #[serde(default)]
+    pub mode: GenerationMode,
+    /// Grid resolution for density analysis
+    #[serde(default = "default_grid_resolution")]
+    pub grid_resolution: usize,
+    /// Anomaly detection method: grid or kde (default from config)
+    pub method: Option<DetectionMethod>,
+    /// GeoJSON exclusion zones, on top of those in config
+    pub exclude: Option<serde_json::Value>,
+    /// Test the committed entropy: off, warn or reject (default from config)
+    pub entropy_check: Option<EntropyCheck>,
+}
+
+/// Commit to the entropy for a generation
+///
+/// POST /api/commit
+///
+/// Draws the entropy now and returns its hash and the parameters; the
+/// result is only computed by `/api/reveal/:id`.
+async fn commit_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CommitRequest>,
+) -> Result<Json<Commitment>, ApiError> {
+    let center = Coordinates::new(req.lat, req.lng);
+    center.validate().map_err(ApiError::from)?;
+    if req.radius <= 0.0 {
+        return Err(ApiError {
+            error: "Radius must be positive".to_string(),
+            code: "INVALID_RADIUS".to_string(),
+        });
+    }
+    validate_size(req.points, req.grid_resolution)?;
+
+    let configured = state.configured_exclusions().await.map_err(ApiError::from)?;
+    let requested = req
+        .exclude
+        .as_ref()
+        .map(Area::from_geojson)
+        .transpose()
+        .map_err(ApiError::from)?;
+    let exclude = Area::union(configured.into_iter().chain(requested)).map_err(ApiError::from)?;
+
+    let (backend_name, backend) = state
+        .requested_backend(req.backend.as_deref())
+        .await
+        .map_err(ApiError::from)?;
+
+    let params = CommitParams {
+        lat: req.lat,
+        lng: req.lng,
+        radius: req.radius,
+        points: req.points,
+        mode: req.mode,
+        method: default_method(&state, req.method).await?,
+        grid_resolution: req.grid_resolution,
+        exclude,
+        entropy_check: default_entropy_check(&state, req.entropy_check).await?,
+    };
+    let needed = entropy_bytes_committed(&params, backend.float_precision())
+        .map_err(ApiError::from)?;
+    usage::check_budget(&*state.config.read().await, &backend_name, needed)
+        .map_err(ApiError::from)?;
+    let sealed = Sealed::commit(params, backend.name(), backend.as_ref()).await;
+    if let Err(e) = usage::meter().flush() {
+        tracing::warn!("Failed to save entropy usage: {}", e);
+    }
+    let sealed = state.add_commitment(sealed.map_err(ApiError::from)?).await;
+
+    Ok(Json(sealed.commitment().clone()))
+}
+
+fn commitment_not_found(id: &str) -> (StatusCode, Json<ApiError>) {
+    (StatusCode::NOT_FOUND, Json(ApiError {
+        error: format!("Commitment not found: {}", id),
+        code: "NOT_FOUND".to_string(),
+    }))
+}
+
+/// Get a published commitment
+///
+/// GET /api/commit/:id
+async fn commitment_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Commitment>, (StatusCode, Json<ApiError>)> {
+    state
+        .commitment(&id)
+        .await
+        .map(|sealed| Json(sealed.commitment().clone()))
+        .ok_or_else(|| commitment_not_found(&id))
+}
+
+/// Reveal a committed generation
+///
+/// GET /api/reveal/:id
+///
+/// Runs the generation on exactly the committed entropy and returns it with
+/// that entropy, so the result can be checked against the commitment.
+/// Revealing again returns the same result.
+async fn reveal_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Reveal>, (StatusCode, Json<ApiError>)> {
+    let sealed = state
+        .commitment(&id)
+        .await
+        .ok_or_else(|| commitment_not_found(&id))?;
+
+    let reveal = tokio::task::spawn_blocking(move || sealed.reveal())
+        .await
+        .map_err(|e| Error::Server(format!("Reveal task failed: {}", e)))
+        .and_then(|reveal| reveal)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::from(e))))?;
+
+    Ok(Json(reveal))
+}
+
 /// Status response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StatusResponse {
@@ -180,10 +456,33 @@ pub struct StatusResponse {
     pub version: String,
     /// Current backend
     pub backend: String,
-    /// Entropy quality (if available)
+    /// Latest scheduled sample of the current backend
+    ///
+    /// Left out unless `monitor.backends` lists the current backend and it
+    /// has been sampled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub entropy_quality: Option<EntropyStatus>,
     /// Uptime in seconds
     pub uptime_secs: u64,
+    /// Fill level of each prefetching pool, keyed by backend spec
+    #[serde(default)]
+    pub pools: HashMap<String, PoolStatus>,
+    /// Entropy served per backend name
+    #[serde(default)]
+    pub usage: UsageStatus,
+}
+
+/// Entropy consumption per backend name
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UsageStatus {
+    /// Current UTC day (`YYYY-MM-DD`)
+    pub day: String,
+    /// Served today, across every process sharing the usage file
+    pub today: HashMap<String, Usage>,
+    /// Served since this server started
+    pub process: HashMap<String, Usage>,
+    /// Daily byte budgets of the backends listed in `today` (0 = unlimited)
+    pub budgets: HashMap<String, u64>,
 }
 
 /// Entropy quality status
@@ -194,30 +493,41 @@ pub struct EntropyStatus {
     pub scattered: f64,
     pub overall: f64,
     pub passed: bool,
+    /// When the sample was taken
+    #[serde(default)]
+    pub sampled_at: String,
 }
 
 /// Server status endpoint
 ///
 /// GET /api/status
 async fn status_handler(State(state): State<Arc<AppState>>) -> Json<StatusResponse> {
-    // Get current backend and test entropy quality
-    let backend = state.get_backend().await;
-    let backend_name = backend.name().to_string();
-
-    // Generate some random bytes and test entropy
-    let entropy_status = match backend.bytes(10_000) {
-        Ok(bytes) => {
-            let results = run_all_tests(&bytes);
+    let backend_name = match state.get_backend().await {
+        Ok(backend) => backend.name().to_string(),
+        Err(_) => state.backend_name().await,
+    };
+
+    // Latest scheduled sample rather than a fresh draw
+    let entropy_status = state
+        .entropy
+        .lock()
+        .await
+        .latest(&state.backend_name().await)
+        .and_then(|sample| {
+            let results = sample.results.as_ref()?;
             Some(EntropyStatus {
                 balanced: results.balanced,
                 uniform: results.uniform,
                 scattered: results.scattered,
                 overall: results.overall,
                 passed: results.all_passed(),
+                sampled_at: sample.timestamp.clone(),
             })
-        }
-        Err(_) => None,
-    };
+        });
+    // Only writes if something (e.g. a pool refill) drew since the last flush
+    if let Err(e) = usage::meter().flush() {
+        tracing::warn!("Failed to save entropy usage: {}", e);
+    }
 
     Json(StatusResponse {
         running: true,
@@ -225,9 +535,78 @@ async fn status_handler(State(state): State<Arc<AppState>>) -> Json<StatusRespon
         backend: backend_name,
         entropy_quality: entropy_status,
         uptime_secs: 0, // TODO: track actual uptime
+        pools: state.pool_statuses().await,
+        usage: usage_status(&*state.config.read().await),
     })
 }
 
+/// Entropy series query parameters
+#[derive(Debug, Deserialize)]
+pub struct EntropyQuery {
+    /// Only this backend
+    pub backend: Option<String>,
+}
+
+/// Entropy samples and trends per backend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntropyResponse {
+    /// Seconds between samples (0 = sampling off)
+    pub interval_secs: u64,
+    pub backends: BTreeMap<String, BackendEntropy>,
+}
+
+/// One backend's entropy history
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackendEntropy {
+    pub trend: Trend,
+    /// Oldest first
+    pub samples: Vec<EntropySample>,
+}
+
+/// Scheduled entropy quality samples
+///
+/// GET /api/entropy?backend=<name>
+async fn entropy_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EntropyQuery>,
+) -> Json<EntropyResponse> {
+    let interval_secs = state.config.read().await.monitor.interval_secs;
+    let series = state.entropy.lock().await;
+    let backends = series
+        .backends()
+        .into_iter()
+        .filter(|name| query.backend.as_deref().is_none_or(|b| b == *name))
+        .map(|name| {
+            let entropy = BackendEntropy {
+                trend: series.trend(name),
+                samples: series.samples(name).into_iter().cloned().collect(),
+            };
+            (name.to_string(), entropy)
+        })
+        .collect();
+
+    Json(EntropyResponse {
+        interval_secs,
+        backends,
+    })
+}
+
+/// Snapshot of the process-wide usage meter
+fn usage_status(config: &crate::config::Config) -> UsageStatus {
+    let meter = usage::meter();
+    let today = meter.today();
+    let budgets = today
+        .keys()
+        .map(|name| (name.clone(), usage::daily_budget(config, name).unwrap_or(0)))
+        .collect();
+    UsageStatus {
+        day: usage::today(),
+        today,
+        process: meter.process(),
+        budgets,
+    }
+}
+
 /// Backends list response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BackendsResponse {
@@ -240,8 +619,9 @@ pub struct BackendsResponse {
 /// GET /api/backends
 async fn backends_handler(State(state): State<Arc<AppState>>) -> Json<BackendsResponse> {
     let current = state.backend_name().await;
+    let config = state.config.read().await;
     Json(BackendsResponse {
-        backends: available_backends(),
+        backends: available_backends(&config),
         current,
     })
 }
@@ -262,16 +642,11 @@ pub struct TypeInfo {
 ///
 /// GET /api/types
 async fn types_handler() -> Json<TypesResponse> {
-    let types = available_types()
-        .into_iter()
-        .map(|t| TypeInfo {
-            name: t.to_string(),
-            description: match t {
-                AnomalyType::BlindSpot => "Random point with no analysis".to_string(),
-                AnomalyType::Attractor => "Densest cluster of points".to_string(),
-                AnomalyType::Void => "Emptiest region".to_string(),
-                AnomalyType::Power => "Most statistically anomalous".to_string(),
-            },
+    let types = detectors()
+        .iter()
+        .map(|detector| TypeInfo {
+            name: detector.name().to_string(),
+            description: detector.description().to_string(),
         })
         .collect();
 
@@ -495,6 +870,8 @@ async fn history_update_handler(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::coord::AnomalyType;
+    use crate::server::state::MAX_CLIENT_POOLS;
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use http_body_util::BodyExt;
@@ -506,8 +883,22 @@ mod tests {
 
     #[tokio::test]
     async fn test_status_endpoint() {
-        let state = create_test_state();
-        let app = create_router(state);
+        let mut config = crate::config::Config::default();
+        config.set("monitor.backends", "pseudo").unwrap();
+        let state = Arc::new(AppState::new(config));
+        let app = create_router(state.clone());
+
+        // No sample yet, and status doesn't draw one
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/status").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(status.get("entropy_quality").is_none(), "{}", status);
+
+        crate::server::monitor::sample_all(&state).await;
 
         let response = app
             .oneshot(Request::builder().uri("/api/status").body(Body::empty()).unwrap())
@@ -521,6 +912,102 @@ mod tests {
 
         assert!(status.running);
         assert_eq!(status.backend, "pseudo");
+        assert!(status.pools.is_empty());
+        let quality = status.entropy_quality.unwrap();
+        assert!(quality.overall > 0.0);
+        assert!(!quality.sampled_at.is_empty());
+
+        // The scheduled sample is counted
+        assert!(status.usage.today["pseudo"].bytes >= 10_000);
+        assert!(status.usage.process["pseudo"].requests >= 1);
+        assert_eq!(status.usage.budgets["pseudo"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_entropy_endpoint() {
+        let mut config = crate::config::Config::default();
+        config.set("monitor.backends", "pseudo,nonexistent").unwrap();
+        config.set("monitor.sample_bytes", "2000").unwrap();
+        let state = Arc::new(AppState::new(config));
+        crate::server::monitor::sample_all(&state).await;
+        crate::server::monitor::sample_all(&state).await;
+        let app = create_router(state);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/entropy").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let entropy: EntropyResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(entropy.interval_secs, 3600);
+        assert_eq!(entropy.backends.len(), 2);
+        let pseudo = &entropy.backends["pseudo"];
+        assert_eq!(pseudo.samples.len(), 2);
+        assert_eq!(pseudo.trend.samples, 2);
+        assert_eq!(pseudo.samples[0].results.as_ref().unwrap().bytes_analyzed, 2000);
+        assert_eq!(entropy.backends["nonexistent"].trend.errors, 2);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/entropy?backend=pseudo")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let entropy: EntropyResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(entropy.backends.keys().collect::<Vec<_>>(), vec!["pseudo"]);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_pool_fill() {
+        let state = create_test_state();
+        state.set_backend("pool:pseudo").await;
+        let app = create_router(state);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/status").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let status: StatusResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status.backend, "pool");
+        let pool = status.pools.get("pool:pseudo").unwrap();
+        assert_eq!(pool.backend, "pseudo");
+        assert_eq!(pool.capacity, 65_536);
+    }
+
+    #[tokio::test]
+    async fn test_client_pools_are_capped() {
+        let state = create_test_state();
+        state.set_backend("pool:extract:sha256:pseudo").await;
+        state.get_backend().await.unwrap();
+
+        let specs = [
+            "pool:pseudo",
+            "pool:pool:pseudo",
+            "pool:mix:pseudo,pseudo",
+            "pool:failover:pseudo",
+            "pool:extract:von-neumann:pseudo",
+        ];
+        for spec in specs {
+            state.requested_backend(Some(spec)).await.unwrap();
+        }
+
+        let pools = state.pool_statuses().await;
+        assert_eq!(pools.len(), MAX_CLIENT_POOLS + 1);
+        // The server's own pool stays; the least recently used client's goes
+        assert!(pools.contains_key("pool:extract:sha256:pseudo"));
+        assert!(!pools.contains_key("pool:pseudo"));
     }
 
     #[tokio::test]
@@ -547,6 +1034,148 @@ mod tests {
         assert_eq!(backends.current, "pseudo");
     }
 
+    #[tokio::test]
+    async fn test_backends_endpoint_lists_configured_instances() {
+        let mut config = crate::config::Config::default();
+        config.set("backends.lab.type", "anu").unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/backends").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let backends: BackendsResponse = serde_json::from_slice(&body).unwrap();
+
+        assert!(backends.backends.iter().any(|b| b.name == "lab"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_over_budget_is_refused() {
+        let mut config = crate::config::Config::default();
+        config.set("backends.budgeted.type", "pseudo").unwrap();
+        config.set("backends.budgeted.daily_budget_bytes", "1000").unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+
+        // 100 points x 2 floats x 8 bytes needs 1600 bytes
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/generate")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"lat": 40.7, "lng": -74.0, "radius": 1000, "points": 100, "backend": "budgeted"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, "ENTROPY_BUDGET_EXCEEDED");
+        assert!(error.error.contains("budgeted"), "{}", error.error);
+        assert!(!usage::meter().today().contains_key("budgeted"));
+    }
+
+    #[tokio::test]
+    async fn test_budget_check_counts_exclusion_zones() {
+        let mut config = crate::config::Config::default();
+        config.set("backends.zoned.type", "pseudo").unwrap();
+        config.set("backends.zoned.daily_budget_bytes", "2500").unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+
+        // 1600 bytes without zones; excluding half the circle about doubles it
+        let body = serde_json::json!({
+            "lat": 40.7,
+            "lng": -74.0,
+            "radius": 1000.0,
+            "points": 100,
+            "backend": "zoned",
+            "exclude": {
+                "type": "Polygon",
+                "coordinates": [[[-74.1, 40.6], [-74.0, 40.6], [-74.0, 40.8], [-74.1, 40.8], [-74.1, 40.6]]],
+            },
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/generate")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, "ENTROPY_BUDGET_EXCEEDED");
+        assert!(!usage::meter().today().contains_key("zoned"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_unknown_backend() {
+        let state = create_test_state();
+        let app = create_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/generate")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"lat": 40.7, "lng": -74.0, "radius": 1000, "backend": "nonexistent"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, "CONFIG_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_generate_refuses_paths_in_backend_spec() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = dir.path().join("capture.bin");
+
+        for spec in [
+            format!("record:pseudo:{}", capture.display()),
+            format!("replay:{}", capture.display()),
+            format!("pool:record:pseudo:{}", capture.display()),
+        ] {
+            let app = create_router(create_test_state());
+            let body = serde_json::json!({
+                "lat": 40.7, "lng": -74.0, "radius": 1000, "points": 100, "backend": spec
+            });
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/generate")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", spec);
+        }
+        assert!(!capture.exists());
+    }
+
     #[tokio::test]
     async fn test_types_endpoint() {
         let state = create_test_state();
@@ -623,7 +1252,7 @@ mod tests {
         let gen: GenerationResponse = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(gen.circles.len(), 1);
-        assert!(gen.winners.contains_key(&AnomalyType::Attractor));
+        assert!(gen.winners.contains_key(&AnomalyType::ATTRACTOR));
     }
 
     #[tokio::test]
@@ -659,6 +1288,116 @@ mod tests {
         assert_eq!(gen.circles.len(), 7);
     }
 
+    #[tokio::test]
+    async fn test_generate_in_area() {
+        let app = create_router(create_test_state());
+        let area = serde_json::json!({
+            "type": "Feature",
+            "properties": { "name": "Central Park" },
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [[
+                    [-73.9819, 40.7681], [-73.9580, 40.8006], [-73.9493, 40.7968],
+                    [-73.9730, 40.7644], [-73.9819, 40.7681],
+                ]],
+            },
+        });
+        let request = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/generate")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(serde_json::json!({ "area": area, "points": 2000 })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let gen: GenerationResponse = serde_json::from_slice(&body).unwrap();
+        let area = gen.request.area.as_ref().unwrap();
+        assert_eq!(gen.circles[0].id, "area");
+        for winner in gen.winners.values() {
+            assert!(area.contains(winner.result.coords));
+        }
+
+        for body in [
+            serde_json::json!({ "area": { "type": "Point", "coordinates": [0.0, 0.0] } }),
+            serde_json::json!({ "area": area, "mode": "flower_power" }),
+        ] {
+            let response = app.clone().oneshot(request(body)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let err: ApiError = serde_json::from_slice(&body).unwrap();
+            assert_eq!(err.code, "INVALID_AREA");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_exclusions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let lake = temp_dir.path().join("lake.geojson");
+        std::fs::write(
+            &lake,
+            serde_json::json!({
+                "type": "Polygon",
+                "coordinates": [[[-74.02, 40.70], [-74.006, 40.70], [-74.006, 40.73], [-74.02, 40.73], [-74.02, 40.70]]],
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let mut config = crate::config::Config::default();
+        config.set("exclusions.files", lake.to_str().unwrap()).unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+        let request = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/generate")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // The configured zone covers the west half, the requested one the north
+        let body = serde_json::json!({
+            "lat": 40.7128,
+            "lng": -74.0060,
+            "radius": 1000.0,
+            "points": 2000,
+            "include_points": true,
+            "exclude": {
+                "type": "Polygon",
+                "coordinates": [[[-74.02, 40.7128], [-73.99, 40.7128], [-73.99, 40.73], [-74.02, 40.73], [-74.02, 40.7128]]],
+            },
+        });
+        let response = app.clone().oneshot(request(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let gen: GenerationResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(gen.request.exclude.as_ref().unwrap().polygon_count(), 2);
+        let points = gen.circles[0].points.as_ref().unwrap();
+        assert!(points.iter().all(|p| p.lng >= -74.006 && p.lat <= 40.7128));
+
+        // The zones were read once, so the file is no longer needed
+        std::fs::remove_file(&lake).unwrap();
+        let body = serde_json::json!({ "lat": 40.7128, "lng": -74.0060, "points": 100 });
+        let response = app.oneshot(request(body.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut config = crate::config::Config::default();
+        config.set("exclusions.files", lake.to_str().unwrap()).unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+        let response = app.oneshot(request(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let err: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(err.code, "INVALID_AREA");
+    }
+
     #[tokio::test]
     async fn test_generate_invalid_coordinates() {
         let state = create_test_state();
@@ -720,4 +1459,221 @@ mod tests {
 
         assert_eq!(err.code, "INVALID_RADIUS");
     }
+
+    #[tokio::test]
+    async fn test_generate_with_entropy_check() {
+        let app = create_router(create_test_state());
+
+        let request_body = serde_json::json!({
+            "lat": 40.7128,
+            "lng": -74.0060,
+            "radius": 1000.0,
+            "points": 500,
+            "entropy_check": "warn"
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/generate")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let gen: GenerationResponse = serde_json::from_slice(&body).unwrap();
+        let quality = gen.metadata.entropy_quality.unwrap();
+        assert_eq!(quality.bytes_analyzed, 500 * 2 * 8);
+    }
+
+    #[tokio::test]
+    async fn test_generate_detection_method() {
+        let mut config = crate::config::Config::default();
+        config.set("defaults.method", "kde").unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+        let generate = |body: serde_json::Value| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/generate")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+        };
+        let body = serde_json::json!({ "lat": 40.7128, "lng": -74.0060, "radius": 1000.0, "points": 500 });
+
+        // The configured default applies unless the request names a method
+        for (method, expected) in [(None, DetectionMethod::Kde), (Some("grid"), DetectionMethod::Grid)] {
+            let mut body = body.clone();
+            if let Some(method) = method {
+                body["method"] = method.into();
+            }
+            let response = generate(body).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let gen: GenerationResponse = serde_json::from_slice(&body).unwrap();
+            assert_eq!(gen.request.method, expected);
+            assert_eq!(gen.winners.len(), 4);
+        }
+
+        let mut unknown = body;
+        unknown["method"] = "dowsing".into();
+        assert!(generate(unknown).await.unwrap().status().is_client_error());
+    }
+
+    #[tokio::test]
+    async fn test_commit_then_reveal() {
+        let app = create_router(create_test_state());
+
+        let request_body = serde_json::json!({
+            "lat": 40.7128,
+            "lng": -74.0060,
+            "radius": 1000.0,
+            "points": 200,
+            "backend": "pseudo",
+            "entropy_check": "warn"
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/commit")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(request_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let commitment: Commitment = serde_json::from_slice(&body).unwrap();
+        assert_eq!(commitment.entropy_bytes, 200 * 2 * 8);
+
+        let get = |uri: String| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let response = get(format!("/api/commit/{}", commitment.id)).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(serde_json::from_slice::<Commitment>(&body).unwrap(), commitment);
+
+        let response = get(format!("/api/reveal/{}", commitment.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let reveal: Reveal = serde_json::from_slice(&body).unwrap();
+        assert_eq!(reveal.commitment, commitment);
+        assert_eq!(reveal.result.request.backend, "pseudo");
+        let quality = reveal.result.metadata.entropy_quality.as_ref().unwrap();
+        assert_eq!(quality.bytes_analyzed, 200 * 2 * 8);
+        assert!(reveal.verify().unwrap());
+
+        let response = get("/api/reveal/unknown".to_string()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_generate_and_commit_validate_size() {
+        let app = create_router(create_test_state());
+        let post = |uri: &'static str, body: serde_json::Value| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+        };
+
+        for uri in ["/api/generate", "/api/commit"] {
+            for (body, code) in [
+                (
+                    serde_json::json!({ "lat": 40.7, "lng": -74.0, "points": MAX_POINTS + 1 }),
+                    "INVALID_POINTS",
+                ),
+                (serde_json::json!({ "lat": 40.7, "lng": -74.0, "points": 0 }), "INVALID_POINTS"),
+                (
+                    serde_json::json!({
+                        "lat": 40.7,
+                        "lng": -74.0,
+                        "grid_resolution": MAX_GRID_RESOLUTION + 1,
+                    }),
+                    "INVALID_GRID_RESOLUTION",
+                ),
+            ] {
+                let response = post(uri, body).await.unwrap();
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                assert_eq!(serde_json::from_slice::<ApiError>(&body).unwrap().code, code);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commit_applies_configured_exclusions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let lake = temp_dir.path().join("lake.geojson");
+        std::fs::write(
+            &lake,
+            serde_json::json!({
+                "type": "Polygon",
+                "coordinates": [[[-74.02, 40.70], [-74.006, 40.70], [-74.006, 40.73], [-74.02, 40.73], [-74.02, 40.70]]],
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let mut config = crate::config::Config::default();
+        config.set("exclusions.files", lake.to_str().unwrap()).unwrap();
+        let app = create_router(Arc::new(AppState::new(config)));
+
+        let body = serde_json::json!({
+            "lat": 40.7128,
+            "lng": -74.0060,
+            "radius": 1000.0,
+            "points": 500,
+            "backend": "pseudo"
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/commit")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let commitment: Commitment = serde_json::from_slice(&body).unwrap();
+        assert!(commitment.params.exclude.is_some());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/reveal/{}", commitment.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let reveal: Reveal = serde_json::from_slice(&body).unwrap();
+        assert!(reveal
+            .result
+            .winners
+            .values()
+            .all(|w| w.result.coords.lng >= -74.006));
+        assert!(reveal.verify().unwrap());
+    }
 }
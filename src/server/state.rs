@@ -3,9 +3,38 @@
 //! Holds configuration and shared resources for the HTTP server.
 
 use crate::config::Config;
-use crate::qrng::{get_backend_with_key, QrngBackend};
+use crate::coord::area::Area;
+use crate::coord::commit::Sealed;
+use crate::entropy::EntropySeries;
+use crate::error::Result;
+use crate::qrng::pool::{reserve_bytes, PoolBackend, PoolHandle, PoolStatus};
+use crate::qrng::{registry, AsyncQrngBackend, QrngBackend, SyncAdapter};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// Most pools kept for `pool:` specs sent by clients
+///
+/// Each pool runs a refill thread and holds a prefetched reserve, so the
+/// least recently used one is dropped to make room for another.
+pub const MAX_CLIENT_POOLS: usize = 4;
+
+/// How long committed entropy is held waiting for its reveal
+pub const COMMITMENT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most committed entropy held at once, in bytes
+///
+/// The oldest commitments are dropped to make room for a new one.
+pub const MAX_COMMITTED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A cached pool
+struct SharedPool {
+    pool: Arc<PoolBackend>,
+    /// Whether only clients have asked for it (so it can be evicted)
+    remote: bool,
+    last_used: Instant,
+}
 
 /// Shared state for the HTTP server
 pub struct AppState {
@@ -14,31 +43,196 @@ pub struct AppState {
 
     /// Current QRNG backend
     backend_name: RwLock<String>,
+
+    /// Long-lived entropy pools, keyed by backend spec (e.g. "pool:anu")
+    ///
+    /// Pools for the server's own backend live as long as the server so
+    /// their reserve survives between requests; at most `MAX_CLIENT_POOLS`
+    /// others are kept.
+    pools: Mutex<HashMap<String, SharedPool>>,
+
+    /// Commit–reveal generations, keyed by commitment ID, with when they
+    /// were made
+    ///
+    /// Held in memory only: commitments not revealed before a restart, or
+    /// within `COMMITMENT_TTL`, are lost along with their entropy.
+    commitments: Mutex<HashMap<String, (Arc<Sealed>, Instant)>>,
+
+    /// Scheduled entropy samples per backend (see `server::monitor`)
+    pub entropy: Mutex<EntropySeries>,
+
+    /// Zones loaded from `exclusions.files`, with the file list they came
+    /// from (see `configured_exclusions`)
+    exclusions: Mutex<Option<(Vec<String>, Option<Area>)>>,
 }
 
 impl AppState {
     /// Create new application state
     pub fn new(config: Config) -> Self {
         let backend_name = config.defaults.backend.clone();
+        let entropy = EntropySeries::new(config.monitor.max_samples);
         Self {
             config: Arc::new(RwLock::new(config)),
             backend_name: RwLock::new(backend_name),
+            pools: Mutex::new(HashMap::new()),
+            commitments: Mutex::new(HashMap::new()),
+            entropy: Mutex::new(entropy),
+            exclusions: Mutex::new(None),
         }
     }
 
+    /// Use an existing (e.g. persisted) entropy series
+    pub fn with_entropy_series(mut self, series: EntropySeries) -> Self {
+        self.entropy = Mutex::new(series);
+        self
+    }
+
     /// Get the current QRNG backend
-    pub async fn get_backend(&self) -> Box<dyn QrngBackend> {
-        let name = self.backend_name.read().await;
+    pub async fn get_backend(&self) -> Result<Box<dyn AsyncQrngBackend>> {
+        let name = self.backend_name().await;
+        self.backend(&name).await
+    }
+
+    /// Get the backend a request asked for, or the current one
+    ///
+    /// Returns the spec used along with the backend. Specs sent by clients
+    /// are built with `BackendRegistry::create_remote`, so they can't name
+    /// files, directories or URLs.
+    pub async fn requested_backend(
+        &self,
+        requested: Option<&str>,
+    ) -> Result<(String, Box<dyn AsyncQrngBackend>)> {
+        match requested {
+            Some(name) => Ok((name.to_string(), self.build_backend(name, true).await?)),
+            None => {
+                let name = self.backend_name().await;
+                let backend = self.build_backend(&name, false).await?;
+                Ok((name, backend))
+            }
+        }
+    }
+
+    /// Get a QRNG backend by name
+    ///
+    /// `pool:` specs are shared across requests; everything else is created
+    /// fresh. Returns an error for unknown backend names.
+    pub async fn backend(&self, name: &str) -> Result<Box<dyn AsyncQrngBackend>> {
+        self.build_backend(name, false).await
+    }
+
+    async fn build_backend(&self, name: &str, remote: bool) -> Result<Box<dyn AsyncQrngBackend>> {
         let config = self.config.read().await;
 
-        // Get API key for ANU backend if configured
-        let api_key = if name.as_str() == "anu" && !config.api_keys.anu.is_empty() {
-            Some(config.api_keys.anu.as_str())
+        if let Some(inner) = name.strip_prefix("pool:") {
+            let mut pools = self.pools.lock().await;
+            let pool = match pools.get_mut(name) {
+                Some(shared) => {
+                    shared.remote &= remote;
+                    shared.last_used = Instant::now();
+                    shared.pool.clone()
+                }
+                None => {
+                    let reserve = reserve_bytes(config.backends.get("pool"), &config)?;
+                    let inner = if remote {
+                        registry().create_remote(inner, &config)?
+                    } else {
+                        registry().create(inner, &config)?
+                    };
+                    if remote {
+                        evict_client_pools(&mut pools, MAX_CLIENT_POOLS - 1);
+                    }
+                    let pool = Arc::new(PoolBackend::new(inner, reserve));
+                    pools.insert(
+                        name.to_string(),
+                        SharedPool {
+                            pool: pool.clone(),
+                            remote,
+                            last_used: Instant::now(),
+                        },
+                    );
+                    pool
+                }
+            };
+            // Each request gets its own handle so its sources aren't
+            // mixed up with other requests drawing from the same pool
+            let handle: Arc<dyn QrngBackend> = Arc::new(PoolHandle::new(pool));
+            return Ok(Box::new(SyncAdapter::new(handle)));
+        }
+
+        if remote {
+            registry().create_async_remote(name, &config)
         } else {
-            None
+            registry().create_async(name, &config)
+        }
+    }
+
+    /// Exclusion zones from the files in `exclusions.files`
+    ///
+    /// The files are read on first use and again only when the configured
+    /// list changes, not on every request. Failures aren't kept, so a file
+    /// that can't be read is retried next time.
+    pub async fn configured_exclusions(&self) -> Result<Option<Area>> {
+        let files = self.config.read().await.exclusions.files.clone();
+        let mut exclusions = self.exclusions.lock().await;
+        if let Some((loaded_from, zones)) = exclusions.as_ref() {
+            if *loaded_from == files {
+                return Ok(zones.clone());
+            }
+        }
+        let zones = Area::load_all(&files)?;
+        *exclusions = Some((files, zones.clone()));
+        Ok(zones)
+    }
+
+    /// Fill levels of all pools created so far
+    pub async fn pool_statuses(&self) -> HashMap<String, PoolStatus> {
+        self.pools
+            .lock()
+            .await
+            .iter()
+            .map(|(name, shared)| (name.clone(), shared.pool.status()))
+            .collect()
+    }
+
+    /// Hold committed entropy until it is revealed
+    ///
+    /// Drops expired commitments, then the oldest ones until this one fits
+    /// within `MAX_COMMITTED_BYTES`.
+    pub async fn add_commitment(&self, sealed: Sealed) -> Arc<Sealed> {
+        let sealed = Arc::new(sealed);
+        let mut commitments = self.commitments.lock().await;
+        commitments.retain(|_, (_, made)| made.elapsed() < COMMITMENT_TTL);
+
+        let held = |commitments: &HashMap<String, (Arc<Sealed>, Instant)>| -> u64 {
+            commitments
+                .values()
+                .map(|(sealed, _)| sealed.commitment().entropy_bytes)
+                .sum()
         };
+        let incoming = sealed.commitment().entropy_bytes;
+        while !commitments.is_empty() && held(&commitments) + incoming > MAX_COMMITTED_BYTES {
+            let Some(oldest) = commitments
+                .iter()
+                .min_by_key(|(_, (_, made))| *made)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            commitments.remove(&oldest);
+        }
+
+        commitments.insert(sealed.commitment().id.clone(), (sealed.clone(), Instant::now()));
+        sealed
+    }
 
-        get_backend_with_key(&name, api_key)
+    /// Get a commitment by ID, unless it has expired
+    pub async fn commitment(&self, id: &str) -> Option<Arc<Sealed>> {
+        self.commitments
+            .lock()
+            .await
+            .get(id)
+            .filter(|(_, made)| made.elapsed() < COMMITMENT_TTL)
+            .map(|(sealed, _)| sealed.clone())
     }
 
     /// Set the current QRNG backend
@@ -52,3 +246,60 @@ impl AppState {
         self.backend_name.read().await.clone()
     }
 }
+
+/// Drop the least recently used client pools until at most `keep` remain
+///
+/// Requests already holding a dropped pool keep it until they finish.
+fn evict_client_pools(pools: &mut HashMap<String, SharedPool>, keep: usize) {
+    while pools.values().filter(|shared| shared.remote).count() > keep {
+        let Some(oldest) = pools
+            .iter()
+            .filter(|(_, shared)| shared.remote)
+            .min_by_key(|(_, shared)| shared.last_used)
+            .map(|(name, _)| name.clone())
+        else {
+            return;
+        };
+        pools.remove(&oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::commit::CommitParams;
+    use crate::coord::{DetectionMethod, GenerationMode};
+    use crate::entropy::EntropyCheck;
+    use crate::qrng::pseudo::PseudoBackend;
+
+    async fn sealed(points: usize) -> Sealed {
+        let params = CommitParams {
+            lat: 0.0,
+            lng: 0.0,
+            radius: 1000.0,
+            points,
+            mode: GenerationMode::Standard,
+            method: DetectionMethod::Grid,
+            grid_resolution: 10,
+            exclude: None,
+            entropy_check: EntropyCheck::Off,
+        };
+        let rng = SyncAdapter::from_box(Box::new(PseudoBackend::new()));
+        Sealed::commit(params, "pseudo", &rng).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oldest_commitments_make_room() {
+        let state = AppState::new(Config::default());
+        // Each holds just over a third of the cap
+        let points = (MAX_COMMITTED_BYTES / 3 / 16 + 1) as usize;
+
+        let first = state.add_commitment(sealed(points).await).await;
+        let second = state.add_commitment(sealed(points).await).await;
+        let third = state.add_commitment(sealed(points).await).await;
+
+        assert!(state.commitment(&first.commitment().id).await.is_none());
+        assert!(state.commitment(&second.commitment().id).await.is_some());
+        assert!(state.commitment(&third.commitment().id).await.is_some());
+    }
+}
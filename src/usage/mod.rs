@@ -0,0 +1,483 @@
+//! Entropy consumption accounting
+//!
+//! Every backend built by the registry is metered (see `qrng::metered`):
+//! bytes served and upstream requests are counted per backend name, both for
+//! this process and per UTC day. Once `Meter::persist_to` is called, daily
+//! totals are merged into `usage.json` in the data directory (next to the
+//! history) on each `flush`, so several processes share one daily count. A
+//! lock file next to it serializes flushes between processes, and the file
+//! is replaced atomically so readers never see half of it.
+//!
+//! A daily budget (`usage.daily_budget_bytes`, or a backend's own
+//! `daily_budget_bytes` option) makes drawing refuse with
+//! `Error::EntropyBudget` rather than go over it. Draws reserve their bytes
+//! up front (`Meter::reserve`), so concurrent draws can't all pass the check
+//! and overshoot together.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::qrng::registry::budget_name;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const USAGE_FILE_NAME: &str = "usage.json";
+
+/// Days of history kept in the usage file
+const MAX_USAGE_DAYS: usize = 90;
+
+/// Bytes and requests served by a backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Bytes handed out
+    pub bytes: u64,
+    /// Upstream requests made (API calls for network backends)
+    pub requests: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: Usage) {
+        self.bytes += other.bytes;
+        self.requests += other.requests;
+    }
+}
+
+/// Per-backend usage for each UTC day, keyed by `YYYY-MM-DD`
+pub type DailyUsage = BTreeMap<String, HashMap<String, Usage>>;
+
+/// Current UTC day key
+pub fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Daily budget for a backend in bytes (0 = unlimited)
+///
+/// `name` is the backend name as written in the spec or config table.
+pub fn daily_budget(config: &Config, name: &str) -> Result<u64> {
+    let own = match config.backends.get(name) {
+        Some(settings) => settings.option::<u64>("daily_budget_bytes")?,
+        None => None,
+    };
+    Ok(own.unwrap_or(config.usage.daily_budget_bytes))
+}
+
+/// Refuse up front if drawing `bytes` from `spec` would exceed its budget
+pub fn check_budget(config: &Config, spec: &str, bytes: u64) -> Result<()> {
+    let name = budget_name(spec);
+    meter().check(name, daily_budget(config, name)?, bytes)
+}
+
+/// Persist the process-wide meter to the default usage file
+pub fn persist() -> Result<()> {
+    meter().persist_to(Meter::usage_path()?)
+}
+
+#[derive(Default)]
+struct MeterState {
+    /// Where daily totals are persisted, if anywhere
+    path: Option<PathBuf>,
+    /// Totals since this process started
+    process: HashMap<String, Usage>,
+    /// Day `today` refers to
+    day: String,
+    /// Totals for `day`, including other processes as of the last flush
+    today: HashMap<String, Usage>,
+    /// Usage not yet written to `path`
+    unsaved: DailyUsage,
+    /// Bytes reserved by draws still in progress
+    reserved: HashMap<String, u64>,
+}
+
+impl MeterState {
+    /// Start a new day's totals if the date has changed
+    fn roll(&mut self) {
+        let day = today();
+        if self.day != day {
+            self.today = match &self.path {
+                Some(path) => load(path)
+                    .ok()
+                    .and_then(|mut days| days.remove(&day))
+                    .unwrap_or_default(),
+                None => HashMap::new(),
+            };
+            self.day = day;
+        }
+    }
+
+    /// Refuse if `bytes` more from `name`, on top of what it has served and
+    /// has reserved, would exceed `budget`
+    fn check(&mut self, name: &str, budget: u64, bytes: u64) -> Result<()> {
+        if budget == 0 {
+            return Ok(());
+        }
+        self.roll();
+        let served = self.today.get(name).map_or(0, |u| u.bytes);
+        let used = served + self.reserved.get(name).copied().unwrap_or(0);
+        if used + bytes > budget {
+            return Err(Error::EntropyBudget(format!(
+                "{} has served or reserved {} of its {} bytes today; {} more requested",
+                name, used, budget, bytes
+            )));
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, name: &str, usage: Usage) {
+        self.roll();
+        let day = self.day.clone();
+        self.process.entry(name.to_string()).or_default().add(usage);
+        self.today.entry(name.to_string()).or_default().add(usage);
+        self.unsaved
+            .entry(day)
+            .or_default()
+            .entry(name.to_string())
+            .or_default()
+            .add(usage);
+    }
+
+    fn release(&mut self, name: &str, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        if let Some(reserved) = self.reserved.get_mut(name) {
+            *reserved = reserved.saturating_sub(bytes);
+            if *reserved == 0 {
+                self.reserved.remove(name);
+            }
+        }
+    }
+}
+
+/// Bytes held against a budget while a draw is in progress
+///
+/// `record` charges what the draw actually used; dropping the reservation
+/// without recording (e.g. when the draw fails) gives the bytes back.
+pub struct Reservation<'a> {
+    meter: &'a Meter,
+    name: String,
+    bytes: u64,
+}
+
+impl Reservation<'_> {
+    /// Release the reservation and record `usage` in its place
+    pub fn record(mut self, usage: Usage) {
+        let mut state = self.meter.state.lock().unwrap();
+        state.release(&self.name, std::mem::take(&mut self.bytes));
+        state.record(&self.name, usage);
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.bytes > 0 {
+            self.meter.state.lock().unwrap().release(&self.name, self.bytes);
+        }
+    }
+}
+
+/// Counts entropy served by each backend
+#[derive(Default)]
+pub struct Meter {
+    state: Mutex<MeterState>,
+}
+
+impl Meter {
+    /// Create an in-memory meter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default usage file path
+    pub fn usage_path() -> Result<PathBuf> {
+        Ok(History::data_dir()?.join(USAGE_FILE_NAME))
+    }
+
+    /// Persist daily totals to `path`, loading today's totals from it
+    pub fn persist_to(&self, path: impl Into<PathBuf>) -> Result<()> {
+        let path = path.into();
+        let mut days = load(&path)?;
+        let mut state = self.state.lock().unwrap();
+        state.day = today();
+        state.today = days.remove(&state.day).unwrap_or_default();
+        for (name, usage) in state.unsaved.get(&state.day).cloned().unwrap_or_default() {
+            state.today.entry(name).or_default().add(usage);
+        }
+        state.path = Some(path);
+        Ok(())
+    }
+
+    /// Refuse if serving `bytes` more from `name` would exceed `budget`
+    ///
+    /// A budget of 0 means unlimited.
+    pub fn check(&self, name: &str, budget: u64, bytes: u64) -> Result<()> {
+        self.state.lock().unwrap().check(name, budget, bytes)
+    }
+
+    /// Reserve `bytes` from `name`'s budget for a draw, refusing like `check`
+    ///
+    /// With no budget nothing is held, but the reservation still records.
+    pub fn reserve(&self, name: &str, budget: u64, bytes: u64) -> Result<Reservation<'_>> {
+        let mut state = self.state.lock().unwrap();
+        state.check(name, budget, bytes)?;
+        let bytes = if budget == 0 { 0 } else { bytes };
+        if bytes > 0 {
+            *state.reserved.entry(name.to_string()).or_default() += bytes;
+        }
+        Ok(Reservation {
+            meter: self,
+            name: name.to_string(),
+            bytes,
+        })
+    }
+
+    /// Record `usage` served by `name`
+    pub fn record(&self, name: &str, usage: Usage) {
+        self.state.lock().unwrap().record(name, usage);
+    }
+
+    /// Totals since this process started
+    pub fn process(&self) -> HashMap<String, Usage> {
+        self.state.lock().unwrap().process.clone()
+    }
+
+    /// Totals for the current UTC day
+    pub fn today(&self) -> HashMap<String, Usage> {
+        let mut state = self.state.lock().unwrap();
+        state.roll();
+        state.today.clone()
+    }
+
+    /// Merge unsaved usage into the usage file and reload today's totals
+    ///
+    /// Only writes when something was drawn since the last flush; otherwise
+    /// just reads the file for other processes' usage. Does nothing for an
+    /// in-memory meter.
+    pub fn flush(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let Some(path) = state.path.clone() else {
+            return Ok(());
+        };
+        if state.unsaved.is_empty() {
+            let mut days = load(&path)?;
+            state.roll();
+            state.today = days.remove(&state.day).unwrap_or_default();
+            return Ok(());
+        }
+
+        let _lock = lock(&path)?;
+        let mut days = load(&path)?;
+        for (day, usage) in std::mem::take(&mut state.unsaved) {
+            let totals = days.entry(day).or_default();
+            for (name, usage) in usage {
+                totals.entry(name).or_default().add(usage);
+            }
+        }
+        while days.len() > MAX_USAGE_DAYS {
+            days.pop_first();
+        }
+        save(&path, &days)?;
+
+        state.roll();
+        state.today = days.remove(&state.day).unwrap_or_default();
+        Ok(())
+    }
+}
+
+/// The process-wide meter used by registry-built backends
+pub fn meter() -> &'static Arc<Meter> {
+    static METER: OnceLock<Arc<Meter>> = OnceLock::new();
+    METER.get_or_init(|| Arc::new(Meter::new()))
+}
+
+/// Load daily usage from `path` (empty if the file doesn't exist)
+pub fn load(path: &Path) -> Result<DailyUsage> {
+    if !path.exists() {
+        return Ok(DailyUsage::new());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Config(format!("Failed to read usage file: {}", e)))?;
+    serde_json::from_str(&content)
+        .map_err(|e| Error::Config(format!("Failed to parse usage file: {}", e)))
+}
+
+/// `path` with `suffix` appended to its file name
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Hold the usage file's lock until the returned file is dropped
+fn lock(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::Config(format!("Failed to create usage directory: {}", e)))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(sibling(path, ".lock"))
+        .map_err(|e| Error::Config(format!("Failed to open usage lock: {}", e)))?;
+    file.lock()
+        .map_err(|e| Error::Config(format!("Failed to lock usage file: {}", e)))?;
+    Ok(file)
+}
+
+/// Replace the usage file atomically; the lock must be held
+fn save(path: &Path, days: &DailyUsage) -> Result<()> {
+    let content = serde_json::to_string_pretty(days)
+        .map_err(|e| Error::Config(format!("Failed to serialize usage: {}", e)))?;
+    let temp = sibling(path, ".tmp");
+    File::create(&temp)
+        .and_then(|mut file| file.write_all(content.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|e| Error::Config(format!("Failed to write usage file: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn usage(bytes: u64) -> Usage {
+        Usage { bytes, requests: 1 }
+    }
+
+    #[test]
+    fn test_record_and_budget() {
+        let meter = Meter::new();
+        meter.record("anu", usage(600));
+        meter.record("anu", usage(300));
+
+        assert_eq!(meter.process()["anu"], Usage { bytes: 900, requests: 2 });
+        assert!(meter.check("anu", 1000, 100).is_ok());
+        assert!(matches!(
+            meter.check("anu", 1000, 101),
+            Err(Error::EntropyBudget(_))
+        ));
+        assert!(meter.check("anu", 0, u64::MAX / 2).is_ok());
+        assert!(meter.check("pseudo", 1000, 1000).is_ok());
+    }
+
+    #[test]
+    fn test_reservations_count_against_budget() {
+        let meter = Meter::new();
+        let first = meter.reserve("anu", 100, 60).unwrap();
+        assert!(matches!(
+            meter.reserve("anu", 100, 50),
+            Err(Error::EntropyBudget(_))
+        ));
+
+        // Recording charges what was used and frees the rest
+        first.record(usage(30));
+        let second = meter.reserve("anu", 100, 70).unwrap();
+        assert!(meter.check("anu", 100, 1).is_err());
+
+        // A failed draw gives its reservation back
+        drop(second);
+        assert!(meter.check("anu", 100, 70).is_ok());
+        assert_eq!(meter.today()["anu"].bytes, 30);
+    }
+
+    #[test]
+    fn test_flush_merges_processes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("usage.json");
+
+        let first = Meter::new();
+        first.persist_to(&path).unwrap();
+        first.record("anu", usage(100));
+        first.flush().unwrap();
+
+        let second = Meter::new();
+        second.record("anu", usage(50));
+        second.persist_to(&path).unwrap();
+        assert_eq!(second.today()["anu"].bytes, 150);
+        second.flush().unwrap();
+
+        first.record("anu", usage(1));
+        first.flush().unwrap();
+        assert_eq!(first.today()["anu"], Usage { bytes: 151, requests: 3 });
+        assert_eq!(first.process()["anu"].bytes, 101);
+        assert_eq!(load(&path).unwrap()[&today()]["anu"].bytes, 151);
+    }
+
+    #[test]
+    fn test_flush_writes_only_after_drawing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("usage.json");
+        let meter = Meter::new();
+        meter.persist_to(&path).unwrap();
+
+        meter.flush().unwrap();
+        assert!(!path.exists());
+
+        meter.record("anu", usage(10));
+        meter.flush().unwrap();
+        let written = fs::metadata(&path).unwrap().modified().unwrap();
+
+        // Another process's usage is still picked up, without a write
+        let other = Meter::new();
+        other.persist_to(&path).unwrap();
+        other.record("anu", usage(5));
+        other.flush().unwrap();
+        let written = fs::metadata(&path).unwrap().modified().unwrap().max(written);
+        meter.flush().unwrap();
+        assert_eq!(meter.today()["anu"].bytes, 15);
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), written);
+    }
+
+    #[test]
+    fn test_concurrent_flushes_lose_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("usage.json");
+
+        // Separate meters stand in for separate processes
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let meter = Meter::new();
+                    meter.persist_to(&path).unwrap();
+                    for _ in 0..20 {
+                        meter.record("anu", usage(1));
+                        meter.flush().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let totals = &load(&path).unwrap()[&today()]["anu"];
+        assert_eq!(*totals, Usage { bytes: 160, requests: 160 });
+    }
+
+    #[test]
+    fn test_pooled_specs_check_the_inner_budget() {
+        let mut config = Config::default();
+        config.set("backends.budget_probe.daily_budget_bytes", "100").unwrap();
+        meter().record("budget_probe", usage(90));
+
+        assert!(check_budget(&config, "budget_probe", 20).is_err());
+        assert!(check_budget(&config, "pool:budget_probe", 20).is_err());
+        assert!(check_budget(&config, "pool:budget_probe", 10).is_ok());
+    }
+
+    #[test]
+    fn test_daily_budget_override() {
+        let mut config = Config::default();
+        assert_eq!(daily_budget(&config, "anu").unwrap(), 0);
+
+        config.set("usage.daily_budget_bytes", "5000").unwrap();
+        config.set("backends.anu.daily_budget_bytes", "100").unwrap();
+        assert_eq!(daily_budget(&config, "anu").unwrap(), 100);
+        assert_eq!(daily_budget(&config, "pseudo").unwrap(), 5000);
+    }
+}
@@ -222,6 +222,10 @@ function switchTab(tabName) {
         const contentId = content.id.replace('-tab', '');
         content.classList.toggle('active', contentId === tabName);
     });
+
+    if (tabName === 'settings') {
+        loadEntropyTrends();
+    }
 }
 
 // Anomaly type selection
@@ -42,6 +42,63 @@ function loadSettingsToPanel() {
     }
 }
 
+// Load scheduled entropy samples and show a trend per backend
+async function loadEntropyTrends() {
+    const container = document.getElementById('entropy-trends');
+    if (!container) return;
+
+    try {
+        const response = await fetch('/api/entropy');
+        if (!response.ok) throw new Error(`HTTP ${response.status}`);
+        const data = await response.json();
+        const names = Object.keys(data.backends);
+
+        if (names.length === 0) {
+            container.innerHTML = '<p class="empty-state">No samples yet.</p>';
+            return;
+        }
+
+        container.innerHTML = names.map(name => {
+            const { trend, samples } = data.backends[name];
+            const perDay = trend.overall_per_day;
+            const arrow = perDay > 0.01 ? '&uarr;' : perDay < -0.01 ? '&darr;' : '&rarr;';
+            return `
+                <div class="entropy-trend">
+                    <div class="entropy-trend-header">
+                        <span class="entropy-trend-name">${name}</span>
+                        <span class="text-muted">${Math.round(trend.pass_rate * 100)}% passed ${arrow}</span>
+                    </div>
+                    ${entropySparkline(samples)}
+                    <div class="text-muted">
+                        ${trend.mean_min_entropy.toFixed(2)} bits/byte &middot;
+                        ${trend.samples} samples${trend.errors ? ` (${trend.errors} failed)` : ''}
+                    </div>
+                </div>
+            `;
+        }).join('');
+    } catch (error) {
+        container.innerHTML = `<p class="empty-state">Entropy history unavailable: ${error.message}</p>`;
+    }
+}
+
+// Overall score of each sample (0-1) as a small line chart
+function entropySparkline(samples) {
+    const scores = samples.filter(s => s.results).map(s => s.results.overall);
+    if (scores.length < 2) return '';
+
+    const width = 200;
+    const height = 24;
+    const points = scores.map((score, i) => {
+        const x = (i / (scores.length - 1)) * width;
+        const y = height - score * height;
+        return `${x.toFixed(1)},${y.toFixed(1)}`;
+    }).join(' ');
+
+    return `<svg class="entropy-sparkline" viewBox="0 0 ${width} ${height}" preserveAspectRatio="none">
+        <polyline points="${points}" fill="none" stroke="currentColor" stroke-width="1.5"/>
+    </svg>`;
+}
+
 // Export history to JSON file
 function exportHistory() {
     const history = JSON.parse(localStorage.getItem('q-explore-history') || '[]');